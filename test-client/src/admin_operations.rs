@@ -1,213 +1,89 @@
-use std::sync::Arc;
-
-use affinidi_tdk::{
-    didcomm::Message,
-    messaging::{ATM, profiles::ATMProfile, protocols::Protocols},
+use serde_json::Value;
+use trust_registry::{
+    client::{AdminClientError, TrustRegistryAdminClient},
+    didcomm::handlers::admin::messages::{ListRecordsRequest, RecordBody, RecordKeyBody},
 };
-use serde_json::{Value, json};
-use uuid::Uuid;
-
-pub const CREATE_RECORD_MESSAGE_TYPE: &str =
-    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/create-record";
-pub const UPDATE_RECORD_MESSAGE_TYPE: &str =
-    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/update-record";
-pub const DELETE_RECORD_MESSAGE_TYPE: &str =
-    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/delete-record";
-pub const READ_RECORD_MESSAGE_TYPE: &str =
-    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-record";
-pub const LIST_RECORDS_MESSAGE_TYPE: &str =
-    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/list-records";
 
-pub struct CommonCrudInput {
-    pub atm: Arc<ATM>,
-    pub profile: Arc<ATMProfile>,
-    pub trust_registry_did: String,
-    pub protocols: Arc<Protocols>,
-    pub mediator_did: String,
-    pub entity_id: String,
-    pub authority_id: String,
-    pub action: String,
-    pub resource: String,
+pub fn record_key(
+    entity_id: &str,
+    authority_id: &str,
+    action: &str,
+    resource: &str,
+) -> RecordKeyBody {
+    RecordKeyBody {
+        entity_id: entity_id.to_string(),
+        authority_id: authority_id.to_string(),
+        action: action.to_string(),
+        resource: resource.to_string(),
+    }
 }
 
-pub async fn create_record(
-    input: CommonCrudInput,
+fn record_body(
+    key: RecordKeyBody,
     recognized: bool,
     authorized: bool,
     context: Option<Value>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut body = json!({
-        "entity_id": input.entity_id,
-        "authority_id": input.authority_id,
-        "action": input.action,
-        "resource": input.resource,
-        "recognized": recognized,
-        "authorized": authorized,
-    });
-
-    if let Some(ctx) = context {
-        body["context"] = ctx;
+) -> RecordBody {
+    RecordBody {
+        key,
+        recognized,
+        authorized,
+        context,
+        record_type: "assertion".to_string(),
+        expires_at: None,
+        tags: vec![],
+        expected_version: None,
     }
-
-    send_admin_message(
-        &input.atm,
-        input.profile,
-        &input.trust_registry_did,
-        &input.protocols,
-        &input.mediator_did,
-        &body,
-        CREATE_RECORD_MESSAGE_TYPE,
-    )
-    .await
 }
 
-pub async fn update_record(
-    input: CommonCrudInput,
+pub async fn create_record(
+    client: &TrustRegistryAdminClient,
+    key: RecordKeyBody,
     recognized: bool,
     authorized: bool,
     context: Option<Value>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut body = json!({
-        "entity_id": input.entity_id,
-        "authority_id": input.authority_id,
-        "action": input.action,
-        "resource": input.resource,
-        "recognized": recognized,
-        "authorized": authorized,
-    });
-
-    if let Some(ctx) = context {
-        body["context"] = ctx;
-    }
-
-    send_admin_message(
-        &input.atm,
-        input.profile,
-        &input.trust_registry_did,
-        &input.protocols,
-        &input.mediator_did,
-        &body,
-        UPDATE_RECORD_MESSAGE_TYPE,
-    )
-    .await
+) -> Result<(), AdminClientError> {
+    let created = client
+        .create_record(&record_body(key, recognized, authorized, context))
+        .await?;
+    println!("   Created: {created:#?}");
+    Ok(())
 }
 
-pub async fn delete_record(input: CommonCrudInput) -> Result<(), Box<dyn std::error::Error>> {
-    let body = json!({
-        "entity_id": input.entity_id,
-        "authority_id": input.authority_id,
-        "action": input.action,
-        "resource": input.resource,
-    });
-
-    send_admin_message(
-        &input.atm,
-        input.profile,
-        &input.trust_registry_did,
-        &input.protocols,
-        &input.mediator_did,
-        &body,
-        DELETE_RECORD_MESSAGE_TYPE,
-    )
-    .await
+pub async fn update_record(
+    client: &TrustRegistryAdminClient,
+    key: RecordKeyBody,
+    recognized: bool,
+    authorized: bool,
+    context: Option<Value>,
+) -> Result<(), AdminClientError> {
+    let updated = client
+        .update_record(&record_body(key, recognized, authorized, context))
+        .await?;
+    println!("   Updated: {updated:#?}");
+    Ok(())
 }
 
-pub async fn read_record(input: CommonCrudInput) -> Result<(), Box<dyn std::error::Error>> {
-    let body = json!({
-        "entity_id": input.entity_id,
-        "authority_id": input.authority_id,
-        "action": input.action,
-        "resource": input.resource,
-    });
-
-    send_admin_message(
-        &input.atm,
-        input.profile,
-        &input.trust_registry_did,
-        &input.protocols,
-        &input.mediator_did,
-        &body,
-        READ_RECORD_MESSAGE_TYPE,
-    )
-    .await
+pub async fn delete_record(
+    client: &TrustRegistryAdminClient,
+    key: RecordKeyBody,
+) -> Result<(), AdminClientError> {
+    let deleted = client.delete_record(&key).await?;
+    println!("   Deleted: {deleted:#?}");
+    Ok(())
 }
 
-pub async fn list_records(
-    atm: &Arc<ATM>,
-    profile: Arc<ATMProfile>,
-    trust_registry_did: &str,
-    protocols: &Arc<Protocols>,
-    mediator_did: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let body = json!({});
-
-    send_admin_message(
-        atm,
-        profile,
-        trust_registry_did,
-        protocols,
-        mediator_did,
-        &body,
-        LIST_RECORDS_MESSAGE_TYPE,
-    )
-    .await
+pub async fn read_record(
+    client: &TrustRegistryAdminClient,
+    key: RecordKeyBody,
+) -> Result<(), AdminClientError> {
+    let record = client.read_record(&key).await?;
+    println!("   Record: {record:#?}");
+    Ok(())
 }
 
-/// Helper function to send admin messages
-async fn send_admin_message(
-    atm: &Arc<ATM>,
-    profile: Arc<ATMProfile>,
-    trust_registry_did: &str,
-    _protocols: &Arc<Protocols>,
-    _mediator_did: &str,
-    body: &Value,
-    message_type: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let message_id = Uuid::new_v4().to_string();
-    let message = Message::build(message_id.clone(), message_type.to_string(), body.clone())
-        .from(profile.inner.did.clone())
-        .to(trust_registry_did.to_string())
-        .finalize();
-
-    println!(
-        "\nSending admin message: {}",
-        message_type.split('/').next_back().unwrap_or(message_type)
-    );
-    println!("   Message ID: {message_id}");
-    println!("   Body: {}", serde_json::to_string_pretty(body)?);
-
-    let packed_msg = atm
-        .pack_encrypted(
-            &message,
-            trust_registry_did,
-            Some(&profile.inner.did),
-            Some(&profile.inner.did),
-            None,
-        )
-        .await?;
-
-    let sending_result = atm
-        .forward_and_send_message(
-            &profile,
-            false,
-            &packed_msg.0,
-            Some(&message_id),
-            &profile.to_tdk_profile().mediator.unwrap(),
-            trust_registry_did,
-            None,
-            None,
-            false,
-        )
-        .await;
-
-    match sending_result {
-        Ok(_) => {
-            println!("Admin message sent successfully");
-            Ok(())
-        }
-        Err(err) => {
-            println!("Failed to send admin message: {err:?}");
-            Err(err.into())
-        }
-    }
+pub async fn list_records(client: &TrustRegistryAdminClient) -> Result<(), AdminClientError> {
+    let list = client.list_records(&ListRecordsRequest::default()).await?;
+    println!("   {} record(s): {:#?}", list.count, list.records);
+    Ok(())
 }
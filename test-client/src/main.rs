@@ -16,10 +16,11 @@ use dotenvy::dotenv;
 
 use serde_json::json;
 use sha256::digest;
+use trust_registry::client::TrustRegistryAdminClient;
 
 use crate::{
     admin_operations::{
-        CommonCrudInput, create_record, delete_record, list_records, read_record, update_record,
+        create_record, delete_record, list_records, read_record, record_key, update_record,
     },
     receivers::users_listener::user_listener,
     service_configs::load_user_config,
@@ -111,18 +112,23 @@ async fn main() {
                 .unwrap();
             tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
+            let client = TrustRegistryAdminClient::new(
+                Arc::clone(&atm),
+                Arc::clone(&profile),
+                &trust_registry_did,
+            );
+            let key = || {
+                record_key(
+                    "did:example:entity123",
+                    "did:example:authority456",
+                    "action_xyz",
+                    "resource_abc",
+                )
+            };
+
             match create_record(
-                CommonCrudInput {
-                    atm: Arc::clone(&atm),
-                    profile: Arc::clone(&profile),
-                    trust_registry_did: trust_registry_did.clone(),
-                    protocols: Arc::clone(&protocols),
-                    mediator_did: mediator_did.clone(),
-                    entity_id: "did:example:entity123".to_string(),
-                    authority_id: "did:example:authority456".to_string(),
-                    action: "action_xyz".to_string(),
-                    resource: "resource_abc".to_string(),
-                },
+                &client,
+                key(),
                 true,
                 true,
                 Some(json!({
@@ -134,42 +140,17 @@ async fn main() {
             .await
             {
                 Ok(_) => println!("Create record completed"),
-                Err(err) => println!("Create record failed: {err:#?}"),
+                Err(err) => println!("Create record failed: {err}"),
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-            match read_record(CommonCrudInput {
-                atm: Arc::clone(&atm),
-                profile: Arc::clone(&profile),
-                trust_registry_did: trust_registry_did.clone(),
-                protocols: Arc::clone(&protocols),
-                mediator_did: mediator_did.clone(),
-                entity_id: "did:example:entity123".to_string(),
-                authority_id: "did:example:authority456".to_string(),
-                action: "action_xyz".to_string(),
-                resource: "resource_abc".to_string(),
-            })
-            .await
-            {
+            match read_record(&client, key()).await {
                 Ok(_) => println!("Read record completed"),
-                Err(err) => println!("Read record failed: {err:#?}"),
+                Err(err) => println!("Read record failed: {err}"),
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
             match update_record(
-                CommonCrudInput {
-                    atm: Arc::clone(&atm),
-                    profile: Arc::clone(&profile),
-                    trust_registry_did: trust_registry_did.clone(),
-                    protocols: Arc::clone(&protocols),
-                    mediator_did: mediator_did.clone(),
-                    entity_id: "did:example:entity123".to_string(),
-                    authority_id: "did:example:authority456".to_string(),
-                    action: "action_xyz".to_string(),
-                    resource: "resource_abc".to_string(),
-                },
+                &client,
+                key(),
                 false,
                 true,
                 Some(json!({
@@ -181,60 +162,22 @@ async fn main() {
             .await
             {
                 Ok(_) => println!("Update record completed"),
-                Err(err) => println!("Update record failed: {err:#?}"),
+                Err(err) => println!("Update record failed: {err}"),
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-            match list_records(
-                &atm,
-                profile.clone(),
-                &trust_registry_did,
-                &protocols,
-                &mediator_did,
-            )
-            .await
-            {
+            match list_records(&client).await {
                 Ok(_) => println!("List records completed"),
-                Err(err) => println!("List records failed: {err:#?}"),
+                Err(err) => println!("List records failed: {err}"),
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-            match delete_record(CommonCrudInput {
-                atm: Arc::clone(&atm),
-                profile: Arc::clone(&profile),
-                trust_registry_did: trust_registry_did.clone(),
-                protocols: Arc::clone(&protocols),
-                mediator_did: mediator_did.clone(),
-                entity_id: "did:example:entity123".to_string(),
-                authority_id: "did:example:authority456".to_string(),
-                action: "action_xyz".to_string(),
-                resource: "resource_abc".to_string(),
-            })
-            .await
-            {
+            match delete_record(&client, key()).await {
                 Ok(_) => println!("Delete record completed"),
-                Err(err) => println!("Delete record failed: {err:#?}"),
+                Err(err) => println!("Delete record failed: {err}"),
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-            match read_record(CommonCrudInput {
-                atm: Arc::clone(&atm),
-                profile: Arc::clone(&profile),
-                trust_registry_did: trust_registry_did.clone(),
-                protocols: Arc::clone(&protocols),
-                mediator_did: mediator_did.clone(),
-                entity_id: "did:example:entity123".to_string(),
-                authority_id: "did:example:authority456".to_string(),
-                action: "action_xyz".to_string(),
-                resource: "resource_abc".to_string(),
-            })
-            .await
-            {
+            match read_record(&client, key()).await {
                 Ok(_) => println!("Read record (after delete) completed"),
-                Err(err) => println!("Read record (after delete) failed: {err:#?}"),
+                Err(err) => println!("Read record (after delete) failed: {err}"),
             }
 
             println!("\n{}", "=".repeat(60));
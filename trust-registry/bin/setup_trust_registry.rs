@@ -19,7 +19,7 @@ use affinidi_tdk::{
     secrets_resolver::secrets::{KeyType, Secret, SecretMaterial},
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use did_peer::{
     DIDPeer, DIDPeerCreateKeys, DIDPeerKeyType, DIDPeerKeys, DIDPeerService, PeerServiceEndPoint,
     PeerServiceEndPointLong, PeerServiceEndPointLongMap,
@@ -36,7 +36,7 @@ use crossterm::{
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::{self, File, OpenOptions},
     io::{BufRead, BufReader, Write},
@@ -55,6 +55,9 @@ struct ProfileConfig {
 #[derive(Parser, Debug)]
 #[command(version, about = "Affinidi Trust Registry Setup Tool", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Mediator URL to connect the Trust Registry (e.g., https://mediator.goodcompany.com)
     #[arg(long, short = 'u')]
     mediator_url: Option<String>,
@@ -134,6 +137,99 @@ struct Args {
     /// Trust Registry only admin operations. use didcomm
     #[arg(long, short = 'x', default_value = "false")]
     only_admin_operations: Option<bool>,
+
+    /// Preview the setup without writing files or configuring mediator ACLs
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Key type of the generated Trust Registry authentication key (ed25519, p256 or secp256k1)
+    #[arg(long, value_enum, default_value_t = KeyAlgorithm::P256)]
+    verification_key_type: KeyAlgorithm,
+
+    /// Key type of the generated Trust Registry key agreement key (x25519, p256 or secp256k1)
+    #[arg(long, value_enum, default_value_t = KeyAlgorithm::Secp256k1)]
+    encryption_key_type: KeyAlgorithm,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rotate the keys of a did:webvh Trust Registry DID, keeping its identifier
+    RotateKeys(RotateKeysArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct RotateKeysArgs {
+    /// did:webvh log of the Trust Registry DID, the rotation is appended to it
+    #[arg(long, default_value = "did.jsonl")]
+    did_log: String,
+
+    /// Where the DID document of the rotated keys is written
+    #[arg(long, default_value = "did.json")]
+    did_document: String,
+
+    /// Pre-rotation update key saved by the setup or the previous rotation, replaced by the next one
+    #[arg(long, default_value = WEBVH_UPDATE_KEY_FILE)]
+    update_key: String,
+
+    /// Env file whose PROFILE_CONFIG secrets are replaced with the new keys
+    #[arg(long, default_value = "./.env")]
+    env_file: String,
+
+    /// Preview the rotation without writing files
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Key type of the new Trust Registry authentication key (ed25519, p256 or secp256k1)
+    #[arg(long, value_enum, default_value_t = KeyAlgorithm::P256)]
+    verification_key_type: KeyAlgorithm,
+
+    /// Key type of the new Trust Registry key agreement key (x25519, p256 or secp256k1)
+    #[arg(long, value_enum, default_value_t = KeyAlgorithm::Secp256k1)]
+    encryption_key_type: KeyAlgorithm,
+}
+
+/// Key types supported by TDK for generated DIDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyAlgorithm {
+    Ed25519,
+    X25519,
+    P256,
+    Secp256k1,
+}
+
+impl KeyAlgorithm {
+    fn key_type(self) -> KeyType {
+        match self {
+            KeyAlgorithm::Ed25519 => KeyType::Ed25519,
+            KeyAlgorithm::X25519 => KeyType::X25519,
+            KeyAlgorithm::P256 => KeyType::P256,
+            KeyAlgorithm::Secp256k1 => KeyType::Secp256k1,
+        }
+    }
+
+    fn peer_key_type(self) -> Option<DIDPeerKeyType> {
+        match self {
+            KeyAlgorithm::Ed25519 => Some(DIDPeerKeyType::Ed25519),
+            KeyAlgorithm::P256 => Some(DIDPeerKeyType::P256),
+            KeyAlgorithm::Secp256k1 => Some(DIDPeerKeyType::Secp256k1),
+            // did:peer takes the key from its multibase, the type is only used to generate one
+            KeyAlgorithm::X25519 => None,
+        }
+    }
+}
+
+/// X25519 cannot sign and Ed25519 cannot be used for key agreement.
+pub fn validate_key_types(
+    verification: KeyAlgorithm,
+    encryption: KeyAlgorithm,
+) -> Result<(), String> {
+    if verification == KeyAlgorithm::X25519 {
+        return Err("--verification-key-type x25519 is not a signing key type.".to_string());
+    }
+    if encryption == KeyAlgorithm::Ed25519 {
+        return Err("--encryption-key-type ed25519 is not a key agreement key type.".to_string());
+    }
+    Ok(())
 }
 
 fn insert_env_vars(
@@ -181,6 +277,39 @@ fn insert_env_vars(
     Ok(())
 }
 
+/// Env vars whose values carry private keys.
+const SECRET_ENV_VARS: [&str; 2] = ["PROFILE_CONFIG", "CLIENT_SECRETS"];
+
+/// `value` as it may be printed: secrets are replaced with a placeholder.
+fn printable_env_value<'a>(key: &str, value: &'a str) -> &'a str {
+    if SECRET_ENV_VARS.contains(&key) {
+        "<redacted>"
+    } else {
+        value
+    }
+}
+
+/// Writes the env file, or only prints what would be written when `dry_run` is set.
+/// Secrets are redacted from the printed values.
+fn write_env_vars(
+    file_path: &str,
+    new_vars: HashMap<String, String>,
+    example_file_path: Option<&str>,
+    dry_run: bool,
+) -> std::io::Result<()> {
+    if !dry_run {
+        return insert_env_vars(file_path, new_vars, example_file_path);
+    }
+
+    let mut vars: Vec<_> = new_vars.into_iter().collect();
+    vars.sort();
+    println!("[dry-run] Would write {} with:", file_path);
+    for (key, value) in vars {
+        println!("  {}={}", key, printable_env_value(&key, &value));
+    }
+    Ok(())
+}
+
 pub async fn set_acl(alias: &str, did: &str, mediator_did: &str, secrets: Vec<Secret>) {
     let profile = TDKProfile::new(alias, did, Some(mediator_did), secrets);
 
@@ -248,11 +377,11 @@ pub async fn set_acl(alias: &str, did: &str, mediator_did: &str, secrets: Vec<Se
     }
 }
 
-fn create_keys() -> (Secret, Secret) {
-    let mut verification_key =
-        Secret::generate_p256(None, None).expect("Failed to generate P256 key");
-    let mut encryption_key =
-        Secret::generate_secp256k1(None, None).expect("Failed to generate Secp256k1 key");
+fn create_keys(verification: KeyAlgorithm, encryption: KeyAlgorithm) -> (Secret, Secret) {
+    let (_, mut verification_key) = DIDKey::generate(verification.key_type())
+        .unwrap_or_else(|e| panic!("Failed to generate {verification:?} key: {e}"));
+    let (_, mut encryption_key) = DIDKey::generate(encryption.key_type())
+        .unwrap_or_else(|e| panic!("Failed to generate {encryption:?} key: {e}"));
 
     verification_key.id = verification_key.get_public_keymultibase().unwrap();
     encryption_key.id = encryption_key.get_public_keymultibase().unwrap();
@@ -260,19 +389,24 @@ fn create_keys() -> (Secret, Secret) {
     (verification_key, encryption_key)
 }
 
-pub fn create_did(service: Option<Vec<String>>, auth_service: bool) -> (String, Vec<Secret>) {
-    let (e_did_key, mut e_secp256k1_key) = DIDKey::generate(KeyType::Secp256k1).unwrap();
-    let (v_did_key, mut v_p256) = DIDKey::generate(KeyType::P256).unwrap();
+pub fn create_did(
+    service: Option<Vec<String>>,
+    auth_service: bool,
+    verification: KeyAlgorithm,
+    encryption: KeyAlgorithm,
+) -> (String, Vec<Secret>) {
+    let (e_did_key, mut e_key) = DIDKey::generate(encryption.key_type()).unwrap();
+    let (v_did_key, mut v_key) = DIDKey::generate(verification.key_type()).unwrap();
 
     let keys = vec![
         DIDPeerCreateKeys {
             purpose: DIDPeerKeys::Verification,
-            type_: Some(DIDPeerKeyType::P256),
+            type_: verification.peer_key_type(),
             public_key_multibase: Some(v_did_key[8..].to_string()),
         },
         DIDPeerCreateKeys {
             purpose: DIDPeerKeys::Encryption,
-            type_: Some(DIDPeerKeyType::Secp256k1),
+            type_: encryption.peer_key_type(),
             public_key_multibase: Some(e_did_key[8..].to_string()),
         },
     ];
@@ -308,43 +442,41 @@ pub fn create_did(service: Option<Vec<String>>, auth_service: bool) -> (String,
 
     let (did_peer, _) =
         DIDPeer::create_peer_did(&keys, services).expect("Failed to create did:peer");
-    v_p256.id = [did_peer.as_str(), "#key-1"].concat();
-    e_secp256k1_key.id = [did_peer.as_str(), "#key-2"].concat();
+    v_key.id = [did_peer.as_str(), "#key-1"].concat();
+    e_key.id = [did_peer.as_str(), "#key-2"].concat();
 
-    let secrets_json = vec![v_p256, e_secp256k1_key];
+    let secrets_json = vec![v_key, e_key];
     (did_peer, secrets_json)
 }
 
-pub fn setup_did_peer_tr(mediator_url: String) -> (String, Vec<Secret>) {
+pub fn setup_did_peer_tr(
+    mediator_url: String,
+    verification: KeyAlgorithm,
+    encryption: KeyAlgorithm,
+) -> (String, Vec<Secret>) {
     println!("Setting up did:peer for Trust Registry...");
-    let tr_did = create_did(Some(vec![mediator_url.clone()]), true);
+    let tr_did = create_did(
+        Some(vec![mediator_url.clone()]),
+        true,
+        verification,
+        encryption,
+    );
 
     println!("✓ Trust Registry DID created: {}", tr_did.0);
 
     (tr_did.0, tr_did.1)
 }
 
-pub fn setup_did_web_tr(
+/// Builds the did:web document with the authentication key as `#key-1`, the key
+/// agreement key as `#key-2` and the mediator DIDComm services.
+fn build_did_document(
+    tr_did: &str,
+    verification_key: &Secret,
+    encryption_key: &Secret,
     mediator_url: String,
-    web_url: String,
-    did_method: String,
-) -> Result<(String, Vec<Secret>), Box<dyn Error>> {
-    println!("Setting up did:{} for Trust Registry...", did_method);
-
-    let parsed_url = Url::parse(&web_url)?;
-    let did_url_raw = WebVHURL::parse_url(&parsed_url)?;
-    // remove webvh part for did:web
-    let mut tr_did = if did_method == "web" {
-        did_url_raw.to_string().replace("webvh:{SCID}", "web")
-    } else {
-        did_url_raw.to_string()
-    };
-
-    // Create keys
-    let (verification_key, encryption_key) = create_keys();
-
+) -> Result<Document, Box<dyn Error>> {
     // Create the basic DID Document Structure
-    let mut did_document = Document::new(&tr_did.to_string())?;
+    let mut did_document = Document::new(tr_did)?;
 
     // Add the verification methods to the DID Document
     let mut property_set: HashMap<String, Value> = HashMap::new();
@@ -358,7 +490,7 @@ pub fn setup_did_web_tr(
     did_document.verification_method.push(VerificationMethod {
         id: v_key_id.clone(),
         type_: "Multikey".to_string(),
-        controller: Url::parse(&tr_did.to_string())?,
+        controller: Url::parse(tr_did)?,
         revoked: None,
         expires: None,
         property_set: property_set.clone(),
@@ -380,7 +512,7 @@ pub fn setup_did_web_tr(
     did_document.verification_method.push(VerificationMethod {
         id: e_key_id.clone(),
         type_: "Multikey".to_string(),
-        controller: Url::parse(&tr_did.to_string())?,
+        controller: Url::parse(tr_did)?,
         revoked: None,
         expires: None,
         property_set: property_set.clone(),
@@ -412,25 +544,84 @@ pub fn setup_did_web_tr(
         service_endpoint: auth_endpoint,
     });
 
+    Ok(did_document)
+}
+
+/// Pre-rotation update key of a did:webvh DID, the only key that can sign its next log entry.
+const WEBVH_UPDATE_KEY_FILE: &str = "did-update-key.json";
+
+/// Ed25519 key with the `did:key` id that proofs of did:webvh log entries are made with.
+fn create_update_key() -> Result<Secret, Box<dyn Error>> {
+    let mut update_key = Secret::generate_ed25519(None, None);
+    let multibase = update_key.get_public_keymultibase()?;
+    update_key.id = ["did:key:", &multibase, "#", &multibase].concat();
+    Ok(update_key)
+}
+
+/// Authorizes `update_key` for the next log entry and commits to `next_update_key` for the one after.
+fn webvh_parameters(
+    update_key: &Secret,
+    next_update_key: &Secret,
+) -> Result<Parameters, Box<dyn Error>> {
+    Ok(Parameters::new()
+        .with_key_pre_rotation(true)
+        .with_update_keys(vec![update_key.get_public_keymultibase()?])
+        .with_next_key_hashes(vec![next_update_key.get_public_keymultibase_hash()?])
+        .with_portable(true)
+        .build())
+}
+
+/// The profile secrets of the `#key-1` and `#key-2` keys of the DID document.
+fn profile_secrets(
+    tr_did: &str,
+    verification_key: &Secret,
+    encryption_key: &Secret,
+) -> Vec<Secret> {
+    let mut secrets: Vec<Secret> = Vec::new();
+    for (fragment, key) in [("#key-1", verification_key), ("#key-2", encryption_key)] {
+        if let SecretMaterial::JWK(jwk) = &key.secret_material {
+            let secret: Secret = serde_json::from_value(json!({
+                "id": ([tr_did, fragment].concat()),
+                "type": "JsonWebKey2020",
+                "privateKeyJwk": jwk
+            }))
+            .expect("Failed to deserialize key");
+            secrets.push(secret);
+        }
+    }
+    secrets
+}
+
+pub fn setup_did_web_tr(
+    mediator_url: String,
+    web_url: String,
+    did_method: String,
+    dry_run: bool,
+    verification: KeyAlgorithm,
+    encryption: KeyAlgorithm,
+) -> Result<(String, Vec<Secret>), Box<dyn Error>> {
+    println!("Setting up did:{} for Trust Registry...", did_method);
+
+    let parsed_url = Url::parse(&web_url)?;
+    let did_url_raw = WebVHURL::parse_url(&parsed_url)?;
+    // remove webvh part for did:web
+    let mut tr_did = if did_method == "web" {
+        did_url_raw.to_string().replace("webvh:{SCID}", "web")
+    } else {
+        did_url_raw.to_string()
+    };
+
+    // Create keys
+    let (verification_key, encryption_key) = create_keys(verification, encryption);
+
+    let mut did_document =
+        build_did_document(&tr_did, &verification_key, &encryption_key, mediator_url)?;
+
     if did_method == "webvh" {
         // Create the WebVH Parameters
-        let mut update_secret = Secret::generate_ed25519(None, None);
-        update_secret.id = [
-            "did:key:",
-            &update_secret.get_public_keymultibase()?,
-            "#",
-            &update_secret.get_public_keymultibase()?,
-        ]
-        .concat();
-
-        let next_update_secret = Secret::generate_ed25519(None, None);
-
-        let parameters = Parameters::new()
-            .with_key_pre_rotation(true)
-            .with_update_keys(vec![update_secret.get_public_keymultibase()?])
-            .with_next_key_hashes(vec![next_update_secret.get_public_keymultibase_hash()?])
-            .with_portable(true)
-            .build();
+        let update_secret = create_update_key()?;
+        let next_update_secret = create_update_key()?;
+        let parameters = webvh_parameters(&update_secret, &next_update_secret)?;
 
         // Create the WebVH DID
         let mut didwebvh = DIDWebVHState::default();
@@ -444,38 +635,33 @@ pub fn setup_did_web_tr(
         tr_did = log_entry.get_state().get("id").unwrap().to_string();
         tr_did = tr_did.replace("\"", "");
         // Save the log entry to a file
-        log_entry.log_entry.save_to_file("did.jsonl")?;
+        if dry_run {
+            println!("[dry-run] Would write did.jsonl and {WEBVH_UPDATE_KEY_FILE}");
+        } else {
+            log_entry.log_entry.save_to_file("did.jsonl")?;
+            // The pre-rotation key signs the next log entry, see `rotate-keys`
+            fs::write(
+                WEBVH_UPDATE_KEY_FILE,
+                serde_json::to_string_pretty(&next_update_secret)?,
+            )?;
+        }
         // Update the DID Document to the latest from the log entry
         did_document = serde_json::from_value(log_entry.get_did_document()?)?;
     }
 
     // Build JWKS secrets
-    let mut secrets: Vec<Secret> = Vec::new();
-    let jwk_v_id = [tr_did.to_string(), "#key-1".to_string()].concat();
-    let jwk_e_id = [tr_did.to_string(), "#key-2".to_string()].concat();
+    let secrets = profile_secrets(&tr_did, &verification_key, &encryption_key);
 
-    if let SecretMaterial::JWK(jwk) = &verification_key.secret_material {
-        let secret: Secret = serde_json::from_value(json!({
-            "id": jwk_v_id,
-            "type": "JsonWebKey2020",
-            "privateKeyJwk": jwk
-        }))
-        .expect("Failed to deserialize verification key");
-        secrets.push(secret);
-    }
+    println!("✓ Trust Registry DID created: {}", tr_did);
+    println!();
 
-    if let SecretMaterial::JWK(jwk) = &encryption_key.secret_material {
-        let secret: Secret = serde_json::from_value(json!({
-            "id": jwk_e_id,
-            "type": "JsonWebKey2020",
-            "privateKeyJwk": jwk
-        }))
-        .expect("Failed to deserialize encryption key");
-        secrets.push(secret);
+    if dry_run {
+        println!("[dry-run] Would write did.json with:");
+        println!("{}", serde_json::to_string_pretty(&did_document)?);
+        println!();
+        return Ok((tr_did, secrets));
     }
 
-    println!("✓ Trust Registry DID created: {}", tr_did);
-    println!();
     println!(
         "Saving DID document with did:{} method in the current directory...",
         did_method
@@ -516,15 +702,192 @@ pub fn setup_did_web_tr(
     Ok((tr_did, secrets))
 }
 
+/// Loads a did:webvh log and verifies its entries.
+fn load_did_log(path: &str) -> Result<DIDWebVHState, Box<dyn Error>> {
+    let mut state = DIDWebVHState::default();
+    state
+        .load_log_entries_from_file(path)
+        .map_err(|e| format!("Failed to read DID log {path}: {e}"))?;
+    state
+        .validate()
+        .map_err(|e| format!("DID log {path} is not valid: {e}"))?;
+    Ok(state)
+}
+
+/// Keys replacing the current ones of the Trust Registry DID.
+struct RotatedKeys {
+    verification: Secret,
+    encryption: Secret,
+    /// Committed to by the rotation entry, signs the rotation after it.
+    next_update: Secret,
+}
+
+/// Appends the log entry moving the DID document's `#key-1` and `#key-2` to the rotated
+/// keys. It is signed with `update_key`, which must be the pre-rotation key the last entry
+/// committed to. Returns the new DID document.
+fn append_rotation_entry(
+    state: &mut DIDWebVHState,
+    update_key: &Secret,
+    keys: &RotatedKeys,
+) -> Result<Value, Box<dyn Error>> {
+    let last_entry = state.log_entries.last().ok_or("The DID log is empty")?;
+
+    let committed_hashes = last_entry
+        .validated_parameters
+        .next_key_hashes
+        .as_deref()
+        .cloned()
+        .unwrap_or_default();
+    let update_key_hash = update_key.get_public_keymultibase_hash()?;
+    if !committed_hashes.contains(&update_key_hash) {
+        return Err(format!(
+            "Update key {} doesn't match the pre-rotation key hashes of the DID log: {}",
+            update_key.get_public_keymultibase()?,
+            committed_hashes.join(", ")
+        )
+        .into());
+    }
+
+    let mut document = last_entry.get_state().clone();
+    let methods = document["verificationMethod"]
+        .as_array_mut()
+        .ok_or("The DID document has no verification methods")?;
+    for (fragment, key) in [("#key-1", &keys.verification), ("#key-2", &keys.encryption)] {
+        let method = methods
+            .iter_mut()
+            .find(|method| {
+                method["id"]
+                    .as_str()
+                    .is_some_and(|id| id.ends_with(fragment))
+            })
+            .ok_or_else(|| format!("The DID document has no {fragment} verification method"))?;
+        method["publicKeyMultibase"] = Value::String(key.get_public_keymultibase()?);
+    }
+
+    let mut parameters = last_entry.validated_parameters.clone();
+    parameters.update_keys = Some(Arc::new(vec![update_key.get_public_keymultibase()?]));
+    parameters.next_key_hashes = Some(Arc::new(vec![
+        keys.next_update.get_public_keymultibase_hash()?,
+    ]));
+
+    let entry = state.create_log_entry(None, &document, &parameters, update_key)?;
+    Ok(entry.get_did_document()?)
+}
+
+/// The profile written to `env_file` by the setup.
+fn read_env_profile(env_file: &str) -> Result<ProfileConfig, Box<dyn Error>> {
+    let file = File::open(env_file).map_err(|e| format!("Failed to read {env_file}: {e}"))?;
+    let profile = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "PROFILE_CONFIG").then(|| value.trim().to_string())
+        })
+        .ok_or_else(|| format!("{env_file} has no PROFILE_CONFIG"))?;
+    let profile = profile.trim_matches('\'');
+    Ok(serde_json::from_str(profile)
+        .map_err(|e| format!("PROFILE_CONFIG in {env_file} is not a valid profile: {e}"))?)
+}
+
+fn rotate_keys(args: RotateKeysArgs) -> Result<(), Box<dyn Error>> {
+    let dry_run = args.dry_run;
+    validate_key_types(args.verification_key_type, args.encryption_key_type)?;
+
+    println!();
+    println!("🔑 Rotating the Trust Registry DID keys");
+    if dry_run {
+        println!("Dry run: no files will be written.");
+    }
+    println!();
+
+    let mut state = load_did_log(&args.did_log)?;
+    let update_key: Secret = serde_json::from_str(
+        &fs::read_to_string(&args.update_key)
+            .map_err(|e| format!("Failed to read {}: {e}", args.update_key))?,
+    )
+    .map_err(|e| format!("{} is not a valid key: {e}", args.update_key))?;
+    let mut profile_config = read_env_profile(&args.env_file)?;
+
+    let (verification, encryption) =
+        create_keys(args.verification_key_type, args.encryption_key_type);
+    let keys = RotatedKeys {
+        verification,
+        encryption,
+        next_update: create_update_key()?,
+    };
+    let did_document = append_rotation_entry(&mut state, &update_key, &keys)?;
+    let tr_did = did_document["id"]
+        .as_str()
+        .ok_or("The DID document has no id")?
+        .to_string();
+    if profile_config.did != tr_did {
+        return Err(format!(
+            "PROFILE_CONFIG in {} belongs to {}, not to {tr_did}",
+            args.env_file, profile_config.did
+        )
+        .into());
+    }
+    println!("✓ Rotated the keys of {tr_did}");
+
+    profile_config.secrets = profile_secrets(&tr_did, &keys.verification, &keys.encryption);
+    let mut vars = HashMap::new();
+    vars.insert(
+        "PROFILE_CONFIG".to_string(),
+        format!("'{}'", serde_json::to_string(&profile_config)?),
+    );
+
+    if dry_run {
+        println!("[dry-run] Would append to {}", args.did_log);
+        println!("[dry-run] Would write {} with:", args.did_document);
+        println!("{}", serde_json::to_string_pretty(&did_document)?);
+        write_env_vars(&args.env_file, vars, None, true)?;
+        println!("✓ Dry run complete. Nothing was written.");
+        return Ok(());
+    }
+
+    // Keep the next update key until the entry committing to it is in the log
+    let next_update_key_file = format!("{}.next", args.update_key);
+    fs::write(
+        &next_update_key_file,
+        serde_json::to_string_pretty(&keys.next_update)?,
+    )?;
+    let entry = state.log_entries.last().ok_or("The DID log is empty")?;
+    entry.log_entry.save_to_file(&args.did_log)?;
+    fs::rename(&next_update_key_file, &args.update_key)?;
+
+    File::create(&args.did_document)?
+        .write_all(serde_json::to_string_pretty(&did_document)?.as_bytes())?;
+    write_env_vars(&args.env_file, vars, None, false)?;
+
+    println!(
+        "✓ Updated {}, {}, {} and {}",
+        args.did_log, args.did_document, args.update_key, args.env_file
+    );
+    println!();
+    println!(
+        "Publish the updated {} and restart the Trust Registry.",
+        args.did_log
+    );
+    println!();
+    Ok(())
+}
+
 pub async fn setup_test_trust_registry(
     mediator_url: String,
     mediator_did: String,
     in_pipeline: bool,
+    dry_run: bool,
 ) -> std::io::Result<()> {
     println!("Generating test DIDs for Trust Registry...");
 
     let mut dids_and_secrets: Vec<(String, Vec<Secret>)> = vec![];
-    let test_tr_did = create_did(Some(vec![mediator_url.to_string()]), true);
+    let test_tr_did = create_did(
+        Some(vec![mediator_url.to_string()]),
+        true,
+        KeyAlgorithm::P256,
+        KeyAlgorithm::Secp256k1,
+    );
     dids_and_secrets.push(test_tr_did.clone());
     let test_tr_profile_configs = json!({
         "did": test_tr_did.0,
@@ -532,7 +895,12 @@ pub async fn setup_test_trust_registry(
         "secrets": test_tr_did.1
     });
 
-    let test_client_did = create_did(Some(vec![mediator_url.to_string()]), true);
+    let test_client_did = create_did(
+        Some(vec![mediator_url.to_string()]),
+        true,
+        KeyAlgorithm::P256,
+        KeyAlgorithm::Secp256k1,
+    );
     dids_and_secrets.push(test_client_did.clone());
 
     let client_secrets = serde_json::to_string(&serde_json::to_string(&test_client_did.1)?)?;
@@ -548,8 +916,10 @@ pub async fn setup_test_trust_registry(
             "PROFILE_CONFIG".to_string(),
             format!("'{}'", test_profile_configs_stringified),
         );
-        insert_env_vars("./.env.pipeline", vars, None)?;
-        println!("✓ Configured .env.pipeline file for testing.");
+        write_env_vars("./.env.pipeline", vars, None, dry_run)?;
+        if !dry_run {
+            println!("✓ Configured .env.pipeline file for testing.");
+        }
     } else {
         let mut test_vars = HashMap::new();
         test_vars.insert("TRUST_REGISTRY_DID".to_string(), test_tr_did.0);
@@ -561,12 +931,20 @@ pub async fn setup_test_trust_registry(
             "PROFILE_CONFIG".to_string(),
             format!("'{}'", test_profile_configs_stringified),
         );
-        insert_env_vars(
+        write_env_vars(
             "./.env.test",
             test_vars,
             Some("./testing/.env.test.example"),
+            dry_run,
         )?;
-        println!("✓ Configured .env.test file for testing.");
+        if !dry_run {
+            println!("✓ Configured .env.test file for testing.");
+        }
+    }
+
+    if dry_run {
+        println!("[dry-run] Skipped configuring mediator ACLs for test DIDs.");
+        return Ok(());
     }
 
     println!("Configuring mediator ACLs for test DIDs...");
@@ -580,11 +958,23 @@ pub async fn setup_test_trust_registry(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+    run(Args::parse()).await
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    if let Some(Command::RotateKeys(rotate_args)) = args.command {
+        return rotate_keys(rotate_args);
+    }
+
     let mut server_vars = HashMap::new();
+    let dry_run = args.dry_run;
+    validate_key_types(args.verification_key_type, args.encryption_key_type)?;
 
     println!();
     println!("🚀 Setting up Affinidi Trust Registry");
+    if dry_run {
+        println!("Dry run: no files will be written and mediator ACLs will not be changed.");
+    }
     println!();
 
     // DIDComm mediator configuration
@@ -652,14 +1042,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
             println!();
 
             let (tr_did, tr_secrets) = match did_method.as_str() {
-                "peer" => setup_did_peer_tr(parsed_mediator_url.to_string()),
+                "peer" => setup_did_peer_tr(
+                    parsed_mediator_url.to_string(),
+                    args.verification_key_type,
+                    args.encryption_key_type,
+                ),
                 "web" | "webvh" => {
                     let web_url = args.didweb_url.ok_or(format!(
                         "--didweb-url is required when using did:{} method.",
                         did_method
                     ))?;
 
-                    setup_did_web_tr(parsed_mediator_url.to_string(), web_url, did_method.clone())?
+                    setup_did_web_tr(
+                        parsed_mediator_url.to_string(),
+                        web_url,
+                        did_method.clone(),
+                        dry_run,
+                        args.verification_key_type,
+                        args.encryption_key_type,
+                    )?
                 }
                 _ => {
                     return Err(format!("Unsupported DID method: {}.", did_method).into());
@@ -701,7 +1102,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
             println!();
         }
 
-        if let Some(config) = &profile_config
+        if profile_config.is_some() && !only_admin_operations && dry_run {
+            println!("[dry-run] Skipped configuring mediator ACLs for Trust Registry DID.");
+        } else if let Some(config) = &profile_config
             && !only_admin_operations
         {
             println!("Configuring mediator ACLs for Trust Registry DID...");
@@ -717,8 +1120,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
 
         // Configure test Trust Registry
-        setup_test_trust_registry(mediator_url.clone(), mediator_did.clone(), test_in_pipeline)
-            .await?;
+        setup_test_trust_registry(
+            mediator_url.clone(),
+            mediator_did.clone(),
+            test_in_pipeline,
+            dry_run,
+        )
+        .await?;
     } else {
         println!("No Mediator configuration specified. Skipping Trust Registry DID configuration.");
     }
@@ -772,12 +1180,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Display server configuration in JSON format
     println!();
     println!("Environment Configuration:");
-    let config_json = serde_json::to_value(&server_vars)?;
+    let printable_vars: BTreeMap<_, _> = server_vars
+        .iter()
+        .map(|(key, value)| (key, printable_env_value(key, value)))
+        .collect();
+    let config_json = serde_json::to_value(&printable_vars)?;
     println!("{}", serde_json::to_string_pretty(&config_json)?);
     println!();
 
     // Insert variables into .env file
-    insert_env_vars("./.env", server_vars, Some("./.env.example"))?;
+    write_env_vars("./.env", server_vars, Some("./.env.example"), dry_run)?;
+    if dry_run {
+        println!("✓ Dry run complete. Nothing was written.");
+        println!();
+        return Ok(());
+    }
     println!("✓ .env file updated with Trust Registry configuration");
     println!();
     println!("Start Trust Registry with the following command:");
@@ -791,3 +1208,202 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_did_peer_creates_no_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let original_dir = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.path()).unwrap();
+
+        let args = Args::parse_from([
+            "setup-trust-registry",
+            "--mediator-url",
+            "https://mediator.example.com",
+            "--mediator-did",
+            "did:web:mediator.example.com",
+            "--did-method",
+            "peer",
+            "--admin-dids",
+            "did:example:admin",
+            "--dry-run",
+        ]);
+        let result = run(args).await;
+
+        std::env::set_current_dir(original_dir).unwrap();
+        assert!(result.is_ok(), "dry run failed: {:?}", result.err());
+
+        let entries: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert!(entries.is_empty(), "dry run created files: {:?}", entries);
+    }
+
+    #[test]
+    fn test_printable_env_value_redacts_secrets() {
+        let profile = r#"'{"did":"did:example:tr","secrets":[{"privateKeyMultibase":"z1"}]}'"#;
+
+        assert_eq!(printable_env_value("PROFILE_CONFIG", profile), "<redacted>");
+        assert_eq!(printable_env_value("CLIENT_SECRETS", "[]"), "<redacted>");
+        assert_eq!(
+            printable_env_value("MEDIATOR_DID", "did:web:mediator.example.com"),
+            "did:web:mediator.example.com"
+        );
+    }
+
+    #[test]
+    fn test_ed25519_x25519_did_lists_key_types() {
+        let (did, secrets) = create_did(
+            Some(vec!["https://mediator.example.com".to_string()]),
+            true,
+            KeyAlgorithm::Ed25519,
+            KeyAlgorithm::X25519,
+        );
+
+        assert_eq!(secrets[0].get_key_type(), KeyType::Ed25519);
+        assert_eq!(secrets[1].get_key_type(), KeyType::X25519);
+        // Multicodec prefixes of Ed25519 (z6Mk) and X25519 (z6LS) multikeys
+        assert!(did.contains(".Vz6Mk"), "unexpected did:peer: {did}");
+        assert!(did.contains(".Ez6LS"), "unexpected did:peer: {did}");
+
+        let (verification_key, encryption_key) =
+            create_keys(KeyAlgorithm::Ed25519, KeyAlgorithm::X25519);
+        let document = build_did_document(
+            "did:web:registry.example.com",
+            &verification_key,
+            &encryption_key,
+            "https://mediator.example.com".to_string(),
+        )
+        .unwrap();
+        let document = serde_json::to_value(&document).unwrap();
+        let methods = document["verificationMethod"].as_array().unwrap();
+
+        assert_eq!(methods.len(), 2);
+        for (method, prefix) in methods.iter().zip(["z6Mk", "z6LS"]) {
+            assert_eq!(method["type"], "Multikey");
+            assert!(
+                method["publicKeyMultibase"]
+                    .as_str()
+                    .unwrap()
+                    .starts_with(prefix),
+                "unexpected verification method: {method}"
+            );
+        }
+        assert_eq!(document["keyAgreement"][0], methods[1]["id"]);
+    }
+
+    /// A did:webvh log of one entry created the way the setup does, and its pre-rotation key.
+    fn initial_did_log(path: &str) -> (String, Secret) {
+        let update_key = create_update_key().unwrap();
+        let next_update_key = create_update_key().unwrap();
+        let (verification_key, encryption_key) =
+            create_keys(KeyAlgorithm::Ed25519, KeyAlgorithm::X25519);
+        let document = build_did_document(
+            "did:webvh:{SCID}:registry.example.com",
+            &verification_key,
+            &encryption_key,
+            "https://mediator.example.com".to_string(),
+        )
+        .unwrap();
+
+        let mut state = DIDWebVHState::default();
+        let entry = state
+            .create_log_entry(
+                None,
+                &serde_json::to_value(&document).unwrap(),
+                &webvh_parameters(&update_key, &next_update_key).unwrap(),
+                &update_key,
+            )
+            .unwrap();
+        entry.log_entry.save_to_file(path).unwrap();
+        let did = entry.get_state()["id"].as_str().unwrap().to_string();
+        (did, next_update_key)
+    }
+
+    #[test]
+    fn test_rotation_entry_moves_keys_and_commits_to_next_update_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("did.jsonl");
+        let path = path.to_str().unwrap();
+        let (did, update_key) = initial_did_log(path);
+        let (verification, encryption) = create_keys(KeyAlgorithm::P256, KeyAlgorithm::Secp256k1);
+        let keys = RotatedKeys {
+            verification,
+            encryption,
+            next_update: create_update_key().unwrap(),
+        };
+
+        let mut state = load_did_log(path).unwrap();
+        let document = append_rotation_entry(&mut state, &update_key, &keys).unwrap();
+
+        assert_eq!(document["id"], did.as_str());
+        let methods = document["verificationMethod"].as_array().unwrap();
+        assert_eq!(methods[0]["id"], format!("{did}#key-1"));
+        assert_eq!(
+            methods[0]["publicKeyMultibase"],
+            keys.verification.get_public_keymultibase().unwrap()
+        );
+        assert_eq!(methods[1]["id"], format!("{did}#key-2"));
+        assert_eq!(
+            methods[1]["publicKeyMultibase"],
+            keys.encryption.get_public_keymultibase().unwrap()
+        );
+
+        let entry = state.log_entries.last().unwrap();
+        assert!(entry.get_version_id().starts_with("2-"));
+        let entry = serde_json::to_value(&entry.log_entry).unwrap();
+        assert_eq!(
+            entry["parameters"]["updateKeys"],
+            json!([update_key.get_public_keymultibase().unwrap()])
+        );
+        assert_eq!(
+            entry["parameters"]["nextKeyHashes"],
+            json!([keys.next_update.get_public_keymultibase_hash().unwrap()])
+        );
+
+        // the appended log still verifies, with the rotation signed by the pre-rotation key
+        state
+            .log_entries
+            .last()
+            .unwrap()
+            .log_entry
+            .save_to_file(path)
+            .unwrap();
+        let reloaded = load_did_log(path).unwrap();
+        assert_eq!(reloaded.log_entries.len(), 2);
+    }
+
+    #[test]
+    fn test_rotation_rejects_key_not_committed_by_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("did.jsonl");
+        let path = path.to_str().unwrap();
+        initial_did_log(path);
+        let (verification, encryption) = create_keys(KeyAlgorithm::P256, KeyAlgorithm::Secp256k1);
+        let keys = RotatedKeys {
+            verification,
+            encryption,
+            next_update: create_update_key().unwrap(),
+        };
+
+        let mut state = load_did_log(path).unwrap();
+        let error = append_rotation_entry(&mut state, &create_update_key().unwrap(), &keys)
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.contains("doesn't match the pre-rotation key hashes"),
+            "unexpected error: {error}"
+        );
+        assert_eq!(state.log_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_key_types_rejects_unsupported_purposes() {
+        assert!(validate_key_types(KeyAlgorithm::Ed25519, KeyAlgorithm::X25519).is_ok());
+        assert!(validate_key_types(KeyAlgorithm::P256, KeyAlgorithm::Secp256k1).is_ok());
+        assert!(validate_key_types(KeyAlgorithm::X25519, KeyAlgorithm::X25519).is_err());
+        assert!(validate_key_types(KeyAlgorithm::Ed25519, KeyAlgorithm::Ed25519).is_err());
+    }
+}
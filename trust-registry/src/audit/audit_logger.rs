@@ -1,16 +1,35 @@
+use std::sync::{Arc, Mutex};
+
 use crate::{
-    audit::model::{AuditLog, AuditLogger, AuditOperation, AuditResource},
+    audit::{
+        hash_chain::HashChain,
+        model::{AuditLog, AuditLogger, AuditOperation, AuditResource},
+    },
     configs::AuditConfig,
+    domain::{Action, AuthorityId, EntityId, Resource},
 };
 use chrono::Utc;
-use serde_json::{Value, json};
+use hmac::{Hmac, Mac};
+use serde_json::{Map, Value, json};
+use sha2::Sha256;
 use tracing::info;
 
 pub use crate::audit::model::{AuditLogBuilder, AuditStatus};
 
 pub const AUDIT_ROLE_ADMIN: &str = "ADMIN";
 pub const NA: &str = "N/A";
+/// Fields that `AUDIT_REDACT_FIELDS` may list.
+pub const REDACTABLE_FIELDS: &[&str] = &[
+    "actor",
+    "entity_id",
+    "authority_id",
+    "action",
+    "resource",
+    "thread_id",
+    "correlation_id",
+];
 
+#[derive(Clone)]
 pub struct EmitInput {
     pub target: String,
     pub operation: AuditOperation,
@@ -19,22 +38,107 @@ pub struct EmitInput {
     pub resource: AuditResource,
     pub extra: Option<String>,
     pub thread_id: Option<String>,
+    pub correlation_id: Option<String>,
     pub timestamp: chrono::DateTime<Utc>,
 }
+
+impl From<AuditLog> for EmitInput {
+    fn from(audit_log: AuditLog) -> Self {
+        Self {
+            target: audit_log.target,
+            operation: audit_log.operation,
+            actor: audit_log.actor,
+            status: audit_log.status.to_string(),
+            resource: audit_log.resource,
+            extra: audit_log.extra,
+            thread_id: audit_log.thread_id,
+            correlation_id: audit_log.correlation_id,
+            timestamp: audit_log.timestamp,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BaseAuditLogger {
     config: AuditConfig,
+    chain: Option<Arc<Mutex<HashChain>>>,
 }
 
 impl BaseAuditLogger {
     pub fn new(config: AuditConfig) -> Self {
-        Self { config }
+        let chain = config
+            .hash_chain
+            .then(|| Arc::new(Mutex::new(HashChain::new())));
+        Self { config, chain }
+    }
+
+    /// Stable pseudonym for a value: hex HMAC-SHA256 keyed with the redaction salt.
+    fn pseudonymize(&self, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.config.redaction_salt.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(value.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn redacts(&self, field: &str) -> bool {
+        self.config.redact_fields.iter().any(|f| f == field)
+    }
+
+    fn redacted(&self, input: &EmitInput) -> EmitInput {
+        let mut input = input.clone();
+        if self.config.redact_fields.is_empty() {
+            return input;
+        }
+        if self.redacts("actor") {
+            input.actor = self.pseudonymize(&input.actor);
+        }
+        if self.redacts("thread_id") {
+            input.thread_id = input.thread_id.map(|t| self.pseudonymize(&t));
+        }
+        if self.redacts("correlation_id") {
+            input.correlation_id = input.correlation_id.map(|c| self.pseudonymize(&c));
+        }
+        let resource = &mut input.resource;
+        if self.redacts("entity_id") {
+            resource.entity_id = resource
+                .entity_id
+                .take()
+                .map(|v| EntityId::new(self.pseudonymize(v.as_str())));
+        }
+        if self.redacts("authority_id") {
+            resource.authority_id = resource
+                .authority_id
+                .take()
+                .map(|v| AuthorityId::new(self.pseudonymize(v.as_str())));
+        }
+        if self.redacts("action") {
+            resource.action = resource
+                .action
+                .take()
+                .map(|v| Action::new(self.pseudonymize(v.as_str())));
+        }
+        if self.redacts("resource") {
+            resource.resource = resource
+                .resource
+                .take()
+                .map(|v| Resource::new(self.pseudonymize(v.as_str())));
+        }
+        input
     }
 
     fn thread_id_or_na(&self, thread_id: Option<String>) -> String {
         thread_id.unwrap_or_else(|| NA.to_string())
     }
 
+    /// The caller's correlation id, or the thread id when none was supplied.
+    fn correlation_id_or_thread_id(&self, input: &EmitInput) -> String {
+        self.thread_id_or_na(input.correlation_id.clone().or(input.thread_id.clone()))
+    }
+
     fn opt_to_string<T: ToString>(&self, opt: &Option<T>) -> String {
         opt.as_ref()
             .map_or_else(|| NA.to_string(), |v| v.to_string())
@@ -58,8 +162,9 @@ impl BaseAuditLogger {
         )
     }
 
-    fn emit_json(&self, input: &EmitInput) {
-        let mut map = serde_json::Map::new();
+    pub(crate) fn json_entry(&self, input: &EmitInput) -> Map<String, Value> {
+        let input = &self.redacted(input);
+        let mut map = Map::new();
         let op_value = serde_json::to_value(input.operation)
             .unwrap_or(json!(format!("{:?}", input.operation)));
         map.insert("role".to_string(), json!(AUDIT_ROLE_ADMIN));
@@ -82,11 +187,34 @@ impl BaseAuditLogger {
             "thread_id".to_string(),
             json!(self.thread_id_or_na(input.thread_id.clone())),
         );
-        let value = Value::Object(map);
+        map.insert(
+            "correlation_id".to_string(),
+            json!(self.correlation_id_or_thread_id(input)),
+        );
+        map
+    }
+
+    fn emit_json(&self, input: &EmitInput) {
+        let entry = self.json_entry(input);
+        let value = match &self.chain {
+            Some(chain) => chain.lock().unwrap().append_json(entry),
+            None => Value::Object(entry).to_string(),
+        };
         info!(target = ?input.target, "{}", value);
     }
 
     fn emit_text(&self, input: &EmitInput) {
+        let entry = self.text_entry(input);
+        let entry = match &self.chain {
+            Some(chain) => chain.lock().unwrap().append_text(&entry),
+            None => entry,
+        };
+
+        info!("{}", entry);
+    }
+
+    fn text_entry(&self, input: &EmitInput) -> String {
+        let input = &self.redacted(input);
         let (entity_id, authority_id, action, resource_id) =
             self.resource_text_fields(&input.resource);
         let thread_id_str = self.thread_id_or_na(input.thread_id.clone());
@@ -136,6 +264,10 @@ impl BaseAuditLogger {
             format!("audit.resource.resource={}", resource_id),
             format!("audit.timestamp={}", input.timestamp.to_rfc3339()),
             format!("audit.thread_id={}", thread_id_str),
+            format!(
+                "audit.correlation_id={}",
+                self.correlation_id_or_thread_id(input)
+            ),
         ];
 
         if let Some((key, val)) = extra {
@@ -143,24 +275,14 @@ impl BaseAuditLogger {
         }
 
         let structured_log = log_parts.join(" ");
-
-        info!("{} | {}", text, structured_log);
+        format!("{text} | {structured_log}")
     }
 }
 
 #[async_trait::async_trait]
 impl AuditLogger for BaseAuditLogger {
     async fn log(&self, audit_log: AuditLog) {
-        let emit_input = EmitInput {
-            target: audit_log.target,
-            operation: audit_log.operation,
-            actor: audit_log.actor,
-            status: audit_log.status.to_string(),
-            resource: audit_log.resource,
-            extra: audit_log.extra,
-            thread_id: audit_log.thread_id,
-            timestamp: audit_log.timestamp,
-        };
+        let emit_input = EmitInput::from(audit_log);
 
         match self.config.log_format {
             crate::configs::AuditLogFormat::Json => self.emit_json(&emit_input),
@@ -179,6 +301,7 @@ mod tests {
     async fn test_log_success_text() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Text,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -205,6 +328,7 @@ mod tests {
     async fn test_log_success_json() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Json,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -231,6 +355,7 @@ mod tests {
     async fn test_log_failure_text() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Text,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -251,6 +376,7 @@ mod tests {
     async fn test_log_failure_json() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Json,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -271,6 +397,7 @@ mod tests {
     async fn test_log_unauthorized_text() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Text,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -291,6 +418,7 @@ mod tests {
     async fn test_log_unauthorized_json() {
         let config = AuditConfig {
             log_format: AuditLogFormat::Json,
+            ..Default::default()
         };
         let logger = BaseAuditLogger::new(config);
 
@@ -306,4 +434,116 @@ mod tests {
             )
             .await;
     }
+
+    fn redacting_logger(log_format: AuditLogFormat, redact_fields: &[&str]) -> BaseAuditLogger {
+        BaseAuditLogger::new(AuditConfig {
+            log_format,
+            redact_fields: redact_fields.iter().map(|f| f.to_string()).collect(),
+            redaction_salt: "salt".to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn admin_input() -> EmitInput {
+        EmitInput::from(
+            AuditLogBuilder::new()
+                .operation(AuditOperation::Create)
+                .actor("did:example:admin")
+                .resource(AuditResource::new(
+                    Some(EntityId::new("entity-1")),
+                    Some(AuthorityId::new("authority-1")),
+                    None,
+                    None,
+                ))
+                .build_success(),
+        )
+    }
+
+    #[test]
+    fn test_actor_is_plain_without_redaction() {
+        let logger = redacting_logger(AuditLogFormat::Json, &[]);
+
+        assert_eq!(
+            logger.json_entry(&admin_input())["actor"],
+            "did:example:admin"
+        );
+        assert!(
+            logger
+                .text_entry(&admin_input())
+                .contains("audit.actor=did:example:admin")
+        );
+    }
+
+    #[test]
+    fn test_redacted_actor_is_hashed_in_json_and_text() {
+        let logger = redacting_logger(AuditLogFormat::Json, &["actor"]);
+        let hashed = logger.pseudonymize("did:example:admin");
+        assert_eq!(hashed.len(), 64);
+
+        let entry = logger.json_entry(&admin_input());
+        assert_eq!(entry["actor"], json!(hashed));
+        assert_eq!(entry["resource"]["entity_id"], "entity-1");
+
+        let text = logger.text_entry(&admin_input());
+        assert!(!text.contains("did:example:admin"));
+        assert!(text.contains(&format!("audit.actor={hashed}")));
+    }
+
+    #[test]
+    fn test_redaction_is_stable_and_salted() {
+        let logger = redacting_logger(AuditLogFormat::Json, &["actor", "entity_id"]);
+        let other_salt = BaseAuditLogger::new(AuditConfig {
+            redact_fields: vec!["actor".to_string()],
+            redaction_salt: "other".to_string(),
+            ..Default::default()
+        });
+
+        let first = logger.json_entry(&admin_input());
+        let second = logger.json_entry(&admin_input());
+
+        assert_eq!(first["actor"], second["actor"]);
+        assert_eq!(
+            first["resource"]["entity_id"],
+            json!(logger.pseudonymize("entity-1"))
+        );
+        assert_ne!(
+            first["actor"],
+            other_salt.json_entry(&admin_input())["actor"]
+        );
+    }
+
+    #[test]
+    fn test_correlation_id_is_emitted() {
+        let logger = redacting_logger(AuditLogFormat::Json, &[]);
+        let input = EmitInput::from(
+            AuditLogBuilder::new()
+                .operation(AuditOperation::Create)
+                .actor("did:example:admin")
+                .thread_id(Some("thread-1".to_string()))
+                .correlation_id(Some("req-42".to_string()))
+                .build_success(),
+        );
+
+        let entry = logger.json_entry(&input);
+        assert_eq!(entry["correlation_id"], "req-42");
+        assert_eq!(entry["thread_id"], "thread-1");
+        assert!(
+            logger
+                .text_entry(&input)
+                .contains("audit.correlation_id=req-42")
+        );
+    }
+
+    #[test]
+    fn test_correlation_id_falls_back_to_thread_id() {
+        let logger = redacting_logger(AuditLogFormat::Json, &[]);
+        let input = EmitInput::from(
+            AuditLogBuilder::new()
+                .thread_id(Some("thread-1".to_string()))
+                .build_success(),
+        );
+
+        assert_eq!(logger.json_entry(&input)["correlation_id"], "thread-1");
+        assert_eq!(logger.json_entry(&admin_input())["correlation_id"], NA);
+    }
 }
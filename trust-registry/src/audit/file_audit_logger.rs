@@ -0,0 +1,204 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use serde_json::Value;
+use tracing::error;
+
+use crate::{
+    audit::{
+        audit_logger::{BaseAuditLogger, EmitInput},
+        hash_chain::HashChain,
+        model::{AuditLog, AuditLogger},
+    },
+    configs::AuditConfig,
+};
+
+/// Bytes read per step while looking for the last line of an existing log.
+const TAIL_BLOCK_BYTES: u64 = 8 * 1024;
+
+/// Last non-blank line of the file, read backwards from its end block by block, so
+/// reopening a large log doesn't read all of it. `None` for a missing or blank file.
+fn last_line(path: &Path) -> std::io::Result<Option<String>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let line = |bytes: &[u8]| {
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+
+    let mut start = file.metadata()?.len();
+    let mut tail = Vec::new();
+    while start > 0 {
+        let block_start = start.saturating_sub(TAIL_BLOCK_BYTES);
+        let mut block = vec![0; (start - block_start) as usize];
+        file.seek(SeekFrom::Start(block_start))?;
+        file.read_exact(&mut block)?;
+        block.extend_from_slice(&tail);
+        tail = block;
+        start = block_start;
+
+        let Some(last) = tail.iter().rposition(|b| !b.is_ascii_whitespace()) else {
+            continue;
+        };
+        let end = tail[last..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(tail.len(), |i| last + i);
+        // The line is complete once the newline before it, or the file start, is read
+        if let Some(newline) = tail[..last].iter().rposition(|&b| b == b'\n') {
+            return Ok(Some(line(&tail[newline + 1..end])));
+        }
+        if start == 0 {
+            return Ok(Some(line(&tail[..end])));
+        }
+    }
+    Ok(None)
+}
+
+struct LogFile {
+    file: File,
+    chain: Option<HashChain>,
+}
+
+/// Appends audit entries to a file as JSON lines, regardless of `AUDIT_LOG_FORMAT`.
+///
+/// With `AUDIT_HASH_CHAIN` enabled the chain resumes from the last line already in the
+/// file, so it can be checked across restarts with [`crate::audit::hash_chain::verify_log_file`].
+pub struct FileAuditLogger {
+    formatter: BaseAuditLogger,
+    log_file: Mutex<LogFile>,
+}
+
+impl FileAuditLogger {
+    pub fn open(path: &Path, config: AuditConfig) -> std::io::Result<Self> {
+        let chain = if config.hash_chain {
+            Some(HashChain::resume(last_line(path)?.as_deref()))
+        } else {
+            None
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            formatter: BaseAuditLogger::new(config),
+            log_file: Mutex::new(LogFile { file, chain }),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogger for FileAuditLogger {
+    async fn log(&self, audit_log: AuditLog) {
+        let entry = self.formatter.json_entry(&EmitInput::from(audit_log));
+
+        let mut log_file = self.log_file.lock().unwrap();
+        let line = match log_file.chain.as_mut() {
+            Some(chain) => chain.append_json(entry),
+            None => Value::Object(entry).to_string(),
+        };
+        if let Err(e) = writeln!(log_file.file, "{line}").and_then(|_| log_file.file.flush()) {
+            error!("Failed to write audit entry: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{
+        hash_chain::{ChainVerificationError, verify_log_file},
+        model::{AuditLogBuilder, AuditOperation},
+    };
+    use tempfile::NamedTempFile;
+
+    fn chained_config() -> AuditConfig {
+        AuditConfig {
+            hash_chain: true,
+            ..Default::default()
+        }
+    }
+
+    async fn write_entries(path: &Path, actors: &[&str]) {
+        let logger = FileAuditLogger::open(path, chained_config()).unwrap();
+        for actor in actors {
+            logger
+                .log(
+                    AuditLogBuilder::new()
+                        .operation(AuditOperation::Create)
+                        .actor(*actor)
+                        .build_success(),
+                )
+                .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_three_chained_entries() {
+        let file = NamedTempFile::new().unwrap();
+
+        write_entries(
+            file.path(),
+            &["did:example:a", "did:example:b", "did:example:c"],
+        )
+        .await;
+
+        assert_eq!(verify_log_file(file.path()).unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reopened_log_continues_chain() {
+        let file = NamedTempFile::new().unwrap();
+
+        write_entries(file.path(), &["did:example:a", "did:example:b"]).await;
+        write_entries(file.path(), &["did:example:c"]).await;
+
+        assert_eq!(verify_log_file(file.path()).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_last_line_reads_only_the_tail() {
+        let file = NamedTempFile::new().unwrap();
+        let long_entry = "x".repeat(3 * TAIL_BLOCK_BYTES as usize);
+        std::fs::write(file.path(), format!("first\n{long_entry}\r\n\n  \n")).unwrap();
+
+        assert_eq!(last_line(file.path()).unwrap(), Some(long_entry));
+
+        std::fs::write(file.path(), "only").unwrap();
+        assert_eq!(last_line(file.path()).unwrap().as_deref(), Some("only"));
+        std::fs::write(file.path(), "\n \n").unwrap();
+        assert_eq!(last_line(file.path()).unwrap(), None);
+        assert_eq!(
+            last_line(Path::new("/nonexistent/audit.log")).unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verifier_detects_mutated_middle_entry() {
+        let file = NamedTempFile::new().unwrap();
+        write_entries(
+            file.path(),
+            &["did:example:a", "did:example:b", "did:example:c"],
+        )
+        .await;
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        std::fs::write(
+            file.path(),
+            contents.replace("did:example:b", "did:example:mallory"),
+        )
+        .unwrap();
+
+        let error = verify_log_file(file.path()).unwrap_err();
+        assert!(matches!(
+            error,
+            ChainVerificationError::BrokenLink { line: 3, .. }
+        ));
+    }
+}
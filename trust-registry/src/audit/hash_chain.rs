@@ -0,0 +1,189 @@
+use std::{fmt, path::Path};
+
+use serde_json::{Map, Value, json};
+
+/// `prev_hash` of the first entry in a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+pub const PREV_HASH_FIELD: &str = "prev_hash";
+
+/// Links audit entries by embedding the SHA-256 of the previous entry's serialized form,
+/// so editing or removing an entry breaks every link after it.
+#[derive(Debug, Clone)]
+pub struct HashChain {
+    last_hash: String,
+}
+
+impl HashChain {
+    pub fn new() -> Self {
+        Self {
+            last_hash: GENESIS_HASH.to_string(),
+        }
+    }
+
+    /// Continues a chain whose last written entry is `last_entry`.
+    pub fn resume(last_entry: Option<&str>) -> Self {
+        match last_entry {
+            Some(entry) => Self {
+                last_hash: sha256::digest(entry),
+            },
+            None => Self::new(),
+        }
+    }
+
+    /// Adds `prev_hash` to the entry and returns its serialized form, advancing the chain.
+    pub fn append_json(&mut self, mut entry: Map<String, Value>) -> String {
+        entry.insert(PREV_HASH_FIELD.to_string(), json!(self.last_hash));
+        let serialized = Value::Object(entry).to_string();
+        self.last_hash = sha256::digest(&serialized);
+        serialized
+    }
+
+    /// Same as [`Self::append_json`] for `key=value` text entries.
+    pub fn append_text(&mut self, entry: &str) -> String {
+        let serialized = format!("{entry} audit.{PREV_HASH_FIELD}={}", self.last_hash);
+        self.last_hash = sha256::digest(&serialized);
+        serialized
+    }
+}
+
+impl Default for HashChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum ChainVerificationError {
+    Io(std::io::Error),
+    /// The line is not a JSON object with a `prev_hash` field.
+    Malformed {
+        line: usize,
+    },
+    /// The line's `prev_hash` doesn't match the previous line, which was altered or removed.
+    BrokenLink {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for ChainVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read audit log: {e}"),
+            Self::Malformed { line } => write!(f, "Line {line} is not a chained audit entry"),
+            Self::BrokenLink {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Broken hash chain at line {line}: expected prev_hash {expected}, found {found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChainVerificationError {}
+
+/// Checks every link of a JSON-lines audit log. Returns the number of verified entries.
+pub fn verify_chain(contents: &str) -> Result<usize, ChainVerificationError> {
+    let mut expected = GENESIS_HASH.to_string();
+    let mut count = 0;
+
+    for (index, entry) in contents.lines().enumerate() {
+        if entry.trim().is_empty() {
+            continue;
+        }
+        let line = index + 1;
+        let found = serde_json::from_str::<Value>(entry)
+            .ok()
+            .and_then(|value| value.get(PREV_HASH_FIELD)?.as_str().map(String::from))
+            .ok_or(ChainVerificationError::Malformed { line })?;
+        if found != expected {
+            return Err(ChainVerificationError::BrokenLink {
+                line,
+                expected,
+                found,
+            });
+        }
+        expected = sha256::digest(entry);
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+pub fn verify_log_file(path: &Path) -> Result<usize, ChainVerificationError> {
+    let contents = std::fs::read_to_string(path).map_err(ChainVerificationError::Io)?;
+    verify_chain(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(actor: &str) -> Map<String, Value> {
+        let Value::Object(map) = json!({ "actor": actor, "operation": "CREATE" }) else {
+            unreachable!()
+        };
+        map
+    }
+
+    fn chained_log() -> Vec<String> {
+        let mut chain = HashChain::new();
+        ["did:example:a", "did:example:b", "did:example:c"]
+            .into_iter()
+            .map(|actor| chain.append_json(entry(actor)))
+            .collect()
+    }
+
+    #[test]
+    fn test_first_entry_links_to_genesis() {
+        let lines = chained_log();
+
+        let first: Value = serde_json::from_str(&lines[0]).unwrap();
+
+        assert_eq!(first[PREV_HASH_FIELD], GENESIS_HASH);
+    }
+
+    #[test]
+    fn test_verify_accepts_intact_chain() {
+        assert_eq!(verify_chain(&chained_log().join("\n")).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_verify_detects_mutated_middle_entry() {
+        let mut lines = chained_log();
+        lines[1] = lines[1].replace("did:example:b", "did:example:mallory");
+
+        let error = verify_chain(&lines.join("\n")).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ChainVerificationError::BrokenLink { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_verify_detects_removed_entry() {
+        let mut lines = chained_log();
+        lines.remove(0);
+
+        let error = verify_chain(&lines.join("\n")).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ChainVerificationError::BrokenLink { line: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_resume_continues_chain() {
+        let mut lines = chained_log();
+        let mut chain = HashChain::resume(lines.last().map(String::as_str));
+        lines.push(chain.append_json(entry("did:example:d")));
+
+        assert_eq!(verify_chain(&lines.join("\n")).unwrap(), 4);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod audit_logger;
+pub mod file_audit_logger;
+pub mod hash_chain;
 pub mod model;
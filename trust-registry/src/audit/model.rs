@@ -14,6 +14,9 @@ pub struct AuditLog {
     pub resource: AuditResource,
     pub extra: Option<String>,
     pub thread_id: Option<String>,
+    /// Request id supplied by the caller, e.g. from an upstream gateway.
+    #[serde(default)]
+    pub correlation_id: Option<String>,
     pub timestamp: chrono::DateTime<Utc>,
 }
 
@@ -50,6 +53,7 @@ impl AuditLogBuilder {
                 resource: AuditResource::empty(),
                 extra: None,
                 thread_id: None,
+                correlation_id: None,
                 timestamp: Utc::now(),
             },
         }
@@ -75,6 +79,11 @@ impl AuditLogBuilder {
         self
     }
 
+    pub fn correlation_id(mut self, correlation_id: Option<String>) -> Self {
+        self.audit_log.correlation_id = correlation_id;
+        self
+    }
+
     pub fn build_success(mut self) -> AuditLog {
         self.audit_log.status = AuditStatus::Success;
         self.audit_log.timestamp = Utc::now();
@@ -112,9 +121,12 @@ pub trait AuditLogger: Send + Sync {
 pub enum AuditOperation {
     Create,
     Update,
+    Upsert,
     Delete,
     Read,
     List,
+    Truncate,
+    Reconcile,
 }
 
 impl fmt::Display for AuditOperation {
@@ -122,9 +134,12 @@ impl fmt::Display for AuditOperation {
         match self {
             Self::Create => write!(f, "CREATE"),
             Self::Update => write!(f, "UPDATE"),
+            Self::Upsert => write!(f, "UPSERT"),
             Self::Delete => write!(f, "DELETE"),
             Self::Read => write!(f, "READ"),
             Self::List => write!(f, "LIST"),
+            Self::Truncate => write!(f, "TRUNCATE"),
+            Self::Reconcile => write!(f, "RECONCILE"),
         }
     }
 }
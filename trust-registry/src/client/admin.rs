@@ -0,0 +1,431 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use affinidi_tdk::{
+    didcomm::Message,
+    messaging::{ATM, errors::ATMError, profiles::ATMProfile, protocols::Protocols},
+};
+use chrono::Utc;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::didcomm::{
+    handlers::admin::{
+        BULK_DELETE_RECORDS_MESSAGE_TYPE, CREATE_RECORD_MESSAGE_TYPE, DELETE_RECORD_MESSAGE_TYPE,
+        LIST_BY_TAG_MESSAGE_TYPE, LIST_RECORDS_MESSAGE_TYPE, READ_RECORD_MESSAGE_TYPE,
+        READ_RECORDS_MESSAGE_TYPE, RECONCILE_MESSAGE_TYPE, REFRESH_RECORD_MESSAGE_TYPE,
+        TRUNCATE_RECORDS_MESSAGE_TYPE, UPDATE_RECORD_MESSAGE_TYPE, UPSERT_RECORD_MESSAGE_TYPE,
+        messages::{
+            BulkDeleteRecordsBody, BulkDeleteRecordsResponse, ListByTagRequest, ListRecordsRequest,
+            ListRecordsResponse, ReadRecordsBody, ReadRecordsResponse, ReconcileBody,
+            ReconcileResponse, RecordBody, RecordKeyBody, RecordResponse, RefreshRecordBody,
+            RefreshRecordResponse, TruncateRecordsBody, TruncateRecordsResponse,
+            UpsertRecordResponse,
+        },
+    },
+    new_message_id,
+    problem_report::ProblemReport,
+    transport::PROBLEM_REPORT_TYPE,
+};
+
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum AdminClientError {
+    /// Packing, sending or receiving a message failed.
+    Transport(ATMError),
+    /// No response arrived within the response timeout.
+    Timeout(Duration),
+    /// The registry rejected the request.
+    ProblemReport(ProblemReport),
+    /// The request could not be encoded or the response was not the one expected.
+    InvalidMessage(String),
+}
+
+impl fmt::Display for AdminClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "Transport error: {e}"),
+            Self::Timeout(timeout) => write!(f, "No response within {} ms", timeout.as_millis()),
+            Self::ProblemReport(report) => {
+                write!(f, "Problem report {}: {}", report.code, report.comment)
+            }
+            Self::InvalidMessage(msg) => write!(f, "Invalid message: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for AdminClientError {}
+
+impl From<ATMError> for AdminClientError {
+    fn from(e: ATMError) -> Self {
+        Self::Transport(e)
+    }
+}
+
+/// Builds an admin request from `from` to the registry. The message id doubles as
+/// the thread id, so the request is accepted under the strict thread id strategy.
+pub fn build_request(from: &str, to: &str, message_type: &str, body: Value) -> Message {
+    let id = new_message_id();
+    Message::build(id.clone(), message_type.to_string(), body)
+        .from(from.to_string())
+        .to(to.to_string())
+        .thid(id)
+        .created_time(Utc::now().timestamp() as u64)
+        .finalize()
+}
+
+/// Reads the registry's answer to a request of `request_type`: its typed response
+/// body, or the problem report it sent instead.
+pub fn parse_response<T: DeserializeOwned>(
+    request_type: &str,
+    response: Message,
+) -> Result<T, AdminClientError> {
+    if response.type_ == PROBLEM_REPORT_TYPE {
+        return Err(ProblemReport::from_body(&response.body)
+            .map(AdminClientError::ProblemReport)
+            .unwrap_or_else(|| {
+                AdminClientError::InvalidMessage(format!(
+                    "malformed problem report: {}",
+                    response.body
+                ))
+            }));
+    }
+    let expected_type = format!("{request_type}/response");
+    if response.type_ != expected_type {
+        return Err(AdminClientError::InvalidMessage(format!(
+            "expected {expected_type}, got {}",
+            response.type_
+        )));
+    }
+    serde_json::from_value(response.body)
+        .map_err(|e| AdminClientError::InvalidMessage(format!("{expected_type} body: {e}")))
+}
+
+/// Sends admin protocol requests to a trust registry and waits for the typed response.
+///
+/// Responses are picked up over the profile's live stream, so the profile must be
+/// added to `atm` with live streaming enabled.
+pub struct TrustRegistryAdminClient {
+    atm: Arc<ATM>,
+    profile: Arc<ATMProfile>,
+    protocols: Protocols,
+    trust_registry_did: String,
+    response_timeout: Duration,
+}
+
+impl TrustRegistryAdminClient {
+    pub fn new(atm: Arc<ATM>, profile: Arc<ATMProfile>, trust_registry_did: &str) -> Self {
+        Self {
+            atm,
+            profile,
+            protocols: Protocols::new(),
+            trust_registry_did: trust_registry_did.to_string(),
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+        }
+    }
+
+    /// How long to wait for each response. Defaults to 10 seconds.
+    pub fn with_response_timeout(mut self, response_timeout: Duration) -> Self {
+        self.response_timeout = response_timeout;
+        self
+    }
+
+    pub async fn create_record(
+        &self,
+        record: &RecordBody,
+    ) -> Result<RecordKeyBody, AdminClientError> {
+        self.send(CREATE_RECORD_MESSAGE_TYPE, record).await
+    }
+
+    pub async fn update_record(
+        &self,
+        record: &RecordBody,
+    ) -> Result<RecordKeyBody, AdminClientError> {
+        self.send(UPDATE_RECORD_MESSAGE_TYPE, record).await
+    }
+
+    pub async fn upsert_record(
+        &self,
+        record: &RecordBody,
+    ) -> Result<UpsertRecordResponse, AdminClientError> {
+        self.send(UPSERT_RECORD_MESSAGE_TYPE, record).await
+    }
+
+    pub async fn delete_record(
+        &self,
+        key: &RecordKeyBody,
+    ) -> Result<RecordKeyBody, AdminClientError> {
+        self.send(DELETE_RECORD_MESSAGE_TYPE, key).await
+    }
+
+    /// Deletes every key it can, reporting the keys without a record in `not_found`.
+    pub async fn bulk_delete_records(
+        &self,
+        keys: Vec<RecordKeyBody>,
+    ) -> Result<BulkDeleteRecordsResponse, AdminClientError> {
+        self.send(
+            BULK_DELETE_RECORDS_MESSAGE_TYPE,
+            &BulkDeleteRecordsBody { keys },
+        )
+        .await
+    }
+
+    pub async fn read_record(
+        &self,
+        key: &RecordKeyBody,
+    ) -> Result<RecordResponse, AdminClientError> {
+        self.send(READ_RECORD_MESSAGE_TYPE, key).await
+    }
+
+    pub async fn read_records(
+        &self,
+        keys: Vec<RecordKeyBody>,
+    ) -> Result<ReadRecordsResponse, AdminClientError> {
+        self.send(READ_RECORDS_MESSAGE_TYPE, &ReadRecordsBody { keys })
+            .await
+    }
+
+    pub async fn list_records(
+        &self,
+        filter: &ListRecordsRequest,
+    ) -> Result<ListRecordsResponse, AdminClientError> {
+        self.send(LIST_RECORDS_MESSAGE_TYPE, filter).await
+    }
+
+    pub async fn list_by_tag(&self, tag: &str) -> Result<ListRecordsResponse, AdminClientError> {
+        let request = ListByTagRequest {
+            tag: tag.to_string(),
+        };
+        self.send(LIST_BY_TAG_MESSAGE_TYPE, &request).await
+    }
+
+    pub async fn refresh_record(
+        &self,
+        refresh: &RefreshRecordBody,
+    ) -> Result<RefreshRecordResponse, AdminClientError> {
+        self.send(REFRESH_RECORD_MESSAGE_TYPE, refresh).await
+    }
+
+    /// Changes needed to make the registry hold exactly `records`, applied if `apply` is set.
+    pub async fn reconcile(
+        &self,
+        records: Vec<RecordBody>,
+        apply: bool,
+    ) -> Result<ReconcileResponse, AdminClientError> {
+        self.send(RECONCILE_MESSAGE_TYPE, &ReconcileBody { records, apply })
+            .await
+    }
+
+    /// Deletes every record. Only succeeds on a registry with truncate enabled.
+    pub async fn truncate_records(
+        &self,
+        confirmation_token: &str,
+    ) -> Result<TruncateRecordsResponse, AdminClientError> {
+        let body = TruncateRecordsBody {
+            confirmation_token: confirmation_token.to_string(),
+        };
+        self.send(TRUNCATE_RECORDS_MESSAGE_TYPE, &body).await
+    }
+
+    async fn send<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        message_type: &str,
+        request: &Req,
+    ) -> Result<Resp, AdminClientError> {
+        let body = serde_json::to_value(request)
+            .map_err(|e| AdminClientError::InvalidMessage(e.to_string()))?;
+        let did = &self.profile.inner.did;
+        let message = build_request(did, &self.trust_registry_did, message_type, body);
+        let message_id = message.id.clone();
+
+        let (packed, _) = self
+            .atm
+            .pack_encrypted(
+                &message,
+                &self.trust_registry_did,
+                Some(did),
+                Some(did),
+                None,
+            )
+            .await?;
+        let mediator_did = self.profile.to_tdk_profile().mediator.ok_or_else(|| {
+            AdminClientError::Transport(ATMError::ProfileError(
+                "No Mediator set for profile".into(),
+            ))
+        })?;
+        self.atm
+            .forward_and_send_message(
+                &self.profile,
+                false,
+                &packed,
+                Some(&message_id),
+                &mediator_did,
+                &self.trust_registry_did,
+                None,
+                None,
+                false,
+            )
+            .await?;
+
+        let (response, _) = self
+            .protocols
+            .message_pickup
+            .live_stream_get(
+                &self.atm,
+                &self.profile,
+                &message_id,
+                self.response_timeout,
+                true,
+            )
+            .await?
+            .ok_or(AdminClientError::Timeout(self.response_timeout))?;
+        parse_response(message_type, response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::didcomm::problem_report::codes;
+    use serde_json::json;
+
+    const ADMIN_DID: &str = "did:example:admin";
+    const REGISTRY_DID: &str = "did:example:registry";
+
+    fn key() -> RecordKeyBody {
+        RecordKeyBody {
+            entity_id: "did:example:entity".to_string(),
+            authority_id: "did:example:authority".to_string(),
+            action: "issue".to_string(),
+            resource: "credential".to_string(),
+        }
+    }
+
+    fn response(type_: &str, body: Value) -> Message {
+        Message::build("response-1".to_string(), type_.to_string(), body).finalize()
+    }
+
+    #[test]
+    fn test_build_request_threads_on_its_own_id() {
+        let record = RecordBody {
+            key: key(),
+            recognized: true,
+            authorized: false,
+            context: None,
+            record_type: "assertion".to_string(),
+            expires_at: None,
+            tags: vec![],
+            expected_version: None,
+        };
+        let body = serde_json::to_value(&record).unwrap();
+
+        let message = build_request(ADMIN_DID, REGISTRY_DID, CREATE_RECORD_MESSAGE_TYPE, body);
+
+        assert_eq!(message.type_, CREATE_RECORD_MESSAGE_TYPE);
+        assert_eq!(message.from.as_deref(), Some(ADMIN_DID));
+        assert_eq!(message.to, Some(vec![REGISTRY_DID.to_string()]));
+        assert_eq!(message.thid.as_deref(), Some(message.id.as_str()));
+        assert!(message.created_time.is_some());
+        assert_eq!(
+            message.body,
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential",
+                "recognized": true,
+                "authorized": false,
+                "record_type": "assertion"
+            })
+        );
+    }
+
+    #[test]
+    fn test_list_filter_omits_unset_fields() {
+        let filter = ListRecordsRequest {
+            recognized: None,
+            authorized: Some(true),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            json!({"authorized": true})
+        );
+    }
+
+    #[test]
+    fn test_parse_response_reads_typed_body() {
+        let body = json!({
+            "records": [{
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential",
+                "recognized": true,
+                "authorized": true,
+                "context": {"level": 1},
+                "version": 2,
+                "created_at": "2026-01-01T00:00:00Z"
+            }],
+            "count": 1
+        });
+
+        let list: ListRecordsResponse = parse_response(
+            LIST_RECORDS_MESSAGE_TYPE,
+            response(&format!("{LIST_RECORDS_MESSAGE_TYPE}/response"), body),
+        )
+        .unwrap();
+
+        assert_eq!(list.count, 1);
+        assert_eq!(list.records[0].key, key());
+        assert_eq!(list.records[0].version, 2);
+        assert_eq!(list.records[0].context, json!({"level": 1}));
+        assert!(list.records[0].updated_at.is_none());
+    }
+
+    #[test]
+    fn test_parse_response_surfaces_problem_reports() {
+        let body = ProblemReport::not_found("Record not found: did:example:entity").to_body();
+
+        let err = parse_response::<RecordResponse>(
+            READ_RECORD_MESSAGE_TYPE,
+            response(PROBLEM_REPORT_TYPE, body),
+        )
+        .unwrap_err();
+
+        match err {
+            AdminClientError::ProblemReport(report) => {
+                assert_eq!(report.code, codes::ERROR_NOT_FOUND);
+                assert_eq!(report.comment, "Record not found: did:example:entity");
+            }
+            other => panic!("expected a problem report, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_response_rejects_other_types_and_bodies() {
+        let wrong_type = parse_response::<RecordKeyBody>(
+            DELETE_RECORD_MESSAGE_TYPE,
+            response(
+                &format!("{CREATE_RECORD_MESSAGE_TYPE}/response"),
+                serde_json::to_value(key()).unwrap(),
+            ),
+        );
+        assert!(matches!(
+            wrong_type,
+            Err(AdminClientError::InvalidMessage(_))
+        ));
+
+        let wrong_body = parse_response::<UpsertRecordResponse>(
+            UPSERT_RECORD_MESSAGE_TYPE,
+            response(
+                &format!("{UPSERT_RECORD_MESSAGE_TYPE}/response"),
+                serde_json::to_value(key()).unwrap(),
+            ),
+        );
+        assert!(matches!(
+            wrong_body,
+            Err(AdminClientError::InvalidMessage(_))
+        ));
+    }
+}
@@ -0,0 +1,5 @@
+//! Clients for talking to a trust registry over DIDComm.
+
+pub mod admin;
+
+pub use admin::{AdminClientError, TrustRegistryAdminClient};
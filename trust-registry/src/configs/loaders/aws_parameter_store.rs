@@ -1,7 +1,9 @@
 use aws_config::{self, BehaviorVersion};
 use aws_sdk_ssm;
 
-pub async fn load(param_name: &str) -> Result<String, String> {
+use super::LoaderError;
+
+pub async fn load(param_name: &str) -> Result<String, LoaderError> {
     let config = aws_config::load_defaults(BehaviorVersion::v2025_08_07()).await;
     let client = aws_sdk_ssm::Client::new(&config);
 
@@ -11,11 +13,13 @@ pub async fn load(param_name: &str) -> Result<String, String> {
         .with_decryption(true)
         .send()
         .await
-        .map_err(|e| format!("Failed to get parameter '{param_name}': {e}"))?;
+        .map_err(|e| {
+            LoaderError::from_aws(&e, format!("Failed to get parameter '{param_name}': {e}"))
+        })?;
 
     result
         .parameter()
         .and_then(|p| p.value())
         .map(|v| v.to_string())
-        .ok_or_else(|| format!("Parameter '{param_name}' has no value"))
+        .ok_or_else(|| LoaderError::EmptyValue(format!("Parameter '{param_name}' has no value")))
 }
@@ -1,7 +1,9 @@
 use aws_config::{self, BehaviorVersion};
 use aws_sdk_secretsmanager;
 
-pub async fn load(secret_name: &str) -> Result<String, String> {
+use super::LoaderError;
+
+pub async fn load(secret_name: &str) -> Result<String, LoaderError> {
     let config = aws_config::load_defaults(BehaviorVersion::v2025_08_07()).await;
     let client = aws_sdk_secretsmanager::Client::new(&config);
 
@@ -10,10 +12,14 @@ pub async fn load(secret_name: &str) -> Result<String, String> {
         .secret_id(secret_name)
         .send()
         .await
-        .map_err(|e| format!("Failed to get secret '{secret_name}': {e}"))?;
+        .map_err(|e| {
+            LoaderError::from_aws(&e, format!("Failed to get secret '{secret_name}': {e}"))
+        })?;
 
     result
         .secret_string()
         .map(|s| s.to_string())
-        .ok_or_else(|| format!("Secret '{secret_name}' has no string value"))
+        .ok_or_else(|| {
+            LoaderError::EmptyValue(format!("Secret '{secret_name}' has no string value"))
+        })
 }
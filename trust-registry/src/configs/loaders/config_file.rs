@@ -0,0 +1,108 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Settings read from the TOML file named by `CONFIG_FILE`, keyed by the environment
+/// variable each one stands for. Keys are upper-cased and nested tables are joined with
+/// `_`, so `[redis] url = "..."` sets `REDIS_URL`. Arrays become comma-separated lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    values: HashMap<String, String>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &str) -> Result<Self, String> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("toml") => {}
+            _ => {
+                return Err(format!(
+                    "Invalid config file '{path}': only .toml is supported"
+                ));
+            }
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file '{path}': {e}"))?;
+        Self::parse(&content).map_err(|e| format!("Invalid config file '{path}': {e}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let document: DocumentMut = content.parse().map_err(|e| format!("{e}"))?;
+        let mut values = HashMap::new();
+        flatten_table(document.as_table(), "", &mut values)?;
+        Ok(Self { values })
+    }
+
+    pub fn get(&self, env_name: &str) -> Option<&str> {
+        self.values.get(env_name).map(String::as_str)
+    }
+}
+
+fn flatten_table(
+    table: &Table,
+    prefix: &str,
+    values: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    for (key, item) in table.iter() {
+        let name = format!("{prefix}{}", key.to_uppercase());
+        match item {
+            Item::Table(table) => flatten_table(table, &format!("{name}_"), values)?,
+            Item::Value(Value::InlineTable(table)) => {
+                flatten_table(&table.clone().into_table(), &format!("{name}_"), values)?
+            }
+            Item::Value(value) => {
+                values.insert(name, scalar(value, key)?);
+            }
+            Item::ArrayOfTables(_) => return Err(format!("'{key}' can't be an array of tables")),
+            Item::None => {}
+        }
+    }
+    Ok(())
+}
+
+fn scalar(value: &Value, key: &str) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.value().clone()),
+        Value::Integer(i) => Ok(i.value().to_string()),
+        Value::Float(f) => Ok(f.value().to_string()),
+        Value::Boolean(b) => Ok(b.value().to_string()),
+        Value::Datetime(d) => Ok(d.value().to_string()),
+        Value::Array(array) => Ok(array
+            .iter()
+            .map(|value| scalar(value, key))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(",")),
+        Value::InlineTable(_) => Err(format!("'{key}' can't list tables")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flattens_tables_and_arrays() {
+        let file = ConfigFile::parse(
+            r#"
+            listen_address = "0.0.0.0:3232"
+            cache_ttl_sec = 60
+            enable_didcomm = false
+            admin_dids = ["did:example:a", "did:example:b"]
+
+            [redis]
+            url = "redis://localhost:6379"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.get("LISTEN_ADDRESS"), Some("0.0.0.0:3232"));
+        assert_eq!(file.get("CACHE_TTL_SEC"), Some("60"));
+        assert_eq!(file.get("ENABLE_DIDCOMM"), Some("false"));
+        assert_eq!(file.get("ADMIN_DIDS"), Some("did:example:a,did:example:b"));
+        assert_eq!(file.get("REDIS_URL"), Some("redis://localhost:6379"));
+    }
+
+    #[test]
+    fn test_load_rejects_other_formats() {
+        assert!(ConfigFile::load("config.yaml").is_err());
+    }
+}
@@ -1,11 +1,50 @@
-use std::env;
+use std::{env, future::Future, sync::Arc};
+
+use super::config_file::ConfigFile;
+
+tokio::task_local! {
+    static ENV_PREFIX: String;
+    static CONFIG_FILE: Arc<ConfigFile>;
+}
+
+/// Runs `load` with every lookup trying `{prefix}{NAME}` before `NAME`, so several
+/// instances can be configured from one environment, e.g. `TR1_LISTEN_ADDRESS`.
+pub async fn with_env_prefix<F: Future>(prefix: impl Into<String>, load: F) -> F::Output {
+    ENV_PREFIX.scope(prefix.into(), load).await
+}
+
+/// Runs `load` with lookups falling back to the values of `file` for variables that
+/// aren't set, so environment variables override the config file.
+pub async fn with_config_file<F: Future>(file: ConfigFile, load: F) -> F::Output {
+    CONFIG_FILE.scope(Arc::new(file), load).await
+}
+
+fn prefixed_name(env_name: &str) -> Option<String> {
+    ENV_PREFIX
+        .try_with(|prefix| format!("{prefix}{env_name}"))
+        .ok()
+        .filter(|name| name != env_name)
+}
 
 pub fn required_env(env_name: &str) -> Result<String, String> {
-    env::var(env_name).map_err(|_| format!("Required environment variable '{env_name}' is not set"))
+    optional_env(env_name).ok_or_else(|| match prefixed_name(env_name) {
+        Some(prefixed) => {
+            format!("Required environment variable '{prefixed}' or '{env_name}' is not set")
+        }
+        None => format!("Required environment variable '{env_name}' is not set"),
+    })
 }
 
 pub fn optional_env(env_name: &str) -> Option<String> {
-    env::var(env_name).ok()
+    prefixed_name(env_name)
+        .and_then(|prefixed| env::var(prefixed).ok())
+        .or_else(|| env::var(env_name).ok())
+        .or_else(|| {
+            CONFIG_FILE
+                .try_with(|file| file.get(env_name).map(str::to_string))
+                .ok()
+                .flatten()
+        })
 }
 
 pub fn env_or(env_name: &str, default: &str) -> String {
@@ -76,4 +115,34 @@ mod tests {
         }
         assert_eq!(env_or("MISSING", "default"), "default");
     }
+
+    #[tokio::test]
+    async fn test_prefixed_env_falls_back_to_unprefixed() {
+        unsafe {
+            std::env::set_var("PREFIX_TEST_SHARED", "shared");
+            std::env::set_var("PFX1_PREFIX_TEST_OWN", "first");
+        }
+
+        let (own, shared) = with_env_prefix("PFX1_", async {
+            (
+                optional_env("PREFIX_TEST_OWN"),
+                optional_env("PREFIX_TEST_SHARED"),
+            )
+        })
+        .await;
+        let missing = with_env_prefix("PFX2_", async { required_env("PREFIX_TEST_OWN") }).await;
+
+        assert_eq!(own.as_deref(), Some("first"));
+        assert_eq!(shared.as_deref(), Some("shared"));
+        assert_eq!(
+            missing.unwrap_err(),
+            "Required environment variable 'PFX2_PREFIX_TEST_OWN' or 'PREFIX_TEST_OWN' is not set"
+        );
+        assert_eq!(optional_env("PREFIX_TEST_OWN"), None);
+
+        unsafe {
+            std::env::remove_var("PREFIX_TEST_SHARED");
+            std::env::remove_var("PFX1_PREFIX_TEST_OWN");
+        }
+    }
 }
@@ -1,7 +1,16 @@
-use std::fs;
+use std::{fs, io::ErrorKind};
 
-pub fn load(path: &str) -> Result<String, String> {
-    fs::read_to_string(path).map_err(|e| format!("Failed to read file '{path}': {e}"))
+use super::LoaderError;
+
+pub fn load(path: &str) -> Result<String, LoaderError> {
+    fs::read_to_string(path).map_err(|e| {
+        let message = format!("Failed to read file '{path}': {e}");
+        match e.kind() {
+            ErrorKind::NotFound => LoaderError::NotFound(message),
+            ErrorKind::PermissionDenied => LoaderError::AccessDenied(message),
+            _ => LoaderError::Other(message),
+        }
+    })
 }
 
 #[cfg(test)]
@@ -23,7 +32,12 @@ mod tests {
     fn test_load_file_not_found() {
         let result = load("/nonexistent/file.txt");
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Failed to read file"));
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Failed to read file")
+        );
     }
 
     #[test]
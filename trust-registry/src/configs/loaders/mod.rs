@@ -1,10 +1,98 @@
 pub mod aws_parameter_store;
 pub mod aws_secrets;
+pub mod config_file;
 pub mod environment;
 pub mod file;
 pub mod string;
+pub mod template;
 
-pub async fn load(input: &str) -> Result<String, String> {
+use std::fmt;
+
+use aws_sdk_ssm::error::{ProvideErrorMetadata, SdkError};
+
+/// Why a config value couldn't be loaded. Each variant carries the full message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoaderError {
+    /// The file, secret or parameter doesn't exist.
+    NotFound(String),
+    AccessDenied(String),
+    /// Throttling, timeouts and connection failures, worth retrying.
+    Transient(String),
+    /// The secret or parameter exists but has no value.
+    EmptyValue(String),
+    /// A `${...}` reference in the content is malformed or can't be resolved.
+    InvalidReference(String),
+    Other(String),
+}
+
+impl LoaderError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(m)
+            | Self::AccessDenied(m)
+            | Self::Transient(m)
+            | Self::EmptyValue(m)
+            | Self::InvalidReference(m)
+            | Self::Other(m) => m,
+        }
+    }
+
+    /// Same failure class with a reworded message.
+    pub(crate) fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::NotFound(m) => Self::NotFound(f(m)),
+            Self::AccessDenied(m) => Self::AccessDenied(f(m)),
+            Self::Transient(m) => Self::Transient(f(m)),
+            Self::EmptyValue(m) => Self::EmptyValue(f(m)),
+            Self::InvalidReference(m) => Self::InvalidReference(f(m)),
+            Self::Other(m) => Self::Other(f(m)),
+        }
+    }
+
+    /// Classifies an AWS SDK failure by its error code.
+    pub(crate) fn from_aws<E: ProvideErrorMetadata, R>(
+        error: &SdkError<E, R>,
+        message: String,
+    ) -> Self {
+        if matches!(
+            error,
+            SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_)
+        ) {
+            return Self::Transient(message);
+        }
+        match error.code() {
+            Some("ResourceNotFoundException" | "ParameterNotFound") => Self::NotFound(message),
+            Some("AccessDeniedException" | "AccessDenied") => Self::AccessDenied(message),
+            Some(
+                "ThrottlingException"
+                | "InternalServiceError"
+                | "InternalServerError"
+                | "InternalFailure"
+                | "ServiceUnavailable",
+            ) => Self::Transient(message),
+            _ => Self::Other(message),
+        }
+    }
+}
+
+impl fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for LoaderError {}
+
+/// URI schemes [`load`] resolves.
+pub const URI_SCHEMES: [&str; 4] = ["string", "file", "aws_secrets", "aws_parameter_store"];
+
+/// Loads a config value from a `string://`, `file://`, `aws_secrets://` or
+/// `aws_parameter_store://` URI. Anything else is returned as a literal string.
+pub async fn load(input: &str) -> Result<String, LoaderError> {
     if let Some(content) = input.strip_prefix("string://") {
         string::load(content)
     } else if let Some(path) = input.strip_prefix("file://") {
@@ -47,6 +135,18 @@ mod tests {
         assert_eq!(result, "file content");
     }
 
+    #[tokio::test]
+    async fn test_load_missing_file_is_not_found() {
+        let error = load("file:///nonexistent/config.json").await.unwrap_err();
+
+        assert!(matches!(error, LoaderError::NotFound(_)));
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            "Failed to read file '/nonexistent/config.json': No such file or directory (os error 2)"
+        );
+    }
+
     #[tokio::test]
     async fn test_load_invalid_uri_scheme() {
         let result = load("invalid://test").await;
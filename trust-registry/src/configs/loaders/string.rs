@@ -1,4 +1,6 @@
-pub fn load(content: &str) -> Result<String, String> {
+use super::LoaderError;
+
+pub fn load(content: &str) -> Result<String, LoaderError> {
     Ok(content.to_string())
 }
 
@@ -0,0 +1,169 @@
+use super::{LoaderError, URI_SCHEMES, environment::optional_env, load};
+
+/// Expands `${...}` references inside loaded config content.
+///
+/// A reference containing a URI scheme (e.g. `${aws_secrets://name}` or
+/// `${file://path}`) is resolved through [`load`], and one with a scheme [`load`]
+/// doesn't know is an error; anything else is treated as an environment variable name. Substituted values are inserted verbatim and
+/// are not expanded again.
+pub async fn expand(content: &str) -> Result<String, LoaderError> {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| {
+            LoaderError::InvalidReference(format!("Unterminated reference '${{{after}' in config"))
+        })?;
+        let reference = after[..end].trim();
+        if reference.is_empty() {
+            return Err(LoaderError::InvalidReference(
+                "Empty reference '${}' in config".to_string(),
+            ));
+        }
+        output.push_str(&resolve(reference).await?);
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+async fn resolve(reference: &str) -> Result<String, LoaderError> {
+    if let Some((scheme, _)) = reference.split_once("://") {
+        if !URI_SCHEMES.contains(&scheme) {
+            return Err(LoaderError::InvalidReference(format!(
+                "Unknown scheme '{scheme}' in reference '${{{reference}}}'"
+            )));
+        }
+        load(reference).await.map_err(|e| {
+            e.map_message(|m| format!("Failed to resolve reference '${{{reference}}}': {m}"))
+        })
+    } else {
+        optional_env(reference).ok_or_else(|| {
+            LoaderError::InvalidReference(format!(
+                "Unresolved reference '${{{reference}}}': environment variable is not set"
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_expand_without_references() {
+        let content = r#"{"did":"did:example:123"}"#;
+        assert_eq!(expand(content).await.unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_expand_env_reference() {
+        unsafe {
+            std::env::set_var("TEMPLATE_TEST_DID", "did:example:from-env");
+        }
+
+        let result = expand(r#"{"did":"${TEMPLATE_TEST_DID}"}"#).await.unwrap();
+        assert_eq!(result, r#"{"did":"did:example:from-env"}"#);
+
+        unsafe {
+            std::env::remove_var("TEMPLATE_TEST_DID");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_nested_uri_reference() {
+        let secrets = NamedTempFile::new().unwrap();
+        fs::write(&secrets, r#"[{"id":"did:example:123#key-1"}]"#).unwrap();
+
+        let content = format!(
+            r#"{{"did":"did:example:123","secrets":${{file://{}}}}}"#,
+            secrets.path().to_str().unwrap()
+        );
+        let result = expand(&content).await.unwrap();
+        assert_eq!(
+            result,
+            r#"{"did":"did:example:123","secrets":[{"id":"did:example:123#key-1"}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expand_env_and_uri_references_together() {
+        unsafe {
+            std::env::set_var("TEMPLATE_TEST_ALIAS", "Trust Registry");
+        }
+
+        let result =
+            expand(r#"{"alias":"${TEMPLATE_TEST_ALIAS}","did":"${string://did:example:1}"}"#)
+                .await
+                .unwrap();
+        assert_eq!(
+            result,
+            r#"{"alias":"Trust Registry","did":"did:example:1"}"#
+        );
+
+        unsafe {
+            std::env::remove_var("TEMPLATE_TEST_ALIAS");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_does_not_reexpand_substituted_values() {
+        unsafe {
+            std::env::set_var("TEMPLATE_TEST_LITERAL", "${NOT_EXPANDED}");
+        }
+
+        let result = expand("${TEMPLATE_TEST_LITERAL}").await.unwrap();
+        assert_eq!(result, "${NOT_EXPANDED}");
+
+        unsafe {
+            std::env::remove_var("TEMPLATE_TEST_LITERAL");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_unresolved_env_reference_fails() {
+        unsafe {
+            std::env::remove_var("TEMPLATE_TEST_MISSING");
+        }
+
+        let err = expand(r#"{"did":"${TEMPLATE_TEST_MISSING}"}"#)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("TEMPLATE_TEST_MISSING"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_unresolved_secret_reference_fails() {
+        let err = expand(r#"{"secrets":${file:///nonexistent/secrets.json}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LoaderError::NotFound(_)));
+        assert!(err.to_string().contains("file:///nonexistent/secrets.json"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_unknown_scheme_fails() {
+        let err = expand(r#"{"secrets":${vault://prod/keys}}"#)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, LoaderError::InvalidReference(_)));
+        assert!(err.to_string().contains("${vault://prod/keys}"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_unterminated_reference_fails() {
+        let err = expand(r#"{"did":"${TEMPLATE_TEST_DID"#).await.unwrap_err();
+        assert!(err.to_string().contains("Unterminated"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_empty_reference_fails() {
+        assert!(expand("${}").await.is_err());
+    }
+}
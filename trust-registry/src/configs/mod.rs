@@ -1,28 +1,55 @@
 pub mod didcomm;
+pub mod effective;
 pub mod loaders;
 pub mod server;
 pub mod storage;
+pub mod validation;
 
-pub use didcomm::{AdminConfig, AuditConfig, AuditLogFormat, DidcommConfig, ProfileConfig};
-pub use server::ServerConfig;
+pub use didcomm::{
+    AdminConfig, AuditConfig, AuditLogFormat, DEFAULT_REGISTRY_NAME, DeadLetterConfig,
+    DidcommConfig, ProfileConfig, ThreadIdStrategy, TruncateConfig, WebhookConfig,
+};
+pub use server::{QueryAllowlist, ServerConfig};
 pub use storage::{
-    DynamoDbStorageConfig, FileStorageConfig, RedisStorageConfig, TrustStorageBackend,
+    DuplicateKeyPolicy, DynamoDbStorageConfig, FileStorageConfig, RedisStorageConfig,
+    TrustStorageBackend,
 };
+pub use validation::ConfigValidationError;
 
-use crate::configs::storage::StorageConfig;
+use crate::configs::{loaders::config_file::ConfigFile, storage::StorageConfig};
 
 #[async_trait::async_trait]
 pub trait Configs: Sized {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TrsutRegistryConfig {
     pub server_config: ServerConfig,
     pub storage_config: StorageConfig,
     pub didcomm_config: DidcommConfig,
 }
 
+impl TrsutRegistryConfig {
+    /// Loads the config preferring `{prefix}`-prefixed environment variables, see
+    /// [`loaders::environment::with_env_prefix`]. Settings that no variable sets are taken
+    /// from the TOML file at `CONFIG_FILE`, when one is given.
+    pub async fn load_with_prefix(
+        prefix: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        loaders::environment::with_env_prefix(prefix, async {
+            match loaders::environment::optional_env("CONFIG_FILE") {
+                Some(path) => {
+                    let file = ConfigFile::load(&path)?;
+                    loaders::environment::with_config_file(file, Self::load()).await
+                }
+                None => Self::load().await,
+            }
+        })
+        .await
+    }
+}
+
 #[async_trait::async_trait]
 impl Configs for TrsutRegistryConfig {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
@@ -33,3 +60,89 @@ impl Configs for TrsutRegistryConfig {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_two_prefixed_configs_from_one_environment() {
+        let vars = [
+            ("MULTI1_LISTEN_ADDRESS", "127.0.0.1:4001"),
+            ("MULTI1_ENABLE_DIDCOMM", "false"),
+            ("MULTI1_TR_STORAGE_BACKEND", "csv"),
+            ("MULTI1_FILE_STORAGE_PATH", "first.csv"),
+            ("MULTI2_LISTEN_ADDRESS", "127.0.0.1:4002"),
+            ("MULTI2_ENABLE_DIDCOMM", "false"),
+            ("MULTI2_TR_STORAGE_BACKEND", "json"),
+        ];
+        for (name, value) in vars {
+            unsafe { std::env::set_var(name, value) };
+        }
+
+        let first = TrsutRegistryConfig::load_with_prefix("MULTI1_").await;
+        let second = TrsutRegistryConfig::load_with_prefix("MULTI2_").await;
+        for (name, _) in vars {
+            unsafe { std::env::remove_var(name) };
+        }
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        assert_eq!(first.server_config.listen_address, "127.0.0.1:4001");
+        assert_eq!(second.server_config.listen_address, "127.0.0.1:4002");
+        assert_eq!(
+            first.storage_config.storage_backend,
+            TrustStorageBackend::Csv
+        );
+        assert_eq!(first.storage_config.file_storage_config.path, "first.csv");
+        assert_eq!(
+            second.storage_config.storage_backend,
+            TrustStorageBackend::Json
+        );
+        assert_eq!(
+            second.storage_config.file_storage_config.path,
+            "trust_records.json"
+        );
+        assert!(!first.didcomm_config.is_enabled && !second.didcomm_config.is_enabled);
+    }
+
+    #[tokio::test]
+    async fn test_config_file_values_are_overridden_by_env() {
+        let file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::fs::write(
+            &file,
+            r#"
+            listen_address = "127.0.0.1:5001"
+            enable_didcomm = false
+            tr_storage_backend = "json"
+
+            [file_storage]
+            path = "from_file.json"
+            "#,
+        )
+        .unwrap();
+        let vars = [
+            ("CFGFILE_CONFIG_FILE", file.path().to_str().unwrap()),
+            ("CFGFILE_LISTEN_ADDRESS", "127.0.0.1:5002"),
+        ];
+        for (name, value) in vars {
+            unsafe { std::env::set_var(name, value) };
+        }
+
+        let config = TrsutRegistryConfig::load_with_prefix("CFGFILE_").await;
+        for (name, _) in vars {
+            unsafe { std::env::remove_var(name) };
+        }
+        let config = config.unwrap();
+
+        assert_eq!(config.server_config.listen_address, "127.0.0.1:5002");
+        assert_eq!(
+            config.storage_config.storage_backend,
+            TrustStorageBackend::Json
+        );
+        assert_eq!(
+            config.storage_config.file_storage_config.path,
+            "from_file.json"
+        );
+        assert!(!config.didcomm_config.is_enabled);
+    }
+}
@@ -1,18 +1,231 @@
+use std::{collections::HashSet, fmt};
+
 use serde::{Deserialize, Serialize};
 
+use crate::domain::{Context, ContextMergeMode};
+
 use super::{Configs, loaders::environment::*};
 
 const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:3232";
+const DEFAULT_DECISION_CACHE_TTL_SEC: u64 = 0;
+const DEFAULT_DECISION_CACHE_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_MAX_FAN_OUT: usize = 100;
+const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+const DEFAULT_DID_CACHE_TTL_SEC: u64 = 300;
+const DEFAULT_DID_CACHE_MAX_ENTRIES: usize = 1_000;
+const DEFAULT_STATS_CACHE_TTL_SEC: u64 = 60;
+const DEFAULT_READINESS_CACHE_TTL_SEC: u64 = 10;
+const DEFAULT_CACHE_TTL_SEC: u64 = 30;
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_HISTORY_COMPACTION_INTERVAL_SEC: u64 = 300;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProfileConfig {
     pub did: String,
 }
 
-#[derive(Debug, Clone)]
+/// Actions and resources TRQP queries may ask about. `None` allows any value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryAllowlist {
+    pub actions: Option<HashSet<String>>,
+    pub resources: Option<HashSet<String>>,
+}
+
+impl QueryAllowlist {
+    /// Explains why a query for `action` on `resource` is refused.
+    pub fn check(&self, action: &str, resource: &str) -> Result<(), String> {
+        if let Some(actions) = &self.actions
+            && !actions.contains(action)
+        {
+            return Err(format!("action '{action}' is not allowed"));
+        }
+        if let Some(resources) = &self.resources
+            && !resources.contains(resource)
+        {
+            return Err(format!("resource '{resource}' is not allowed"));
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated values of `name`, `None` when it is unset or lists nothing.
+fn optional_set(name: &str) -> Option<HashSet<String>> {
+    let values: HashSet<String> = optional_env(name)?
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    (!values.is_empty()).then_some(values)
+}
+
+#[derive(Clone)]
 pub struct ServerConfig {
     pub listen_address: String,
     pub cors_allowed_origins: Vec<String>,
+    pub require_records: bool,
+    /// How long a readiness report is served before the records are counted again.
+    pub readiness_cache_ttl_sec: u64,
+    /// Decision cache TTL in seconds. `0` disables the cache.
+    pub decision_cache_ttl_sec: u64,
+    pub decision_cache_max_entries: usize,
+    /// Memoizes record lookups in front of the storage backend.
+    pub cache_enabled: bool,
+    pub cache_ttl_sec: u64,
+    pub cache_max_entries: usize,
+    /// Maximum number of backend lookups a single request may trigger.
+    pub max_fan_out: usize,
+    /// Requests with larger bodies are rejected with `413`.
+    pub max_body_bytes: usize,
+    /// JSON pointers of context fields returned by TRQP queries. Empty returns the full context.
+    pub trqp_context_allowlist: Vec<String>,
+    /// Merged under every record's context in TRQP responses. `None` leaves contexts as stored.
+    pub trqp_default_context: Option<Context>,
+    /// How request contexts, defaults and policy annotations are merged into record contexts.
+    pub context_merge_mode: ContextMergeMode,
+    /// Adds a `_provenance` map to TRQP response contexts naming where each key came from.
+    pub context_provenance: bool,
+    /// Queries outside `ALLOWED_ACTIONS`/`ALLOWED_RESOURCES` are rejected before storage is read.
+    pub trqp_query_allowlist: QueryAllowlist,
+    /// Gzip/deflate responses for clients sending `Accept-Encoding`.
+    pub enable_compression: bool,
+    /// Starts the registry with admin mutations rejected.
+    pub read_only: bool,
+    /// How long resolved DID documents are reused before resolving again.
+    pub did_cache_ttl_sec: u64,
+    pub did_cache_max_entries: usize,
+    /// Emits a `trqp_query` tracing event for every HTTP TRQP decision.
+    pub log_trqp_queries: bool,
+    /// Logs one in this many successful queries. Not-found and failed queries are always logged.
+    pub log_trqp_sample_rate: u64,
+    /// Serves `GET /stats`.
+    pub enable_stats: bool,
+    /// How long computed statistics are served before the records are scanned again.
+    pub stats_cache_ttl_sec: u64,
+    /// Lets `*` actions and resources of stored records match any queried value.
+    pub enable_wildcards: bool,
+    /// Stores and queries records by canonical entity and authority DIDs, so equivalent
+    /// spellings such as `did:web` hosts in different case match the same record.
+    pub normalize_dids: bool,
+    /// Keeps every version written since startup, so `as_of` queries see past states.
+    pub enable_record_history: bool,
+    /// Versions of each record kept by history compaction. 0 keeps every version.
+    pub history_max_versions: usize,
+    /// Versions in effect within this many seconds are kept by compaction too.
+    pub history_retention_sec: u64,
+    pub history_compaction_interval_sec: u64,
+    /// Base64 AES-256 key contexts are stored encrypted with. `None` stores them in plaintext.
+    pub context_encryption_key: Option<String>,
+    /// PEM certificate chain and private key. When both are set the server speaks HTTPS.
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    /// Serves `GET /export` to callers presenting this key as a `Bearer` token.
+    pub export_api_key: Option<String>,
+    /// Serves `/admin/records` to callers presenting this key as a `Bearer` token.
+    pub admin_api_key: Option<String>,
+    /// Adds a detached JWS of the body to TRQP responses, signed with the profile's Ed25519 key.
+    pub sign_responses: bool,
+    /// Logs the effective configuration, secrets redacted, at startup.
+    pub log_effective_config: bool,
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("listen_address", &self.listen_address)
+            .field("cors_allowed_origins", &self.cors_allowed_origins)
+            .field("require_records", &self.require_records)
+            .field("readiness_cache_ttl_sec", &self.readiness_cache_ttl_sec)
+            .field("decision_cache_ttl_sec", &self.decision_cache_ttl_sec)
+            .field(
+                "decision_cache_max_entries",
+                &self.decision_cache_max_entries,
+            )
+            .field("cache_enabled", &self.cache_enabled)
+            .field("cache_ttl_sec", &self.cache_ttl_sec)
+            .field("cache_max_entries", &self.cache_max_entries)
+            .field("max_fan_out", &self.max_fan_out)
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("trqp_context_allowlist", &self.trqp_context_allowlist)
+            .field("trqp_default_context", &self.trqp_default_context)
+            .field("context_merge_mode", &self.context_merge_mode)
+            .field("context_provenance", &self.context_provenance)
+            .field("trqp_query_allowlist", &self.trqp_query_allowlist)
+            .field("enable_compression", &self.enable_compression)
+            .field("read_only", &self.read_only)
+            .field("did_cache_ttl_sec", &self.did_cache_ttl_sec)
+            .field("did_cache_max_entries", &self.did_cache_max_entries)
+            .field("log_trqp_queries", &self.log_trqp_queries)
+            .field("log_trqp_sample_rate", &self.log_trqp_sample_rate)
+            .field("enable_stats", &self.enable_stats)
+            .field("stats_cache_ttl_sec", &self.stats_cache_ttl_sec)
+            .field("enable_wildcards", &self.enable_wildcards)
+            .field("normalize_dids", &self.normalize_dids)
+            .field("enable_record_history", &self.enable_record_history)
+            .field("history_max_versions", &self.history_max_versions)
+            .field("history_retention_sec", &self.history_retention_sec)
+            .field(
+                "history_compaction_interval_sec",
+                &self.history_compaction_interval_sec,
+            )
+            .field(
+                "context_encryption_key",
+                &self.context_encryption_key.as_ref().map(|_| "***"),
+            )
+            .field("tls_cert_path", &self.tls_cert_path)
+            .field("tls_key_path", &self.tls_key_path)
+            .field(
+                "export_api_key",
+                &self.export_api_key.as_ref().map(|_| "***"),
+            )
+            .field("admin_api_key", &self.admin_api_key.as_ref().map(|_| "***"))
+            .field("sign_responses", &self.sign_responses)
+            .field("log_effective_config", &self.log_effective_config)
+            .finish()
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: DEFAULT_LISTEN_ADDRESS.to_string(),
+            cors_allowed_origins: vec![],
+            require_records: false,
+            readiness_cache_ttl_sec: DEFAULT_READINESS_CACHE_TTL_SEC,
+            decision_cache_ttl_sec: DEFAULT_DECISION_CACHE_TTL_SEC,
+            decision_cache_max_entries: DEFAULT_DECISION_CACHE_MAX_ENTRIES,
+            cache_enabled: false,
+            cache_ttl_sec: DEFAULT_CACHE_TTL_SEC,
+            cache_max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            max_fan_out: DEFAULT_MAX_FAN_OUT,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            trqp_context_allowlist: vec![],
+            trqp_default_context: None,
+            context_merge_mode: ContextMergeMode::default(),
+            context_provenance: false,
+            trqp_query_allowlist: QueryAllowlist::default(),
+            enable_compression: false,
+            read_only: false,
+            did_cache_ttl_sec: DEFAULT_DID_CACHE_TTL_SEC,
+            did_cache_max_entries: DEFAULT_DID_CACHE_MAX_ENTRIES,
+            log_trqp_queries: false,
+            log_trqp_sample_rate: 1,
+            enable_stats: false,
+            stats_cache_ttl_sec: DEFAULT_STATS_CACHE_TTL_SEC,
+            enable_wildcards: false,
+            normalize_dids: false,
+            enable_record_history: false,
+            history_max_versions: 0,
+            history_retention_sec: 0,
+            history_compaction_interval_sec: DEFAULT_HISTORY_COMPACTION_INTERVAL_SEC,
+            context_encryption_key: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            export_api_key: None,
+            admin_api_key: None,
+            sign_responses: false,
+            log_effective_config: true,
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -27,9 +240,201 @@ impl Configs for ServerConfig {
             .filter(|s| !s.is_empty())
             .collect();
 
+        let require_records = env_or("REQUIRE_RECORDS", "false") == "true";
+        let readiness_cache_ttl_sec = env_or(
+            "READINESS_CACHE_TTL_SEC",
+            &DEFAULT_READINESS_CACHE_TTL_SEC.to_string(),
+        )
+        .parse::<u64>()?;
+
+        let decision_cache_ttl_sec = env_or(
+            "DECISION_CACHE_TTL_SEC",
+            &DEFAULT_DECISION_CACHE_TTL_SEC.to_string(),
+        )
+        .parse::<u64>()?;
+        let decision_cache_max_entries = env_or(
+            "DECISION_CACHE_MAX_ENTRIES",
+            &DEFAULT_DECISION_CACHE_MAX_ENTRIES.to_string(),
+        )
+        .parse::<usize>()?;
+        let max_fan_out =
+            env_or("MAX_FAN_OUT", &DEFAULT_MAX_FAN_OUT.to_string()).parse::<usize>()?;
+        let max_body_bytes =
+            env_or("MAX_BODY_BYTES", &DEFAULT_MAX_BODY_BYTES.to_string()).parse::<usize>()?;
+
+        let trqp_context_allowlist: Vec<String> = optional_env("TRQP_CONTEXT_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if let Some(pointer) = trqp_context_allowlist.iter().find(|p| !p.starts_with('/')) {
+            return Err(format!(
+                "TRQP_CONTEXT_ALLOWLIST entries must be JSON pointers starting with '/', got '{pointer}'"
+            )
+            .into());
+        }
+
+        let trqp_default_context =
+            match optional_env("TRQP_DEFAULT_CONTEXT").filter(|s| !s.trim().is_empty()) {
+                Some(raw) => match serde_json::from_str::<serde_json::Value>(&raw) {
+                    Ok(value) if value.as_object().is_some_and(|o| o.is_empty()) => None,
+                    Ok(value @ serde_json::Value::Object(_)) => Some(Context::new(value)),
+                    _ => return Err("TRQP_DEFAULT_CONTEXT must be a JSON object".into()),
+                },
+                None => None,
+            };
+
+        let context_merge_mode =
+            env_or("CONTEXT_MERGE_MODE", "deep").parse::<ContextMergeMode>()?;
+        let context_provenance = env_or("CONTEXT_PROVENANCE", "false") == "true";
+
+        let enable_compression = env_or("ENABLE_COMPRESSION", "false") == "true";
+        let read_only = env_or("READ_ONLY", "false") == "true";
+        let did_cache_ttl_sec =
+            env_or("DID_CACHE_TTL_SEC", &DEFAULT_DID_CACHE_TTL_SEC.to_string()).parse::<u64>()?;
+        let did_cache_max_entries = env_or(
+            "DID_CACHE_MAX_ENTRIES",
+            &DEFAULT_DID_CACHE_MAX_ENTRIES.to_string(),
+        )
+        .parse::<usize>()?;
+        let log_trqp_queries = env_or("LOG_TRQP_QUERIES", "false") == "true";
+        let log_trqp_sample_rate = env_or("LOG_TRQP_SAMPLE_RATE", "1").parse::<u64>()?;
+        if log_trqp_sample_rate == 0 {
+            return Err("LOG_TRQP_SAMPLE_RATE must be at least 1".into());
+        }
+        let enable_stats = env_or("ENABLE_STATS", "false") == "true";
+        let stats_cache_ttl_sec = env_or(
+            "STATS_CACHE_TTL_SEC",
+            &DEFAULT_STATS_CACHE_TTL_SEC.to_string(),
+        )
+        .parse::<u64>()?;
+
+        let cache_enabled = env_or("CACHE_ENABLED", "false") == "true";
+        let cache_ttl_sec =
+            env_or("CACHE_TTL_SEC", &DEFAULT_CACHE_TTL_SEC.to_string()).parse::<u64>()?;
+        let cache_max_entries =
+            env_or("CACHE_MAX_ENTRIES", &DEFAULT_CACHE_MAX_ENTRIES.to_string()).parse::<usize>()?;
+
+        let enable_wildcards = env_or("ENABLE_WILDCARDS", "false") == "true";
+        let normalize_dids = env_or("NORMALIZE_DIDS", "false") == "true";
+        let enable_record_history = env_or("ENABLE_RECORD_HISTORY", "false") == "true";
+        let history_max_versions = env_or("HISTORY_MAX_VERSIONS", "0").parse::<usize>()?;
+        let history_retention_sec = env_or("HISTORY_RETENTION_SEC", "0").parse::<u64>()?;
+        let history_compaction_interval_sec = env_or(
+            "HISTORY_COMPACTION_INTERVAL_SEC",
+            &DEFAULT_HISTORY_COMPACTION_INTERVAL_SEC.to_string(),
+        )
+        .parse::<u64>()?;
+        if history_compaction_interval_sec == 0 {
+            return Err("HISTORY_COMPACTION_INTERVAL_SEC must be at least 1".into());
+        }
+        let context_encryption_key =
+            optional_env("CONTEXT_ENCRYPTION_KEY").filter(|s| !s.is_empty());
+
+        let tls_cert_path = optional_env("TLS_CERT_PATH").filter(|s| !s.is_empty());
+        let tls_key_path = optional_env("TLS_KEY_PATH").filter(|s| !s.is_empty());
+        if tls_cert_path.is_some() != tls_key_path.is_some() {
+            return Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".into());
+        }
+
+        let export_api_key = optional_env("EXPORT_API_KEY").filter(|s| !s.is_empty());
+        let admin_api_key = optional_env("ADMIN_API_KEY").filter(|s| !s.is_empty());
+        let sign_responses = env_or("SIGN_RESPONSES", "false") == "true";
+        let log_effective_config = env_or("LOG_EFFECTIVE_CONFIG", "true") == "true";
+
+        let trqp_query_allowlist = QueryAllowlist {
+            actions: optional_set("ALLOWED_ACTIONS"),
+            resources: optional_set("ALLOWED_RESOURCES"),
+        };
+
         Ok(ServerConfig {
             listen_address,
             cors_allowed_origins,
+            require_records,
+            readiness_cache_ttl_sec,
+            decision_cache_ttl_sec,
+            decision_cache_max_entries,
+            cache_enabled,
+            cache_ttl_sec,
+            cache_max_entries,
+            max_fan_out,
+            max_body_bytes,
+            trqp_context_allowlist,
+            trqp_default_context,
+            context_merge_mode,
+            context_provenance,
+            trqp_query_allowlist,
+            enable_compression,
+            read_only,
+            did_cache_ttl_sec,
+            did_cache_max_entries,
+            log_trqp_queries,
+            log_trqp_sample_rate,
+            enable_stats,
+            stats_cache_ttl_sec,
+            enable_wildcards,
+            normalize_dids,
+            enable_record_history,
+            history_max_versions,
+            history_retention_sec,
+            history_compaction_interval_sec,
+            context_encryption_key,
+            tls_cert_path,
+            tls_key_path,
+            export_api_key,
+            admin_api_key,
+            sign_responses,
+            log_effective_config,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(values: &[&str]) -> Option<HashSet<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    #[test]
+    fn test_query_allowlist_allows_everything_by_default() {
+        assert!(QueryAllowlist::default().check("any", "thing").is_ok());
+    }
+
+    #[test]
+    fn test_query_allowlist_checks_actions_and_resources() {
+        let allowlist = QueryAllowlist {
+            actions: set(&["issue"]),
+            resources: set(&["credential"]),
+        };
+
+        assert!(allowlist.check("issue", "credential").is_ok());
+        assert_eq!(
+            allowlist.check("revoke", "credential"),
+            Err("action 'revoke' is not allowed".to_string())
+        );
+        assert_eq!(
+            allowlist.check("issue", "license"),
+            Err("resource 'license' is not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let config = ServerConfig {
+            context_encryption_key: Some("encryption-secret".to_string()),
+            export_api_key: Some("export-secret".to_string()),
+            admin_api_key: Some("admin-secret".to_string()),
+            ..Default::default()
+        };
+
+        let debug = format!("{config:?}");
+
+        assert!(!debug.contains("encryption-secret"));
+        assert!(!debug.contains("export-secret"));
+        assert!(!debug.contains("admin-secret"));
+        assert!(debug.contains("context_encryption_key: Some(\"***\")"));
+    }
+}
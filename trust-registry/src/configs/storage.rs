@@ -1,21 +1,61 @@
 use super::{Configs, loaders::environment::*};
 
 const DEFAULT_TRUST_REGISTRY_FILE_PATH: &str = "trust_records.csv";
+const DEFAULT_TRUST_REGISTRY_JSON_FILE_PATH: &str = "trust_records.json";
 const DEFAULT_TRUST_REGISTRY_UPDATE_INTERVAL_SEC: u64 = 60;
+const DEFAULT_CSV_WRITE_BATCH_MS: u64 = 0;
+const DEFAULT_CSV_WRITE_BATCH_MAX: usize = 100;
 const DEFAULT_REGION: &str = "ap-southeast-1";
+const DEFAULT_REDIS_WAIT_TIMEOUT_MS: u64 = 1_000;
+const DEFAULT_STORAGE_OP_TIMEOUT_MS: u64 = 30_000;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TrustStorageBackend {
+    #[default]
     Csv,
+    /// JSON array file, see `JsonFileStorage`. Shares the `FILE_STORAGE_*` settings with CSV.
+    Json,
     DynamoDb,
     Redis,
 }
 
+/// Which row wins when a CSV load sees the same record key twice, see `ON_DUPLICATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// The later row replaces the earlier one.
+    #[default]
+    LastWins,
+    /// The later row is ignored.
+    FirstWins,
+    /// The load fails.
+    Error,
+}
+
+impl std::str::FromStr for DuplicateKeyPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "last-wins" => Ok(Self::LastWins),
+            "first-wins" => Ok(Self::FirstWins),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("Invalid duplicate key policy: {s}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct FileStorageConfig {
     pub is_enabled: bool,
     pub path: String,
     pub update_interval_sec: u64,
+    /// Fail the CSV load on the first malformed row instead of skipping it.
+    pub csv_strict: bool,
+    pub csv_on_duplicate: DuplicateKeyPolicy,
+    /// Window in which CSV mutations are coalesced into one file write. `0` writes on every mutation.
+    pub csv_write_batch_ms: u64,
+    /// Buffered CSV mutations that trigger a write before the window ends.
+    pub csv_write_batch_max: usize,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -31,14 +71,79 @@ pub struct DynamoDbStorageConfig {
 pub struct RedisStorageConfig {
     pub is_enabled: bool,
     pub redis_url: String,
+    /// Namespaces keys as `tr:{prefix}:...`. Empty keeps unprefixed keys.
+    pub key_prefix: String,
+    /// Replicas that must acknowledge each write. `0` doesn't wait for replication.
+    pub wait_replicas: u32,
+    /// Waits for each write to be fsynced to the AOF.
+    pub fsync: bool,
+    pub wait_timeout_ms: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StorageConfig {
     pub ddb_storage_config: DynamoDbStorageConfig,
     pub file_storage_config: FileStorageConfig,
     pub redis_storage_config: RedisStorageConfig,
     pub storage_backend: TrustStorageBackend,
+    /// Backend reads fall back to when `storage_backend` doesn't hold a record, see
+    /// `TR_STORAGE_LAYERED`.
+    pub secondary_backend: Option<TrustStorageBackend>,
+    /// Time each backend operation may take before failing. `0` waits forever.
+    pub op_timeout_ms: u64,
+}
+
+impl std::str::FromStr for TrustStorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "dynamodb" | "ddb" => Ok(Self::DynamoDb),
+            "redis" => Ok(Self::Redis),
+            _ => Err(format!("Invalid storage backend: {s}")),
+        }
+    }
+}
+
+/// `TR_STORAGE_LAYERED` as `<primary>,<secondary>`, e.g. `redis,csv`.
+fn parse_layered(value: &str) -> Result<(TrustStorageBackend, TrustStorageBackend), String> {
+    let Some((primary, secondary)) = value.split_once(',') else {
+        return Err(format!(
+            "TR_STORAGE_LAYERED must name two backends as <primary>,<secondary>, got '{value}'"
+        ));
+    };
+    let (primary, secondary) = (primary.parse()?, secondary.parse()?);
+    if primary == secondary {
+        return Err(format!(
+            "TR_STORAGE_LAYERED must name two different backends, got '{value}'"
+        ));
+    }
+    if matches!(
+        (primary, secondary),
+        (TrustStorageBackend::Csv, TrustStorageBackend::Json)
+            | (TrustStorageBackend::Json, TrustStorageBackend::Csv)
+    ) {
+        return Err(
+            "TR_STORAGE_LAYERED can't layer csv and json, they share FILE_STORAGE_PATH".into(),
+        );
+    }
+    Ok((primary, secondary))
+}
+
+fn load_layered_backends() -> Result<Option<(TrustStorageBackend, TrustStorageBackend)>, String> {
+    optional_env("TR_STORAGE_LAYERED")
+        .map(|value| parse_layered(&value))
+        .transpose()
+}
+
+/// The configured backends, the primary first. `TR_STORAGE_LAYERED` overrides `TR_STORAGE_BACKEND`.
+fn load_storage_backends() -> Vec<TrustStorageBackend> {
+    match load_layered_backends() {
+        Ok(Some((primary, secondary))) => vec![primary, secondary],
+        _ => vec![load_storage_backend()],
+    }
 }
 
 fn load_storage_backend() -> TrustStorageBackend {
@@ -46,6 +151,7 @@ fn load_storage_backend() -> TrustStorageBackend {
     match storage_backend_str.as_str() {
         "dynamodb" | "ddb" => TrustStorageBackend::DynamoDb,
         "redis" => TrustStorageBackend::Redis,
+        "json" => TrustStorageBackend::Json,
         _ => TrustStorageBackend::Csv,
     }
 }
@@ -53,26 +159,42 @@ fn load_storage_backend() -> TrustStorageBackend {
 #[async_trait::async_trait]
 impl Configs for FileStorageConfig {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        if load_storage_backend() == TrustStorageBackend::Csv {
-            Ok(FileStorageConfig {
-                is_enabled: true,
-                path: env_or("FILE_STORAGE_PATH", DEFAULT_TRUST_REGISTRY_FILE_PATH),
-                update_interval_sec: env_or(
-                    "FILE_STORAGE_UPDATE_INTERVAL_SEC",
-                    &DEFAULT_TRUST_REGISTRY_UPDATE_INTERVAL_SEC.to_string(),
-                )
-                .parse::<u64>()?,
-            })
+        let backends = load_storage_backends();
+        let default_path = if backends.contains(&TrustStorageBackend::Csv) {
+            DEFAULT_TRUST_REGISTRY_FILE_PATH
+        } else if backends.contains(&TrustStorageBackend::Json) {
+            DEFAULT_TRUST_REGISTRY_JSON_FILE_PATH
         } else {
-            Ok(Default::default())
-        }
+            return Ok(Default::default());
+        };
+        Ok(FileStorageConfig {
+            is_enabled: true,
+            path: env_or("FILE_STORAGE_PATH", default_path),
+            update_interval_sec: env_or(
+                "FILE_STORAGE_UPDATE_INTERVAL_SEC",
+                &DEFAULT_TRUST_REGISTRY_UPDATE_INTERVAL_SEC.to_string(),
+            )
+            .parse::<u64>()?,
+            csv_strict: env_or("CSV_STRICT", "false") == "true",
+            csv_on_duplicate: env_or("ON_DUPLICATE", "last-wins").parse()?,
+            csv_write_batch_ms: env_or(
+                "CSV_WRITE_BATCH_MS",
+                &DEFAULT_CSV_WRITE_BATCH_MS.to_string(),
+            )
+            .parse::<u64>()?,
+            csv_write_batch_max: env_or(
+                "CSV_WRITE_BATCH_MAX",
+                &DEFAULT_CSV_WRITE_BATCH_MAX.to_string(),
+            )
+            .parse::<usize>()?,
+        })
     }
 }
 
 #[async_trait::async_trait]
 impl Configs for DynamoDbStorageConfig {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        if load_storage_backend() == TrustStorageBackend::DynamoDb {
+        if load_storage_backends().contains(&TrustStorageBackend::DynamoDb) {
             Ok(DynamoDbStorageConfig {
                 is_enabled: true,
                 table_name: required_env("DDB_TABLE_NAME")?,
@@ -90,10 +212,25 @@ impl Configs for DynamoDbStorageConfig {
 #[async_trait::async_trait]
 impl Configs for RedisStorageConfig {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        if load_storage_backend() == TrustStorageBackend::Redis {
+        if load_storage_backends().contains(&TrustStorageBackend::Redis) {
+            let key_prefix = env_or("REDIS_KEY_PREFIX", "");
+            if key_prefix.contains([':', '|']) {
+                return Err(format!(
+                    "REDIS_KEY_PREFIX must not contain ':' or '|', got '{key_prefix}'"
+                )
+                .into());
+            }
             Ok(RedisStorageConfig {
                 is_enabled: true,
                 redis_url: required_env("REDIS_URL")?,
+                key_prefix,
+                wait_replicas: env_or("REDIS_WAIT_REPLICAS", "0").parse::<u32>()?,
+                fsync: env_or("REDIS_FSYNC", "false") == "true",
+                wait_timeout_ms: env_or(
+                    "REDIS_WAIT_TIMEOUT_MS",
+                    &DEFAULT_REDIS_WAIT_TIMEOUT_MS.to_string(),
+                )
+                .parse::<u64>()?,
             })
         } else {
             Ok(Default::default())
@@ -104,12 +241,52 @@ impl Configs for RedisStorageConfig {
 #[async_trait::async_trait]
 impl Configs for StorageConfig {
     async fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let storage_backend = load_storage_backend();
+        let (storage_backend, secondary_backend) = match load_layered_backends()? {
+            Some((primary, secondary)) => (primary, Some(secondary)),
+            None => (load_storage_backend(), None),
+        };
         Ok(StorageConfig {
             ddb_storage_config: DynamoDbStorageConfig::load().await?,
             file_storage_config: FileStorageConfig::load().await?,
             redis_storage_config: RedisStorageConfig::load().await?,
             storage_backend,
+            secondary_backend,
+            op_timeout_ms: env_or(
+                "STORAGE_OP_TIMEOUT_MS",
+                &DEFAULT_STORAGE_OP_TIMEOUT_MS.to_string(),
+            )
+            .parse::<u64>()?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_layered_names_primary_then_secondary() {
+        assert_eq!(
+            parse_layered("redis, csv"),
+            Ok((TrustStorageBackend::Redis, TrustStorageBackend::Csv))
+        );
+        assert_eq!(
+            parse_layered("DDB,json"),
+            Ok((TrustStorageBackend::DynamoDb, TrustStorageBackend::Json))
+        );
+    }
+
+    #[test]
+    fn test_parse_layered_rejects_invalid_pairs() {
+        assert_eq!(
+            parse_layered("redis").unwrap_err(),
+            "TR_STORAGE_LAYERED must name two backends as <primary>,<secondary>, got 'redis'"
+        );
+        assert_eq!(
+            parse_layered("redis,mongo").unwrap_err(),
+            "Invalid storage backend: mongo"
+        );
+        assert!(parse_layered("redis,redis").is_err());
+        assert!(parse_layered("csv,json").is_err());
+    }
+}
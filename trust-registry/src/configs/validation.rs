@@ -0,0 +1,235 @@
+use std::{fmt, fs::OpenOptions, path::Path, sync::Arc};
+
+use crate::{
+    domain::Did,
+    storage::{encryption::ContextCipher, factory::TrustStorageRepoFactory},
+};
+
+use super::TrsutRegistryConfig;
+
+/// Every problem found by [`TrsutRegistryConfig::validate`], reported together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValidationError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigValidationError {}
+
+/// Checks the `did:<method>:<method-specific-id>` shape from DID Core, see [`Did::parse`].
+pub fn is_well_formed_did(did: &str) -> bool {
+    Did::parse(did).is_ok()
+}
+
+/// Checks that `path` can be appended to, or created if missing, without writing anything.
+fn check_writable(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        return OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match dir.metadata() {
+        Ok(metadata) if !metadata.is_dir() => Err(format!("{} is not a directory", dir.display())),
+        Ok(metadata) if metadata.permissions().readonly() => {
+            Err(format!("{} is read-only", dir.display()))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{}: {e}", dir.display())),
+    }
+}
+
+impl TrsutRegistryConfig {
+    /// Checks the loaded configuration before anything is started, so misconfiguration
+    /// is reported in one go instead of surfacing as a panic later.
+    pub async fn validate(self: &Arc<Self>) -> Result<(), ConfigValidationError> {
+        let mut problems = vec![];
+
+        let didcomm = &self.didcomm_config;
+        if didcomm.is_enabled {
+            for admin_did in &didcomm.admin_config.admin_dids {
+                if !admin_did.is_empty() && !is_well_formed_did(admin_did) {
+                    problems.push(format!("ADMIN_DIDS contains an invalid DID: {admin_did:?}"));
+                }
+            }
+            if !is_well_formed_did(&didcomm.mediator_did) {
+                problems.push(format!(
+                    "MEDIATOR_DID is not a valid DID: {:?}",
+                    didcomm.mediator_did
+                ));
+            }
+            if let Some(mediator_did) = &didcomm.profile_config.mediator_did
+                && !is_well_formed_did(mediator_did)
+            {
+                problems.push(format!(
+                    "PROFILE_CONFIG mediator_did is not a valid DID: {mediator_did:?}"
+                ));
+            }
+            if let Some(path) = &didcomm.admin_config.audit_config.file_path
+                && let Err(e) = check_writable(Path::new(path))
+            {
+                problems.push(format!("AUDIT_LOG_FILE {path} can't be opened: {e}"));
+            }
+        }
+
+        if let Some(key) = &self.server_config.context_encryption_key
+            && let Err(e) = ContextCipher::from_base64(key)
+        {
+            problems.push(e);
+        }
+
+        if let Err(e) = TrustStorageRepoFactory::new(Arc::clone(self)).check().await {
+            problems.push(e);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigValidationError { problems })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::{
+        AdminConfig, DidcommConfig, FileStorageConfig, TrustStorageBackend, storage::StorageConfig,
+    };
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn config(csv_path: &str, admin_dids: Vec<&str>) -> Arc<TrsutRegistryConfig> {
+        Arc::new(TrsutRegistryConfig {
+            storage_config: StorageConfig {
+                storage_backend: TrustStorageBackend::Csv,
+                file_storage_config: FileStorageConfig {
+                    is_enabled: true,
+                    path: csv_path.to_string(),
+                    update_interval_sec: 60,
+                    csv_strict: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            didcomm_config: DidcommConfig {
+                is_enabled: true,
+                mediator_did: "did:web:mediator.example.com".to_string(),
+                admin_config: AdminConfig {
+                    admin_dids: admin_dids.into_iter().map(String::from).collect(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    fn csv_file() -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "entity_id,authority_id,action,resource,recognized,authorized,context,record_type"
+        )
+        .unwrap();
+        file
+    }
+
+    #[test]
+    fn test_is_well_formed_did() {
+        assert!(is_well_formed_did("did:example:123"));
+        assert!(is_well_formed_did("did:web:localhost%3A3232:users:alice"));
+        assert!(is_well_formed_did("did:peer:2.Ez6LSb"));
+        assert!(!is_well_formed_did("example:123"));
+        assert!(!is_well_formed_did("did:example"));
+        assert!(!is_well_formed_did("did:Example:123"));
+        assert!(!is_well_formed_did("did:example:"));
+        assert!(!is_well_formed_did("did:example:a b"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_accepts_valid_config() {
+        let file = csv_file();
+        let config = config(file.path().to_str().unwrap(), vec!["did:example:admin", ""]);
+
+        assert_eq!(config.validate().await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_does_not_create_the_audit_log_file() {
+        let file = csv_file();
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = dir.path().join("audit.log");
+        let mut config = config(file.path().to_str().unwrap(), vec![]);
+        Arc::get_mut(&mut config)
+            .unwrap()
+            .didcomm_config
+            .admin_config
+            .audit_config
+            .file_path = Some(audit_log.to_str().unwrap().to_string());
+
+        assert_eq!(config.validate().await, Ok(()));
+        assert!(!audit_log.exists());
+
+        let missing_dir = dir.path().join("missing").join("audit.log");
+        Arc::get_mut(&mut config)
+            .unwrap()
+            .didcomm_config
+            .admin_config
+            .audit_config
+            .file_path = Some(missing_dir.to_str().unwrap().to_string());
+        let error = config.validate().await.unwrap_err();
+        assert!(error.problems[0].contains("AUDIT_LOG_FILE"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_bad_admin_did() {
+        let file = csv_file();
+        let config = config(
+            file.path().to_str().unwrap(),
+            vec!["did:example:admin", "not-a-did"],
+        );
+
+        let error = config.validate().await.unwrap_err();
+
+        assert_eq!(error.problems.len(), 1);
+        assert!(error.problems[0].contains("not-a-did"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_unreachable_csv_path() {
+        let config = config("/nonexistent/trust_records.csv", vec!["did:example:admin"]);
+
+        let error = config.validate().await.unwrap_err();
+
+        assert_eq!(error.problems.len(), 1);
+        assert!(error.problems[0].contains("/nonexistent/trust_records.csv"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_aggregates_all_problems() {
+        let config = config("/nonexistent/trust_records.csv", vec!["bad-admin"]);
+
+        let error = config.validate().await.unwrap_err();
+
+        assert_eq!(error.problems.len(), 2);
+        let message = error.to_string();
+        assert!(message.starts_with("Invalid configuration:\n  - "));
+        assert!(message.contains("bad-admin"));
+        assert!(message.contains("/nonexistent/trust_records.csv"));
+    }
+}
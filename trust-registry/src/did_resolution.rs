@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DidResolutionError {
+    NotFound(String),
+    Failed(String),
+}
+
+impl fmt::Display for DidResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(did) => write!(f, "DID {did} could not be found"),
+            Self::Failed(e) => write!(f, "DID resolution failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DidResolutionError {}
+
+/// Resolves a DID to its DID document, for verifying proofs made by authorities.
+#[async_trait::async_trait]
+pub trait DidResolver: Send + Sync {
+    async fn resolve(&self, did: &str) -> Result<Value, DidResolutionError>;
+}
+
+/// JWK parameters only present on private keys.
+const PRIVATE_JWK_PARAMS: [&str; 7] = ["d", "p", "q", "dp", "dq", "qi", "k"];
+
+/// The DID document without private key material, for handing out to clients:
+/// `privateKey*` properties are dropped and JWKs lose their private parameters.
+pub fn public_document(document: Value) -> Value {
+    match document {
+        Value::Object(map) => {
+            let is_jwk = map.contains_key("kty");
+            Value::Object(
+                map.into_iter()
+                    .filter(|(key, _)| {
+                        let private = key.to_lowercase().starts_with("privatekey")
+                            || (is_jwk && PRIVATE_JWK_PARAMS.contains(&key.as_str()));
+                        !private
+                    })
+                    .map(|(key, value)| (key, public_document(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(public_document).collect()),
+        other => other,
+    }
+}
+
+struct CachedDocument {
+    document: Value,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+struct CacheState {
+    entries: HashMap<String, CachedDocument>,
+    clock: u64,
+}
+
+/// LRU cache of resolved DID documents in front of another resolver.
+///
+/// Entries expire after the TTL so rotated keys are picked up. Failed resolutions are
+/// not cached.
+pub struct CachingDidResolver {
+    inner: Arc<dyn DidResolver>,
+    ttl: Duration,
+    max_entries: usize,
+    state: Mutex<CacheState>,
+}
+
+impl CachingDidResolver {
+    pub fn new(inner: Arc<dyn DidResolver>, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            max_entries,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    fn get(&self, did: &str) -> Option<Value> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let cached = state.entries.get_mut(did)?;
+        if cached.expires_at <= Instant::now() {
+            state.entries.remove(did);
+            return None;
+        }
+        cached.last_used = clock;
+        Some(cached.document.clone())
+    }
+
+    fn insert(&self, did: &str, document: Value) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.entries.contains_key(did) && state.entries.len() >= self.max_entries {
+            let now = Instant::now();
+            state.entries.retain(|_, cached| cached.expires_at > now);
+            if state.entries.len() >= self.max_entries
+                && let Some(least_recent) = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.last_used)
+                    .map(|(did, _)| did.clone())
+            {
+                state.entries.remove(&least_recent);
+            }
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.entries.insert(
+            did.to_string(),
+            CachedDocument {
+                document,
+                expires_at: Instant::now() + self.ttl,
+                last_used,
+            },
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl DidResolver for CachingDidResolver {
+    async fn resolve(&self, did: &str) -> Result<Value, DidResolutionError> {
+        if let Some(document) = self.get(did) {
+            return Ok(document);
+        }
+        let document = self.inner.resolve(did).await?;
+        self.insert(did, document.clone());
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingResolver {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl DidResolver for CountingResolver {
+        async fn resolve(&self, did: &str) -> Result<Value, DidResolutionError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if did == "did:example:missing" {
+                return Err(DidResolutionError::NotFound(did.to_string()));
+            }
+            Ok(json!({ "id": did }))
+        }
+    }
+
+    fn cache(ttl: Duration, max_entries: usize) -> (Arc<CountingResolver>, CachingDidResolver) {
+        let inner = Arc::new(CountingResolver::default());
+        let cache = CachingDidResolver::new(inner.clone(), ttl, max_entries);
+        (inner, cache)
+    }
+
+    #[tokio::test]
+    async fn test_repeated_resolution_within_ttl_hits_resolver_once() {
+        let (inner, cache) = cache(Duration::from_secs(60), 10);
+
+        let first = cache.resolve("did:example:authority").await.unwrap();
+        let second = cache.resolve("did:example:authority").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_is_resolved_again() {
+        let (inner, cache) = cache(Duration::from_millis(10), 10);
+
+        cache.resolve("did:example:authority").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cache.resolve("did:example:authority").await.unwrap();
+
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let (inner, cache) = cache(Duration::from_secs(60), 2);
+
+        cache.resolve("did:example:a").await.unwrap();
+        cache.resolve("did:example:b").await.unwrap();
+        cache.resolve("did:example:a").await.unwrap();
+        cache.resolve("did:example:c").await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+
+        cache.resolve("did:example:a").await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+        cache.resolve("did:example:b").await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_not_cached() {
+        let (inner, cache) = cache(Duration::from_secs(60), 10);
+
+        assert!(cache.resolve("did:example:missing").await.is_err());
+        assert!(cache.resolve("did:example:missing").await.is_err());
+
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_public_document_drops_private_key_material() {
+        let document = json!({
+            "id": "did:example:authority",
+            "verificationMethod": [{
+                "id": "did:example:authority#key-0",
+                "publicKeyJwk": { "kty": "OKP", "crv": "Ed25519", "x": "public", "d": "secret" },
+                "privateKeyMultibase": "zSecret"
+            }]
+        });
+
+        let public = public_document(document);
+
+        assert_eq!(
+            public["verificationMethod"][0],
+            json!({
+                "id": "did:example:authority#key-0",
+                "publicKeyJwk": { "kty": "OKP", "crv": "Ed25519", "x": "public" }
+            })
+        );
+    }
+}
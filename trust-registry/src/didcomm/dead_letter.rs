@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use affinidi_tdk::messaging::{ATM, profiles::ATMProfile};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use opentelemetry::metrics::ObservableGauge;
+use redis::{Client, aio::MultiplexedConnection};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{configs::DeadLetterConfig, didcomm::transport};
+
+type DeadLetterResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// The connection a profile resends its dead letters through.
+type Sender = (Arc<ATM>, Arc<ATMProfile>);
+
+/// Redis list holding dead letters when `DEAD_LETTER_STORE` is a Redis URL.
+pub const DEAD_LETTER_REDIS_KEY: &str = "trust_registry:dead_letters";
+
+/// An outbound message that couldn't be delivered after every send attempt. The packed
+/// message is kept as sent, so a retry delivers exactly what the recipient would have got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Alias of the profile that sent the message and has to resend it. Letters queued
+    /// before it was recorded have none and are resent by any connected profile.
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub recipient: String,
+    pub thid: Option<String>,
+    pub message_id: String,
+    pub packed_msg: String,
+    pub failed_at: DateTime<Utc>,
+    /// Delivery retries made from the queue so far.
+    pub retries: u32,
+}
+
+impl DeadLetter {
+    pub fn new(
+        profile: &str,
+        recipient: &str,
+        thid: Option<&str>,
+        message_id: &str,
+        packed_msg: &str,
+    ) -> Self {
+        Self {
+            profile: Some(profile.to_string()),
+            recipient: recipient.to_string(),
+            thid: thid.map(str::to_string),
+            message_id: message_id.to_string(),
+            packed_msg: packed_msg.to_string(),
+            failed_at: Utc::now(),
+            retries: 0,
+        }
+    }
+}
+
+/// Where dead letters are kept between retries.
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    async fn push(&self, letters: Vec<DeadLetter>) -> DeadLetterResult<()>;
+    /// Removes and returns every stored letter, oldest first.
+    async fn take_all(&self) -> DeadLetterResult<Vec<DeadLetter>>;
+    async fn count(&self) -> DeadLetterResult<usize>;
+}
+
+/// Dead letters as JSON lines in a file.
+pub struct FileDeadLetterStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileDeadLetterStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn read(&self) -> DeadLetterResult<Vec<DeadLetter>> {
+        let contents = match fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for FileDeadLetterStore {
+    async fn push(&self, letters: Vec<DeadLetter>) -> DeadLetterResult<()> {
+        let _guard = self.lock.lock().await;
+        let mut lines = String::new();
+        for letter in &letters {
+            lines.push_str(&serde_json::to_string(letter)?);
+            lines.push('\n');
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(lines.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn take_all(&self) -> DeadLetterResult<Vec<DeadLetter>> {
+        let _guard = self.lock.lock().await;
+        let letters = self.read().await?;
+        if !letters.is_empty() {
+            fs::write(&self.path, "").await?;
+        }
+        Ok(letters)
+    }
+
+    async fn count(&self) -> DeadLetterResult<usize> {
+        let _guard = self.lock.lock().await;
+        Ok(self.read().await?.len())
+    }
+}
+
+/// Dead letters in a Redis list, shared by every instance pointing at the same server.
+pub struct RedisDeadLetterStore {
+    connection: MultiplexedConnection,
+    key: String,
+}
+
+impl RedisDeadLetterStore {
+    pub async fn connect(redis_url: &str, key: &str) -> DeadLetterResult<Self> {
+        let client = Client::open(redis_url)?;
+        Ok(Self {
+            connection: client.get_multiplexed_async_connection().await?,
+            key: key.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for RedisDeadLetterStore {
+    async fn push(&self, letters: Vec<DeadLetter>) -> DeadLetterResult<()> {
+        if letters.is_empty() {
+            return Ok(());
+        }
+        let values = letters
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        redis::cmd("RPUSH")
+            .arg(&self.key)
+            .arg(values)
+            .query_async::<()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn take_all(&self) -> DeadLetterResult<Vec<DeadLetter>> {
+        // read and clear in one transaction, so letters pushed meanwhile aren't lost
+        let (values, _): (Vec<String>, u64) = redis::pipe()
+            .atomic()
+            .cmd("LRANGE")
+            .arg(&self.key)
+            .arg(0)
+            .arg(-1)
+            .cmd("DEL")
+            .arg(&self.key)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        values
+            .iter()
+            .map(|value| serde_json::from_str(value).map_err(Into::into))
+            .collect()
+    }
+
+    async fn count(&self) -> DeadLetterResult<usize> {
+        Ok(redis::cmd("LLEN")
+            .arg(&self.key)
+            .query_async(&mut self.connection.clone())
+            .await?)
+    }
+}
+
+/// Keeps responses that failed to send and retries them, see `DEAD_LETTER_STORE`.
+/// The number of queued letters is exported as the `didcomm.dead_letters` gauge.
+///
+/// One queue is shared by every profile listener, so a single retry task drains the
+/// store. Letters are resent through the profile that queued them, once its listener has
+/// registered with [`DeadLetterQueue::register_profile`].
+pub struct DeadLetterQueue {
+    store: Arc<dyn DeadLetterStore>,
+    max_retries: u32,
+    depth: Arc<AtomicU64>,
+    _depth_gauge: ObservableGauge<u64>,
+    senders: RwLock<HashMap<String, Sender>>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(store: Arc<dyn DeadLetterStore>, max_retries: u32) -> Self {
+        let depth = Arc::new(AtomicU64::new(0));
+        let observed = depth.clone();
+        let depth_gauge = opentelemetry::global::meter(env!("CARGO_PKG_NAME"))
+            .u64_observable_gauge("didcomm.dead_letters")
+            .with_description("Outbound DIDComm messages waiting to be retried")
+            .with_callback(move |observer| observer.observe(observed.load(Ordering::Relaxed), &[]))
+            .build();
+        Self {
+            store,
+            max_retries,
+            depth,
+            _depth_gauge: depth_gauge,
+            senders: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Lets the retry task resend the profile's letters through its connection.
+    pub fn register_profile(&self, atm: Arc<ATM>, profile: Arc<ATMProfile>) {
+        self.senders
+            .write()
+            .unwrap()
+            .insert(profile.inner.alias.clone(), (atm, profile));
+    }
+
+    /// Stops resending the profile's letters, as when its listener stopped.
+    pub fn unregister_profile(&self, alias: &str) {
+        self.senders.write().unwrap().remove(alias);
+    }
+
+    /// The connection to resend the letter through, if its profile is registered.
+    fn sender_for(&self, letter: &DeadLetter) -> Option<Sender> {
+        let senders = self.senders.read().unwrap();
+        match &letter.profile {
+            Some(alias) => senders.get(alias).cloned(),
+            None => senders.values().next().cloned(),
+        }
+    }
+
+    /// Opens the store named by `DEAD_LETTER_STORE`: a Redis list for `redis://` and
+    /// `rediss://` URLs, a JSON lines file otherwise.
+    pub async fn open(config: &DeadLetterConfig) -> DeadLetterResult<Self> {
+        let store: Arc<dyn DeadLetterStore> =
+            if config.store.starts_with("redis://") || config.store.starts_with("rediss://") {
+                Arc::new(RedisDeadLetterStore::connect(&config.store, DEAD_LETTER_REDIS_KEY).await?)
+            } else {
+                Arc::new(FileDeadLetterStore::new(&config.store))
+            };
+        let queue = Self::new(store, config.max_retries);
+        queue.refresh_depth().await;
+        Ok(queue)
+    }
+
+    /// Letters waiting to be retried.
+    pub fn depth(&self) -> u64 {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    async fn refresh_depth(&self) {
+        match self.store.count().await {
+            Ok(len) => self.depth.store(len as u64, Ordering::Relaxed),
+            Err(e) => warn!("Failed to read dead letter queue depth: {e}"),
+        }
+    }
+
+    /// Queues a message that failed to send. A failure to queue it is logged, as the
+    /// message is lost either way.
+    pub async fn push(&self, letter: DeadLetter) {
+        info!(
+            "[thid = {:?}] Queued undelivered message {} to {} for retry",
+            letter.thid, letter.message_id, letter.recipient
+        );
+        if let Err(e) = self.store.push(vec![letter]).await {
+            error!("Failed to queue undelivered message for retry: {e}");
+        }
+        self.refresh_depth().await;
+    }
+
+    /// Tries every queued letter once with `send`. Letters that fail again are queued
+    /// for the next round until they have been retried `max_retries` times, then dropped.
+    /// Returns how many letters were delivered.
+    pub async fn retry_all<F, Fut, E>(&self, mut send: F) -> usize
+    where
+        F: FnMut(DeadLetter) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: std::fmt::Debug,
+    {
+        let letters = match self.store.take_all().await {
+            Ok(letters) => letters,
+            Err(e) => {
+                error!("Failed to read dead letter queue: {e}");
+                return 0;
+            }
+        };
+
+        let mut delivered = 0;
+        let mut failed = vec![];
+        for mut letter in letters {
+            match send(letter.clone()).await {
+                Ok(()) => delivered += 1,
+                Err(e) => {
+                    letter.retries += 1;
+                    if letter.retries >= self.max_retries {
+                        error!(
+                            "[thid = {:?}] Dropping message {} to {} after {} retries: {e:?}",
+                            letter.thid, letter.message_id, letter.recipient, letter.retries
+                        );
+                    } else {
+                        failed.push(letter);
+                    }
+                }
+            }
+        }
+        if let Err(e) = self.store.push(failed).await {
+            error!("Failed to re-queue undelivered messages: {e}");
+        }
+        self.refresh_depth().await;
+        delivered
+    }
+
+    /// Retries queued letters every `interval` through the mediator of the profile that
+    /// queued them, until the returned task is aborted. A letter whose profile has no
+    /// registered listener counts as a failed retry.
+    pub fn spawn_retry_task(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if queue.depth() == 0 {
+                    continue;
+                }
+                let delivered = queue
+                    .retry_all(|letter| {
+                        let sender = queue.sender_for(&letter);
+                        async move {
+                            let Some((atm, profile)) = sender else {
+                                return Err(format!(
+                                    "profile {:?} has no connected listener",
+                                    letter.profile
+                                ));
+                            };
+                            transport::forward_with_retry(
+                                &atm,
+                                &profile,
+                                &letter.packed_msg,
+                                &letter.message_id,
+                                &letter.recipient,
+                            )
+                            .await
+                            .map_err(|e| format!("{e:?}"))
+                        }
+                    })
+                    .await;
+                info!(
+                    "Delivered {delivered} queued messages, {} still queued",
+                    queue.depth()
+                );
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{didcomm::transport::queue_if_undelivered, test_support::offline_atm};
+
+    fn queue(dir: &tempfile::TempDir, max_retries: u32) -> DeadLetterQueue {
+        let store = FileDeadLetterStore::new(dir.path().join("dead_letters.jsonl"));
+        DeadLetterQueue::new(Arc::new(store), max_retries)
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_is_queued_and_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = queue(&dir, 3);
+
+        let sent: Result<(), String> = queue_if_undelivered(
+            Err("mediator unavailable".to_string()),
+            Some(&queue),
+            || {
+                DeadLetter::new(
+                    "registry",
+                    "did:example:bob",
+                    Some("thread-1"),
+                    "message-1",
+                    "packed",
+                )
+            },
+        )
+        .await;
+        assert!(sent.is_err());
+        assert_eq!(queue.depth(), 1);
+
+        // still failing: kept for the next round
+        let delivered = queue
+            .retry_all(|_| async { Err::<(), _>("mediator unavailable") })
+            .await;
+        assert_eq!(delivered, 0);
+        assert_eq!(queue.depth(), 1);
+
+        let mut retried = vec![];
+        let delivered = queue
+            .retry_all(|letter| {
+                retried.push(letter);
+                async { Ok::<(), String>(()) }
+            })
+            .await;
+        assert_eq!(delivered, 1);
+        assert_eq!(queue.depth(), 0);
+        assert_eq!(retried[0].recipient, "did:example:bob");
+        assert_eq!(retried[0].thid.as_deref(), Some("thread-1"));
+        assert_eq!(retried[0].packed_msg, "packed");
+        assert_eq!(retried[0].retries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_letters_are_dropped_after_max_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = queue(&dir, 2);
+        queue
+            .push(DeadLetter::new(
+                "registry",
+                "did:example:bob",
+                None,
+                "message-1",
+                "packed",
+            ))
+            .await;
+
+        for _ in 0..2 {
+            queue
+                .retry_all(|_| async { Err::<(), _>("unreachable") })
+                .await;
+        }
+
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_letters_are_resent_by_the_profile_that_queued_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = queue(&dir, 3);
+        let (atm, registry) = offline_atm("registry").await;
+        let (_, other) = offline_atm("other").await;
+        queue.register_profile(atm.clone(), registry);
+        queue.register_profile(atm, other);
+        let letter = |profile: &str| DeadLetter::new(profile, "did:example:bob", None, "m", "p");
+        let legacy: DeadLetter = serde_json::from_str(
+            r#"{"recipient":"did:example:bob","thid":null,"message_id":"m","packed_msg":"p","failed_at":"2026-01-01T00:00:00Z","retries":0}"#,
+        )
+        .unwrap();
+
+        let alias_for = |letter: &DeadLetter| {
+            queue
+                .sender_for(letter)
+                .map(|(_, profile)| profile.inner.alias.clone())
+        };
+
+        assert_eq!(alias_for(&letter("other")).as_deref(), Some("other"));
+        assert_eq!(alias_for(&letter("registry")).as_deref(), Some("registry"));
+        assert_eq!(alias_for(&letter("stopped")), None);
+        assert!(alias_for(&legacy).is_some());
+
+        queue.unregister_profile("other");
+        assert_eq!(alias_for(&letter("other")), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        queue(&dir, 3)
+            .push(DeadLetter::new(
+                "registry",
+                "did:example:bob",
+                None,
+                "message-1",
+                "packed",
+            ))
+            .await;
+
+        let reopened = FileDeadLetterStore::new(dir.path().join("dead_letters.jsonl"));
+
+        assert_eq!(reopened.count().await.unwrap(), 1);
+        assert_eq!(
+            reopened.take_all().await.unwrap()[0].message_id,
+            "message-1"
+        );
+        assert_eq!(reopened.count().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Remembers processed messages for a TTL so a re-delivered message is not processed twice.
+///
+/// Messages are keyed on sender and message id; a zero TTL disables deduplication. A
+/// message only counts as processed once its handler succeeded: one that failed or
+/// panicked is processed again when redelivered. While a message is being processed its
+/// redeliveries are skipped.
+pub struct MessageDeduplicator {
+    ttl: Duration,
+    seen: Mutex<SeenMessages>,
+}
+
+type MessageKey = (String, String);
+
+#[derive(Default)]
+struct SeenMessages {
+    keys: HashSet<MessageKey>,
+    /// Insertion order, which is also expiry order as every entry has the same TTL.
+    order: VecDeque<(Instant, MessageKey)>,
+    in_flight: HashSet<MessageKey>,
+}
+
+impl SeenMessages {
+    fn expire(&mut self, now: Instant, ttl: Duration) {
+        while let Some((processed_at, _)) = self.order.front()
+            && now.duration_since(*processed_at) >= ttl
+        {
+            let (_, key) = self.order.pop_front().unwrap();
+            self.keys.remove(&key);
+        }
+    }
+}
+
+/// A message being processed, see [`MessageDeduplicator::begin`]. Dropping it without
+/// [`InFlight::complete`] lets a redelivery process the message again.
+pub struct InFlight<'a> {
+    deduplicator: &'a MessageDeduplicator,
+    key: Option<MessageKey>,
+}
+
+impl InFlight<'_> {
+    /// Records the message as processed, so redeliveries within the TTL are skipped.
+    pub fn complete(mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        let mut seen = self.deduplicator.seen.lock().unwrap();
+        seen.in_flight.remove(&key);
+        seen.keys.insert(key.clone());
+        seen.order.push_back((Instant::now(), key));
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.deduplicator
+                .seen
+                .lock()
+                .unwrap()
+                .in_flight
+                .remove(&key);
+        }
+    }
+}
+
+impl MessageDeduplicator {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            seen: Mutex::new(SeenMessages::default()),
+        }
+    }
+
+    /// Starts processing the message, or returns `None` if it was processed within the TTL
+    /// or is being processed right now.
+    pub fn begin(&self, sender: &str, message_id: &str) -> Option<InFlight<'_>> {
+        if self.ttl.is_zero() {
+            return Some(InFlight {
+                deduplicator: self,
+                key: None,
+            });
+        }
+        let mut seen = self.seen.lock().unwrap();
+        seen.expire(Instant::now(), self.ttl);
+
+        let key = (sender.to_string(), message_id.to_string());
+        if seen.keys.contains(&key) || !seen.in_flight.insert(key.clone()) {
+            return None;
+        }
+        Some(InFlight {
+            deduplicator: self,
+            key: Some(key),
+        })
+    }
+
+    /// Records the delivery as processed and returns `false` if the message was already
+    /// seen within the TTL.
+    pub fn first_delivery(&self, sender: &str, message_id: &str) -> bool {
+        self.begin(sender, message_id)
+            .map(InFlight::complete)
+            .is_some()
+    }
+
+    /// Runs `process` unless the message was already processed or is being processed.
+    /// The message counts as processed only if `process` succeeds.
+    pub async fn run_once<F, Fut, T, E>(
+        &self,
+        sender: &str,
+        message_id: &str,
+        process: F,
+    ) -> Option<Result<T, E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let in_flight = self.begin(sender, message_id)?;
+        let result = process().await;
+        if result.is_ok() {
+            in_flight.complete();
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_redelivered_message_runs_once() {
+        let deduplicator = MessageDeduplicator::new(Duration::from_secs(60));
+        let runs = AtomicU32::new(0);
+
+        for _ in 0..2 {
+            deduplicator
+                .run_once("did:example:admin", "msg-1", || async {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Ok::<(), ()>(())
+                })
+                .await;
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        // the same id from another sender is a different message
+        assert!(deduplicator.first_delivery("did:example:other", "msg-1"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_message_is_processed_again() {
+        let deduplicator = MessageDeduplicator::new(Duration::from_secs(60));
+
+        let failed = deduplicator
+            .run_once("did:example:admin", "msg-1", || async {
+                Err::<(), _>("failed")
+            })
+            .await;
+        let retried = deduplicator
+            .run_once("did:example:admin", "msg-1", || async {
+                Ok::<_, ()>("done")
+            })
+            .await;
+
+        assert_eq!(failed, Some(Err("failed")));
+        assert_eq!(retried, Some(Ok("done")));
+        assert!(!deduplicator.first_delivery("did:example:admin", "msg-1"));
+    }
+
+    #[test]
+    fn test_redelivery_while_in_flight_is_skipped_until_released() {
+        let deduplicator = MessageDeduplicator::new(Duration::from_secs(60));
+
+        let in_flight = deduplicator.begin("did:example:admin", "msg-1").unwrap();
+        assert!(deduplicator.begin("did:example:admin", "msg-1").is_none());
+
+        // dropped without completing, as when the handler panics
+        drop(in_flight);
+        assert!(deduplicator.begin("did:example:admin", "msg-1").is_some());
+    }
+
+    #[test]
+    fn test_message_is_processed_again_after_ttl() {
+        let deduplicator = MessageDeduplicator::new(Duration::from_millis(20));
+        assert!(deduplicator.first_delivery("did:example:admin", "msg-1"));
+        assert!(!deduplicator.first_delivery("did:example:admin", "msg-1"));
+
+        std::thread::sleep(Duration::from_millis(30));
+
+        assert!(deduplicator.first_delivery("did:example:admin", "msg-1"));
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_deduplication() {
+        let deduplicator = MessageDeduplicator::new(Duration::ZERO);
+
+        assert!(deduplicator.first_delivery("did:example:admin", "msg-1"));
+        assert!(deduplicator.first_delivery("did:example:admin", "msg-1"));
+    }
+}
@@ -1,8 +1,14 @@
+use affinidi_tdk::secrets_resolver::{
+    jwk::{JWK, Params},
+    secrets::{Secret, SecretMaterial},
+};
+use tracing::warn;
+
 use crate::configs::ProfileConfig;
 
-pub fn build_public_jwk(jwk: &affinidi_tdk::secrets_resolver::jwk::JWK) -> serde_json::Value {
+pub fn build_public_jwk(jwk: &JWK) -> serde_json::Value {
     match &jwk.params {
-        affinidi_tdk::secrets_resolver::jwk::Params::EC(params) => {
+        Params::EC(params) => {
             let mut jwk_obj = serde_json::json!({
                 "kty": "EC",
                 "crv": params.curve,
@@ -14,7 +20,7 @@ pub fn build_public_jwk(jwk: &affinidi_tdk::secrets_resolver::jwk::JWK) -> serde
             }
             jwk_obj
         }
-        affinidi_tdk::secrets_resolver::jwk::Params::OKP(params) => {
+        Params::OKP(params) => {
             let mut jwk_obj = serde_json::json!({
                 "kty": "OKP",
                 "crv": params.curve,
@@ -28,35 +34,75 @@ pub fn build_public_jwk(jwk: &affinidi_tdk::secrets_resolver::jwk::JWK) -> serde
     }
 }
 
-pub fn build_verification_methods(profile_config: &ProfileConfig) -> Vec<serde_json::Value> {
+/// The DID URL of a profile key. Secrets normally carry it as their id, relative ids are
+/// resolved against the profile DID and secrets without one are numbered.
+pub(crate) fn verification_method_id(
+    profile_config: &ProfileConfig,
+    secret: &Secret,
+    index: usize,
+) -> String {
+    if secret.id.starts_with('#') {
+        format!("{}{}", profile_config.did, secret.id)
+    } else if secret.id.contains('#') {
+        secret.id.clone()
+    } else {
+        format!("{}#key-{}", profile_config.did, index)
+    }
+}
+
+/// Public keys of the profile with their verification method ids. Only the public
+/// parameters of each JWK are kept, secrets in any other form are skipped.
+fn public_keys(profile_config: &ProfileConfig) -> Vec<(String, &JWK)> {
     profile_config
         .secrets
         .iter()
         .enumerate()
-        .map(|(index, secret)| {
-            let public_jwk = match &secret.secret_material {
-                affinidi_tdk::secrets_resolver::secrets::SecretMaterial::JWK(jwk) => {
-                    build_public_jwk(jwk)
-                }
-                _ => serde_json::json!({}),
-            };
+        .filter_map(|(index, secret)| match &secret.secret_material {
+            SecretMaterial::JWK(jwk) => {
+                Some((verification_method_id(profile_config, secret, index), jwk))
+            }
+            _ => {
+                warn!(
+                    "Secret {} of {} is not a JWK, leaving it out of the DID document",
+                    secret.id, profile_config.did
+                );
+                None
+            }
+        })
+        .collect()
+}
 
+/// X25519 keys only agree on keys, Ed25519 keys only sign, EC keys do both.
+fn is_key_agreement_key(jwk: &JWK) -> bool {
+    !matches!(&jwk.params, Params::OKP(params) if params.curve == "Ed25519")
+}
+
+fn is_signing_key(jwk: &JWK) -> bool {
+    !matches!(&jwk.params, Params::OKP(params) if params.curve == "X25519")
+}
+
+pub fn build_verification_methods(profile_config: &ProfileConfig) -> Vec<serde_json::Value> {
+    public_keys(profile_config)
+        .into_iter()
+        .map(|(id, jwk)| {
             serde_json::json!({
-                "id": format!("{}#key-{}", profile_config.did, index),
+                "id": id,
                 "type": "JsonWebKey2020",
                 "controller": profile_config.did,
-                "publicKeyJwk": public_jwk,
+                "publicKeyJwk": build_public_jwk(jwk),
             })
         })
         .collect()
 }
 
 pub fn build_did_document(profile_config: &ProfileConfig, mediator_did: &str) -> String {
-    let verification_methods = build_verification_methods(profile_config);
-
-    let key_refs: Vec<String> = (0..profile_config.secrets.len())
-        .map(|index| format!("{}#key-{}", profile_config.did, index))
-        .collect();
+    let keys = public_keys(profile_config);
+    let key_refs = |purpose: fn(&JWK) -> bool| -> Vec<&str> {
+        keys.iter()
+            .filter(|(_, jwk)| purpose(jwk))
+            .map(|(id, _)| id.as_str())
+            .collect()
+    };
 
     serde_json::json!({
         "@context": [
@@ -64,10 +110,10 @@ pub fn build_did_document(profile_config: &ProfileConfig, mediator_did: &str) ->
             "https://w3id.org/security/suites/jws-2020/v1"
         ],
         "id": profile_config.did,
-        "verificationMethod": verification_methods,
-        "authentication": key_refs,
-        "assertionMethod": key_refs,
-        "keyAgreement": key_refs,
+        "verificationMethod": build_verification_methods(profile_config),
+        "authentication": key_refs(is_signing_key),
+        "assertionMethod": key_refs(is_signing_key),
+        "keyAgreement": key_refs(is_key_agreement_key),
         "service": [{
             "id": format!("{}#didcomm", profile_config.did),
             "type": "DIDCommMessaging",
@@ -84,7 +130,6 @@ pub fn build_did_document(profile_config: &ProfileConfig, mediator_did: &str) ->
 #[cfg(test)]
 mod tests {
     use super::*;
-    use affinidi_tdk::secrets_resolver::{jwk::JWK, secrets::Secret};
     use serde_json::json;
 
     #[test]
@@ -144,6 +189,7 @@ mod tests {
             did: "did:web:example.com".to_string(),
             alias: "test".to_string(),
             secrets: vec![secret],
+            mediator_did: None,
         };
 
         let methods = build_verification_methods(&profile);
@@ -203,6 +249,7 @@ mod tests {
             did: "did:web:example.com".to_string(),
             alias: "test".to_string(),
             secrets: vec![secret1, secret2, secret3],
+            mediator_did: None,
         };
 
         let methods = build_verification_methods(&profile);
@@ -233,6 +280,7 @@ mod tests {
             did: "did:web:localhost%3A3232".to_string(),
             alias: "local-test".to_string(),
             secrets: vec![/* test secret */],
+            mediator_did: None,
         };
 
         let doc = build_did_document(&profile, "did:web:mediator.example.com");
@@ -253,6 +301,7 @@ mod tests {
             did: "did:web:example.com".to_string(),
             alias: "test".to_string(),
             secrets: vec![],
+            mediator_did: None,
         };
 
         let doc = build_did_document(&profile, "did:web:mediator.com");
@@ -263,4 +312,64 @@ mod tests {
         assert_eq!(service["serviceEndpoint"]["uri"], "did:web:mediator.com");
         assert_eq!(service["serviceEndpoint"]["accept"][0], "didcomm/v2");
     }
+
+    fn peer_profile() -> ProfileConfig {
+        let signing: Secret = serde_json::from_value(json!({
+            "id": "#key-1",
+            "type": "JsonWebKey2020",
+            "privateKeyJwk": {
+                "crv": "Ed25519",
+                // not real, just copy of x
+                "d": "DfRiO5mCASvWyPxr20GQEfzOmFFh50spyP7KHMjvGQo",
+                "kty": "OKP",
+                "x": "DfRiO5mCASvWyPxr20GQEfzOmFFh50spyP7KHMjvGQo"
+            }
+        }))
+        .unwrap();
+        let agreement: Secret = serde_json::from_value(json!({
+            "id": "did:peer:2.Ez6LS.Vz6Mk#key-2",
+            "type": "JsonWebKey2020",
+            "privateKeyJwk": {
+                "crv": "X25519",
+                // not real, just copy of x
+                "d": "rJcdID8WLUt3Fby5ZsVgyVtrkaEXv050hISLxwY5RrI",
+                "kty": "OKP",
+                "x": "rJcdID8WLUt3Fby5ZsVgyVtrkaEXv050hISLxwY5RrI"
+            }
+        }))
+        .unwrap();
+        ProfileConfig {
+            did: "did:peer:2.Ez6LS.Vz6Mk".to_string(),
+            alias: "peer".to_string(),
+            secrets: vec![signing, agreement],
+            mediator_did: None,
+        }
+    }
+
+    #[test]
+    fn test_did_document_uses_secret_ids_and_key_purposes() {
+        let doc = build_did_document(&peer_profile(), "did:web:mediator.com");
+        let parsed: serde_json::Value = serde_json::from_str(&doc).unwrap();
+
+        assert_eq!(
+            parsed["verificationMethod"][0]["id"],
+            "did:peer:2.Ez6LS.Vz6Mk#key-1"
+        );
+        assert_eq!(
+            parsed["verificationMethod"][1]["id"],
+            "did:peer:2.Ez6LS.Vz6Mk#key-2"
+        );
+        assert_eq!(
+            parsed["authentication"],
+            json!(["did:peer:2.Ez6LS.Vz6Mk#key-1"])
+        );
+        assert_eq!(
+            parsed["assertionMethod"],
+            json!(["did:peer:2.Ez6LS.Vz6Mk#key-1"])
+        );
+        assert_eq!(
+            parsed["keyAgreement"],
+            json!(["did:peer:2.Ez6LS.Vz6Mk#key-2"])
+        );
+    }
 }
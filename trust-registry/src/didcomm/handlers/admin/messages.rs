@@ -1,32 +1,113 @@
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
-// TODO: refactor function signatures to reduce amount of input params
 use crate::{
-    domain::{Action, AuthorityId, Context, EntityId, RecordType, Resource, TrustRecordBuilder},
-    storage::repository::{TrustRecordAdminRepository, TrustRecordQuery},
+    domain::{
+        Action, AuthorityId, Context, EntityId, INITIAL_VERSION, RecordType, Resource, TrustRecord,
+        TrustRecordBuilder, TrustRecordError, TrustRecordErrors,
+    },
+    storage::repository::{
+        RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        UpsertOutcome,
+    },
 };
-use affinidi_tdk::didcomm::Message;
-use serde::Deserialize;
-use serde_json::json;
-use tracing::debug;
+use affinidi_tdk::didcomm::{Attachment, AttachmentData, Message};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
 
 use super::AdminMessagesHandler;
+use crate::didcomm::problem_report::ProblemReport;
 
-#[derive(Debug, Deserialize)]
-struct CreateRecordRequest {
-    entity_id: String,
-    authority_id: String,
-    action: String,
-    resource: String,
-    recognized: bool,
-    authorized: bool,
-    #[serde(default)]
-    context: Option<serde_json::Value>,
-    record_type: String,
+/// Context key holding the DIDComm attachments of create, update and upsert requests.
+pub const ATTACHMENTS_CONTEXT_KEY: &str = "attachments";
+
+/// Parses a DIDComm message body into a typed request.
+fn parse_body<T: DeserializeOwned>(message: Message) -> Result<T, String> {
+    serde_json::from_value(message.body).map_err(|e| e.to_string())
+}
+
+fn to_body<T: Serialize>(response: T) -> Result<Value, ProblemReport> {
+    serde_json::to_value(response).map_err(|e| ProblemReport::internal_error(e.to_string()))
+}
+
+/// Composite key identifying a record, used by delete and read requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordKeyBody {
+    pub entity_id: String,
+    pub authority_id: String,
+    pub action: String,
+    pub resource: String,
+}
+
+impl TryFrom<Message> for RecordKeyBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct UpdateRecordRequest {
+impl From<RecordKeyBody> for TrustRecordQuery {
+    fn from(key: RecordKeyBody) -> Self {
+        TrustRecordQuery::new(
+            EntityId::new(key.entity_id),
+            AuthorityId::new(key.authority_id),
+            Action::new(key.action),
+            Resource::new(key.resource),
+        )
+    }
+}
+
+impl From<&TrustRecord> for RecordKeyBody {
+    fn from(record: &TrustRecord) -> Self {
+        Self {
+            entity_id: record.entity_id().to_string(),
+            authority_id: record.authority_id().to_string(),
+            action: record.action().to_string(),
+            resource: record.resource().to_string(),
+        }
+    }
+}
+
+impl RecordKeyBody {
+    pub fn describe(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.entity_id, self.authority_id, self.action, self.resource
+        )
+    }
+}
+
+/// Full record body, used by create and update requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RecordBodyFields")]
+pub struct RecordBody {
+    #[serde(flatten)]
+    pub key: RecordKeyBody,
+    pub recognized: bool,
+    pub authorized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+    pub record_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Replaces the record's tags; omitted means no tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Version of the record the update was based on. When set, an update of a record
+    /// changed since is rejected as a conflict. Ignored by create and upsert.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<u64>,
+}
+
+/// [`RecordBody`] as parsed, with the key fields inline: fields a flattened struct doesn't
+/// know are dropped unseen, which would hide them from `STRICT_REQUESTS`.
+#[derive(Deserialize)]
+struct RecordBodyFields {
     entity_id: String,
     authority_id: String,
     action: String,
@@ -34,204 +115,977 @@ struct UpdateRecordRequest {
     recognized: bool,
     authorized: bool,
     #[serde(default)]
-    context: Option<serde_json::Value>,
+    context: Option<Value>,
     record_type: String,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    expected_version: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
-struct DeleteRecordRequest {
-    entity_id: String,
-    authority_id: String,
-    action: String,
-    resource: String,
+impl From<RecordBodyFields> for RecordBody {
+    fn from(fields: RecordBodyFields) -> Self {
+        Self {
+            key: RecordKeyBody {
+                entity_id: fields.entity_id,
+                authority_id: fields.authority_id,
+                action: fields.action,
+                resource: fields.resource,
+            },
+            recognized: fields.recognized,
+            authorized: fields.authorized,
+            context: fields.context,
+            record_type: fields.record_type,
+            expires_at: fields.expires_at,
+            tags: fields.tags,
+            expected_version: fields.expected_version,
+        }
+    }
+}
+
+impl TryFrom<Message> for RecordBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
+
+impl RecordBody {
+    /// Parses the body and splits off the message attachments: their metadata goes into
+    /// the context under [`ATTACHMENTS_CONTEXT_KEY`], their content is returned to be
+    /// stored with the record. Without attachments the context is left as is.
+    fn from_message(
+        mut message: Message,
+        max_attachment_bytes: usize,
+    ) -> Result<(Self, Vec<Value>), String> {
+        let attachments = message.attachments.take().unwrap_or_default();
+        let mut body = Self::try_from(message)?;
+        if attachments.is_empty() {
+            return Ok((body, Vec::new()));
+        }
+
+        let content = attachments
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let size: usize = content.iter().map(|c| c.to_string().len()).sum();
+        if size > max_attachment_bytes {
+            return Err(format!(
+                "attachments are {size} bytes, the limit is {max_attachment_bytes} bytes"
+            ));
+        }
+        let metadata: Vec<AttachmentMetadata> = attachments
+            .into_iter()
+            .map(AttachmentMetadata::from)
+            .collect();
+        let metadata = serde_json::to_value(metadata).map_err(|e| e.to_string())?;
+        let context = body
+            .context
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        match context.as_object_mut() {
+            Some(context) => {
+                context.insert(ATTACHMENTS_CONTEXT_KEY.to_string(), metadata);
+            }
+            None => return Err("context must be an object to carry attachments".to_string()),
+        }
+        Ok((body, content))
+    }
+}
+
+impl TryFrom<RecordBody> for TrustRecord {
+    type Error = String;
+
+    fn try_from(body: RecordBody) -> Result<Self, Self::Error> {
+        let record_type = RecordType::from_str(&body.record_type).map_err(|e| e.to_string())?;
+        let key = body.key;
+
+        let mut builder = TrustRecordBuilder::new()
+            .entity_id(EntityId::new(key.entity_id))
+            .authority_id(AuthorityId::new(key.authority_id))
+            .action(Action::new(key.action))
+            .resource(Resource::new(key.resource))
+            .recognized(body.recognized)
+            .authorized(body.authorized)
+            .record_type(record_type)
+            .tags(body.tags);
+
+        if let Some(ctx) = body.context {
+            builder = builder.context(Context::new(ctx));
+        }
+        if let Some(expires_at) = body.expires_at {
+            builder = builder.expires_at(expires_at);
+        }
+
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+/// Checks the record fields of a create, update or upsert body before it is parsed,
+/// so every missing or invalid field is reported at once.
+pub fn validate_record_fields(body: &Value) -> Result<(), TrustRecordErrors> {
+    let field = |name: &str| body.get(name).and_then(Value::as_str);
+
+    let mut builder = TrustRecordBuilder::new();
+    if let Some(entity_id) = field("entity_id") {
+        builder = builder.entity_id(EntityId::new(entity_id));
+    }
+    if let Some(authority_id) = field("authority_id") {
+        builder = builder.authority_id(AuthorityId::new(authority_id));
+    }
+    if let Some(action) = field("action") {
+        builder = builder.action(Action::new(action));
+    }
+    if let Some(resource) = field("resource") {
+        builder = builder.resource(Resource::new(resource));
+    }
+    let record_type = field("record_type").map(RecordType::from_str);
+    if let Some(Ok(record_type)) = &record_type {
+        builder = builder.record_type(record_type.clone());
+    }
+
+    match builder.build() {
+        Ok(_) => Ok(()),
+        Err(errors) => {
+            let errors = errors
+                .into_errors()
+                .into_iter()
+                .map(|error| match (&error, &record_type) {
+                    (TrustRecordError::MissingRecordType, Some(Err(invalid))) => invalid.clone(),
+                    _ => error,
+                })
+                .collect();
+            Err(TrustRecordErrors::new(errors))
+        }
+    }
+}
+
+/// Describes a stored attachment without its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// `base64`, `json` or `links`.
+    pub data_type: String,
+    /// Locations of attachments stored by reference.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+}
+
+impl From<Attachment> for AttachmentMetadata {
+    fn from(attachment: Attachment) -> Self {
+        let (data_type, links) = match attachment.data {
+            AttachmentData::Base64 { .. } => ("base64", vec![]),
+            AttachmentData::Json { .. } => ("json", vec![]),
+            AttachmentData::Links { value } => ("links", value.links),
+        };
+        Self {
+            id: attachment.id,
+            description: attachment.description,
+            filename: attachment.filename,
+            media_type: attachment.media_type,
+            format: attachment.format,
+            data_type: data_type.to_string(),
+            links,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct ReadRecordRequest {
+/// Record as returned by read and list responses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordResponse {
+    #[serde(flatten)]
+    pub key: RecordKeyBody,
+    pub recognized: bool,
+    pub authorized: bool,
+    pub context: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<&TrustRecord> for RecordResponse {
+    fn from(record: &TrustRecord) -> Self {
+        Self {
+            key: RecordKeyBody::from(record),
+            recognized: record.is_recognized(),
+            authorized: record.is_authorized(),
+            context: record.context().as_value().clone(),
+            expires_at: record.expires_at().copied(),
+            tags: record.tags().to_vec(),
+            version: record.version(),
+            created_at: record.created_at().copied(),
+            updated_at: record.updated_at().copied(),
+        }
+    }
+}
+
+/// Keys requested by a read-records request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadRecordsBody {
+    pub keys: Vec<RecordKeyBody>,
+}
+
+impl TryFrom<Message> for ReadRecordsBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadRecordsResponse {
+    pub records: Vec<RecordResponse>,
+    pub not_found: Vec<RecordKeyBody>,
+}
+
+/// Keys to delete by a bulk-delete-records request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkDeleteRecordsBody {
+    pub keys: Vec<RecordKeyBody>,
+}
+
+impl TryFrom<Message> for BulkDeleteRecordsBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulkDeleteRecordsResponse {
+    pub deleted: Vec<RecordKeyBody>,
+    pub not_found: Vec<RecordKeyBody>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpsertRecordResponse {
+    #[serde(flatten)]
+    pub key: RecordKeyBody,
+    /// `true` if the record was created, `false` if an existing one was replaced.
+    pub created: bool,
+}
+
+/// New expiry for an existing record, used by refresh requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RefreshRecordBodyFields")]
+pub struct RefreshRecordBody {
+    #[serde(flatten)]
+    pub key: RecordKeyBody,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// [`RefreshRecordBody`] as parsed, see [`RecordBodyFields`].
+#[derive(Deserialize)]
+struct RefreshRecordBodyFields {
     entity_id: String,
     authority_id: String,
     action: String,
     resource: String,
+    expires_at: DateTime<Utc>,
 }
 
-pub async fn handle_create_record<R: ?Sized + TrustRecordAdminRepository>(
-    handler: &AdminMessagesHandler<R>,
-    message: Message,
-) -> Result<serde_json::Value, String> {
-    let request: CreateRecordRequest =
-        serde_json::from_value(message.body).map_err(|e| e.to_string())?;
+impl From<RefreshRecordBodyFields> for RefreshRecordBody {
+    fn from(fields: RefreshRecordBodyFields) -> Self {
+        Self {
+            key: RecordKeyBody {
+                entity_id: fields.entity_id,
+                authority_id: fields.authority_id,
+                action: fields.action,
+                resource: fields.resource,
+            },
+            expires_at: fields.expires_at,
+        }
+    }
+}
 
-    debug!(
-        "Creating record: {}|{}|{}|{}",
-        request.entity_id, request.authority_id, request.action, request.resource
-    );
+impl TryFrom<Message> for RefreshRecordBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
 
-    let record_type = RecordType::from_str(&request.record_type).map_err(|e| e.to_string())?;
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefreshRecordResponse {
+    #[serde(flatten)]
+    pub key: RecordKeyBody,
+    pub expires_at: DateTime<Utc>,
+    pub version: u64,
+}
 
-    let mut builder = TrustRecordBuilder::new()
-        .entity_id(EntityId::new(request.entity_id.clone()))
-        .authority_id(AuthorityId::new(request.authority_id.clone()))
-        .action(Action::new(request.action.clone()))
-        .resource(Resource::new(request.resource.clone()))
-        .recognized(request.recognized)
-        .authorized(request.authorized)
-        .record_type(record_type);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListRecordsResponse {
+    pub records: Vec<RecordResponse>,
+    pub count: usize,
+}
 
-    if let Some(ctx) = request.context {
-        builder = builder.context(Context::new(ctx));
+impl From<&TrustRecordList> for ListRecordsResponse {
+    fn from(list: &TrustRecordList) -> Self {
+        let records: Vec<RecordResponse> =
+            list.records().iter().map(RecordResponse::from).collect();
+        Self {
+            count: records.len(),
+            records,
+        }
     }
+}
+
+/// Optional status filters of a list request. Absent filters match every record.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListRecordsRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recognized: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorized: Option<bool>,
+}
+
+impl TryFrom<Message> for ListRecordsRequest {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        if message.body.is_null() {
+            return Ok(Self::default());
+        }
+        parse_body(message)
+    }
+}
+
+/// Tag selecting the records of a list-by-tag request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListByTagRequest {
+    pub tag: String,
+}
+
+impl TryFrom<Message> for ListByTagRequest {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
 
-    let record = builder.build().map_err(|e| e.to_string())?;
+/// Confirmation of a truncate request, checked against `TRUNCATE_CONFIRMATION_TOKEN`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TruncateRecordsBody {
+    pub confirmation_token: String,
+}
+
+impl TryFrom<Message> for TruncateRecordsBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TruncateRecordsResponse {
+    pub deleted: usize,
+}
+
+/// Desired state of the registry, used by reconcile requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileBody {
+    pub records: Vec<RecordBody>,
+    /// Applies the plan instead of only returning it.
+    #[serde(default)]
+    pub apply: bool,
+}
+
+impl TryFrom<Message> for ReconcileBody {
+    type Error = String;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        parse_body(message)
+    }
+}
+
+/// Changes that make storage match the desired records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileResponse {
+    /// Desired records missing from storage.
+    pub add: Vec<RecordKeyBody>,
+    /// Stored records whose content differs from the desired one.
+    pub update: Vec<RecordKeyBody>,
+    /// Stored records that are not desired.
+    pub delete: Vec<RecordKeyBody>,
+    pub applied: bool,
+    /// Set when applying stopped at a failed write. The changes made before it stay applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<PartialReconcile>,
+}
 
+/// How far an applied reconcile got before a write failed. Nothing after `failed` was attempted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialReconcile {
+    pub added: Vec<RecordKeyBody>,
+    pub updated: Vec<RecordKeyBody>,
+    pub deleted: Vec<RecordKeyBody>,
+    pub failed: RecordKeyBody,
+    pub error: String,
+}
+
+impl ListRecordsRequest {
+    fn matches(&self, record: &TrustRecord) -> bool {
+        self.recognized
+            .is_none_or(|recognized| record.is_recognized() == recognized)
+            && self
+                .authorized
+                .is_none_or(|authorized| record.is_authorized() == authorized)
+    }
+}
+
+pub async fn handle_create_record<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let (request, attachments) =
+        RecordBody::from_message(message, handler.admin_config.max_attachment_bytes)?;
+    debug!("Creating record: {}", request.key.describe());
+
+    let key = request.key.clone();
+    let now = Utc::now();
+    let record = TrustRecord::try_from(request)?
+        .with_attachments(attachments)
+        .with_created_at(Some(now))
+        .with_updated_at(Some(now));
     handler
         .repository
         .create(record)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ProblemReport::from)?;
 
-    Ok(json!({
-        "entity_id": request.entity_id,
-        "authority_id": request.authority_id,
-        "action": request.action,
-        "resource": request.resource
-    }))
+    to_body(key)
 }
 
 pub async fn handle_update_record<R: ?Sized + TrustRecordAdminRepository>(
     handler: &AdminMessagesHandler<R>,
     message: Message,
-) -> Result<serde_json::Value, String> {
-    let request: UpdateRecordRequest =
-        serde_json::from_value(message.body).map_err(|e| e.to_string())?;
+) -> Result<Value, ProblemReport> {
+    let (request, attachments) =
+        RecordBody::from_message(message, handler.admin_config.max_attachment_bytes)?;
+    debug!("Updating record: {}", request.key.describe());
 
-    debug!(
-        "Updating record: {}|{}|{}|{}",
-        request.entity_id, request.authority_id, request.action, request.resource
-    );
-    let record_type = RecordType::from_str(&request.record_type).map_err(|e| e.to_string())?;
-    let mut builder = TrustRecordBuilder::new()
-        .entity_id(EntityId::new(request.entity_id.clone()))
-        .authority_id(AuthorityId::new(request.authority_id.clone()))
-        .action(Action::new(request.action.clone()))
-        .resource(Resource::new(request.resource.clone()))
-        .recognized(request.recognized)
-        .authorized(request.authorized)
-        .record_type(record_type);
+    let key = request.key.clone();
+    let expected_version = request.expected_version;
+    let current = handler
+        .repository
+        .read(key.clone().into())
+        .await
+        .map_err(ProblemReport::from)?;
+    let record = TrustRecord::try_from(request)?
+        .with_attachments(attachments)
+        .with_version(current.version() + 1)
+        .with_created_at(current.created_at().copied())
+        .with_updated_at(Some(Utc::now()));
+    let updated = match expected_version {
+        Some(expected_version) => {
+            handler
+                .repository
+                .update_if_version(record, expected_version)
+                .await
+        }
+        None => handler.repository.update(record).await,
+    };
+    updated.map_err(ProblemReport::from)?;
 
-    if let Some(ctx) = request.context {
-        builder = builder.context(Context::new(ctx));
-    }
+    to_body(key)
+}
 
-    let record = builder.build().map_err(|e| e.to_string())?;
+pub async fn handle_upsert_record<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let (request, attachments) =
+        RecordBody::from_message(message, handler.admin_config.max_attachment_bytes)?;
+    debug!("Upserting record: {}", request.key.describe());
 
+    let key = request.key.clone();
+    // the write itself is atomic; the version is best effort under concurrent upserts
+    let now = Utc::now();
+    let (version, created_at) = match handler.repository.read(key.clone().into()).await {
+        Ok(current) => (current.version() + 1, current.created_at().copied()),
+        Err(RepositoryError::RecordNotFound(_)) => (INITIAL_VERSION, Some(now)),
+        Err(e) => return Err(e.into()),
+    };
+    let record = TrustRecord::try_from(request)?
+        .with_attachments(attachments)
+        .with_version(version)
+        .with_created_at(created_at)
+        .with_updated_at(Some(now));
+    let outcome = handler
+        .repository
+        .upsert(record)
+        .await
+        .map_err(ProblemReport::from)?;
+
+    to_body(UpsertRecordResponse {
+        key,
+        created: outcome == UpsertOutcome::Created,
+    })
+}
+
+pub async fn handle_refresh_record<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = RefreshRecordBody::try_from(message)?;
+    debug!(
+        "Refreshing record: {} until {}",
+        request.key.describe(),
+        request.expires_at
+    );
+
+    let current = handler
+        .repository
+        .read(request.key.clone().into())
+        .await
+        .map_err(ProblemReport::from)?;
+    let version = current.version() + 1;
+    let record = current
+        .with_expires_at(Some(request.expires_at))
+        .with_version(version)
+        .with_updated_at(Some(Utc::now()));
     handler
         .repository
         .update(record)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ProblemReport::from)?;
 
-    Ok(json!({
-        "entity_id": request.entity_id,
-        "authority_id": request.authority_id,
-        "action": request.action,
-        "resource": request.resource
-    }))
+    to_body(RefreshRecordResponse {
+        key: request.key,
+        expires_at: request.expires_at,
+        version,
+    })
 }
 
 pub async fn handle_delete_record<R: ?Sized + TrustRecordAdminRepository>(
     handler: &AdminMessagesHandler<R>,
     message: Message,
-) -> Result<serde_json::Value, String> {
-    let request: DeleteRecordRequest =
-        serde_json::from_value(message.body).map_err(|e| e.to_string())?;
+) -> Result<Value, ProblemReport> {
+    let key = RecordKeyBody::try_from(message)?;
+    debug!("Deleting record: {}", key.describe());
 
-    debug!(
-        "Deleting record: {}|{}|{}|{}",
-        request.entity_id, request.authority_id, request.action, request.resource
-    );
+    handler
+        .repository
+        .delete(key.clone().into())
+        .await
+        .map_err(ProblemReport::from)?;
 
-    let query = TrustRecordQuery::new(
-        EntityId::new(request.entity_id.clone()),
-        AuthorityId::new(request.authority_id.clone()),
-        Action::new(request.action.clone()),
-        Resource::new(request.resource.clone()),
-    );
+    to_body(key)
+}
 
-    handler
+/// Deletes every requested key in one repository call. Keys without a record are
+/// reported in `not_found` instead of failing the request.
+pub async fn handle_bulk_delete_records<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = BulkDeleteRecordsBody::try_from(message)?;
+    debug!("Deleting {} records", request.keys.len());
+
+    let queries = request.keys.iter().cloned().map(Into::into).collect();
+    let deleted = handler
         .repository
-        .delete(query)
+        .delete_many(queries)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ProblemReport::from)?;
+
+    let mut response = BulkDeleteRecordsResponse {
+        deleted: vec![],
+        not_found: vec![],
+    };
+    for (key, deleted) in request.keys.into_iter().zip(deleted) {
+        if deleted {
+            response.deleted.push(key);
+        } else {
+            response.not_found.push(key);
+        }
+    }
 
-    Ok(json!({
-        "entity_id": request.entity_id,
-        "authority_id": request.authority_id,
-        "action": request.action,
-        "resource": request.resource
-    }))
+    to_body(response)
 }
 
 pub async fn handle_read_record<R: ?Sized + TrustRecordAdminRepository>(
     handler: &AdminMessagesHandler<R>,
     message: Message,
-) -> Result<serde_json::Value, String> {
-    let request: ReadRecordRequest =
-        serde_json::from_value(message.body).map_err(|e| e.to_string())?;
+) -> Result<Value, ProblemReport> {
+    let key = RecordKeyBody::try_from(message)?;
+    debug!("Reading record: {}", key.describe());
 
-    debug!(
-        "Reading record: {}|{}|{}|{}",
-        request.entity_id, request.authority_id, request.action, request.resource
-    );
+    let record = handler
+        .repository
+        .read(key.into())
+        .await
+        .map_err(ProblemReport::from)?;
 
-    let query = TrustRecordQuery::new(
-        EntityId::new(request.entity_id.clone()),
-        AuthorityId::new(request.authority_id.clone()),
-        Action::new(request.action.clone()),
-        Resource::new(request.resource.clone()),
-    );
+    to_body(RecordResponse::from(&record))
+}
 
-    let record = handler
+pub async fn handle_read_records<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = ReadRecordsBody::try_from(message)?;
+    debug!("Reading {} records", request.keys.len());
+
+    let queries = request.keys.iter().cloned().map(Into::into).collect();
+    let found = handler
         .repository
-        .read(query)
+        .read_many(queries)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(ProblemReport::from)?;
+
+    let mut response = ReadRecordsResponse {
+        records: vec![],
+        not_found: vec![],
+    };
+    for (key, record) in request.keys.into_iter().zip(found) {
+        match record {
+            Some(record) => response.records.push(RecordResponse::from(&record)),
+            None => response.not_found.push(key),
+        }
+    }
 
-    Ok(json!({
-        "entity_id": record.entity_id().to_string(),
-        "authority_id": record.authority_id().to_string(),
-        "action": record.action().to_string(),
-        "resource": record.resource().to_string(),
-        "recognized": record.is_recognized(),
-        "authorized": record.is_authorized(),
-        "context": record.context().as_value()
-    }))
+    to_body(response)
 }
 
 pub async fn handle_list_records<R: ?Sized + TrustRecordAdminRepository>(
     handler: &AdminMessagesHandler<R>,
-) -> Result<serde_json::Value, String> {
-    debug!("Listing all records");
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = ListRecordsRequest::try_from(message)?;
+    debug!("Listing records: {:?}", request);
+
+    let mut record_list = handler.repository.list().await?;
+    if request.recognized.is_some() || request.authorized.is_some() {
+        record_list = TrustRecordList::new(
+            record_list
+                .into_records()
+                .into_iter()
+                .filter(|record| request.matches(record))
+                .collect(),
+        );
+    }
+
+    to_body(ListRecordsResponse::from(&record_list))
+}
+
+pub async fn handle_list_by_tag<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = ListByTagRequest::try_from(message).map_err(ProblemReport::bad_request)?;
+    debug!("Listing records tagged {:?}", request.tag);
+
+    let records = handler.repository.find_by_tag(&request.tag).await?;
+    to_body(ListRecordsResponse::from(&TrustRecordList::new(records)))
+}
+
+/// Deletes every record, one by one so caches and history see each deletion. The
+/// confirmation token is checked before the request gets here.
+pub async fn handle_truncate_records<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    _message: Message,
+) -> Result<Value, ProblemReport> {
+    let records = handler
+        .repository
+        .list()
+        .await
+        .map_err(ProblemReport::from)?
+        .into_records();
+    warn!(
+        "Truncating the registry: deleting {} records",
+        records.len()
+    );
 
-    let record_list = handler.repository.list().await.map_err(|e| e.to_string())?;
+    for record in &records {
+        handler
+            .repository
+            .delete(TrustRecordQuery::from(record))
+            .await
+            .map_err(ProblemReport::from)?;
+    }
 
-    let records_json: Vec<serde_json::Value> = record_list
-        .records()
+    to_body(TruncateRecordsResponse {
+        deleted: records.len(),
+    })
+}
+
+/// Compares the desired records with storage and, if asked to, applies the difference.
+/// Records are matched on their key and compared with [`TrustRecord::same_content`].
+pub async fn handle_reconcile<R: ?Sized + TrustRecordAdminRepository>(
+    handler: &AdminMessagesHandler<R>,
+    message: Message,
+) -> Result<Value, ProblemReport> {
+    let request = ReconcileBody::try_from(message).map_err(ProblemReport::bad_request)?;
+    debug!(
+        "Reconciling {} desired records, apply = {}",
+        request.records.len(),
+        request.apply
+    );
+
+    let mut desired = Vec::with_capacity(request.records.len());
+    let mut desired_keys = HashSet::new();
+    for body in request.records {
+        let key = body.key.clone();
+        if !desired_keys.insert(key.describe()) {
+            return Err(ProblemReport::bad_request(format!(
+                "record {} is listed more than once",
+                key.describe()
+            )));
+        }
+        let record = TrustRecord::try_from(body).map_err(|e| {
+            ProblemReport::bad_request(format!("invalid record {}: {e}", key.describe()))
+        })?;
+        desired.push((key, record));
+    }
+
+    let stored_records = handler
+        .repository
+        .list()
+        .await
+        .map_err(ProblemReport::from)?
+        .into_records();
+    let stored: HashMap<String, &TrustRecord> = stored_records
         .iter()
-        .map(|record| {
+        .map(|record| (RecordKeyBody::from(record).describe(), record))
+        .collect();
+
+    let mut plan = ReconcileResponse::default();
+    let mut adds = Vec::new();
+    let mut updates = Vec::new();
+    for (key, record) in desired {
+        match stored.get(&key.describe()) {
+            None => {
+                plan.add.push(key);
+                adds.push(record);
+            }
+            Some(current) if !current.same_content(&record) => {
+                plan.update.push(key);
+                updates.push(
+                    record
+                        .with_version(current.version() + 1)
+                        .with_created_at(current.created_at().copied()),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    let deletes: Vec<&TrustRecord> = stored_records
+        .iter()
+        .filter(|record| !desired_keys.contains(&RecordKeyBody::from(*record).describe()))
+        .collect();
+    plan.delete = deletes
+        .iter()
+        .map(|record| RecordKeyBody::from(*record))
+        .collect();
+
+    if request.apply {
+        plan.partial = apply_reconcile(&*handler.repository, adds, updates, deletes)
+            .await
+            .err();
+        plan.applied = plan.partial.is_none();
+    }
+
+    to_body(plan)
+}
+
+/// Writes a reconcile plan in order, stopping at the first failed write.
+async fn apply_reconcile<R: ?Sized + TrustRecordAdminRepository>(
+    repository: &R,
+    adds: Vec<TrustRecord>,
+    updates: Vec<TrustRecord>,
+    deletes: Vec<&TrustRecord>,
+) -> Result<(), PartialReconcile> {
+    let now = Utc::now();
+    let (mut added, mut updated, mut deleted) = (Vec::new(), Vec::new(), Vec::new());
+    let result: Result<(), (RecordKeyBody, RepositoryError)> = async {
+        for record in adds {
+            let key = RecordKeyBody::from(&record);
+            repository
+                .create(record.with_created_at(Some(now)).with_updated_at(Some(now)))
+                .await
+                .map_err(|e| (key.clone(), e))?;
+            added.push(key);
+        }
+        for record in updates {
+            let key = RecordKeyBody::from(&record);
+            repository
+                .update(record.with_updated_at(Some(now)))
+                .await
+                .map_err(|e| (key.clone(), e))?;
+            updated.push(key);
+        }
+        for record in deletes {
+            let key = RecordKeyBody::from(record);
+            repository
+                .delete(TrustRecordQuery::from(record))
+                .await
+                .map_err(|e| (key.clone(), e))?;
+            deleted.push(key);
+        }
+        Ok(())
+    }
+    .await;
+    result.map_err(|(failed, e)| {
+        warn!("Reconcile stopped at {}: {e}", failed.describe());
+        PartialReconcile {
+            added,
+            updated,
+            deleted,
+            failed,
+            error: e.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(body: Value) -> Message {
+        Message::build("msg-1".to_string(), "test".to_string(), body).finalize()
+    }
+
+    fn record_body() -> Value {
+        json!({
+            "entity_id": "entity-1",
+            "authority_id": "authority-1",
+            "action": "action-1",
+            "resource": "resource-1",
+            "recognized": true,
+            "authorized": false,
+            "context": {"level": 1},
+            "record_type": "assertion"
+        })
+    }
+
+    #[test]
+    fn test_record_body_to_trust_record() {
+        let body = RecordBody::try_from(message(record_body())).unwrap();
+        let record = TrustRecord::try_from(body).unwrap();
+
+        assert_eq!(record.entity_id().as_str(), "entity-1");
+        assert_eq!(record.authority_id().as_str(), "authority-1");
+        assert_eq!(record.action().as_str(), "action-1");
+        assert_eq!(record.resource().as_str(), "resource-1");
+        assert!(record.is_recognized());
+        assert!(!record.is_authorized());
+        assert_eq!(record.context().as_value(), &json!({"level": 1}));
+        assert_eq!(record.record_type(), &RecordType::Authorization);
+    }
+
+    #[test]
+    fn test_record_body_without_context_uses_empty_context() {
+        let mut body = record_body();
+        body.as_object_mut().unwrap().remove("context");
+
+        let record = TrustRecord::try_from(RecordBody::try_from(message(body)).unwrap()).unwrap();
+
+        assert_eq!(record.context().as_value(), &json!({}));
+    }
+
+    #[test]
+    fn test_record_body_missing_field_is_rejected() {
+        let mut body = record_body();
+        body.as_object_mut().unwrap().remove("authorized");
+
+        let err = RecordBody::try_from(message(body)).unwrap_err();
+        assert!(err.contains("authorized"));
+    }
+
+    #[test]
+    fn test_record_body_wrong_type_is_rejected() {
+        let mut body = record_body();
+        body["recognized"] = json!("yes");
+
+        assert!(RecordBody::try_from(message(body)).is_err());
+    }
+
+    #[test]
+    fn test_record_body_invalid_record_type_is_rejected() {
+        let mut body = record_body();
+        body["record_type"] = json!("unknown");
+
+        let body = RecordBody::try_from(message(body)).unwrap();
+        assert!(TrustRecord::try_from(body).is_err());
+    }
+
+    #[test]
+    fn test_record_key_body_to_query() {
+        let key = RecordKeyBody::try_from(message(json!({
+            "entity_id": "entity-1",
+            "authority_id": "authority-1",
+            "action": "action-1",
+            "resource": "resource-1"
+        })))
+        .unwrap();
+
+        let query = TrustRecordQuery::from(key);
+        assert_eq!(query.entity_id.as_str(), "entity-1");
+        assert_eq!(query.resource.as_str(), "resource-1");
+    }
+
+    #[test]
+    fn test_record_key_body_non_object_is_rejected() {
+        assert!(RecordKeyBody::try_from(message(json!("not an object"))).is_err());
+        assert!(RecordKeyBody::try_from(message(json!({"entity_id": "only"}))).is_err());
+    }
+
+    #[test]
+    fn test_record_response_shape() {
+        let body = RecordBody::try_from(message(record_body())).unwrap();
+        let record = TrustRecord::try_from(body).unwrap();
+
+        let value = serde_json::to_value(RecordResponse::from(&record)).unwrap();
+
+        assert_eq!(
+            value,
             json!({
-                "entity_id": record.entity_id().to_string(),
-                "authority_id": record.authority_id().to_string(),
-                "action": record.action().to_string(),
-                "resource": record.resource().to_string(),
-                "recognized": record.is_recognized(),
-                "authorized": record.is_authorized(),
-                "context": record.context().as_value()
+                "entity_id": "entity-1",
+                "authority_id": "authority-1",
+                "action": "action-1",
+                "resource": "resource-1",
+                "recognized": true,
+                "authorized": false,
+                "context": {"level": 1},
+                "version": 1
             })
-        })
-        .collect();
+        );
+    }
+
+    #[test]
+    fn test_list_records_response_counts_records() {
+        let body = RecordBody::try_from(message(record_body())).unwrap();
+        let record = TrustRecord::try_from(body).unwrap();
+        let list = TrustRecordList::new(vec![record.clone(), record]);
 
-    Ok(json!({
-        "records": records_json,
-        "count": records_json.len()
-    }))
+        let value = serde_json::to_value(ListRecordsResponse::from(&list)).unwrap();
+
+        assert_eq!(value["count"], 2);
+        assert_eq!(value["records"].as_array().unwrap().len(), 2);
+    }
 }
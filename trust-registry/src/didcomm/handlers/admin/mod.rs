@@ -1,7 +1,9 @@
 use crate::audit::model::{AuditLogBuilder, AuditLogger, AuditOperation, AuditResource};
 use crate::storage::repository::TrustRecordAdminRepository;
+use crate::util::constant_time_eq;
+use crate::webhook::{RecordChangeEvent, WebhookNotifier};
 use crate::{
-    configs::AdminConfig,
+    configs::{AdminConfig, ThreadIdStrategy},
     didcomm::{
         handlers::{HandlerContext, ProtocolHandler},
         problem_report, transport,
@@ -9,7 +11,9 @@ use crate::{
 };
 use affinidi_tdk::didcomm::{Message, UnpackMetadata};
 use async_trait::async_trait;
-use std::sync::Arc;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::{error, info, warn};
 
 pub mod messages;
@@ -25,6 +29,20 @@ pub const READ_RECORD_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-record";
 pub const LIST_RECORDS_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/tr-admin/1.0/list-records";
+pub const REFRESH_RECORD_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/refresh-record";
+pub const UPSERT_RECORD_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/upsert-record";
+pub const READ_RECORDS_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-records";
+pub const TRUNCATE_RECORDS_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/truncate-records";
+pub const RECONCILE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/reconcile";
+pub const BULK_DELETE_RECORDS_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/bulk-delete-records";
+pub const LIST_BY_TAG_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/list-by-tag";
 
 // Response message types
 pub const CREATE_RECORD_RESPONSE_MESSAGE_TYPE: &str =
@@ -37,24 +55,242 @@ pub const READ_RECORD_RESPONSE_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-record/response";
 pub const LIST_RECORDS_RESPONSE_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/tr-admin/1.0/list-records/response";
+pub const REFRESH_RECORD_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/refresh-record/response";
+pub const UPSERT_RECORD_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/upsert-record/response";
+pub const READ_RECORDS_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-records/response";
+pub const TRUNCATE_RECORDS_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/truncate-records/response";
+pub const RECONCILE_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/reconcile/response";
+pub const BULK_DELETE_RECORDS_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/bulk-delete-records/response";
+pub const LIST_BY_TAG_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-admin/1.0/list-by-tag/response";
+
+/// Sent on receipt of a request whose body sets `ack_requested`, ahead of its response.
+pub const ACK_MESSAGE_TYPE: &str = "https://didcomm.org/notification/1.0/ack";
+
+const MESSAGE_TYPES: [(&str, &str); 12] = [
+    (
+        CREATE_RECORD_MESSAGE_TYPE,
+        CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        UPDATE_RECORD_MESSAGE_TYPE,
+        UPDATE_RECORD_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        DELETE_RECORD_MESSAGE_TYPE,
+        DELETE_RECORD_RESPONSE_MESSAGE_TYPE,
+    ),
+    (READ_RECORD_MESSAGE_TYPE, READ_RECORD_RESPONSE_MESSAGE_TYPE),
+    (
+        LIST_RECORDS_MESSAGE_TYPE,
+        LIST_RECORDS_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        REFRESH_RECORD_MESSAGE_TYPE,
+        REFRESH_RECORD_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        UPSERT_RECORD_MESSAGE_TYPE,
+        UPSERT_RECORD_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        READ_RECORDS_MESSAGE_TYPE,
+        READ_RECORDS_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        TRUNCATE_RECORDS_MESSAGE_TYPE,
+        TRUNCATE_RECORDS_RESPONSE_MESSAGE_TYPE,
+    ),
+    (RECONCILE_MESSAGE_TYPE, RECONCILE_RESPONSE_MESSAGE_TYPE),
+    (
+        BULK_DELETE_RECORDS_MESSAGE_TYPE,
+        BULK_DELETE_RECORDS_RESPONSE_MESSAGE_TYPE,
+    ),
+    (LIST_BY_TAG_MESSAGE_TYPE, LIST_BY_TAG_RESPONSE_MESSAGE_TYPE),
+];
+
+/// `(request, response)` message type pairs of the admin protocol.
+pub fn all_message_types() -> &'static [(&'static str, &'static str)] {
+    &MESSAGE_TYPES
+}
 
 pub struct AdminMessagesHandler<R: ?Sized + TrustRecordAdminRepository> {
     pub repository: Arc<R>,
     pub admin_config: AdminConfig,
     pub audit_service: Arc<dyn AuditLogger>,
+    pub webhook: Option<Arc<WebhookNotifier>>,
+    /// Bounds concurrent mutations, see `ADMIN_MAX_CONCURRENT_MUTATIONS`.
+    mutation_slots: Semaphore,
+}
+
+fn ack_requested(message: &Message) -> bool {
+    message
+        .body
+        .get("ack_requested")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
 }
 
 fn get_operation_from_message_type(message_type: &str) -> AuditOperation {
     match message_type {
         CREATE_RECORD_MESSAGE_TYPE => AuditOperation::Create,
-        UPDATE_RECORD_MESSAGE_TYPE => AuditOperation::Update,
-        DELETE_RECORD_MESSAGE_TYPE => AuditOperation::Delete,
-        READ_RECORD_MESSAGE_TYPE => AuditOperation::Read,
-        LIST_RECORDS_MESSAGE_TYPE => AuditOperation::List,
+        UPDATE_RECORD_MESSAGE_TYPE | REFRESH_RECORD_MESSAGE_TYPE => AuditOperation::Update,
+        UPSERT_RECORD_MESSAGE_TYPE => AuditOperation::Upsert,
+        DELETE_RECORD_MESSAGE_TYPE | BULK_DELETE_RECORDS_MESSAGE_TYPE => AuditOperation::Delete,
+        READ_RECORD_MESSAGE_TYPE | READ_RECORDS_MESSAGE_TYPE => AuditOperation::Read,
+        LIST_RECORDS_MESSAGE_TYPE | LIST_BY_TAG_MESSAGE_TYPE => AuditOperation::List,
+        TRUNCATE_RECORDS_MESSAGE_TYPE => AuditOperation::Truncate,
+        RECONCILE_MESSAGE_TYPE => AuditOperation::Reconcile,
         _ => AuditOperation::Create,
     }
 }
 
+/// The records a request writes: its body for create, update and upsert, each desired
+/// record for reconcile.
+fn record_bodies(message: &Message) -> Vec<&serde_json::Value> {
+    match message.type_.as_str() {
+        CREATE_RECORD_MESSAGE_TYPE | UPDATE_RECORD_MESSAGE_TYPE | UPSERT_RECORD_MESSAGE_TYPE => {
+            vec![&message.body]
+        }
+        RECONCILE_MESSAGE_TYPE => message
+            .body
+            .get("records")
+            .and_then(|records| records.as_array())
+            .map(|records| records.iter().collect())
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// `path` as written in a request body, e.g. `keys[1].resource`.
+fn field_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => format!("{}[{index}]", field_path(parent)),
+        serde_ignored::Path::Map { parent, key } => match field_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{parent}.{key}"),
+        },
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => field_path(parent),
+    }
+}
+
+/// The first field of `body` that parsing it as `T` ignores. `ack_requested` and the
+/// correlation id are accepted at the top level of every body.
+fn first_ignored_field<T: serde::de::DeserializeOwned>(body: &serde_json::Value) -> Option<String> {
+    let mut ignored = None;
+    let _ = serde_ignored::deserialize::<_, _, T>(body, |path| {
+        let field = field_path(&path);
+        if ignored.is_none() && !["ack_requested", CORRELATION_ID_FIELD].contains(&field.as_str()) {
+            ignored = Some(field);
+        }
+    });
+    ignored
+}
+
+/// The first body field a request of this type doesn't know, for `STRICT_REQUESTS`,
+/// found by parsing the body as the request's type.
+fn unknown_field(message: &Message) -> Option<String> {
+    let body = &message.body;
+    match message.type_.as_str() {
+        CREATE_RECORD_MESSAGE_TYPE | UPDATE_RECORD_MESSAGE_TYPE | UPSERT_RECORD_MESSAGE_TYPE => {
+            first_ignored_field::<messages::RecordBody>(body)
+        }
+        DELETE_RECORD_MESSAGE_TYPE | READ_RECORD_MESSAGE_TYPE => {
+            first_ignored_field::<messages::RecordKeyBody>(body)
+        }
+        REFRESH_RECORD_MESSAGE_TYPE => first_ignored_field::<messages::RefreshRecordBody>(body),
+        LIST_RECORDS_MESSAGE_TYPE if !body.is_null() => {
+            first_ignored_field::<messages::ListRecordsRequest>(body)
+        }
+        LIST_BY_TAG_MESSAGE_TYPE => first_ignored_field::<messages::ListByTagRequest>(body),
+        READ_RECORDS_MESSAGE_TYPE => first_ignored_field::<messages::ReadRecordsBody>(body),
+        BULK_DELETE_RECORDS_MESSAGE_TYPE => {
+            first_ignored_field::<messages::BulkDeleteRecordsBody>(body)
+        }
+        TRUNCATE_RECORDS_MESSAGE_TYPE => first_ignored_field::<messages::TruncateRecordsBody>(body),
+        RECONCILE_MESSAGE_TYPE => first_ignored_field::<messages::ReconcileBody>(body),
+        _ => None,
+    }
+}
+
+fn is_mutation(operation: AuditOperation) -> bool {
+    matches!(
+        operation,
+        AuditOperation::Create
+            | AuditOperation::Update
+            | AuditOperation::Upsert
+            | AuditOperation::Delete
+            | AuditOperation::Truncate
+            | AuditOperation::Reconcile
+    )
+}
+
+fn key_resource(key: &messages::RecordKeyBody) -> AuditResource {
+    AuditResource::new(
+        Some(crate::domain::EntityId::new(&key.entity_id)),
+        Some(crate::domain::AuthorityId::new(&key.authority_id)),
+        Some(crate::domain::Action::new(&key.action)),
+        Some(crate::domain::Resource::new(&key.resource)),
+    )
+}
+
+/// The keys a bulk-delete-records response reports deleted and not found.
+fn bulk_delete_outcome(
+    response_body: &serde_json::Value,
+) -> Option<messages::BulkDeleteRecordsResponse> {
+    serde_json::from_value(response_body.clone()).ok()
+}
+
+/// Webhook events for a successful mutation: one per changed record, or a single one
+/// for a truncate.
+fn change_events(
+    message_type: &str,
+    operation: AuditOperation,
+    resource: &AuditResource,
+    response_body: &serde_json::Value,
+) -> Vec<RecordChangeEvent> {
+    let keyed = |operation, keys: Vec<messages::RecordKeyBody>| {
+        keys.into_iter()
+            .map(move |key| RecordChangeEvent::new(operation, &key_resource(&key)))
+    };
+    match message_type {
+        BULK_DELETE_RECORDS_MESSAGE_TYPE => bulk_delete_outcome(response_body)
+            .map(|outcome| keyed(AuditOperation::Delete, outcome.deleted).collect())
+            .unwrap_or_default(),
+        RECONCILE_MESSAGE_TYPE => {
+            serde_json::from_value::<messages::ReconcileResponse>(response_body.clone())
+                .ok()
+                .and_then(|plan| match plan.partial {
+                    Some(partial) => Some((partial.added, partial.updated, partial.deleted)),
+                    None if plan.applied => Some((plan.add, plan.update, plan.delete)),
+                    None => None,
+                })
+                .map(|(added, updated, deleted)| {
+                    keyed(AuditOperation::Create, added)
+                        .chain(keyed(AuditOperation::Update, updated))
+                        .chain(keyed(AuditOperation::Delete, deleted))
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        TRUNCATE_RECORDS_MESSAGE_TYPE => {
+            serde_json::from_value::<messages::TruncateRecordsResponse>(response_body.clone())
+                .map(|truncated| vec![RecordChangeEvent::truncated(truncated.deleted)])
+                .unwrap_or_default()
+        }
+        _ => vec![RecordChangeEvent::new(operation, resource)],
+    }
+}
+
 fn extract_audit_resource(message: &Message) -> AuditResource {
     message
         .body
@@ -95,16 +331,39 @@ fn extract_audit_resource(message: &Message) -> AuditResource {
         .unwrap_or_else(AuditResource::empty)
 }
 
+/// Header, or body field, carrying a caller supplied request id for the audit log.
+pub const CORRELATION_ID_FIELD: &str = "correlation_id";
+
+/// The caller's correlation id, read from the message headers before the body.
+fn extract_correlation_id(message: &Message) -> Option<String> {
+    message
+        .extra_headers
+        .get(CORRELATION_ID_FIELD)
+        .or_else(|| message.body.get(CORRELATION_ID_FIELD))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+}
+
 impl<R: ?Sized + TrustRecordAdminRepository> AdminMessagesHandler<R> {
     pub fn new(
         repository: Arc<R>,
         admin_config: AdminConfig,
         audit_service: Arc<dyn AuditLogger>,
     ) -> Self {
+        let webhook = admin_config
+            .webhook_config
+            .clone()
+            .map(|config| Arc::new(WebhookNotifier::new(config)));
+        let mutation_slots = match admin_config.max_concurrent_mutations {
+            0 => Semaphore::MAX_PERMITS,
+            limit => limit,
+        };
         Self {
             repository,
             admin_config,
             audit_service,
+            webhook,
+            mutation_slots: Semaphore::new(mutation_slots),
         }
     }
 
@@ -123,131 +382,498 @@ impl<R: ?Sized + TrustRecordAdminRepository> AdminMessagesHandler<R> {
         }
     }
 
-    async fn handle_success(
-        &self,
-        ctx: &Arc<HandlerContext>,
-        response_message_type: String,
-        response_body: serde_json::Value,
-        operation: AuditOperation,
-        resource: AuditResource,
-    ) {
-        self.audit_service
-            .log(
-                AuditLogBuilder::new()
-                    .operation(operation)
-                    .actor(&ctx.sender_did)
-                    .resource(resource)
-                    .thread_id(ctx.thid.clone())
-                    .build_success(),
-            )
-            .await;
+    /// Validate that a mutation only writes records of authorities the sender owns
+    fn validate_authority_access(&self, sender_did: &str, message: &Message) -> Result<(), String> {
+        if !is_mutation(get_operation_from_message_type(&message.type_)) {
+            return Ok(());
+        }
+        // a truncate or reconcile may delete the records of every authority
+        let authority_ids: Vec<&str> = match message.type_.as_str() {
+            TRUNCATE_RECORDS_MESSAGE_TYPE | RECONCILE_MESSAGE_TYPE => {
+                vec![crate::storage::wildcard::WILDCARD]
+            }
+            BULK_DELETE_RECORDS_MESSAGE_TYPE => message
+                .body
+                .get("keys")
+                .and_then(|keys| keys.as_array())
+                .map(|keys| {
+                    keys.iter()
+                        .map(|key| {
+                            key.get("authority_id")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or_default()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![
+                message
+                    .body
+                    .get("authority_id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default(),
+            ],
+        };
+        match authority_ids.into_iter().find(|authority_id| {
+            !self
+                .admin_config
+                .may_write_authority(sender_did, authority_id)
+        }) {
+            None => Ok(()),
+            Some(authority_id) => Err(format!(
+                "Unauthorized: DID {sender_did} may not write records of authority {authority_id}"
+            )),
+        }
+    }
+
+    /// Rejects records with missing or invalid fields, listing each one in `args`.
+    fn check_record_fields(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        for body in record_bodies(message) {
+            messages::validate_record_fields(body).map_err(|errors| {
+                problem_report::ProblemReport::bad_request(format!("invalid record: {errors}"))
+                    .with_args(errors.errors().iter().map(ToString::to_string).collect())
+            })?;
+        }
+        Ok(())
+    }
 
+    /// Rejects bodies with fields the request type doesn't know under `STRICT_REQUESTS`,
+    /// so a misspelled field isn't silently ignored.
+    fn check_known_fields(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        if !self.admin_config.strict_requests {
+            return Ok(());
+        }
+        match unknown_field(message) {
+            Some(field) => Err(problem_report::ProblemReport::bad_request(format!(
+                "unknown field {field:?}"
+            ))
+            .with_args(vec![field])),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects records whose entity or authority id is not a well-formed DID.
+    fn check_record_dids(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        for body in record_bodies(message) {
+            for field in ["entity_id", "authority_id"] {
+                if let Some(value) = body.get(field).and_then(|v| v.as_str()) {
+                    crate::domain::Did::parse(value).map_err(|e| {
+                        problem_report::ProblemReport::bad_request(format!(
+                            "{field} {value:?} is not a valid DID: {e}"
+                        ))
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects records whose serialized context exceeds `max_context_bytes`.
+    fn check_context_size(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        let limit = self.admin_config.max_context_bytes;
+        if limit == 0 {
+            return Ok(());
+        }
+        for body in record_bodies(message) {
+            let size = body
+                .get("context")
+                .map_or(0, |context| context.to_string().len());
+            if size > limit {
+                return Err(problem_report::ProblemReport::bad_request(format!(
+                    "context is {size} bytes, the limit is {limit} bytes"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuses truncate requests while truncate is disabled or without the configured
+    /// confirmation token.
+    fn check_truncate(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        if message.type_ != TRUNCATE_RECORDS_MESSAGE_TYPE {
+            return Ok(());
+        }
+        let truncate_config = &self.admin_config.truncate_config;
+        if truncate_config.disabled {
+            return Err(problem_report::ProblemReport::bad_request(
+                "truncate-records is disabled on this registry",
+            ));
+        }
+        let presented = message
+            .body
+            .get("confirmation_token")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        match &truncate_config.confirmation_token {
+            Some(token) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => Ok(()),
+            _ => Err(problem_report::ProblemReport::unauthorized(
+                "confirmation token does not match",
+            )),
+        }
+    }
+
+    /// Acknowledges receipt when the body sets `ack_requested`, so a client waiting on a
+    /// slow operation knows the request was accepted.
+    async fn send_ack_if_requested(&self, ctx: &Arc<HandlerContext>, message: &Message) {
+        if !ack_requested(message) {
+            return;
+        }
         if let Err(e) = transport::send_response(
             &ctx.atm,
             &ctx.profile,
-            response_message_type,
-            response_body,
+            ACK_MESSAGE_TYPE.to_string(),
+            json!({ "status": "OK" }),
             &ctx.sender_did,
             ctx.thid.clone(),
             ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
         )
         .await
         {
-            error!("Failed to send response: {}", e);
+            warn!("Failed to acknowledge message {}: {e}", message.id);
+        }
+    }
+
+    /// Rejects messages without a `thid` under the strict thread id strategy.
+    fn check_thread_id(&self, message: &Message) -> Result<(), problem_report::ProblemReport> {
+        match (self.admin_config.thread_id_strategy, &message.thid) {
+            (ThreadIdStrategy::Strict, None) => Err(problem_report::ProblemReport::bad_request(
+                format!("message {} has no thid", message.id),
+            )),
+            _ => Ok(()),
         }
     }
 
-    async fn handle_failure(
+    /// Rejects mutations while the registry is in read-only mode.
+    fn check_writable(&self, message_type: &str) -> Result<(), problem_report::ProblemReport> {
+        let operation = get_operation_from_message_type(message_type);
+        if self.admin_config.read_only.is_enabled() && is_mutation(operation) {
+            return Err(problem_report::ProblemReport::read_only());
+        }
+        Ok(())
+    }
+
+    /// Waits for a mutation slot; reads never wait. Fails as busy once the
+    /// configured queue timeout elapses.
+    async fn acquire_mutation_slot(
         &self,
-        ctx: &Arc<HandlerContext>,
-        error_msg: String,
-        operation: AuditOperation,
-        resource: AuditResource,
+        message_type: &str,
+    ) -> Result<Option<SemaphorePermit<'_>>, problem_report::ProblemReport> {
+        if !is_mutation(get_operation_from_message_type(message_type)) {
+            return Ok(None);
+        }
+        let acquire = self.mutation_slots.acquire();
+        let permit = match self.admin_config.mutation_queue_timeout_ms {
+            0 => acquire.await,
+            timeout_ms => tokio::time::timeout(Duration::from_millis(timeout_ms), acquire)
+                .await
+                .map_err(|_| {
+                    problem_report::ProblemReport::busy(format!(
+                        "registry busy: no admin write slot within {timeout_ms} ms"
+                    ))
+                })?,
+        };
+        // the semaphore is never closed
+        Ok(Some(permit.expect("mutation semaphore closed")))
+    }
+
+    /// [`Self::execute_audited`] once a mutation slot is available.
+    async fn execute_limited(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: Message,
+        message_type: &str,
+    ) -> Result<
+        Option<(
+            &'static str,
+            Result<serde_json::Value, problem_report::ProblemReport>,
+        )>,
+        problem_report::ProblemReport,
+    > {
+        let _permit = self.acquire_mutation_slot(message_type).await?;
+        Ok(self
+            .execute_audited(actor, thread_id, message, message_type)
+            .await)
+    }
+
+    /// Records a request refused before it ran.
+    async fn audit_rejection(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: &Message,
+        report: &problem_report::ProblemReport,
     ) {
+        warn!(
+            "Rejected {} from {}: {}",
+            message.type_, actor, report.comment
+        );
         self.audit_service
             .log(
                 AuditLogBuilder::new()
-                    .operation(operation)
-                    .actor(&ctx.sender_did)
-                    .resource(resource)
-                    .thread_id(ctx.thid.clone())
-                    .build_failure(&error_msg),
+                    .operation(get_operation_from_message_type(&message.type_))
+                    .actor(actor)
+                    .resource(extract_audit_resource(message))
+                    .thread_id(thread_id)
+                    .correlation_id(extract_correlation_id(message))
+                    .build_failure(&report.comment),
             )
             .await;
+    }
 
-        error!(
-            "[profile = {}] Admin operation failed: {}",
-            &ctx.profile.inner.alias, error_msg
-        );
-        let report = problem_report::ProblemReport::internal_error(error_msg);
-        if let Err(send_err) = problem_report::send_problem_report(
+    /// Checks a request before it runs, the same way for every transport: the authority
+    /// ACL, read-only mode, `STRICT_REQUESTS`, the record fields and DIDs, the context size
+    /// and the truncate confirmation. Refusals are audited. `actor` must already be
+    /// authenticated as an administrator.
+    pub async fn admit(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: &Message,
+    ) -> Result<(), problem_report::ProblemReport> {
+        if let Err(auth_error) = self.validate_authority_access(actor, message) {
+            self.audit_unauthorized(actor, thread_id, message, &auth_error)
+                .await;
+            return Err(problem_report::ProblemReport::unauthorized(auth_error));
+        }
+        if let Err(report) = self
+            .check_writable(&message.type_)
+            .and_then(|_| self.check_known_fields(message))
+            .and_then(|_| self.check_record_fields(message))
+            .and_then(|_| self.check_record_dids(message))
+            .and_then(|_| self.check_context_size(message))
+            .and_then(|_| self.check_truncate(message))
+        {
+            self.audit_rejection(actor, thread_id, message, &report)
+                .await;
+            return Err(report);
+        }
+        Ok(())
+    }
+
+    /// Runs an admitted request once a mutation slot is free, see
+    /// `ADMIN_MAX_CONCURRENT_MUTATIONS`. The outcome is audited and successful mutations
+    /// are announced to the webhook. Returns the response message type and body.
+    pub async fn run(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: Message,
+    ) -> Result<(&'static str, serde_json::Value), problem_report::ProblemReport> {
+        let message_type = message.type_.clone();
+        let rejected = message.clone();
+        let executed = match self
+            .execute_limited(actor, thread_id.clone(), message, &message_type)
+            .await
+        {
+            Ok(executed) => executed,
+            Err(report) => {
+                self.audit_rejection(actor, thread_id, &rejected, &report)
+                    .await;
+                return Err(report);
+            }
+        };
+        let Some((response_message_type, result)) = executed else {
+            warn!("Unknown admin message type: {}", message_type);
+            return Err(problem_report::ProblemReport::bad_request(format!(
+                "Unknown message type: {message_type}"
+            )));
+        };
+        result.map(|body| (response_message_type, body))
+    }
+
+    /// [`Self::admit`] and [`Self::run`] for transports without an acknowledgement step.
+    pub async fn process(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: Message,
+    ) -> Result<(&'static str, serde_json::Value), problem_report::ProblemReport> {
+        self.admit(actor, thread_id.clone(), &message).await?;
+        self.run(actor, thread_id, message).await
+    }
+
+    async fn send_problem_report(
+        &self,
+        ctx: &Arc<HandlerContext>,
+        report: problem_report::ProblemReport,
+    ) {
+        if let Err(e) = problem_report::send_problem_report(
             &ctx.atm,
             &ctx.profile,
             report,
             &ctx.sender_did,
             ctx.thid.clone(),
             ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
         )
         .await
         {
-            error!("Failed to send problem report: {}", send_err);
+            error!("Failed to send problem report: {}", e);
         }
     }
 
-    async fn handle_unauthorized(
+    async fn handle_success(
         &self,
         ctx: &Arc<HandlerContext>,
-        auth_error: String,
-        message_type: &str,
+        response_message_type: String,
+        response_body: serde_json::Value,
     ) {
-        warn!(
-            "[profile = {}] Unauthorized admin access attempt from {}: {}",
-            &ctx.profile.inner.alias, ctx.sender_did, auth_error
-        );
-
-        let operation = get_operation_from_message_type(message_type);
-
-        self.audit_service
-            .log(
-                AuditLogBuilder::new()
-                    .operation(operation)
-                    .actor(&ctx.sender_did)
-                    .resource(AuditResource::empty())
-                    .thread_id(ctx.thid.clone())
-                    .build_unauthorized(&auth_error),
-            )
-            .await;
-
-        let report = problem_report::ProblemReport::unauthorized(auth_error);
-        if let Err(e) = problem_report::send_problem_report(
+        if let Err(e) = transport::send_response(
             &ctx.atm,
             &ctx.profile,
-            report,
+            response_message_type,
+            response_body,
             &ctx.sender_did,
             ctx.thid.clone(),
             ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
         )
         .await
         {
-            error!("Failed to send problem report: {}", e);
+            error!("Failed to send response: {}", e);
         }
     }
 
+    async fn audit_unauthorized(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: &Message,
+        auth_error: &str,
+    ) {
+        warn!(
+            "Unauthorized admin access attempt from {}: {}",
+            actor, auth_error
+        );
+        self.audit_service
+            .log(
+                AuditLogBuilder::new()
+                    .operation(get_operation_from_message_type(&message.type_))
+                    .actor(actor)
+                    .resource(AuditResource::empty())
+                    .thread_id(thread_id)
+                    .correlation_id(extract_correlation_id(message))
+                    .build_unauthorized(auth_error),
+            )
+            .await;
+    }
+
     async fn handle_request(
         &self,
         ctx: &Arc<HandlerContext>,
         message: Message,
-        message_type: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
         info!(
             "[profile = {}] Admin operation: {} from {}",
-            &ctx.profile.inner.alias, message_type, ctx.sender_did
+            &ctx.profile.inner.alias, message.type_, ctx.sender_did
         );
 
+        if let Err(report) = self
+            .admit(&ctx.sender_did, ctx.thid.clone(), &message)
+            .await
+        {
+            self.send_problem_report(ctx, report).await;
+            return Ok(());
+        }
+        if let Err(report) = self.check_thread_id(&message) {
+            self.audit_rejection(&ctx.sender_did, ctx.thid.clone(), &message, &report)
+                .await;
+            self.send_problem_report(ctx, report).await;
+            return Ok(());
+        }
+        self.send_ack_if_requested(ctx, &message).await;
+
+        match self.run(&ctx.sender_did, ctx.thid.clone(), message).await {
+            Ok((response_message_type, response_body)) => {
+                self.handle_success(ctx, response_message_type.to_string(), response_body)
+                    .await
+            }
+            Err(report) => {
+                error!(
+                    "[profile = {}] Admin operation failed: {}",
+                    &ctx.profile.inner.alias, report.comment
+                );
+                self.send_problem_report(ctx, report).await
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Runs the admin operation and records its outcome with the audit logger.
+    /// Unknown message types return `None` and are not audited.
+    async fn execute_audited(
+        &self,
+        actor: &str,
+        thread_id: Option<String>,
+        message: Message,
+        message_type: &str,
+    ) -> Option<(
+        &'static str,
+        Result<serde_json::Value, problem_report::ProblemReport>,
+    )> {
         let operation = get_operation_from_message_type(message_type);
         let resource = extract_audit_resource(&message);
+        let correlation_id = extract_correlation_id(&message);
 
-        let (response_message_type, handler_result) = match message_type {
+        let (response_message_type, result) = self
+            .execute(message_type, message, operation, &resource)
+            .await?;
+
+        // a bulk delete is audited once per key
+        let outcomes = match &result {
+            Ok(body) if message_type == BULK_DELETE_RECORDS_MESSAGE_TYPE => {
+                let outcome = bulk_delete_outcome(body);
+                let deleted = outcome.iter().flat_map(|o| &o.deleted);
+                let not_found = outcome.iter().flat_map(|o| &o.not_found);
+                deleted
+                    .map(|key| (key_resource(key), None))
+                    .chain(not_found.map(|key| {
+                        (
+                            key_resource(key),
+                            Some(format!("Record not found: {}", key.describe())),
+                        )
+                    }))
+                    .collect()
+            }
+            Ok(_) => vec![(resource, None)],
+            Err(report) => vec![(resource, Some(report.comment.clone()))],
+        };
+        for (resource, failure) in outcomes {
+            let builder = AuditLogBuilder::new()
+                .operation(operation)
+                .actor(actor)
+                .resource(resource)
+                .thread_id(thread_id.clone())
+                .correlation_id(correlation_id.clone());
+            let audit_log = match failure {
+                None => builder.build_success(),
+                Some(failure) => builder.build_failure(&failure),
+            };
+            self.audit_service.log(audit_log).await;
+        }
+
+        Some((response_message_type, result))
+    }
+
+    /// Runs the admin operation for `message_type`, returning `None` for unknown types.
+    /// Successful mutations are announced to the webhook, if configured.
+    async fn execute(
+        &self,
+        message_type: &str,
+        message: Message,
+        operation: AuditOperation,
+        resource: &AuditResource,
+    ) -> Option<(
+        &'static str,
+        Result<serde_json::Value, problem_report::ProblemReport>,
+    )> {
+        let result = match message_type {
             CREATE_RECORD_MESSAGE_TYPE => (
                 CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
                 messages::handle_create_record(self, message).await,
@@ -266,60 +892,57 @@ impl<R: ?Sized + TrustRecordAdminRepository> AdminMessagesHandler<R> {
             ),
             LIST_RECORDS_MESSAGE_TYPE => (
                 LIST_RECORDS_RESPONSE_MESSAGE_TYPE,
-                messages::handle_list_records(self).await,
+                messages::handle_list_records(self, message).await,
             ),
-            _ => {
-                warn!("Unknown admin message type: {}", message_type);
-                let report = problem_report::ProblemReport::bad_request(format!(
-                    "Unknown message type: {message_type}"
-                ));
-                if let Err(e) = problem_report::send_problem_report(
-                    &ctx.atm,
-                    &ctx.profile,
-                    report,
-                    &ctx.sender_did,
-                    ctx.thid.clone(),
-                    ctx.pthid.clone(),
-                )
-                .await
-                {
-                    error!("Failed to send problem report: {}", e);
-                }
-                return Ok(());
-            }
+            REFRESH_RECORD_MESSAGE_TYPE => (
+                REFRESH_RECORD_RESPONSE_MESSAGE_TYPE,
+                messages::handle_refresh_record(self, message).await,
+            ),
+            UPSERT_RECORD_MESSAGE_TYPE => (
+                UPSERT_RECORD_RESPONSE_MESSAGE_TYPE,
+                messages::handle_upsert_record(self, message).await,
+            ),
+            READ_RECORDS_MESSAGE_TYPE => (
+                READ_RECORDS_RESPONSE_MESSAGE_TYPE,
+                messages::handle_read_records(self, message).await,
+            ),
+            TRUNCATE_RECORDS_MESSAGE_TYPE => (
+                TRUNCATE_RECORDS_RESPONSE_MESSAGE_TYPE,
+                messages::handle_truncate_records(self, message).await,
+            ),
+            RECONCILE_MESSAGE_TYPE => (
+                RECONCILE_RESPONSE_MESSAGE_TYPE,
+                messages::handle_reconcile(self, message).await,
+            ),
+            BULK_DELETE_RECORDS_MESSAGE_TYPE => (
+                BULK_DELETE_RECORDS_RESPONSE_MESSAGE_TYPE,
+                messages::handle_bulk_delete_records(self, message).await,
+            ),
+            LIST_BY_TAG_MESSAGE_TYPE => (
+                LIST_BY_TAG_RESPONSE_MESSAGE_TYPE,
+                messages::handle_list_by_tag(self, message).await,
+            ),
+            _ => return None,
         };
 
-        match handler_result {
-            Ok(response_body) => {
-                self.handle_success(
-                    ctx,
-                    response_message_type.to_string(),
-                    response_body,
-                    operation,
-                    resource,
-                )
-                .await
-            }
-            Err(error_msg) => {
-                self.handle_failure(ctx, error_msg, operation, resource)
-                    .await
+        if let (Some(webhook), Ok(body), true) = (&self.webhook, &result.1, is_mutation(operation))
+        {
+            for event in change_events(message_type, operation, resource, body) {
+                webhook.notify(event);
             }
-        };
+        }
 
-        Ok(())
+        Some(result)
     }
 }
 
 #[async_trait]
 impl<R: ?Sized + TrustRecordAdminRepository + 'static> ProtocolHandler for AdminMessagesHandler<R> {
     fn get_supported_inbound_message_types(&self) -> Vec<String> {
-        vec![
-            CREATE_RECORD_MESSAGE_TYPE.to_string(),
-            UPDATE_RECORD_MESSAGE_TYPE.to_string(),
-            DELETE_RECORD_MESSAGE_TYPE.to_string(),
-            READ_RECORD_MESSAGE_TYPE.to_string(),
-            LIST_RECORDS_MESSAGE_TYPE.to_string(),
-        ]
+        all_message_types()
+            .iter()
+            .map(|(request, _)| request.to_string())
+            .collect()
     }
 
     async fn handle(
@@ -328,14 +951,1489 @@ impl<R: ?Sized + TrustRecordAdminRepository + 'static> ProtocolHandler for Admin
         message: Message,
         _meta: UnpackMetadata,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let message_type = message.type_.clone();
-
         if let Err(auth_error) = self.validate_admin_did(&ctx.sender_did) {
-            self.handle_unauthorized(ctx, auth_error, &message_type)
+            self.audit_unauthorized(&ctx.sender_did, ctx.thid.clone(), &message, &auth_error)
+                .await;
+            self.send_problem_report(ctx, problem_report::ProblemReport::unauthorized(auth_error))
                 .await;
             return Ok(());
         }
 
-        self.handle_request(ctx, message, &message_type).await
+        self.handle_request(ctx, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audit::model::{AuditLog, AuditStatus},
+        configs::WebhookConfig,
+        storage::{
+            adapters::local_storage::LocalStorage,
+            repository::{RepositoryError, TrustRecordRepository},
+        },
+        webhook::{SIGNATURE_HEADER, sign},
+    };
+    use axum::{Router, body::Bytes, extract::State, http::HeaderMap, routing::post};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    struct NoopAuditLogger;
+
+    #[async_trait]
+    impl AuditLogger for NoopAuditLogger {
+        async fn log(&self, _audit_log: AuditLog) {}
+    }
+
+    async fn start_mock_webhook() -> (String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(tx): State<mpsc::UnboundedSender<(HeaderMap, Bytes)>>,
+                     headers: HeaderMap,
+                     body: Bytes| async move {
+                        let _ = tx.send((headers, body));
+                    },
+                ),
+            )
+            .with_state(tx);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{address}/hook"), rx)
+    }
+
+    fn handler_with_webhook(url: String) -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            webhook_config: Some(WebhookConfig {
+                url,
+                secret: "test-secret".to_string(),
+                max_retries: 0,
+            }),
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    fn create_message() -> Message {
+        Message::build(
+            "msg-1".to_string(),
+            CREATE_RECORD_MESSAGE_TYPE.to_string(),
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential",
+                "recognized": true,
+                "authorized": true,
+                "record_type": "assertion"
+            }),
+        )
+        .finalize()
+    }
+
+    #[test]
+    fn test_all_message_types_match_supported_types() {
+        let handler = handler_with_webhook("http://127.0.0.1:9/hook".to_string());
+
+        let requests: Vec<&str> = all_message_types().iter().map(|(r, _)| *r).collect();
+        assert_eq!(handler.get_supported_inbound_message_types(), requests);
+        for (request, response) in all_message_types() {
+            assert_eq!(*response, format!("{request}/response"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_fires_signed_webhook_event() {
+        let (url, mut events) = start_mock_webhook().await;
+        let handler = handler_with_webhook(url);
+        let message = create_message();
+        let resource = extract_audit_resource(&message);
+
+        let (_, result) = handler
+            .execute(
+                CREATE_RECORD_MESSAGE_TYPE,
+                message,
+                AuditOperation::Create,
+                &resource,
+            )
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("webhook was not called")
+            .unwrap();
+        assert_eq!(
+            headers.get(SIGNATURE_HEADER).unwrap().to_str().unwrap(),
+            sign("test-secret", &body)
+        );
+        let event: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event["operation"], "CREATE");
+        assert_eq!(
+            event["key"],
+            "did:example:entity|did:example:authority|issue|credential"
+        );
+        assert!(event["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_bulk_operations_announce_each_changed_key() {
+        let key = |resource: &str| {
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": resource
+            })
+        };
+        let plan = |applied: bool| {
+            json!({
+                "add": [key("new")],
+                "update": [],
+                "delete": [key("old")],
+                "applied": applied
+            })
+        };
+        let events = |message_type, operation, body| {
+            change_events(message_type, operation, &AuditResource::empty(), &body)
+                .into_iter()
+                .map(|event| (event.operation, event.key, event.deleted))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            events(
+                RECONCILE_MESSAGE_TYPE,
+                AuditOperation::Reconcile,
+                plan(true)
+            ),
+            [
+                (
+                    "CREATE".to_string(),
+                    Some("did:example:entity|did:example:authority|issue|new".to_string()),
+                    None
+                ),
+                (
+                    "DELETE".to_string(),
+                    Some("did:example:entity|did:example:authority|issue|old".to_string()),
+                    None
+                ),
+            ]
+        );
+        assert!(
+            events(
+                RECONCILE_MESSAGE_TYPE,
+                AuditOperation::Reconcile,
+                plan(false)
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            events(
+                TRUNCATE_RECORDS_MESSAGE_TYPE,
+                AuditOperation::Truncate,
+                json!({"deleted": 2})
+            ),
+            [("TRUNCATE".to_string(), None, Some(2))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_create_does_not_fire_webhook() {
+        let (url, mut events) = start_mock_webhook().await;
+        let handler = handler_with_webhook(url);
+
+        for _ in 0..2 {
+            let message = create_message();
+            let resource = extract_audit_resource(&message);
+            handler
+                .execute(
+                    CREATE_RECORD_MESSAGE_TYPE,
+                    message,
+                    AuditOperation::Create,
+                    &resource,
+                )
+                .await;
+        }
+
+        // only the first create succeeds; the duplicate must not produce an event
+        assert!(events.recv().await.is_some());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), events.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_down_does_not_fail_operation() {
+        let handler = handler_with_webhook("http://127.0.0.1:9/unreachable".to_string());
+        let message = create_message();
+        let resource = extract_audit_resource(&message);
+
+        let (_, result) = handler
+            .execute(
+                CREATE_RECORD_MESSAGE_TYPE,
+                message,
+                AuditOperation::Create,
+                &resource,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.is_ok());
+    }
+
+    fn handler() -> AdminMessagesHandler<LocalStorage> {
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            AdminConfig::default(),
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    fn refresh_message(expires_at: chrono::DateTime<chrono::Utc>) -> Message {
+        Message::build(
+            "msg-2".to_string(),
+            REFRESH_RECORD_MESSAGE_TYPE.to_string(),
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential",
+                "expires_at": expires_at
+            }),
+        )
+        .finalize()
+    }
+
+    async fn run<R: ?Sized + TrustRecordAdminRepository>(
+        handler: &AdminMessagesHandler<R>,
+        message: Message,
+    ) -> Result<serde_json::Value, String> {
+        let message_type = message.type_.clone();
+        let operation = get_operation_from_message_type(&message_type);
+        let resource = extract_audit_resource(&message);
+        handler
+            .execute(&message_type, message, operation, &resource)
+            .await
+            .unwrap()
+            .1
+            .map_err(|report| report.comment)
+    }
+
+    #[tokio::test]
+    async fn test_refresh_extends_expiry_and_bumps_version() {
+        let handler = handler();
+        let short_expiry = chrono::Utc::now() + chrono::Duration::seconds(1);
+        let mut create = create_message();
+        create.body["expires_at"] = json!(short_expiry);
+        run(&handler, create).await.unwrap();
+
+        let new_expiry = short_expiry + chrono::Duration::days(30);
+        let response = run(&handler, refresh_message(new_expiry)).await.unwrap();
+
+        assert_eq!(response["version"], 2);
+        assert_eq!(response["expires_at"], json!(new_expiry));
+        let stored = handler
+            .repository
+            .read(crate::storage::repository::TrustRecordQuery::new(
+                crate::domain::EntityId::new("did:example:entity"),
+                crate::domain::AuthorityId::new("did:example:authority"),
+                crate::domain::Action::new("issue"),
+                crate::domain::Resource::new("credential"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(stored.expires_at(), Some(&new_expiry));
+        assert_eq!(stored.version(), 2);
+        assert!(stored.is_recognized());
+        assert!(stored.is_authorized());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_missing_record_fails() {
+        let handler = handler();
+
+        let result = run(&handler, refresh_message(chrono::Utc::now())).await;
+
+        assert!(result.is_err());
+    }
+
+    #[derive(Default)]
+    struct RecordingAuditLogger {
+        logs: std::sync::Mutex<Vec<AuditLog>>,
+    }
+
+    #[async_trait]
+    impl AuditLogger for RecordingAuditLogger {
+        async fn log(&self, audit_log: AuditLog) {
+            self.logs.lock().unwrap().push(audit_log);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_injected_audit_logger_receives_admin_operations() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let handler = AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            AdminConfig::default(),
+            audit_logger.clone(),
+        );
+
+        let message = create_message();
+        let message_type = message.type_.clone();
+        let (_, result) = handler
+            .execute_audited(
+                "did:example:admin",
+                Some("thid-1".to_string()),
+                message,
+                &message_type,
+            )
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+        let message = create_message();
+        let (_, result) = handler
+            .execute_audited("did:example:admin", None, message, &message_type)
+            .await
+            .unwrap();
+        assert!(result.is_err());
+
+        let logs = audit_logger.logs.lock().unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(matches!(logs[0].operation, AuditOperation::Create));
+        assert_eq!(logs[0].actor, "did:example:admin");
+        assert!(matches!(logs[0].status, AuditStatus::Success));
+        assert_eq!(logs[0].thread_id.as_deref(), Some("thid-1"));
+        assert_eq!(
+            logs[0].resource.entity_id.as_ref().map(|e| e.as_str()),
+            Some("did:example:entity")
+        );
+        assert!(matches!(logs[1].status, AuditStatus::Failure));
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_recorded_in_audit_log() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let handler = AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            AdminConfig::default(),
+            audit_logger.clone(),
+        );
+
+        let mut message = create_message();
+        message
+            .extra_headers
+            .insert(CORRELATION_ID_FIELD.to_string(), json!("req-header"));
+        message.body[CORRELATION_ID_FIELD] = json!("req-body");
+        let message_type = message.type_.clone();
+        let (_, result) = handler
+            .execute_audited("did:example:admin", None, message, &message_type)
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+
+        let mut message = create_message();
+        message.body[CORRELATION_ID_FIELD] = json!("req-body");
+        message.type_ = READ_RECORD_MESSAGE_TYPE.to_string();
+        let (_, result) = handler
+            .execute_audited("did:example:admin", None, message, READ_RECORD_MESSAGE_TYPE)
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+
+        let logs = audit_logger.logs.lock().unwrap();
+        assert_eq!(logs[0].correlation_id.as_deref(), Some("req-header"));
+        assert_eq!(logs[1].correlation_id.as_deref(), Some("req-body"));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_creates_then_overwrites() {
+        let handler = handler();
+        let upsert = |authorized: bool| {
+            let mut message = create_message();
+            message.type_ = UPSERT_RECORD_MESSAGE_TYPE.to_string();
+            message.body["authorized"] = json!(authorized);
+            message
+        };
+
+        let inserted = run(&handler, upsert(true)).await.unwrap();
+        assert_eq!(inserted["created"], true);
+        assert_eq!(inserted["entity_id"], "did:example:entity");
+
+        let overwritten = run(&handler, upsert(false)).await.unwrap();
+        assert_eq!(overwritten["created"], false);
+
+        let list = handler.repository.list().await.unwrap();
+        assert_eq!(list.records().len(), 1);
+        assert!(!list.records()[0].is_authorized());
+        assert_eq!(list.records()[0].version(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_records_reports_missing_keys_separately() {
+        let handler = handler();
+        for entity in ["did:example:one", "did:example:two"] {
+            let mut message = create_message();
+            message.body["entity_id"] = json!(entity);
+            run(&handler, message).await.unwrap();
+        }
+        let key = |entity: &str| {
+            json!({
+                "entity_id": entity,
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential"
+            })
+        };
+        let message = Message::build(
+            "msg-3".to_string(),
+            READ_RECORDS_MESSAGE_TYPE.to_string(),
+            json!({ "keys": [key("did:example:one"), key("did:example:missing"), key("did:example:two")] }),
+        )
+        .finalize();
+
+        let response = run(&handler, message).await.unwrap();
+
+        let records = response["records"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["entity_id"], "did:example:one");
+        assert_eq!(records[1]["entity_id"], "did:example:two");
+        assert_eq!(response["not_found"], json!([key("did:example:missing")]));
+    }
+
+    fn bulk_delete_message(entities: &[&str]) -> Message {
+        let keys: Vec<serde_json::Value> = entities
+            .iter()
+            .map(|entity| {
+                json!({
+                    "entity_id": entity,
+                    "authority_id": "did:example:authority",
+                    "action": "issue",
+                    "resource": "credential"
+                })
+            })
+            .collect();
+        Message::build(
+            "msg-4".to_string(),
+            BULK_DELETE_RECORDS_MESSAGE_TYPE.to_string(),
+            json!({ "keys": keys }),
+        )
+        .finalize()
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_reports_missing_keys_and_audits_each() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let handler = AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            AdminConfig::default(),
+            audit_logger.clone(),
+        );
+        for entity in ["did:example:one", "did:example:two", "did:example:kept"] {
+            let mut message = create_message();
+            message.body["entity_id"] = json!(entity);
+            run(&handler, message).await.unwrap();
+        }
+        audit_logger.logs.lock().unwrap().clear();
+
+        let (_, result) = handler
+            .execute_audited(
+                "did:example:admin",
+                None,
+                bulk_delete_message(&["did:example:one", "did:example:missing", "did:example:two"]),
+                BULK_DELETE_RECORDS_MESSAGE_TYPE,
+            )
+            .await
+            .unwrap();
+
+        let response = result.unwrap();
+        assert_eq!(response["deleted"].as_array().unwrap().len(), 2);
+        assert_eq!(response["deleted"][0]["entity_id"], "did:example:one");
+        assert_eq!(response["deleted"][1]["entity_id"], "did:example:two");
+        assert_eq!(response["not_found"][0]["entity_id"], "did:example:missing");
+        let remaining = handler.repository.list().await.unwrap();
+        assert_eq!(remaining.records().len(), 1);
+        assert_eq!(
+            remaining.records()[0].entity_id().as_str(),
+            "did:example:kept"
+        );
+
+        let logs = audit_logger.logs.lock().unwrap();
+        assert_eq!(logs.len(), 3);
+        assert!(
+            logs.iter()
+                .all(|log| matches!(log.operation, AuditOperation::Delete))
+        );
+        let entity = |log: &AuditLog| log.resource.entity_id.as_ref().unwrap().to_string();
+        assert_eq!(entity(&logs[0]), "did:example:one");
+        assert!(matches!(logs[1].status, AuditStatus::Success));
+        assert_eq!(entity(&logs[2]), "did:example:missing");
+        assert!(matches!(logs[2].status, AuditStatus::Failure));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_rejects_create_and_allows_read() {
+        let handler = handler();
+        run(&handler, create_message()).await.unwrap();
+        handler.admin_config.read_only.set(true);
+
+        let report = handler
+            .check_writable(CREATE_RECORD_MESSAGE_TYPE)
+            .unwrap_err();
+        assert_eq!(report.code, problem_report::codes::ERROR_READ_ONLY);
+        assert_eq!(report.comment, "registry in read-only mode");
+        for message_type in [
+            UPDATE_RECORD_MESSAGE_TYPE,
+            UPSERT_RECORD_MESSAGE_TYPE,
+            DELETE_RECORD_MESSAGE_TYPE,
+            REFRESH_RECORD_MESSAGE_TYPE,
+        ] {
+            assert!(handler.check_writable(message_type).is_err());
+        }
+
+        assert!(handler.check_writable(READ_RECORD_MESSAGE_TYPE).is_ok());
+        assert!(handler.check_writable(LIST_RECORDS_MESSAGE_TYPE).is_ok());
+        let read = Message::build(
+            "msg-4".to_string(),
+            READ_RECORD_MESSAGE_TYPE.to_string(),
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential"
+            }),
+        )
+        .finalize();
+        let record = run(&handler, read).await.unwrap();
+        assert_eq!(record["entity_id"], "did:example:entity");
+    }
+
+    #[test]
+    fn test_read_only_mode_can_be_turned_off_at_runtime() {
+        let handler = handler();
+        handler.admin_config.read_only.set(true);
+        assert!(handler.check_writable(CREATE_RECORD_MESSAGE_TYPE).is_err());
+
+        handler.admin_config.read_only.set(false);
+
+        assert!(handler.check_writable(CREATE_RECORD_MESSAGE_TYPE).is_ok());
+    }
+
+    fn read_message() -> Message {
+        Message::build(
+            "msg-5".to_string(),
+            READ_RECORD_MESSAGE_TYPE.to_string(),
+            json!({
+                "entity_id": "did:example:entity",
+                "authority_id": "did:example:authority",
+                "action": "issue",
+                "resource": "credential"
+            }),
+        )
+        .finalize()
+    }
+
+    #[tokio::test]
+    async fn test_update_bumps_updated_at_and_keeps_created_at() {
+        let handler = handler();
+        run(&handler, create_message()).await.unwrap();
+        let created = run(&handler, read_message()).await.unwrap();
+        assert_eq!(created["created_at"], created["updated_at"]);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut update = create_message();
+        update.type_ = UPDATE_RECORD_MESSAGE_TYPE.to_string();
+        update.body["authorized"] = json!(false);
+        run(&handler, update).await.unwrap();
+        let updated = run(&handler, read_message()).await.unwrap();
+
+        assert_eq!(updated["created_at"], created["created_at"]);
+        assert_ne!(updated["updated_at"], created["updated_at"]);
+        let parse = |value: &serde_json::Value| {
+            chrono::DateTime::parse_from_rfc3339(value.as_str().unwrap()).unwrap()
+        };
+        assert!(parse(&updated["updated_at"]) > parse(&created["updated_at"]));
+    }
+
+    #[tokio::test]
+    async fn test_stale_conditional_update_is_a_conflict() {
+        let handler = handler();
+        run(&handler, create_message()).await.unwrap();
+        let read = run(&handler, read_message()).await.unwrap();
+        let version = read["version"].as_u64().unwrap();
+
+        let mut first = create_message();
+        first.type_ = UPDATE_RECORD_MESSAGE_TYPE.to_string();
+        first.body["authorized"] = json!(false);
+        first.body["expected_version"] = json!(version);
+        let mut stale = first.clone();
+        stale.body["authorized"] = json!(true);
+        run(&handler, first).await.unwrap();
+
+        let operation = get_operation_from_message_type(UPDATE_RECORD_MESSAGE_TYPE);
+        let resource = extract_audit_resource(&stale);
+        let (_, result) = handler
+            .execute(UPDATE_RECORD_MESSAGE_TYPE, stale, operation, &resource)
+            .await
+            .unwrap();
+        let report = result.unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_CONFLICT);
+        assert!(
+            report
+                .comment
+                .contains(&format!("at version {}, expected {version}", version + 1))
+        );
+        let current = run(&handler, read_message()).await.unwrap();
+        assert_eq!(current["authorized"], false);
+        assert_eq!(current["version"], version + 1);
+    }
+
+    #[test]
+    fn test_ack_is_opt_in() {
+        let mut message = create_message();
+        assert!(!ack_requested(&message));
+
+        message.body["ack_requested"] = json!(true);
+        assert!(ack_requested(&message));
+        message.body["ack_requested"] = json!("yes");
+        assert!(!ack_requested(&message));
+    }
+
+    #[test]
+    fn test_invalid_record_dids_are_bad_requests() {
+        let handler = handler();
+        let mut message = create_message();
+        assert!(handler.check_record_dids(&message).is_ok());
+
+        message.body["entity_id"] = json!("did;example:entity");
+        let report = handler.check_record_dids(&message).unwrap_err();
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert!(report.comment.contains("entity_id"));
+
+        message.body["entity_id"] = json!("did:example:entity");
+        message.body["authority_id"] = json!("authority");
+        message.type_ = UPSERT_RECORD_MESSAGE_TYPE.to_string();
+        assert!(handler.check_record_dids(&message).is_err());
+    }
+
+    #[test]
+    fn test_missing_record_fields_are_reported_together() {
+        let handler = handler();
+        let mut message = create_message();
+        assert!(handler.check_record_fields(&message).is_ok());
+
+        let body = message.body.as_object_mut().unwrap();
+        body.remove("entity_id");
+        body.remove("action");
+        body.insert("record_type".to_string(), json!("unknown"));
+        let report = handler.check_record_fields(&message).unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert_eq!(
+            report.args.unwrap(),
+            vec![
+                "Entity ID is required",
+                "Action is required",
+                "Record type is invalid"
+            ]
+        );
+    }
+
+    fn context_limited_handler(max_context_bytes: usize) -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            max_context_bytes,
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    #[test]
+    fn test_context_size_limit_boundary() {
+        let mut message = create_message();
+        message.body["context"] = json!({"note": "x".repeat(20)});
+        let size = message.body["context"].to_string().len();
+
+        assert!(
+            context_limited_handler(size)
+                .check_context_size(&message)
+                .is_ok()
+        );
+        let report = context_limited_handler(size - 1)
+            .check_context_size(&message)
+            .unwrap_err();
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert_eq!(
+            report.comment,
+            format!("context is {size} bytes, the limit is {} bytes", size - 1)
+        );
+
+        message.type_ = UPDATE_RECORD_MESSAGE_TYPE.to_string();
+        assert!(
+            context_limited_handler(size - 1)
+                .check_context_size(&message)
+                .is_err()
+        );
+        message.type_ = READ_RECORD_MESSAGE_TYPE.to_string();
+        assert!(
+            context_limited_handler(size - 1)
+                .check_context_size(&message)
+                .is_ok()
+        );
+    }
+
+    fn truncate_handler(
+        truncate_config: crate::configs::TruncateConfig,
+    ) -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            truncate_config,
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    fn truncate_message(confirmation_token: &str) -> Message {
+        Message::build(
+            "msg-truncate".to_string(),
+            TRUNCATE_RECORDS_MESSAGE_TYPE.to_string(),
+            json!({ "confirmation_token": confirmation_token }),
+        )
+        .finalize()
+    }
+
+    fn enabled_truncate() -> crate::configs::TruncateConfig {
+        crate::configs::TruncateConfig {
+            disabled: false,
+            confirmation_token: Some("reset-test-registry".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_truncate_deletes_every_record() {
+        let handler = truncate_handler(enabled_truncate());
+        run(&handler, create_message()).await.unwrap();
+        let mut other = create_message();
+        other.body["entity_id"] = json!("did:example:other");
+        run(&handler, other).await.unwrap();
+
+        let message = truncate_message("reset-test-registry");
+        handler.check_truncate(&message).unwrap();
+        let response = run(&handler, message).await.unwrap();
+
+        assert_eq!(response, json!({ "deleted": 2 }));
+        assert_eq!(handler.repository.count().await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_truncate_with_wrong_token_is_rejected() {
+        let handler = truncate_handler(enabled_truncate());
+
+        for token in ["wrong-token", ""] {
+            let report = handler
+                .check_truncate(&truncate_message(token))
+                .unwrap_err();
+            assert_eq!(report.code, problem_report::codes::ERROR_UNAUTHORIZED);
+            assert_eq!(report.comment, "confirmation token does not match");
+        }
+    }
+
+    #[test]
+    fn test_truncate_is_refused_when_disabled() {
+        // disabled is the default, as in production
+        let handler = truncate_handler(Default::default());
+
+        let report = handler
+            .check_truncate(&truncate_message("reset-test-registry"))
+            .unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert_eq!(
+            report.comment,
+            "truncate-records is disabled on this registry"
+        );
+        assert!(handler.check_truncate(&create_message()).is_ok());
+    }
+
+    #[test]
+    fn test_truncate_needs_unrestricted_authority_access() {
+        let mut admin_config = AdminConfig {
+            truncate_config: enabled_truncate(),
+            ..Default::default()
+        };
+        admin_config.authority_acl.insert(
+            "did:example:admin".to_string(),
+            std::collections::HashSet::from(["did:example:authority".to_string()]),
+        );
+        let handler = AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        );
+
+        assert!(
+            handler
+                .validate_authority_access(
+                    "did:example:admin",
+                    &truncate_message("reset-test-registry")
+                )
+                .is_err()
+        );
+        assert!(
+            handler
+                .validate_authority_access(
+                    "did:example:other",
+                    &truncate_message("reset-test-registry")
+                )
+                .is_ok()
+        );
+    }
+
+    fn record_with_entity(entity_id: &str) -> serde_json::Value {
+        let mut record = create_message().body;
+        record["entity_id"] = json!(entity_id);
+        record
+    }
+
+    fn reconcile_message(records: Vec<serde_json::Value>, apply: bool) -> Message {
+        Message::build(
+            "msg-reconcile".to_string(),
+            RECONCILE_MESSAGE_TYPE.to_string(),
+            json!({ "records": records, "apply": apply }),
+        )
+        .finalize()
+    }
+
+    async fn reconcile_fixture() -> (AdminMessagesHandler<LocalStorage>, Vec<serde_json::Value>) {
+        let handler = handler();
+        for entity_id in [
+            "did:example:unchanged",
+            "did:example:changed",
+            "did:example:extra",
+        ] {
+            let mut create = create_message();
+            create.body = record_with_entity(entity_id);
+            run(&handler, create).await.unwrap();
+        }
+        let mut changed = record_with_entity("did:example:changed");
+        changed["authorized"] = json!(false);
+        let desired = vec![
+            record_with_entity("did:example:unchanged"),
+            changed,
+            record_with_entity("did:example:missing"),
+        ];
+        (handler, desired)
+    }
+
+    fn keys(entity_ids: &[&str]) -> serde_json::Value {
+        entity_ids
+            .iter()
+            .map(|entity_id| {
+                json!({
+                    "entity_id": entity_id,
+                    "authority_id": "did:example:authority",
+                    "action": "issue",
+                    "resource": "credential"
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_plans_adds_updates_and_deletes() {
+        let (handler, desired) = reconcile_fixture().await;
+
+        let plan = run(&handler, reconcile_message(desired, false))
+            .await
+            .unwrap();
+
+        assert_eq!(plan["add"], keys(&["did:example:missing"]));
+        assert_eq!(plan["update"], keys(&["did:example:changed"]));
+        assert_eq!(plan["delete"], keys(&["did:example:extra"]));
+        assert_eq!(plan["applied"], false);
+        // a plan changes nothing
+        assert_eq!(handler.repository.count().await.unwrap(), 3);
+        let extra = handler
+            .repository
+            .read(crate::storage::repository::TrustRecordQuery::new(
+                crate::domain::EntityId::new("did:example:extra"),
+                crate::domain::AuthorityId::new("did:example:authority"),
+                crate::domain::Action::new("issue"),
+                crate::domain::Resource::new("credential"),
+            ))
+            .await;
+        assert!(extra.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_apply_makes_storage_match() {
+        let (handler, desired) = reconcile_fixture().await;
+
+        let applied = run(&handler, reconcile_message(desired.clone(), true))
+            .await
+            .unwrap();
+        assert_eq!(applied["applied"], true);
+
+        let records = handler.repository.list().await.unwrap().into_records();
+        let entities: Vec<&str> = records.iter().map(|r| r.entity_id().as_str()).collect();
+        assert_eq!(
+            entities,
+            vec![
+                "did:example:changed",
+                "did:example:missing",
+                "did:example:unchanged"
+            ]
+        );
+        assert!(!records[0].is_authorized());
+        assert_eq!(records[0].version(), 2);
+        assert_eq!(records[2].version(), 1);
+
+        let plan = run(&handler, reconcile_message(desired, false))
+            .await
+            .unwrap();
+        assert_eq!(plan["add"], json!([]));
+        assert_eq!(plan["update"], json!([]));
+        assert_eq!(plan["delete"], json!([]));
+    }
+
+    /// Local storage whose deletes fail, to stop a reconcile partway.
+    struct FailingDeletes(LocalStorage);
+
+    #[async_trait::async_trait]
+    impl TrustRecordRepository for FailingDeletes {
+        async fn find_by_query(
+            &self,
+            query: crate::storage::repository::TrustRecordQuery,
+        ) -> Result<Option<crate::domain::TrustRecord>, RepositoryError> {
+            self.0.find_by_query(query).await
+        }
+
+        async fn list(
+            &self,
+        ) -> Result<crate::storage::repository::TrustRecordList, RepositoryError> {
+            self.0.list().await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TrustRecordAdminRepository for FailingDeletes {
+        async fn create(&self, record: crate::domain::TrustRecord) -> Result<(), RepositoryError> {
+            self.0.create(record).await
+        }
+
+        async fn update(&self, record: crate::domain::TrustRecord) -> Result<(), RepositoryError> {
+            self.0.update(record).await
+        }
+
+        async fn delete(
+            &self,
+            _query: crate::storage::repository::TrustRecordQuery,
+        ) -> Result<(), RepositoryError> {
+            Err(RepositoryError::ConnectionFailed(
+                "storage went away".to_string(),
+            ))
+        }
+
+        async fn upsert(
+            &self,
+            record: crate::domain::TrustRecord,
+        ) -> Result<crate::storage::repository::UpsertOutcome, RepositoryError> {
+            self.0.upsert(record).await
+        }
+
+        async fn read(
+            &self,
+            query: crate::storage::repository::TrustRecordQuery,
+        ) -> Result<crate::domain::TrustRecord, RepositoryError> {
+            self.0.read(query).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_apply_reports_partial_progress_on_failure() {
+        let (fixture, desired) = reconcile_fixture().await;
+        let stored = fixture.repository.list().await.unwrap().into_records();
+        let handler = AdminMessagesHandler::new(
+            Arc::new(FailingDeletes(LocalStorage::with_records(stored))),
+            AdminConfig::default(),
+            Arc::new(NoopAuditLogger),
+        );
+
+        let response = run(&handler, reconcile_message(desired, true))
+            .await
+            .unwrap();
+
+        assert_eq!(response["applied"], false);
+        let partial = &response["partial"];
+        assert_eq!(partial["added"], keys(&["did:example:missing"]));
+        assert_eq!(partial["updated"], keys(&["did:example:changed"]));
+        assert_eq!(partial["deleted"], json!([]));
+        assert_eq!(partial["failed"], keys(&["did:example:extra"])[0]);
+        assert!(
+            partial["error"]
+                .as_str()
+                .unwrap()
+                .contains("storage went away")
+        );
+
+        let operations: Vec<String> = change_events(
+            RECONCILE_MESSAGE_TYPE,
+            AuditOperation::Reconcile,
+            &AuditResource::empty(),
+            &response,
+        )
+        .into_iter()
+        .map(|event| event.operation)
+        .collect();
+        assert_eq!(
+            operations,
+            ["CREATE", "UPDATE"],
+            "only the writes that landed are announced"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_rejects_duplicate_and_invalid_records() {
+        let handler = handler();
+
+        let duplicate = reconcile_message(
+            vec![
+                record_with_entity("did:example:a"),
+                record_with_entity("did:example:a"),
+            ],
+            true,
+        );
+        assert!(
+            run(&handler, duplicate)
+                .await
+                .unwrap_err()
+                .contains("more than once")
+        );
+
+        let mut invalid = record_with_entity("not-a-did");
+        invalid["context"] = json!({"note": "x".repeat(64)});
+        let message = reconcile_message(vec![invalid], true);
+        assert!(handler.check_record_dids(&message).is_err());
+        assert!(
+            context_limited_handler(16)
+                .check_context_size(&message)
+                .is_err()
+        );
+        assert_eq!(handler.repository.count().await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_context_size_limit_of_zero_is_disabled() {
+        let mut message = create_message();
+        message.body["context"] = json!({"note": "x".repeat(1024)});
+
+        assert!(
+            context_limited_handler(0)
+                .check_context_size(&message)
+                .is_ok()
+        );
+    }
+
+    fn handler_with_thid_strategy(
+        thread_id_strategy: ThreadIdStrategy,
+    ) -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            thread_id_strategy,
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    #[test]
+    fn test_lenient_thid_strategy_accepts_messages_without_thid() {
+        let handler = handler_with_thid_strategy(ThreadIdStrategy::Lenient);
+
+        assert!(handler.check_thread_id(&create_message()).is_ok());
+    }
+
+    #[test]
+    fn test_strict_thid_strategy_requires_thid() {
+        let handler = handler_with_thid_strategy(ThreadIdStrategy::Strict);
+        let mut message = create_message();
+
+        let report = handler.check_thread_id(&message).unwrap_err();
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert!(report.comment.contains("has no thid"));
+
+        message.thid = Some("thread-1".to_string());
+        assert!(handler.check_thread_id(&message).is_ok());
+    }
+
+    #[test]
+    fn test_strict_requests_reject_misspelled_field() {
+        let mut message = create_message();
+        let body = message.body.as_object_mut().unwrap();
+        body.remove("authorized");
+        body.insert("authorised".to_string(), json!(true));
+        body.insert("ack_requested".to_string(), json!(true));
+        let lenient = handler_with_thid_strategy(ThreadIdStrategy::Lenient);
+        let mut strict = handler_with_thid_strategy(ThreadIdStrategy::Lenient);
+        strict.admin_config.strict_requests = true;
+
+        let report = strict.check_known_fields(&message).unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert!(report.comment.contains("\"authorised\""));
+        assert_eq!(report.args, Some(vec!["authorised".to_string()]));
+        assert!(lenient.check_known_fields(&message).is_ok());
+        assert!(strict.check_known_fields(&create_message()).is_ok());
+    }
+
+    #[test]
+    fn test_strict_requests_check_nested_keys() {
+        let message = Message::build(
+            "msg-1".to_string(),
+            READ_RECORDS_MESSAGE_TYPE.to_string(),
+            json!({ "keys": [
+                { "entity_id": "e", "authority_id": "a", "action": "ac", "resource": "r" },
+                { "entity_id": "e", "authority_id": "a", "action": "ac", "resorce": "r" },
+            ] }),
+        )
+        .finalize();
+
+        assert_eq!(unknown_field(&message).as_deref(), Some("keys[1].resorce"));
+    }
+
+    #[test]
+    fn test_strict_requests_check_flattened_key_fields() {
+        let message = Message::build(
+            "msg-1".to_string(),
+            REFRESH_RECORD_MESSAGE_TYPE.to_string(),
+            json!({
+                "entity_id": "e", "authority_id": "a", "action": "ac", "resource": "r",
+                "expires_at": "2030-01-01T00:00:00Z", "expiry": "2031-01-01T00:00:00Z",
+            }),
+        )
+        .finalize();
+
+        assert_eq!(unknown_field(&message).as_deref(), Some("expiry"));
+    }
+
+    fn owned_authority_handler() -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            authority_acl: [(
+                "did:example:tenant-admin".to_string(),
+                ["did:example:tenant".to_string()].into(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    #[test]
+    fn test_admin_cannot_write_authority_it_does_not_own() {
+        let handler = owned_authority_handler();
+        let mut message = create_message();
+
+        let err = handler
+            .validate_authority_access("did:example:tenant-admin", &message)
+            .unwrap_err();
+        assert!(err.contains("may not write records of authority did:example:authority"));
+
+        message.type_ = DELETE_RECORD_MESSAGE_TYPE.to_string();
+        assert!(
+            handler
+                .validate_authority_access("did:example:tenant-admin", &message)
+                .is_err()
+        );
+
+        message.body["authority_id"] = json!("did:example:tenant");
+        assert!(
+            handler
+                .validate_authority_access("did:example:tenant-admin", &message)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_bulk_delete_needs_access_to_every_authority() {
+        let handler = owned_authority_handler();
+        let mut message = bulk_delete_message(&["did:example:one"]);
+        message.body["keys"][0]["authority_id"] = json!("did:example:tenant");
+        assert!(
+            handler
+                .validate_authority_access("did:example:tenant-admin", &message)
+                .is_ok()
+        );
+
+        let mut mixed = bulk_delete_message(&["did:example:one", "did:example:two"]);
+        mixed.body["keys"][0]["authority_id"] = json!("did:example:tenant");
+        let err = handler
+            .validate_authority_access("did:example:tenant-admin", &mixed)
+            .unwrap_err();
+        assert!(err.contains("may not write records of authority did:example:authority"));
+    }
+
+    #[test]
+    fn test_authority_acl_does_not_restrict_reads_or_unmapped_admins() {
+        let handler = owned_authority_handler();
+
+        assert!(
+            handler
+                .validate_authority_access("did:example:tenant-admin", &read_message())
+                .is_ok()
+        );
+        assert!(
+            handler
+                .validate_authority_access("did:example:root-admin", &create_message())
+                .is_ok()
+        );
+    }
+
+    fn attachment_handler(max_attachment_bytes: usize) -> AdminMessagesHandler<LocalStorage> {
+        let admin_config = AdminConfig {
+            max_attachment_bytes,
+            ..Default::default()
+        };
+        AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        )
+    }
+
+    fn schema_attachment() -> affinidi_tdk::didcomm::Attachment {
+        affinidi_tdk::didcomm::Attachment::base64("eyJ0eXBlIjoic2NoZW1hIn0=".to_string())
+            .id("schema-1".to_string())
+            .media_type("application/schema+json".to_string())
+            .finalize()
+    }
+
+    #[tokio::test]
+    async fn test_create_keeps_attachment_content_out_of_the_context() {
+        let handler = attachment_handler(1024);
+        let mut create = create_message();
+        create.body["context"] = json!({"level": 1});
+        create.attachments = Some(vec![schema_attachment()]);
+        run(&handler, create).await.unwrap();
+
+        let record = run(&handler, read_message()).await.unwrap();
+
+        assert_eq!(record["context"]["level"], 1);
+        assert_eq!(
+            record["context"][messages::ATTACHMENTS_CONTEXT_KEY],
+            json!([{
+                "id": "schema-1",
+                "media_type": "application/schema+json",
+                "data_type": "base64"
+            }])
+        );
+        assert!(!record.to_string().contains("eyJ0eXBlIjoic2NoZW1hIn0="));
+        let stored = handler.repository.list().await.unwrap().into_records();
+        assert_eq!(
+            stored[0].attachments()[0]["data"]["base64"],
+            "eyJ0eXBlIjoic2NoZW1hIn0="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attachments_over_the_limit_are_rejected() {
+        let handler = attachment_handler(16);
+        let mut create = create_message();
+        create.attachments = Some(vec![schema_attachment()]);
+
+        let err = run(&handler, create).await.unwrap_err();
+
+        assert!(err.contains("the limit is 16 bytes"));
+        assert!(run(&handler, read_message()).await.is_err());
+    }
+
+    fn limited_handler(max: usize, timeout_ms: u64) -> Arc<AdminMessagesHandler<LocalStorage>> {
+        let admin_config = AdminConfig {
+            max_concurrent_mutations: max,
+            mutation_queue_timeout_ms: timeout_ms,
+            ..Default::default()
+        };
+        Arc::new(AdminMessagesHandler::new(
+            Arc::new(LocalStorage::new()),
+            admin_config,
+            Arc::new(NoopAuditLogger),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_mutations_over_the_limit_queue_and_all_complete() {
+        let handler = limited_handler(2, 0);
+        let mut tasks = tokio::task::JoinSet::new();
+        for i in 0..10 {
+            let handler = handler.clone();
+            tasks.spawn(async move {
+                let mut message = create_message();
+                message.body["entity_id"] = json!(format!("did:example:entity{i}"));
+                handler
+                    .execute_limited(
+                        "did:example:admin",
+                        None,
+                        message,
+                        CREATE_RECORD_MESSAGE_TYPE,
+                    )
+                    .await
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            let (_, result) = result.unwrap().unwrap().unwrap();
+            assert!(result.is_ok(), "create failed: {result:?}");
+        }
+        assert_eq!(handler.repository.list().await.unwrap().records().len(), 10);
+        assert_eq!(handler.mutation_slots.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_mutation_waiting_past_timeout_is_busy() {
+        let handler = limited_handler(1, 20);
+        let _held = handler.mutation_slots.acquire().await.unwrap();
+
+        let report = handler
+            .execute_limited(
+                "did:example:admin",
+                None,
+                create_message(),
+                CREATE_RECORD_MESSAGE_TYPE,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(report.code, problem_report::codes::ERROR_BUSY);
+
+        // reads do not take a slot
+        let read = handler
+            .execute_limited(
+                "did:example:admin",
+                None,
+                read_message(),
+                READ_RECORD_MESSAGE_TYPE,
+            )
+            .await;
+        assert!(read.is_ok());
+    }
+
+    fn list_message(body: serde_json::Value) -> Message {
+        Message::build(
+            "msg-6".to_string(),
+            LIST_RECORDS_MESSAGE_TYPE.to_string(),
+            body,
+        )
+        .finalize()
+    }
+
+    fn listed_entities(response: &serde_json::Value) -> Vec<&str> {
+        response["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["entity_id"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_recognized_and_authorized() {
+        let handler = handler();
+        for (entity, recognized, authorized) in [
+            ("did:example:both", true, true),
+            ("did:example:recognized", true, false),
+            ("did:example:authorized", false, true),
+            ("did:example:revoked", false, false),
+        ] {
+            let mut create = create_message();
+            create.body["entity_id"] = json!(entity);
+            create.body["recognized"] = json!(recognized);
+            create.body["authorized"] = json!(authorized);
+            run(&handler, create).await.unwrap();
+        }
+
+        let unfiltered = run(&handler, list_message(json!({}))).await.unwrap();
+        assert_eq!(unfiltered["count"], 4);
+
+        let unauthorized = run(&handler, list_message(json!({ "authorized": false })))
+            .await
+            .unwrap();
+        assert_eq!(unauthorized["count"], 2);
+        assert_eq!(
+            listed_entities(&unauthorized),
+            ["did:example:recognized", "did:example:revoked"]
+        );
+
+        let revoked = run(
+            &handler,
+            list_message(json!({ "recognized": false, "authorized": false })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(revoked["count"], 1);
+        assert_eq!(listed_entities(&revoked), ["did:example:revoked"]);
+
+        let null_body = run(&handler, list_message(serde_json::Value::Null))
+            .await
+            .unwrap();
+        assert_eq!(null_body, unfiltered);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_tag_returns_only_tagged_records() {
+        let handler = handler();
+        for (entity, tags) in [
+            ("did:example:eu-kyc", json!(["kyc", "eu"])),
+            ("did:example:kyc", json!(["kyc"])),
+            ("did:example:untagged", json!([])),
+        ] {
+            let mut create = create_message();
+            create.body["entity_id"] = json!(entity);
+            create.body["tags"] = tags;
+            run(&handler, create).await.unwrap();
+        }
+        let list_by_tag = |tag: &str| {
+            Message::build(
+                "msg-7".to_string(),
+                LIST_BY_TAG_MESSAGE_TYPE.to_string(),
+                json!({ "tag": tag }),
+            )
+            .finalize()
+        };
+
+        let kyc = run(&handler, list_by_tag("kyc")).await.unwrap();
+        assert_eq!(kyc["count"], 2);
+        assert_eq!(
+            listed_entities(&kyc),
+            ["did:example:eu-kyc", "did:example:kyc"]
+        );
+        assert_eq!(kyc["records"][0]["tags"], json!(["kyc", "eu"]));
+
+        let eu = run(&handler, list_by_tag("eu")).await.unwrap();
+        assert_eq!(listed_entities(&eu), ["did:example:eu-kyc"]);
+
+        let none = run(&handler, list_by_tag("aml")).await.unwrap();
+        assert_eq!(none["count"], 0);
     }
 }
@@ -1,39 +1,129 @@
 use crate::{
-    audit::audit_logger::BaseAuditLogger, storage::repository::TrustRecordAdminRepository,
+    audit::{
+        audit_logger::BaseAuditLogger, file_audit_logger::FileAuditLogger, model::AuditLogger,
+    },
+    policy::{DefaultEvaluationPolicy, EvaluationPolicy},
+    storage::repository::TrustRecordAdminRepository,
 };
 use crate::{
     configs::DidcommConfig,
+    didcomm::dead_letter::DeadLetterQueue,
+    didcomm::deduplication::MessageDeduplicator,
     didcomm::handlers::{
         BaseHandler, admin::AdminMessagesHandler, problem_report::ProblemReportHandler,
-        trqp::TRQPMessagesHandler,
+        registry_info::RegistryInfoHandler, schemas::SchemaQueryHandler, trqp::TRQPMessagesHandler,
+        trust_ping::TrustPingHandler,
     },
+    didcomm::message_age::MessageAgePolicy,
 };
-use std::sync::Arc;
+use std::{path::Path, sync::Arc, time::Duration};
+use tracing::error;
+
+/// Audit logger configured from the environment: a [`FileAuditLogger`] when
+/// `AUDIT_LOG_FILE` is set, tracing output otherwise.
+pub fn configured_audit_logger(config: &DidcommConfig) -> Arc<dyn AuditLogger> {
+    let audit_config = config.admin_config.audit_config.clone();
+    match &audit_config.file_path {
+        Some(path) => match FileAuditLogger::open(Path::new(path), audit_config.clone()) {
+            Ok(logger) => Arc::new(logger),
+            Err(e) => {
+                error!(
+                    "Failed to open audit log file {path}: {e}. Logging audit to tracing output"
+                );
+                Arc::new(BaseAuditLogger::new(audit_config))
+            }
+        },
+        None => Arc::new(BaseAuditLogger::new(audit_config)),
+    }
+}
 
 impl<R: ?Sized + TrustRecordAdminRepository + 'static> BaseHandler<R> {
+    /// Builds the handlers with the audit logger configured from the environment.
     pub fn build_from_arc(repository: Arc<R>, config: Arc<DidcommConfig>) -> BaseHandler<R> {
-        let trqp = TRQPMessagesHandler {
-            repository: repository.clone(),
-        };
+        let audit_logger = configured_audit_logger(&config);
+        Self::build_with_audit_logger(repository, config, audit_logger)
+    }
 
-        let audit_logger = Arc::new(BaseAuditLogger::new(
-            config.admin_config.audit_config.clone(),
+    pub fn build_with_audit_logger(
+        repository: Arc<R>,
+        config: Arc<DidcommConfig>,
+        audit_logger: Arc<dyn AuditLogger>,
+    ) -> BaseHandler<R> {
+        Self::build_with(
+            repository,
+            config,
+            audit_logger,
+            Arc::new(DefaultEvaluationPolicy),
+        )
+    }
+
+    pub fn build_with(
+        repository: Arc<R>,
+        config: Arc<DidcommConfig>,
+        audit_logger: Arc<dyn AuditLogger>,
+        evaluation_policy: Arc<dyn EvaluationPolicy>,
+    ) -> BaseHandler<R> {
+        let tradmin = Arc::new(AdminMessagesHandler::new(
+            repository.clone(),
+            config.admin_config.clone(),
+            audit_logger,
         ));
-        let tradmin = AdminMessagesHandler {
+        Self::build_with_admin(repository, config, tradmin, evaluation_policy)
+    }
+
+    /// Builds the handlers around an admin handler shared with the HTTP admin API, so both
+    /// transports share its mutation limit.
+    pub fn build_with_admin<A: ?Sized + TrustRecordAdminRepository + 'static>(
+        repository: Arc<R>,
+        config: Arc<DidcommConfig>,
+        tradmin: Arc<AdminMessagesHandler<A>>,
+        evaluation_policy: Arc<dyn EvaluationPolicy>,
+    ) -> BaseHandler<R> {
+        let trqp = TRQPMessagesHandler {
             repository: repository.clone(),
-            admin_config: config.admin_config.clone(),
-            audit_service: audit_logger,
+            evaluation_policy,
+            default_context: config.trqp_default_context.clone(),
+            context_allowlist: config.trqp_context_allowlist.clone(),
+            context_merge_mode: config.trqp_context_merge_mode,
+            context_provenance: config.trqp_context_provenance,
+            query_allowlist: config.trqp_query_allowlist.clone(),
+            max_fan_out: config.trqp_max_fan_out,
         };
 
+        let schemas = SchemaQueryHandler::new(
+            repository.clone(),
+            Duration::from_secs(config.schema_index_ttl_sec),
+        );
         let problem_report_handler = ProblemReportHandler::new();
+        let trust_ping_handler = TrustPingHandler::new();
+        let registry_info_handler = RegistryInfoHandler::new(
+            config.registry_name.clone(),
+            config.governance_framework_url.clone(),
+        );
 
         BaseHandler {
             repository,
             protocols_handlers: vec![
                 Arc::new(trqp),
-                Arc::new(tradmin),
+                Arc::new(schemas),
+                tradmin,
                 Arc::new(problem_report_handler),
+                Arc::new(trust_ping_handler),
+                Arc::new(registry_info_handler),
             ],
+            deduplicator: MessageDeduplicator::new(Duration::from_secs(config.dedup_ttl_sec)),
+            message_age_policy: MessageAgePolicy::new(
+                Duration::from_secs(config.max_message_age_sec),
+                config.require_message_timestamps,
+            ),
+            honor_return_route: config.honor_return_route,
+            dead_letters: None,
         }
     }
+
+    /// Queues responses that fail to send in `dead_letters` instead of dropping them.
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
 }
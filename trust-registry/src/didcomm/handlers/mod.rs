@@ -8,12 +8,24 @@ use affinidi_tdk::{
 use async_trait::async_trait;
 use tracing::{info, warn};
 
-use crate::didcomm::{get_parent_thread_id, get_thread_id, listener::MessageHandler};
+use crate::didcomm::{
+    dead_letter::DeadLetterQueue,
+    deduplication::MessageDeduplicator,
+    get_parent_thread_id, get_thread_id,
+    listener::MessageHandler,
+    message_age::MessageAgePolicy,
+    problem_report::{ProblemReport, send_problem_report},
+    protocol_version,
+    transport::{PROBLEM_REPORT_TYPE, Route},
+};
 
 pub mod admin;
 pub mod build;
 pub mod problem_report;
+pub mod registry_info;
+pub mod schemas;
 pub mod trqp;
+pub mod trust_ping;
 
 pub struct HandlerContext {
     pub atm: Arc<ATM>,
@@ -21,6 +33,10 @@ pub struct HandlerContext {
     pub sender_did: String,
     pub thid: Option<String>,
     pub pthid: Option<String>,
+    /// How responses reach the sender, see [`Route::for_message`].
+    pub route: Route,
+    /// Where responses that fail to send are kept for a retry, see `DEAD_LETTER_STORE`.
+    pub dead_letters: Option<Arc<DeadLetterQueue>>,
 }
 
 #[async_trait]
@@ -39,6 +55,55 @@ pub struct BaseHandler<R: ?Sized + TrustRecordRepository> {
     #[allow(dead_code)]
     repository: Arc<R>,
     protocols_handlers: Vec<Arc<dyn ProtocolHandler>>,
+    deduplicator: MessageDeduplicator,
+    /// Rejects stale and expired messages before they are dispatched.
+    message_age_policy: MessageAgePolicy,
+    /// Replies on the inbound connection to messages with `return_route: all`.
+    honor_return_route: bool,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+}
+
+impl<R: ?Sized + TrustRecordRepository> BaseHandler<R> {
+    /// Checks a message before dispatch and returns the message type it is handled as,
+    /// see [`protocol_version::resolve`].
+    fn admit(&self, message: &Message) -> Result<String, ProblemReport> {
+        self.message_age_policy.check(message)?;
+        let supported: Vec<String> = self
+            .protocols_handlers
+            .iter()
+            .flat_map(|ph| ph.get_supported_inbound_message_types())
+            .collect();
+        Ok(protocol_version::resolve(&message.type_, &supported)?
+            .unwrap_or_else(|| message.type_.clone()))
+    }
+
+    /// Answers a message refused before dispatch with a problem report.
+    async fn reject(
+        &self,
+        ctx: &HandlerContext,
+        message: &Message,
+        report: ProblemReport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        warn!(
+            "[profile = {}, type = {}, from = {}] rejecting message {}: {}",
+            &ctx.profile.inner.alias, message.type_, ctx.sender_did, message.id, report.comment
+        );
+        // anonymous senders cannot be answered, and answering a problem report could loop
+        if message.from.is_none() || message.type_ == PROBLEM_REPORT_TYPE {
+            return Ok(());
+        }
+        send_problem_report(
+            &ctx.atm,
+            &ctx.profile,
+            report,
+            &ctx.sender_did,
+            ctx.thid.clone(),
+            ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
+        )
+        .await
+    }
 }
 
 #[async_trait]
@@ -47,14 +112,14 @@ impl<R: ?Sized + TrustRecordRepository + 'static> MessageHandler for BaseHandler
         &self,
         atm: &Arc<ATM>,
         profile: &Arc<ATMProfile>,
-        message: Message,
+        mut message: Message,
         meta: UnpackMetadata,
     ) -> Result<(), Box<dyn std::error::Error>> {
         // TODO: validate UnpackMetadata, so in config the admin of TR can define would they allow unsign / anon / etc messages
-        let message_type = &message.type_;
         let from = message.from.clone().unwrap_or("anon".into());
         let thid = get_thread_id(&message).or_else(|| Some(message.id.clone()));
         let pthid = get_parent_thread_id(&message);
+        let route = Route::for_message(&message, self.honor_return_route);
 
         let ctx = Arc::new(HandlerContext {
             atm: atm.clone(),
@@ -62,8 +127,26 @@ impl<R: ?Sized + TrustRecordRepository + 'static> MessageHandler for BaseHandler
             sender_did: from.clone(),
             thid,
             pthid,
+            route,
+            dead_letters: self.dead_letters.clone(),
         });
 
+        let message_type = match self.admit(&message) {
+            Ok(message_type) => message_type,
+            Err(report) => {
+                self.reject(&ctx, &message, report).await?;
+                return Ok(());
+            }
+        };
+        if message_type != message.type_ {
+            info!(
+                "[profile = {}] handling {} as {}",
+                &profile.inner.alias, message.type_, message_type
+            );
+            message.type_ = message_type.clone();
+        }
+        let message_type = &message_type;
+
         let ph = self.protocols_handlers.iter().find(|ph| {
             ph.get_supported_inbound_message_types()
                 .contains(message_type)
@@ -74,7 +157,20 @@ impl<R: ?Sized + TrustRecordRepository + 'static> MessageHandler for BaseHandler
                 "[profile = {}, type = {}, from = {}] new message",
                 &profile.inner.alias, message_type, from
             );
-            protocol_handler.handle(&ctx, message, meta).await?;
+            let message_id = message.id.clone();
+            let processed = self
+                .deduplicator
+                .run_once(&from, &message_id, || {
+                    protocol_handler.handle(&ctx, message, meta)
+                })
+                .await;
+            match processed {
+                Some(result) => result?,
+                None => info!(
+                    "[profile = {}, from = {}] message {} already processed, skipping redelivery",
+                    &profile.inner.alias, from, message_id
+                ),
+            }
         } else {
             // send problem report
             warn!(
@@ -85,3 +181,115 @@ impl<R: ?Sized + TrustRecordRepository + 'static> MessageHandler for BaseHandler
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        didcomm::problem_report::codes, storage::adapters::local_storage::LocalStorage,
+        test_support::offline_atm,
+    };
+    use serde_json::json;
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    const FLAKY_MESSAGE_TYPE: &str = "https://example.com/protocols/flaky/1.0/ping";
+
+    /// Fails the first message it handles and succeeds afterwards.
+    struct FlakyHandler {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ProtocolHandler for FlakyHandler {
+        fn get_supported_inbound_message_types(&self) -> Vec<String> {
+            vec![FLAKY_MESSAGE_TYPE.to_string()]
+        }
+
+        async fn handle(
+            &self,
+            _ctx: &Arc<HandlerContext>,
+            _message: Message,
+            _meta: UnpackMetadata,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("response could not be sent".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn base_handler(protocol_handler: Arc<dyn ProtocolHandler>) -> BaseHandler<LocalStorage> {
+        BaseHandler {
+            repository: Arc::new(LocalStorage::new()),
+            protocols_handlers: vec![protocol_handler],
+            deduplicator: MessageDeduplicator::new(Duration::from_secs(60)),
+            message_age_policy: MessageAgePolicy::new(Duration::ZERO, false),
+            honor_return_route: false,
+            dead_letters: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_message_is_handled_again_on_redelivery() {
+        let (atm, profile) = offline_atm("registry").await;
+        let flaky = Arc::new(FlakyHandler {
+            calls: AtomicU32::new(0),
+        });
+        let handler = base_handler(flaky.clone());
+        let message = Message::build(
+            "msg-1".to_string(),
+            FLAKY_MESSAGE_TYPE.to_string(),
+            json!({}),
+        )
+        .from("did:example:sender".to_string())
+        .finalize();
+        let deliver = || handler.handle(&atm, &profile, message.clone(), UnpackMetadata::default());
+
+        assert!(deliver().await.is_err());
+        assert!(deliver().await.is_ok());
+        // processed successfully now, so further redeliveries are skipped
+        assert!(deliver().await.is_ok());
+
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_major_version_is_reported() {
+        let (atm, profile) = offline_atm("registry").await;
+        let flaky = Arc::new(FlakyHandler {
+            calls: AtomicU32::new(0),
+        });
+        let handler = base_handler(flaky.clone());
+        let message = |message_type: &str| {
+            Message::build("msg-2".to_string(), message_type.to_string(), json!({}))
+                .from("did:example:sender".to_string())
+                .finalize()
+        };
+
+        let report = handler
+            .admit(&message("https://example.com/protocols/flaky/2.0/ping"))
+            .unwrap_err();
+        assert_eq!(report.code, codes::ERROR_UNSUPPORTED_VERSION);
+        assert_eq!(report.args, Some(vec!["1.0".to_string()]));
+        assert_eq!(
+            handler
+                .admit(&message("https://example.com/protocols/flaky/1.3/ping"))
+                .unwrap(),
+            FLAKY_MESSAGE_TYPE
+        );
+
+        // the offline ATM can't send the report, but the message is never dispatched
+        let _ = handler
+            .handle(
+                &atm,
+                &profile,
+                message("https://example.com/protocols/flaky/2.0/ping"),
+                UnpackMetadata::default(),
+            )
+            .await;
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use affinidi_tdk::didcomm::{Message, UnpackMetadata};
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::info;
+
+use crate::didcomm::{
+    handlers::{HandlerContext, ProtocolHandler},
+    transport,
+};
+
+pub const REGISTRY_INFO_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-info/1.0/registry-info";
+pub const REGISTRY_INFO_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/tr-info/1.0/registry-info/response";
+
+/// Records are keyed on `(entity, authority, action, resource)`, see
+/// [`TrustRecordQuery`](crate::storage::repository::TrustRecordQuery).
+pub const RECORD_MODEL: &str = "action-resource";
+
+/// Body of a `registry-info` response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegistryInfo {
+    pub did: String,
+    pub name: String,
+    pub record_model: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub governance_framework_url: Option<String>,
+    pub version: &'static str,
+}
+
+/// Describes the registry to any sender, see `REGISTRY_NAME`.
+pub struct RegistryInfoHandler {
+    name: String,
+    governance_framework_url: Option<String>,
+}
+
+impl RegistryInfoHandler {
+    pub fn new(name: String, governance_framework_url: Option<String>) -> Self {
+        Self {
+            name,
+            governance_framework_url,
+        }
+    }
+
+    pub fn registry_info(&self, did: &str) -> RegistryInfo {
+        RegistryInfo {
+            did: did.to_string(),
+            name: self.name.clone(),
+            record_model: RECORD_MODEL,
+            governance_framework_url: self.governance_framework_url.clone(),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for RegistryInfoHandler {
+    fn get_supported_inbound_message_types(&self) -> Vec<String> {
+        vec![REGISTRY_INFO_MESSAGE_TYPE.to_string()]
+    }
+
+    async fn handle(
+        &self,
+        ctx: &Arc<HandlerContext>,
+        _message: Message,
+        _meta: UnpackMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "[profile = {}] Registry info requested by {}",
+            ctx.profile.inner.alias, ctx.sender_did
+        );
+
+        let body = serde_json::to_value(self.registry_info(&ctx.profile.inner.did))?;
+        transport::send_response(
+            &ctx.atm,
+            &ctx.profile,
+            REGISTRY_INFO_RESPONSE_MESSAGE_TYPE.to_string(),
+            body,
+            &ctx.sender_did,
+            ctx.thid.clone(),
+            ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_registry_info_reports_configured_values() {
+        let handler = RegistryInfoHandler::new(
+            "Example Registry".to_string(),
+            Some("https://example.com/governance".to_string()),
+        );
+
+        let body = serde_json::to_value(handler.registry_info("did:example:registry")).unwrap();
+
+        assert_eq!(
+            body,
+            json!({
+                "did": "did:example:registry",
+                "name": "Example Registry",
+                "record_model": "action-resource",
+                "governance_framework_url": "https://example.com/governance",
+                "version": env!("CARGO_PKG_VERSION")
+            })
+        );
+    }
+
+    #[test]
+    fn test_registry_info_omits_unset_governance_framework() {
+        let handler = RegistryInfoHandler::new("Example Registry".to_string(), None);
+
+        let body = serde_json::to_value(handler.registry_info("did:example:registry")).unwrap();
+
+        assert!(body.get("governance_framework_url").is_none());
+    }
+}
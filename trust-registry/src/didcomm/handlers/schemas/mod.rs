@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use affinidi_tdk::didcomm::{Message, UnpackMetadata};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    didcomm::{
+        handlers::{HandlerContext, ProtocolHandler},
+        problem_report::{self, ProblemReport},
+        transport,
+    },
+    domain::{AuthorityId, TrustRecord},
+    storage::repository::{TrustRecordAdminRepository, collect_schemas, endorsed_schemas},
+};
+
+pub const QUERY_SCHEMAS_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-schemas";
+pub const QUERY_SCHEMAS_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-schemas/response";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuerySchemasBody {
+    pub authority_id: AuthorityId,
+}
+
+/// Body of a `query-schemas` response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuerySchemasResponse {
+    pub authority_id: AuthorityId,
+    pub schemas: Vec<Value>,
+}
+
+/// Records endorsing credential schemas, by authority.
+struct SchemaIndex {
+    built_at: Instant,
+    records: HashMap<AuthorityId, Vec<TrustRecord>>,
+}
+
+/// Answers which credential schemas an authority endorses, see [`collect_schemas`].
+///
+/// The query is unauthenticated, so answers come from an index built from one
+/// [`list`](crate::storage::repository::TrustRecordRepository::list) scan instead of a scan
+/// per query. The index is rebuilt once older than the TTL, see `SCHEMA_INDEX_TTL_SEC`.
+pub struct SchemaQueryHandler<R: ?Sized + TrustRecordAdminRepository> {
+    repository: Arc<R>,
+    ttl: Duration,
+    /// Async, so queries arriving during a rebuild wait for it instead of scanning again.
+    index: tokio::sync::Mutex<Option<Arc<SchemaIndex>>>,
+}
+
+impl<R: ?Sized + TrustRecordAdminRepository> SchemaQueryHandler<R> {
+    pub fn new(repository: Arc<R>, ttl: Duration) -> Self {
+        Self {
+            repository,
+            ttl,
+            index: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub async fn query_schemas(&self, body: Value) -> Result<QuerySchemasResponse, ProblemReport> {
+        let QuerySchemasBody { authority_id } = serde_json::from_value(body)
+            .map_err(|e| ProblemReport::bad_request(format!("invalid query: {e}")))?;
+        let index = self.index().await?;
+        let schemas = collect_schemas(
+            index.records.get(&authority_id).into_iter().flatten(),
+            &authority_id,
+            chrono::Utc::now(),
+        );
+        Ok(QuerySchemasResponse {
+            authority_id,
+            schemas,
+        })
+    }
+
+    async fn index(&self) -> Result<Arc<SchemaIndex>, ProblemReport> {
+        let mut index = self.index.lock().await;
+        if let Some(current) = index.as_ref()
+            && current.built_at.elapsed() < self.ttl
+        {
+            return Ok(current.clone());
+        }
+        let mut records: HashMap<AuthorityId, Vec<TrustRecord>> = HashMap::new();
+        for record in self.repository.list().await?.into_records() {
+            if endorsed_schemas(&record).is_some() {
+                records
+                    .entry(record.authority_id().clone())
+                    .or_default()
+                    .push(record);
+            }
+        }
+        let rebuilt = Arc::new(SchemaIndex {
+            built_at: Instant::now(),
+            records,
+        });
+        *index = Some(rebuilt.clone());
+        Ok(rebuilt)
+    }
+}
+
+#[async_trait]
+impl<R: ?Sized + TrustRecordAdminRepository + 'static> ProtocolHandler for SchemaQueryHandler<R> {
+    fn get_supported_inbound_message_types(&self) -> Vec<String> {
+        vec![QUERY_SCHEMAS_MESSAGE_TYPE.to_string()]
+    }
+
+    async fn handle(
+        &self,
+        ctx: &Arc<HandlerContext>,
+        message: Message,
+        _meta: UnpackMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "[profile = {}] Schemas queried by {}",
+            ctx.profile.inner.alias, ctx.sender_did
+        );
+
+        let response = match self.query_schemas(message.body).await {
+            Ok(response) => response,
+            Err(report) => {
+                return problem_report::send_problem_report(
+                    &ctx.atm,
+                    &ctx.profile,
+                    report,
+                    &ctx.sender_did,
+                    ctx.thid.clone(),
+                    ctx.pthid.clone(),
+                    ctx.route,
+                    ctx.dead_letters.as_deref(),
+                )
+                .await;
+            }
+        };
+        transport::send_response(
+            &ctx.atm,
+            &ctx.profile,
+            QUERY_SCHEMAS_RESPONSE_MESSAGE_TYPE.to_string(),
+            serde_json::to_value(response)?,
+            &ctx.sender_did,
+            ctx.thid.clone(),
+            ctx.pthid.clone(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        didcomm::problem_report::codes,
+        domain::*,
+        storage::{
+            adapters::local_storage::LocalStorage, repository::CREDENTIAL_SCHEMAS_CONTEXT_KEY,
+        },
+        test_support::{AUTHORITY, record_builder},
+    };
+    use serde_json::json;
+
+    fn schema_record(entity_id: &str, schema: &str) -> TrustRecord {
+        record_builder(entity_id, AUTHORITY, "issue", "credential")
+            .context(Context::new(
+                json!({ CREDENTIAL_SCHEMAS_CONTEXT_KEY: [schema] }),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    fn handler() -> SchemaQueryHandler<LocalStorage> {
+        let record = schema_record(
+            "did:example:issuer",
+            "https://example.com/schemas/degree.json",
+        );
+        SchemaQueryHandler::new(
+            Arc::new(LocalStorage::with_records(vec![record])),
+            Duration::from_secs(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_query_schemas_returns_schemas_of_the_authority() {
+        let response = handler()
+            .query_schemas(json!({ "authority_id": "did:example:authority" }))
+            .await
+            .unwrap();
+        let unknown = handler()
+            .query_schemas(json!({ "authority_id": "did:example:unknown" }))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.schemas,
+            vec![json!("https://example.com/schemas/degree.json")]
+        );
+        assert!(unknown.schemas.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_schemas_without_authority_is_rejected() {
+        let report = handler().query_schemas(json!({})).await.unwrap_err();
+
+        assert_eq!(report.code, codes::ERROR_BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_index_is_reused_within_ttl() {
+        let storage = Arc::new(LocalStorage::new());
+        let cached = SchemaQueryHandler::new(storage.clone(), Duration::from_secs(60));
+        let uncached = SchemaQueryHandler::new(storage.clone(), Duration::ZERO);
+        let query = || json!({ "authority_id": "did:example:authority" });
+        cached.query_schemas(query()).await.unwrap();
+        uncached.query_schemas(query()).await.unwrap();
+
+        storage
+            .create(schema_record(
+                "did:example:issuer",
+                "https://example.com/schemas/degree.json",
+            ))
+            .await
+            .unwrap();
+
+        assert!(
+            cached
+                .query_schemas(query())
+                .await
+                .unwrap()
+                .schemas
+                .is_empty()
+        );
+        assert_eq!(
+            uncached.query_schemas(query()).await.unwrap().schemas.len(),
+            1
+        );
+    }
+}
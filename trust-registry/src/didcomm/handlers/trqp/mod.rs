@@ -1,13 +1,25 @@
 use std::sync::Arc;
 
-use crate::storage::repository::{TrustRecordQuery, TrustRecordRepository};
+use crate::configs::QueryAllowlist;
+use crate::domain::{Context, ContextMergeMode};
+use crate::policy::{self, EvaluationPolicy};
+use crate::storage::repository::{
+    Determination, RepositoryError, TrustRecordQuery, TrustRecordRepository,
+};
 use affinidi_tdk::didcomm::{Message, UnpackMetadata};
 use async_trait::async_trait;
-use serde_json::json;
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use serde_json::{Value, json};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
-use crate::didcomm::handlers::{HandlerContext, ProtocolHandler};
+use crate::didcomm::{
+    handlers::{HandlerContext, ProtocolHandler},
+    problem_report::{self, ProblemReport},
+    transport,
+};
 
 pub const QUERY_AUTHORIZATION_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/trqp/1.0/query-authorization";
@@ -17,18 +29,149 @@ pub const QUERY_AUTHORIZATION_RESPONSE_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/trqp/1.0/query-authorization/response";
 pub const QUERY_RECOGNITION_RESPONSE_MESSAGE_TYPE: &str =
     "https://affinidi.com/didcomm/protocols/trqp/1.0/query-recognition/response";
+pub const QUERY_AUTHORIZATION_BATCH_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-authorization-batch";
+pub const QUERY_RECOGNITION_BATCH_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-recognition-batch";
+pub const QUERY_AUTHORIZATION_BATCH_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-authorization-batch/response";
+pub const QUERY_RECOGNITION_BATCH_RESPONSE_MESSAGE_TYPE: &str =
+    "https://affinidi.com/didcomm/protocols/trqp/1.0/query-recognition-batch/response";
+
+/// Queries of one batch message evaluated at once.
+pub const BATCH_CONCURRENCY: usize = 8;
+
+const MESSAGE_TYPES: [(&str, &str); 4] = [
+    (
+        QUERY_AUTHORIZATION_MESSAGE_TYPE,
+        QUERY_AUTHORIZATION_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        QUERY_RECOGNITION_MESSAGE_TYPE,
+        QUERY_RECOGNITION_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        QUERY_AUTHORIZATION_BATCH_MESSAGE_TYPE,
+        QUERY_AUTHORIZATION_BATCH_RESPONSE_MESSAGE_TYPE,
+    ),
+    (
+        QUERY_RECOGNITION_BATCH_MESSAGE_TYPE,
+        QUERY_RECOGNITION_BATCH_RESPONSE_MESSAGE_TYPE,
+    ),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+struct BatchQueryBody {
+    queries: Vec<Value>,
+}
+
+/// `(request, response)` message type pairs of the TRQP protocol.
+pub fn all_message_types() -> &'static [(&'static str, &'static str)] {
+    &MESSAGE_TYPES
+}
 
 pub struct TRQPMessagesHandler<R: ?Sized + TrustRecordRepository> {
     pub repository: Arc<R>,
+    pub evaluation_policy: Arc<dyn EvaluationPolicy>,
+    /// Merged under the context of returned records, see `TRQP_DEFAULT_CONTEXT`.
+    pub default_context: Option<Context>,
+    /// JSON pointers of the context fields returned, see `TRQP_CONTEXT_ALLOWLIST`.
+    pub context_allowlist: Vec<String>,
+    /// See `CONTEXT_MERGE_MODE`.
+    pub context_merge_mode: ContextMergeMode,
+    /// See `CONTEXT_PROVENANCE`.
+    pub context_provenance: bool,
+    /// Queries outside it are answered with a problem report, see `ALLOWED_ACTIONS`.
+    pub query_allowlist: QueryAllowlist,
+    /// Most queries one batch message may carry, see `MAX_FAN_OUT`.
+    pub max_fan_out: usize,
+}
+
+impl<R: ?Sized + TrustRecordRepository> TRQPMessagesHandler<R> {
+    /// Answers one query with the determination of the matching record, or `{}` when
+    /// no record matches.
+    pub async fn evaluate(
+        &self,
+        body: Value,
+        determination: Determination,
+    ) -> Result<Value, ProblemReport> {
+        let as_of: Option<DateTime<Utc>> = body
+            .get("as_of")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| ProblemReport::bad_request(format!("invalid as_of: {e}")))?;
+        let query: TrustRecordQuery = serde_json::from_value(body)
+            .map_err(|e| ProblemReport::bad_request(format!("invalid query: {e}")))?;
+        self.query_allowlist
+            .check(query.action.as_str(), query.resource.as_str())
+            .map_err(ProblemReport::bad_request)?;
+
+        let evaluated = self
+            .repository
+            .evaluate(query, determination, as_of)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::HistoryUnavailable(_) => ProblemReport::bad_request(e.to_string()),
+                e => ProblemReport::from(e),
+            })?;
+        let Some(mut tr) = evaluated.record else {
+            return Ok(json!({}));
+        };
+        if let Some(defaults) = &self.default_context {
+            tr = tr.with_context_defaults(defaults, self.context_merge_mode);
+        }
+        let mut tr = policy::apply(
+            self.evaluation_policy.as_ref(),
+            tr,
+            None,
+            self.context_merge_mode,
+            self.context_provenance,
+        );
+        if !self.context_allowlist.is_empty() {
+            tr = tr.project_context(&self.context_allowlist);
+        }
+        serde_json::to_value(determination.isolate(tr))
+            .map_err(|e| ProblemReport::internal_error(e.to_string()))
+    }
+
+    /// Answers each query of a batch like [`Self::evaluate`], at most [`BATCH_CONCURRENCY`]
+    /// at once. Results keep the order of the queries; a failed query gets the body of
+    /// its problem report in its slot instead of failing the batch.
+    pub async fn evaluate_batch(
+        &self,
+        body: Value,
+        determination: Determination,
+    ) -> Result<Value, ProblemReport> {
+        let BatchQueryBody { queries } = serde_json::from_value(body)
+            .map_err(|e| ProblemReport::bad_request(format!("invalid batch: {e}")))?;
+        if queries.len() > self.max_fan_out {
+            return Err(ProblemReport::bad_request(format!(
+                "batch carries {} queries, exceeding the maximum fan-out of {}",
+                queries.len(),
+                self.max_fan_out
+            )));
+        }
+        let results: Vec<Value> = stream::iter(queries)
+            .map(|query| async move {
+                self.evaluate(query, determination)
+                    .await
+                    .unwrap_or_else(|report| report.to_body())
+            })
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+        Ok(json!({ "results": results }))
+    }
 }
 
 #[async_trait]
 impl<R: ?Sized + TrustRecordRepository + 'static> ProtocolHandler for TRQPMessagesHandler<R> {
     fn get_supported_inbound_message_types(&self) -> Vec<String> {
-        vec![
-            QUERY_AUTHORIZATION_MESSAGE_TYPE.to_string(),
-            QUERY_RECOGNITION_MESSAGE_TYPE.to_string(),
-        ]
+        all_message_types()
+            .iter()
+            .map(|(request, _)| request.to_string())
+            .collect()
     }
 
     async fn handle(
@@ -38,12 +181,38 @@ impl<R: ?Sized + TrustRecordRepository + 'static> ProtocolHandler for TRQPMessag
         _meta: UnpackMetadata,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let output_message_type: String = format!("{}/response", message.type_);
-        let query: TrustRecordQuery = serde_json::from_value(message.body)?;
-        let record = self.repository.find_by_query(query).await?;
-        let mut output_body = json!({});
-        if let Some(tr) = record {
-            output_body = serde_json::to_value(tr)?;
-        }
+        let determination = match message.type_.as_str() {
+            QUERY_RECOGNITION_MESSAGE_TYPE | QUERY_RECOGNITION_BATCH_MESSAGE_TYPE => {
+                Determination::Recognition
+            }
+            _ => Determination::Authorization,
+        };
+        let evaluated = match message.type_.as_str() {
+            QUERY_AUTHORIZATION_BATCH_MESSAGE_TYPE | QUERY_RECOGNITION_BATCH_MESSAGE_TYPE => {
+                self.evaluate_batch(message.body, determination).await
+            }
+            _ => self.evaluate(message.body, determination).await,
+        };
+        let output_body = match evaluated {
+            Ok(output_body) => output_body,
+            Err(report) => {
+                info!(
+                    "[profile = {}] Rejected query from {}: {}",
+                    &ctx.profile.inner.alias, ctx.sender_did, report.comment
+                );
+                return problem_report::send_problem_report(
+                    &ctx.atm,
+                    &ctx.profile,
+                    report,
+                    &ctx.sender_did,
+                    ctx.thid.clone(),
+                    ctx.pthid.clone(),
+                    ctx.route,
+                    ctx.dead_letters.as_deref(),
+                )
+                .await;
+            }
+        };
 
         let message_id = Uuid::new_v4().to_string();
         let output_message = Message::build(message_id.clone(), output_message_type, output_body)
@@ -62,26 +231,26 @@ impl<R: ?Sized + TrustRecordRepository + 'static> ProtocolHandler for TRQPMessag
             )
             .await?;
 
-        let sending_result = ctx
-            .atm
-            .forward_and_send_message(
-                &ctx.profile,
-                false,
-                &packed_msg.0,
-                Some(&message_id),
-                &ctx.profile.to_tdk_profile().mediator.unwrap(),
-                &ctx.sender_did,
-                None,
-                None,
-                false,
-            )
-            .await;
+        let sending_result = transport::deliver_or_dead_letter(
+            &ctx.atm,
+            &ctx.profile,
+            &packed_msg.0,
+            &message_id,
+            &ctx.sender_did,
+            ctx.thid.as_deref(),
+            ctx.route,
+            ctx.dead_letters.as_deref(),
+        )
+        .await;
 
         debug!("sending result {:?}", sending_result);
         if let Err(sending_error) = sending_result {
             error!(
-                "[profile = {}] Failed to forward message. Error: {:?}",
-                &ctx.profile.inner.alias, sending_error
+                "[profile = {}, thid = {:?}] Failed to send response after {} attempts. Error: {:?}",
+                &ctx.profile.inner.alias,
+                ctx.thid,
+                transport::SEND_ATTEMPTS,
+                sending_error
             );
         } else {
             info!(
@@ -92,3 +261,147 @@ impl<R: ?Sized + TrustRecordRepository + 'static> ProtocolHandler for TRQPMessag
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{AUTHORITY, ENTITY, record_builder, record_for};
+    use crate::{
+        domain::*, policy::DefaultEvaluationPolicy, storage::adapters::local_storage::LocalStorage,
+    };
+
+    fn handler(records: Vec<TrustRecord>, max_fan_out: usize) -> TRQPMessagesHandler<LocalStorage> {
+        TRQPMessagesHandler {
+            repository: Arc::new(LocalStorage::with_records(records)),
+            evaluation_policy: Arc::new(DefaultEvaluationPolicy),
+            default_context: None,
+            context_allowlist: vec![],
+            context_merge_mode: ContextMergeMode::default(),
+            context_provenance: false,
+            query_allowlist: QueryAllowlist::default(),
+            max_fan_out,
+        }
+    }
+
+    fn record(resource: &str) -> TrustRecord {
+        record_for(resource, resource != "revoked")
+    }
+
+    fn query(resource: &str) -> Value {
+        json!({
+            "entity_id": "did:example:entity",
+            "authority_id": "did:example:authority",
+            "action": "issue",
+            "resource": resource,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_batch_answers_each_query_in_order() {
+        let handler = handler(vec![record("credential"), record("revoked")], 100);
+
+        let output = handler
+            .evaluate_batch(
+                json!({ "queries": [query("revoked"), query("unknown"), query("credential"), {}] }),
+                Determination::Authorization,
+            )
+            .await
+            .unwrap();
+
+        let results = output["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0]["resource"], "revoked");
+        assert_eq!(results[0]["authorized"], false);
+        assert_eq!(results[1], json!({}));
+        assert_eq!(results[2]["resource"], "credential");
+        assert_eq!(results[2]["authorized"], true);
+        assert_eq!(results[3]["code"], problem_report::codes::ERROR_BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_context_is_projected_onto_the_allowlist() {
+        let record = record_builder(ENTITY, AUTHORITY, "issue", "credential")
+            .context(Context::new(
+                json!({ "framework": { "name": "health", "internal": "x" }, "notes": "y" }),
+            ))
+            .build()
+            .unwrap();
+        let handler = TRQPMessagesHandler {
+            context_allowlist: vec!["/framework/name".to_string()],
+            ..handler(vec![record], 100)
+        };
+
+        let output = handler
+            .evaluate(query("credential"), Determination::Authorization)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output["context"],
+            json!({ "framework": { "name": "health" } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_record_answers_like_a_miss() {
+        let expired =
+            record("credential").with_expires_at(Some(Utc::now() - chrono::Duration::seconds(1)));
+        let handler = handler(vec![expired], 100);
+
+        let single = handler
+            .evaluate(query("credential"), Determination::Authorization)
+            .await
+            .unwrap();
+        let batch = handler
+            .evaluate_batch(
+                json!({ "queries": [query("credential")] }),
+                Determination::Recognition,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(single, json!({}));
+        assert_eq!(batch["results"], json!([{}]));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_as_of_is_a_bad_request() {
+        let handler = handler(vec![record("credential")], 100);
+        let mut body = query("credential");
+        body["as_of"] = json!("yesterday");
+
+        let report = handler
+            .evaluate(body, Determination::Authorization)
+            .await
+            .unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+        assert!(report.comment.contains("invalid as_of"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_over_fan_out_is_rejected() {
+        let handler = handler(vec![], 2);
+
+        let report = handler
+            .evaluate_batch(
+                json!({ "queries": [query("a"), query("b"), query("c")] }),
+                Determination::Recognition,
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(report.code, problem_report::codes::ERROR_BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_all_message_types_match_supported_types() {
+        let handler = handler(vec![], 100);
+
+        let requests: Vec<&str> = all_message_types().iter().map(|(r, _)| *r).collect();
+        assert_eq!(handler.get_supported_inbound_message_types(), requests);
+        for (request, response) in all_message_types() {
+            assert_eq!(*response, format!("{request}/response"));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use affinidi_tdk::didcomm::{Message, UnpackMetadata};
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::{debug, info};
+
+use crate::didcomm::{
+    handlers::{HandlerContext, ProtocolHandler},
+    transport,
+};
+
+pub const TRUST_PING_MESSAGE_TYPE: &str = "https://didcomm.org/trust-ping/2.0/ping";
+pub const TRUST_PING_RESPONSE_MESSAGE_TYPE: &str =
+    "https://didcomm.org/trust-ping/2.0/ping-response";
+
+pub struct TrustPingHandler;
+
+impl Default for TrustPingHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrustPingHandler {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// `response_requested` defaults to `true` per the trust-ping 2.0 spec.
+fn response_requested(message: &Message) -> bool {
+    message
+        .body
+        .get("response_requested")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true)
+}
+
+#[async_trait]
+impl ProtocolHandler for TrustPingHandler {
+    fn get_supported_inbound_message_types(&self) -> Vec<String> {
+        vec![TRUST_PING_MESSAGE_TYPE.to_string()]
+    }
+
+    async fn handle(
+        &self,
+        ctx: &Arc<HandlerContext>,
+        message: Message,
+        _meta: UnpackMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            profile = %ctx.profile.inner.alias,
+            from = %ctx.sender_did,
+            message_id = %message.id,
+            "[profile = {}] Trust ping received",
+            ctx.profile.inner.alias
+        );
+
+        if !response_requested(&message) {
+            debug!("Trust ping {} did not request a response", message.id);
+            return Ok(());
+        }
+
+        // The ping-response thread is the ping itself.
+        transport::send_response(
+            &ctx.atm,
+            &ctx.profile,
+            TRUST_PING_RESPONSE_MESSAGE_TYPE.to_string(),
+            json!({}),
+            &ctx.sender_did,
+            Some(message.id.clone()),
+            None,
+            ctx.route,
+            ctx.dead_letters.as_deref(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(body: serde_json::Value) -> Message {
+        Message::build(
+            "ping-1".to_string(),
+            TRUST_PING_MESSAGE_TYPE.to_string(),
+            body,
+        )
+        .finalize()
+    }
+
+    #[test]
+    fn test_response_requested_defaults_to_true() {
+        assert!(response_requested(&ping(json!({}))));
+        assert!(response_requested(&ping(
+            json!({ "response_requested": true })
+        )));
+        assert!(!response_requested(&ping(
+            json!({ "response_requested": false })
+        )));
+    }
+}
@@ -1,15 +1,17 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use affinidi_tdk::messaging::profiles::ATMProfile;
 use affinidi_tdk::{
     TDK,
     common::{config::TDKConfig, profiles::TDKProfile},
 };
-use tokio::time::timeout;
 
 use crate::{
     configs::ProfileConfig,
-    didcomm::listener::{Listener, MessageHandler},
+    didcomm::{
+        MediatorConnectOptions, connect_with_retry,
+        listener::{Listener, MessageHandler},
+    },
 };
 
 impl<H: MessageHandler> Listener<H> {
@@ -17,6 +19,7 @@ impl<H: MessageHandler> Listener<H> {
         profile_config: ProfileConfig,
         mediator_did: &str,
         handler: H,
+        options: &MediatorConnectOptions,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let alias = &profile_config.alias;
         let did = &profile_config.did;
@@ -35,14 +38,9 @@ impl<H: MessageHandler> Listener<H> {
 
         let atm = tdk.atm.clone().ok_or("Value is missing")?;
 
-        let listener_profile = timeout(
-            Duration::from_secs(10),
-            atm.profile_add(
-                &ATMProfile::from_tdk_profile(&atm, &listener_profile_tdk).await?,
-                live_stream,
-            ),
-        )
-        .await??;
+        let profile = ATMProfile::from_tdk_profile(&atm, &listener_profile_tdk).await?;
+        let listener_profile =
+            connect_with_retry(alias, options, || atm.profile_add(&profile, live_stream)).await?;
 
         Ok(Self::new(
             Arc::new(atm),
@@ -1,19 +1,21 @@
+use crate::policy::EvaluationPolicy;
 use crate::storage::repository::TrustRecordAdminRepository;
-use std::sync::Arc;
-use tokio::task::JoinError;
-use tracing::error;
+use std::{sync::Arc, time::Duration};
 
 use affinidi_tdk::didcomm::{Message, UnpackMetadata};
 use affinidi_tdk::messaging::{ATM, profiles::ATMProfile};
 use async_trait::async_trait;
 use tracing::info;
 
-use super::handlers::BaseHandler;
+use super::dead_letter::DeadLetterQueue;
+use super::handlers::{BaseHandler, admin::AdminMessagesHandler};
 use crate::configs::{DidcommConfig, ProfileConfig};
+use supervisor::{RestartPolicy, supervise};
 
 pub mod build_listener;
 pub mod mediator_functions;
 pub mod start_listener;
+pub mod supervisor;
 
 #[async_trait]
 pub trait MessageHandler: Send + Sync + 'static {
@@ -53,47 +55,100 @@ impl<H: MessageHandler> Listener<H> {
     }
 }
 
+/// Runs the listener of one profile under a supervisor restarting it when it fails,
+/// see [`supervisor::supervise`]. `dead_letters` is the queue shared by every profile.
 pub(crate) async fn start_one_did_listener(
     profile_config: ProfileConfig,
     config: Arc<DidcommConfig>,
     repository: Arc<dyn TrustRecordAdminRepository>,
-) {
+    admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    evaluation_policy: Arc<dyn EvaluationPolicy>,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+) -> Result<(), String> {
+    let policy = RestartPolicy {
+        max_restarts: config.listener_max_restarts,
+        initial_backoff: Duration::from_secs(config.listener_restart_backoff_sec),
+        healthy_after: supervisor::HEALTHY_RUN,
+    };
+    let alias = profile_config.alias.clone();
+
+    supervise(&alias, policy, || {
+        run_did_listener(
+            profile_config.clone(),
+            config.clone(),
+            repository.clone(),
+            admin.clone(),
+            evaluation_policy.clone(),
+            dead_letters.clone(),
+        )
+    })
+    .await
+}
+
+/// Connects a listener for the profile and handles its messages until it fails.
+async fn run_did_listener(
+    profile_config: ProfileConfig,
+    config: Arc<DidcommConfig>,
+    repository: Arc<dyn TrustRecordAdminRepository>,
+    admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    evaluation_policy: Arc<dyn EvaluationPolicy>,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+) -> Result<(), String> {
+    let mut handler =
+        BaseHandler::build_with_admin(repository, config.clone(), admin, evaluation_policy);
+    if let Some(queue) = &dead_letters {
+        handler = handler.with_dead_letters(queue.clone());
+    }
+    let mediator_did = config.mediator_for(&profile_config).to_string();
     let listener = Listener::build_listener(
         profile_config,
-        &config.mediator_did,
-        BaseHandler::build_from_arc(repository, config.clone()),
+        &mediator_did,
+        handler,
+        &config.mediator_connect,
     )
     .await
-    .map_err(|e| {
-        error!("Build listener error: {:?}", e);
-        e
-    })
-    .unwrap();
+    .map_err(|e| format!("Build listener error: {e:?}"))?;
+
+    let alias = listener.profile.inner.alias.clone();
+    if let Some(queue) = &dead_letters {
+        queue.register_profile(listener.atm.clone(), listener.profile.clone());
+    }
 
     info!(
         "[profile = {}] Listener started",
         &listener.profile.inner.alias
     );
 
-    Arc::new(listener)
+    let result = Arc::new(listener)
         .start_listening(config)
         .await
-        .map_err(|e| {
-            error!("Start listener error: {:?}", e);
-            e
-        })
-        .unwrap()
+        .map_err(|e| format!("Start listener error: {e:?}"));
+    // a restarted listener registers its new connection
+    if let Some(queue) = &dead_letters {
+        queue.unregister_profile(&alias);
+    }
+    result
 }
 
 /// starts DIDComm listener for the configured DID profile
 pub(crate) async fn start_didcomm_listener(
     config: DidcommConfig,
     repository: Arc<dyn TrustRecordAdminRepository>,
-) -> Result<(), JoinError> {
+    admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    evaluation_policy: Arc<dyn EvaluationPolicy>,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let profile_config = config.profile_config.clone();
     let config = Arc::new(config);
 
-    let handle = tokio::spawn(start_one_did_listener(profile_config, config, repository));
-
-    handle.await
+    start_one_did_listener(
+        profile_config,
+        config,
+        repository,
+        admin,
+        evaluation_policy,
+        dead_letters,
+    )
+    .await?;
+    Ok(())
 }
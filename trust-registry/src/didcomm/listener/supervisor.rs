@@ -0,0 +1,137 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use tracing::{error, warn};
+
+/// Longest wait between two restarts of a failing listener.
+pub const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+/// How long a listener must run before a failure is counted as a fresh one.
+pub const HEALTHY_RUN: Duration = Duration::from_secs(600);
+
+/// How a failed listener is restarted, see `DIDCOMM_LISTENER_MAX_RESTARTS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Consecutive restarts before giving up.
+    pub max_restarts: u32,
+    /// Wait before the first restart, doubled after each one up to [`MAX_RESTART_BACKOFF`].
+    pub initial_backoff: Duration,
+    /// A run lasting at least this long resets the restart count and the backoff, so
+    /// occasional failures of a long-lived listener don't add up to giving up.
+    pub healthy_after: Duration,
+}
+
+/// Runs the listener built by `run`, restarting it whenever it fails or panics until
+/// `policy` gives up, so one failing listener doesn't take down the process.
+/// Returns when the listener stops without error, or with the last error.
+pub async fn supervise<F, Fut>(alias: &str, policy: RestartPolicy, mut run: F) -> Result<(), String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let mut restarts = 0;
+    let mut backoff = policy.initial_backoff;
+    loop {
+        let started = Instant::now();
+        let error = match tokio::spawn(run()).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(error)) => error,
+            Err(join_error) => format!("listener task failed: {join_error}"),
+        };
+        if started.elapsed() >= policy.healthy_after {
+            restarts = 0;
+            backoff = policy.initial_backoff;
+        }
+        if restarts >= policy.max_restarts {
+            error!(
+                "[profile = {alias}] Listener failed after {restarts} restarts, giving up: {error}"
+            );
+            return Err(error);
+        }
+        restarts += 1;
+        warn!(
+            "[profile = {alias}] Listener failed, restart {restarts}/{} in {backoff:?}: {error}",
+            policy.max_restarts
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    fn policy(max_restarts: u32) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            initial_backoff: Duration::from_millis(1),
+            healthy_after: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_listener_is_restarted() {
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let result = supervise("registry", policy(3), || {
+            let runs = runs.clone();
+            async move {
+                match runs.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("mediator connection closed".to_string()),
+                    1 => panic!("listener panicked"),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_gives_up_after_max_restarts() {
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let result = supervise("registry", policy(2), || {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            async move { Err(format!("run {run} failed")) }
+        })
+        .await;
+
+        assert_eq!(result, Err("run 2 failed".to_string()));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_long_run_resets_restart_count() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            healthy_after: Duration::from_millis(20),
+            ..policy(1)
+        };
+
+        let result = supervise("registry", policy, || {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match run {
+                    // without the reset, the failure of run 1 would exhaust the restarts
+                    1 | 2 => tokio::time::sleep(Duration::from_millis(30)).await,
+                    3 => return Ok(()),
+                    _ => {}
+                }
+                Err(format!("run {run} failed"))
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+    }
+}
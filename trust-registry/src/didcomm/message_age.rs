@@ -0,0 +1,142 @@
+use affinidi_tdk::didcomm::Message;
+use chrono::Utc;
+use std::time::Duration;
+
+use super::problem_report::ProblemReport;
+
+/// Rejects inbound messages that are replayed long after they were sent.
+///
+/// A message is too old when its `created_time` is more than `max_age` ago, a zero `max_age`
+/// disables that check. A message past its own `expires_time` is always rejected. Messages
+/// without a `created_time` are only rejected when timestamps are required.
+pub struct MessageAgePolicy {
+    max_age: Duration,
+    require_timestamps: bool,
+}
+
+impl MessageAgePolicy {
+    pub fn new(max_age: Duration, require_timestamps: bool) -> Self {
+        Self {
+            max_age,
+            require_timestamps,
+        }
+    }
+
+    pub fn check(&self, message: &Message) -> Result<(), ProblemReport> {
+        self.check_at(message, Utc::now().timestamp() as u64)
+    }
+
+    /// Checks the message against `now`, in seconds since the Unix epoch.
+    fn check_at(&self, message: &Message, now: u64) -> Result<(), ProblemReport> {
+        if let Some(expires_time) = message.expires_time
+            && now >= expires_time
+        {
+            return Err(ProblemReport::bad_request(format!(
+                "message expired at {expires_time}"
+            )));
+        }
+
+        match message.created_time {
+            Some(created_time) if !self.max_age.is_zero() => {
+                let age = now.saturating_sub(created_time);
+                if age > self.max_age.as_secs() {
+                    return Err(ProblemReport::bad_request(format!(
+                        "message is {age} seconds old, the limit is {} seconds",
+                        self.max_age.as_secs()
+                    )));
+                }
+            }
+            None if self.require_timestamps => {
+                return Err(ProblemReport::bad_request("message has no created_time"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn message(created_time: Option<u64>, expires_time: Option<u64>) -> Message {
+        let mut message = Message::build(
+            "msg-1".to_string(),
+            "https://affinidi.com/didcomm/protocols/tr-admin/1.0/read-record".to_string(),
+            json!({}),
+        )
+        .finalize();
+        message.created_time = created_time;
+        message.expires_time = expires_time;
+        message
+    }
+
+    #[test]
+    fn test_stale_message_is_rejected() {
+        let policy = MessageAgePolicy::new(Duration::from_secs(600), false);
+
+        let report = policy
+            .check_at(&message(Some(NOW - 601), None), NOW)
+            .unwrap_err();
+
+        assert_eq!(report.code, "e.p.msg.bad-request");
+        assert_eq!(
+            report.comment,
+            "message is 601 seconds old, the limit is 600 seconds"
+        );
+    }
+
+    #[test]
+    fn test_fresh_message_passes() {
+        let policy = MessageAgePolicy::new(Duration::from_secs(600), false);
+
+        assert!(
+            policy
+                .check_at(&message(Some(NOW - 600), None), NOW)
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_at(&message(Some(NOW), Some(NOW + 60)), NOW)
+                .is_ok()
+        );
+        // a sender clock slightly ahead of ours is not a replay
+        assert!(policy.check_at(&message(Some(NOW + 5), None), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_expired_message_is_rejected() {
+        let policy = MessageAgePolicy::new(Duration::ZERO, false);
+
+        let report = policy
+            .check_at(&message(Some(NOW - 10), Some(NOW)), NOW)
+            .unwrap_err();
+
+        assert_eq!(report.comment, format!("message expired at {NOW}"));
+    }
+
+    #[test]
+    fn test_zero_max_age_disables_the_age_check() {
+        let policy = MessageAgePolicy::new(Duration::ZERO, false);
+
+        assert!(policy.check_at(&message(Some(0), None), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_message_without_timestamps_passes_unless_required() {
+        let lenient = MessageAgePolicy::new(Duration::from_secs(600), false);
+        let strict = MessageAgePolicy::new(Duration::from_secs(600), true);
+
+        assert!(lenient.check_at(&message(None, None), NOW).is_ok());
+        assert_eq!(
+            strict
+                .check_at(&message(None, None), NOW)
+                .unwrap_err()
+                .comment,
+            "message has no created_time"
+        );
+    }
+}
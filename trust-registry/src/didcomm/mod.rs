@@ -2,19 +2,24 @@ use affinidi_tdk::{
     TDK,
     common::{config::TDKConfig, profiles::TDKProfile},
     didcomm::Message,
-    messaging::{ATM, profiles::ATMProfile},
+    messaging::{ATM, errors::ATMError, profiles::ATMProfile},
     secrets_resolver::secrets::Secret,
 };
-use std::{sync::Arc, time::Duration};
+use std::{future::Future, sync::Arc, time::Duration};
 use tokio::time::timeout;
-use tracing::error;
+use tracing::{error, warn};
 use uuid::Uuid;
 
+pub mod dead_letter;
+pub mod deduplication;
 pub mod did_document;
 pub mod handlers;
 pub mod listener;
+pub mod message_age;
 pub mod problem_report;
+pub mod protocol_version;
 pub mod transport;
+pub mod webvh_log;
 
 /// Returns the thread ID for a message, falling back to the message ID if no thread ID is set.
 pub fn get_thread_id(msg: &Message) -> Option<String> {
@@ -30,12 +35,92 @@ pub fn new_message_id() -> String {
     Uuid::new_v4().to_string()
 }
 
+/// How [`prepare_atm_and_profile`] and the listeners connect to the mediator, see
+/// `MEDIATOR_CONNECT_TIMEOUT_SEC`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediatorConnectOptions {
+    /// How long one connection attempt may take before the profile is given up on.
+    pub timeout: Duration,
+    /// Attempts made while the mediator can't be reached.
+    pub attempts: u32,
+    /// Wait after the first failed attempt, doubled after each one.
+    pub initial_backoff: Duration,
+}
+
+impl Default for MediatorConnectOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Failures worth another attempt: the mediator could not be reached or dropped the
+/// connection. Denied or misconfigured profiles fail the same way every time.
+fn is_transient(error: &ATMError) -> bool {
+    matches!(
+        error,
+        ATMError::TransportError(_) | ATMError::MsgSendError(_) | ATMError::MsgReceiveError(_)
+    )
+}
+
+/// Runs `connect` until it succeeds, retrying transient failures with backoff.
+///
+/// An attempt running past the timeout is not retried: when the mediator denies the
+/// profile's ACL, the SDK keeps trying to authenticate in an infinite loop instead of
+/// failing
+/// (https://github.com/affinidi/affinidi-messaging/blob/main/affinidi-messaging-sdk/src/transports/websockets/ws_connection.rs#L229),
+/// so a stuck attempt is most likely a denied one.
+async fn connect_with_retry<T, F, Fut>(
+    alias: &str,
+    options: &MediatorConnectOptions,
+    mut connect: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ATMError>>,
+{
+    let attempts = options.attempts.max(1);
+    let mut backoff = options.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        let error = match timeout(options.timeout, connect()).await {
+            Ok(Ok(connected)) => return Ok(connected),
+            Ok(Err(ATMError::ACLDenied(e))) => {
+                return Err(format!("Failed to add profile: {alias:?}, ACL denied: {e}"));
+            }
+            Ok(Err(e)) if is_transient(&e) => e,
+            Ok(Err(e)) => return Err(format!("Failed to add profile: {alias:?}, error: {e}")),
+            Err(_) => {
+                return Err(format!(
+                    "Failed to add profile: {alias:?}, no connection within {:?}, is the profile denied by the mediator ACL?",
+                    options.timeout
+                ));
+            }
+        };
+        if attempt == attempts {
+            return Err(format!(
+                "Failed to add profile: {alias:?} after {attempts} attempts, error: {error}"
+            ));
+        }
+        warn!(
+            "Failed to add profile: {alias:?}, attempt {attempt}/{attempts}, retrying in {backoff:?}: {error}"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
 pub async fn prepare_atm_and_profile(
     alias: &str,
     service_did: &str,
     mediator_did: &str,
     secrets: Vec<Secret>,
     live_stream: bool,
+    options: &MediatorConnectOptions,
 ) -> Result<(Arc<ATM>, Arc<ATMProfile>), Box<dyn std::error::Error>> {
     let service_profile = TDKProfile::new(alias, service_did, Some(mediator_did), secrets);
 
@@ -55,27 +140,16 @@ pub async fn prepare_atm_and_profile(
         .clone()
         .ok_or_else(|| "Failed to initialize ATM client".to_owned())?;
 
-    // When ACL is denied it keeps trying to authenticate in an infinite loop
-    // https://github.com/affinidi/affinidi-messaging/blob/main/affinidi-messaging-sdk/src/transports/websockets/ws_connection.rs#L229
-    // that's why it gets stuck if acl is denied without throwing any errors
-    // that's why using timeout here so we can continue the loop and not get stuck
-    let service_profile = match timeout(
-        Duration::from_secs(5),
-        atm.profile_add(
-            &ATMProfile::from_tdk_profile(&atm, &service_profile)
-                .await
-                .map_err(|e| e.to_string())?,
-            live_stream,
-        ),
-    )
-    .await
-    {
-        Ok(profile) => profile.map_err(|e| e.to_string())?,
-        Err(err) => {
-            error!("Failed to add profile: {alias:?}, error: {err:#?}");
-            return Err(format!("Failed to add profile: {alias:?}, error: {err:#?}").into());
-        }
-    };
+    let profile = ATMProfile::from_tdk_profile(&atm, &service_profile)
+        .await
+        .map_err(|e| e.to_string())?;
+    let service_profile =
+        connect_with_retry(alias, options, || atm.profile_add(&profile, live_stream))
+            .await
+            .map_err(|e| {
+                error!("{e}");
+                e
+            })?;
 
     Ok((Arc::new(atm), service_profile))
 }
@@ -108,6 +182,69 @@ mod tests {
         assert_eq!(get_parent_thread_id(&msg), Some("thread-123".to_string()));
     }
 
+    fn options() -> MediatorConnectOptions {
+        MediatorConnectOptions {
+            timeout: Duration::from_millis(50),
+            attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_connection_failure_is_retried() {
+        let mut calls = 0;
+
+        let result = connect_with_retry("registry", &options(), || {
+            calls += 1;
+            let call = calls;
+            async move {
+                match call {
+                    1 => Err(ATMError::TransportError("connection refused".to_string())),
+                    _ => Ok("connected"),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("connected"));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_acl_denied_and_stuck_connections_are_not_retried() {
+        let mut calls = 0;
+        let denied: Result<(), String> = connect_with_retry("registry", &options(), || {
+            calls += 1;
+            async { Err(ATMError::ACLDenied("not allowed".to_string())) }
+        })
+        .await;
+        assert!(denied.unwrap_err().contains("ACL denied"));
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let stuck: Result<(), String> = connect_with_retry("registry", &options(), || {
+            calls += 1;
+            std::future::pending()
+        })
+        .await;
+        assert!(stuck.unwrap_err().contains("no connection within 50ms"));
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_give_up_after_the_attempts() {
+        let mut calls = 0;
+
+        let result: Result<(), String> = connect_with_retry("registry", &options(), || {
+            calls += 1;
+            async { Err(ATMError::TransportError("connection refused".to_string())) }
+        })
+        .await;
+
+        assert!(result.unwrap_err().contains("after 3 attempts"));
+        assert_eq!(calls, 3);
+    }
+
     #[test]
     fn test_new_message_id() {
         let id1 = new_message_id();
@@ -4,7 +4,11 @@ use affinidi_tdk::messaging::{ATM, profiles::ATMProfile};
 use serde_json::json;
 use tracing::{error, info};
 
-use super::transport;
+use super::{
+    dead_letter::{DeadLetter, DeadLetterQueue},
+    transport,
+};
+use crate::storage::repository::RepositoryError;
 
 pub mod codes {
     pub const ERROR_UNAUTHORIZED: &str = "e.p.msg.unauthorized";
@@ -12,6 +16,10 @@ pub mod codes {
     pub const ERROR_NOT_FOUND: &str = "e.p.msg.not-found";
     pub const ERROR_CONFLICT: &str = "e.p.msg.conflict";
     pub const ERROR_INTERNAL: &str = "e.p.msg.internal-error";
+    pub const ERROR_READ_ONLY: &str = "e.p.msg.read-only";
+    pub const ERROR_BUSY: &str = "e.p.msg.busy";
+    pub const ERROR_UNSUPPORTED_VERSION: &str = "e.p.msg.unsupported-version";
+    pub const ERROR_TIMEOUT: &str = "e.p.msg.timeout";
 }
 
 /// Problem report structure following DIDComm problem-report protocol
@@ -54,6 +62,22 @@ impl ProblemReport {
         Self::new(codes::ERROR_INTERNAL, comment)
     }
 
+    pub fn read_only() -> Self {
+        Self::new(codes::ERROR_READ_ONLY, "registry in read-only mode")
+    }
+
+    pub fn busy(comment: impl Into<String>) -> Self {
+        Self::new(codes::ERROR_BUSY, comment)
+    }
+
+    pub fn unsupported_version(comment: impl Into<String>) -> Self {
+        Self::new(codes::ERROR_UNSUPPORTED_VERSION, comment)
+    }
+
+    pub fn timeout(comment: impl Into<String>) -> Self {
+        Self::new(codes::ERROR_TIMEOUT, comment)
+    }
+
     pub fn with_args(mut self, args: Vec<String>) -> Self {
         self.args = Some(args);
         self
@@ -80,9 +104,46 @@ impl ProblemReport {
 
         body
     }
+
+    /// Parses the body of a received problem report. `None` when `code` or `comment` is missing.
+    pub fn from_body(body: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| body.get(name).and_then(serde_json::Value::as_str);
+        Some(Self {
+            code: field("code")?.to_string(),
+            comment: field("comment")?.to_string(),
+            args: body
+                .get("args")
+                .and_then(|args| serde_json::from_value(args.clone()).ok()),
+            escalate_to: field("escalate_to").map(str::to_string),
+        })
+    }
+}
+
+/// Failures without a more specific code are reported as internal errors.
+impl From<String> for ProblemReport {
+    fn from(comment: String) -> Self {
+        Self::internal_error(comment)
+    }
+}
+
+/// Missing, conflicting and invalid records and backend timeouts are reported as such,
+/// other storage failures as internal errors.
+impl From<RepositoryError> for ProblemReport {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::RecordNotFound(_) => Self::not_found(e.to_string()),
+            RepositoryError::RecordAlreadyExists(_) | RepositoryError::VersionConflict(_) => {
+                Self::conflict(e.to_string())
+            }
+            RepositoryError::ValidationError(_) => Self::bad_request(e.to_string()),
+            RepositoryError::Timeout(_) => Self::timeout(e.to_string()),
+            e => Self::internal_error(e.to_string()),
+        }
+    }
 }
 
 /// Send a problem report message via ATM
+#[allow(clippy::too_many_arguments)]
 pub async fn send_problem_report(
     atm: &Arc<ATM>,
     profile: &Arc<ATMProfile>,
@@ -90,6 +151,8 @@ pub async fn send_problem_report(
     recipient: &str,
     thid: Option<String>,
     pthid: Option<String>,
+    route: transport::Route,
+    dead_letters: Option<&DeadLetterQueue>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let problem_message = transport::build_problem_report(
         profile.inner.did.clone(),
@@ -111,19 +174,36 @@ pub async fn send_problem_report(
         )
         .await?;
 
-    let sending_result = atm
-        .forward_and_send_message(
-            profile,
-            false,
-            &packed_msg.0,
-            Some(&message_id),
-            &profile.to_tdk_profile().mediator.unwrap(),
+    let mediator = profile.to_tdk_profile().mediator;
+    let sending_result = match route.towards(recipient, mediator.as_deref()) {
+        transport::Route::Forward => atm
+            .forward_and_send_message(
+                profile,
+                false,
+                &packed_msg.0,
+                Some(&message_id),
+                mediator.as_deref().unwrap(),
+                recipient,
+                None,
+                None,
+                false,
+            )
+            .await
+            .map(|_| ()),
+        transport::Route::ReturnRoute => {
+            transport::send_on_return_route(atm, profile, &packed_msg.0, &message_id).await
+        }
+    };
+    let sending_result = transport::queue_if_undelivered(sending_result, dead_letters, || {
+        DeadLetter::new(
+            &profile.inner.alias,
             recipient,
-            None,
-            None,
-            false,
+            problem_message.thid.as_deref(),
+            &message_id,
+            &packed_msg.0,
         )
-        .await;
+    })
+    .await;
 
     if let Err(sending_error) = sending_result {
         error!(
@@ -162,4 +242,22 @@ mod tests {
         assert_eq!(body["code"], codes::ERROR_BAD_REQUEST);
         assert!(body["args"].is_array());
     }
+
+    #[test]
+    fn test_problem_report_round_trips_through_body() {
+        let report = ProblemReport::conflict("Stale version")
+            .with_args(vec!["3".to_string()])
+            .with_escalate_to("mailto:admin@example.com".to_string());
+
+        let parsed = ProblemReport::from_body(&report.to_body()).unwrap();
+
+        assert_eq!(parsed.code, codes::ERROR_CONFLICT);
+        assert_eq!(parsed.comment, "Stale version");
+        assert_eq!(parsed.args, Some(vec!["3".to_string()]));
+        assert_eq!(
+            parsed.escalate_to.as_deref(),
+            Some("mailto:admin@example.com")
+        );
+        assert!(ProblemReport::from_body(&json!({"comment": "no code"})).is_none());
+    }
 }
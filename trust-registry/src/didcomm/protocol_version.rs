@@ -0,0 +1,194 @@
+use super::problem_report::ProblemReport;
+
+/// A DIDComm message type URI split around its protocol version, e.g.
+/// `https://affinidi.com/didcomm/protocols/tr-admin`, `1.0` and `create-record`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageType<'a> {
+    pub protocol: &'a str,
+    pub major: u32,
+    pub minor: u32,
+    pub name: &'a str,
+}
+
+impl<'a> MessageType<'a> {
+    /// Splits at the first `<major>.<minor>` path segment. `None` for unversioned types.
+    pub fn parse(uri: &'a str) -> Option<Self> {
+        let mut offset = 0;
+        for segment in uri.split('/') {
+            let start = offset;
+            offset += segment.len() + 1;
+            let Some((major, minor)) = segment.split_once('.') else {
+                continue;
+            };
+            let (Ok(major), Ok(minor)) = (major.parse(), minor.parse()) else {
+                continue;
+            };
+            if start == 0 || offset > uri.len() {
+                return None;
+            }
+            return Some(Self {
+                protocol: &uri[..start - 1],
+                major,
+                minor,
+                name: &uri[offset..],
+            });
+        }
+        None
+    }
+
+    pub fn version(&self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+}
+
+/// Picks the supported message type that handles `message_type`.
+///
+/// An exact match wins. Otherwise, as the DIDComm spec asks, a message of another minor
+/// version of a supported major version is handled by the highest supported minor version.
+/// A message of an unsupported major version is rejected with a problem report listing
+/// the supported versions. `Ok(None)` means no version of the message is supported.
+pub fn resolve(message_type: &str, supported: &[String]) -> Result<Option<String>, ProblemReport> {
+    if supported.iter().any(|s| s == message_type) {
+        return Ok(Some(message_type.to_string()));
+    }
+    let Some(inbound) = MessageType::parse(message_type) else {
+        return Ok(None);
+    };
+
+    let mut candidates: Vec<MessageType> = supported
+        .iter()
+        .filter_map(|s| MessageType::parse(s))
+        .filter(|s| s.protocol == inbound.protocol && s.name == inbound.name)
+        .collect();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    candidates.sort_by_key(|s| (s.major, s.minor));
+
+    if let Some(compatible) = candidates.iter().rev().find(|s| s.major == inbound.major) {
+        return Ok(Some(format!(
+            "{}/{}/{}",
+            compatible.protocol,
+            compatible.version(),
+            compatible.name
+        )));
+    }
+
+    let versions: Vec<String> = candidates.iter().map(MessageType::version).collect();
+    Err(ProblemReport::unsupported_version(format!(
+        "version {} of {} is not supported, supported versions: {}",
+        inbound.version(),
+        inbound.protocol,
+        versions.join(", ")
+    ))
+    .with_args(versions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::didcomm::{
+        handlers::admin::{CREATE_RECORD_MESSAGE_TYPE, all_message_types},
+        problem_report::codes,
+    };
+
+    fn admin_types() -> Vec<String> {
+        all_message_types()
+            .iter()
+            .map(|(request, _)| request.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_message_type() {
+        let parsed =
+            MessageType::parse("https://affinidi.com/didcomm/protocols/tr-admin/1.0/create-record")
+                .unwrap();
+
+        assert_eq!(
+            parsed.protocol,
+            "https://affinidi.com/didcomm/protocols/tr-admin"
+        );
+        assert_eq!((parsed.major, parsed.minor), (1, 0));
+        assert_eq!(parsed.name, "create-record");
+        assert_eq!(
+            MessageType::parse("https://didcomm.org/report-problem/2.0/problem-report")
+                .unwrap()
+                .version(),
+            "2.0"
+        );
+        assert!(MessageType::parse("https://example.com/no-version/message").is_none());
+        assert!(MessageType::parse("https://example.com/protocol/1.0").is_none());
+    }
+
+    #[test]
+    fn test_exact_type_is_dispatched_as_is() {
+        assert_eq!(
+            resolve(CREATE_RECORD_MESSAGE_TYPE, &admin_types()).unwrap(),
+            Some(CREATE_RECORD_MESSAGE_TYPE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_other_minor_version_is_handled_by_the_supported_one() {
+        let resolved = resolve(
+            "https://affinidi.com/didcomm/protocols/tr-admin/1.3/create-record",
+            &admin_types(),
+        )
+        .unwrap();
+
+        assert_eq!(resolved, Some(CREATE_RECORD_MESSAGE_TYPE.to_string()));
+    }
+
+    #[test]
+    fn test_unsupported_major_version_is_reported() {
+        let report = resolve(
+            "https://affinidi.com/didcomm/protocols/tr-admin/2.0/create-record",
+            &admin_types(),
+        )
+        .unwrap_err();
+
+        assert_eq!(report.code, codes::ERROR_UNSUPPORTED_VERSION);
+        assert_eq!(
+            report.comment,
+            "version 2.0 of https://affinidi.com/didcomm/protocols/tr-admin is not supported, supported versions: 1.0"
+        );
+        assert_eq!(report.args, Some(vec!["1.0".to_string()]));
+    }
+
+    #[test]
+    fn test_highest_compatible_version_is_chosen() {
+        let supported = vec![
+            "https://example.com/protocol/1.0/ping".to_string(),
+            "https://example.com/protocol/1.2/ping".to_string(),
+            "https://example.com/protocol/3.0/ping".to_string(),
+        ];
+
+        assert_eq!(
+            resolve("https://example.com/protocol/1.1/ping", &supported).unwrap(),
+            Some("https://example.com/protocol/1.2/ping".to_string())
+        );
+        let report = resolve("https://example.com/protocol/2.0/ping", &supported).unwrap_err();
+        assert_eq!(
+            report.args,
+            Some(vec![
+                "1.0".to_string(),
+                "1.2".to_string(),
+                "3.0".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_message_is_not_a_version_mismatch() {
+        assert_eq!(
+            resolve(
+                "https://affinidi.com/didcomm/protocols/tr-admin/2.0/unknown",
+                &admin_types()
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(resolve("unversioned", &admin_types()).unwrap(), None);
+    }
+}
@@ -1,17 +1,61 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use affinidi_tdk::{
     didcomm::Message,
-    messaging::{ATM, profiles::ATMProfile},
+    messaging::{ATM, errors::ATMError, profiles::ATMProfile},
 };
 use serde_json::Value;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::didcomm::new_message_id;
 
-use super::problem_report::ProblemReport;
+use super::{
+    dead_letter::{DeadLetter, DeadLetterQueue},
+    problem_report::ProblemReport,
+};
+
+pub const PROBLEM_REPORT_TYPE: &str = "https://didcomm.org/report-problem/2.0/problem-report";
+
+pub const SEND_ATTEMPTS: u32 = 3;
+const INITIAL_SEND_RETRY_DELAY: Duration = Duration::from_millis(500);
 
-const PROBLEM_REPORT_TYPE: &str = "https://didcomm.org/report-problem/2.0/problem-report";
+/// Header of the DIDComm `return_route` decorator.
+pub const RETURN_ROUTE_HEADER: &str = "return_route";
+
+/// How a response reaches the sender of the inbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Route {
+    /// Wrapped in a forward envelope and sent through the mediator.
+    #[default]
+    Forward,
+    /// Sent back over the profile's active connection, as asked by `return_route: all`.
+    /// Only the mediator shares that connection, see [`Route::towards`].
+    ReturnRoute,
+}
+
+impl Route {
+    /// [`Route::ReturnRoute`] when `honor_return_route` is set and the message carries
+    /// `return_route: all`. Any other value, or none, falls back to forwarding.
+    pub fn for_message(message: &Message, honor_return_route: bool) -> Self {
+        let return_route = message
+            .extra_headers
+            .get(RETURN_ROUTE_HEADER)
+            .and_then(Value::as_str);
+        match return_route {
+            Some("all") if honor_return_route => Self::ReturnRoute,
+            _ => Self::Forward,
+        }
+    }
+
+    /// The route a message to `recipient` takes. The registry's only live connection is
+    /// the one to its `mediator`, so a return route to anyone else is forwarded instead.
+    pub fn towards(self, recipient: &str, mediator: Option<&str>) -> Self {
+        match self {
+            Self::ReturnRoute if mediator == Some(recipient) => Self::ReturnRoute,
+            _ => Self::Forward,
+        }
+    }
+}
 
 pub fn build_response(
     type_: String,
@@ -50,6 +94,7 @@ pub fn build_problem_report(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn send_response(
     atm: &Arc<ATM>,
     profile: &Arc<ATMProfile>,
@@ -58,6 +103,8 @@ pub async fn send_response(
     recipient: &str,
     thid: Option<String>,
     pthid: Option<String>,
+    route: Route,
+    dead_letters: Option<&DeadLetterQueue>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let response_message = build_response(
         message_type,
@@ -80,24 +127,22 @@ pub async fn send_response(
         )
         .await?;
 
-    let sending_result = atm
-        .forward_and_send_message(
-            profile,
-            false,
-            &packed_msg.0,
-            Some(&message_id),
-            &profile.to_tdk_profile().mediator.unwrap(),
-            recipient,
-            None,
-            None,
-            false,
-        )
-        .await;
+    let sending_result = deliver_or_dead_letter(
+        atm,
+        profile,
+        &packed_msg.0,
+        &message_id,
+        recipient,
+        response_message.thid.as_deref(),
+        route,
+        dead_letters,
+    )
+    .await;
 
     if let Err(sending_error) = sending_result {
         error!(
-            "[profile = {}] Failed to send response. Error: {:?}",
-            &profile.inner.alias, sending_error
+            "[profile = {}, thid = {:?}] Failed to send response. Error: {:?}",
+            &profile.inner.alias, response_message.thid, sending_error
         );
         return Err(sending_error.into());
     }
@@ -109,9 +154,226 @@ pub async fn send_response(
     Ok(())
 }
 
+/// Delivers a packed message like [`deliver_with_retry`], queueing it in `dead_letters`
+/// for a later retry when every attempt fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn deliver_or_dead_letter(
+    atm: &Arc<ATM>,
+    profile: &Arc<ATMProfile>,
+    packed_msg: &str,
+    message_id: &str,
+    recipient: &str,
+    thid: Option<&str>,
+    route: Route,
+    dead_letters: Option<&DeadLetterQueue>,
+) -> Result<(), ATMError> {
+    let result = deliver_with_retry(atm, profile, packed_msg, message_id, recipient, route).await;
+    queue_if_undelivered(result, dead_letters, || {
+        DeadLetter::new(
+            &profile.inner.alias,
+            recipient,
+            thid,
+            message_id,
+            packed_msg,
+        )
+    })
+    .await
+}
+
+/// Queues the letter when `result` is a failed send, then hands `result` back.
+pub async fn queue_if_undelivered<E>(
+    result: Result<(), E>,
+    dead_letters: Option<&DeadLetterQueue>,
+    letter: impl FnOnce() -> DeadLetter,
+) -> Result<(), E> {
+    if result.is_err()
+        && let Some(queue) = dead_letters
+    {
+        queue.push(letter()).await;
+    }
+    result
+}
+
+/// Sends a packed message along `route`, retrying transient failures.
+pub async fn deliver_with_retry(
+    atm: &Arc<ATM>,
+    profile: &Arc<ATMProfile>,
+    packed_msg: &str,
+    message_id: &str,
+    recipient: &str,
+    route: Route,
+) -> Result<(), ATMError> {
+    let mediator = profile.to_tdk_profile().mediator;
+    match route.towards(recipient, mediator.as_deref()) {
+        Route::Forward => forward_with_retry(atm, profile, packed_msg, message_id, recipient).await,
+        Route::ReturnRoute => {
+            retry_with_backoff(SEND_ATTEMPTS, INITIAL_SEND_RETRY_DELAY, |_| {
+                send_on_return_route(atm, profile, packed_msg, message_id)
+            })
+            .await
+        }
+    }
+}
+
+/// Sends a packed message over the profile's live connection, without a forward envelope.
+/// Only the mediator at the other end of that connection can unpack it.
+pub async fn send_on_return_route(
+    atm: &Arc<ATM>,
+    profile: &Arc<ATMProfile>,
+    packed_msg: &str,
+    message_id: &str,
+) -> Result<(), ATMError> {
+    atm.send_message(profile, packed_msg, message_id, false, false)
+        .await
+        .map(|_| ())
+}
+
+/// Forwards a packed message through the profile's mediator, retrying transient failures.
+pub async fn forward_with_retry(
+    atm: &Arc<ATM>,
+    profile: &Arc<ATMProfile>,
+    packed_msg: &str,
+    message_id: &str,
+    recipient: &str,
+) -> Result<(), ATMError> {
+    let mediator = profile.to_tdk_profile().mediator.unwrap();
+    retry_with_backoff(SEND_ATTEMPTS, INITIAL_SEND_RETRY_DELAY, |_| {
+        atm.forward_and_send_message(
+            profile,
+            false,
+            packed_msg,
+            Some(message_id),
+            &mediator,
+            recipient,
+            None,
+            None,
+            false,
+        )
+    })
+    .await
+    .map(|_| ())
+}
+
+/// Calls `send` up to `attempts` times, doubling the delay after each failure.
+/// Returns the last error when every attempt fails.
+pub async fn retry_with_backoff<F, Fut, T, E>(
+    attempts: u32,
+    initial_delay: Duration,
+    mut send: F,
+) -> Result<T, E>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+    loop {
+        match send(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < attempts => {
+                warn!("Send attempt {attempt}/{attempts} failed, retrying: {err:?}");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_retry_delivers_after_transient_failure() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<&str, String> = retry_with_backoff(3, Duration::from_millis(1), |_| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    Err("mediator unavailable".to_string())
+                } else {
+                    Ok("delivered")
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("delivered"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), String> =
+            retry_with_backoff(3, Duration::from_millis(1), |attempt| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move { Err(format!("attempt {attempt} failed")) }
+            })
+            .await;
+
+        assert_eq!(result, Err("attempt 3 failed".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    fn message_with_return_route(return_route: Option<&str>) -> Message {
+        let mut builder = Message::build(
+            new_message_id(),
+            "https://example.com/test".to_string(),
+            serde_json::json!({}),
+        );
+        if let Some(return_route) = return_route {
+            builder = builder.header(
+                RETURN_ROUTE_HEADER.to_string(),
+                Value::String(return_route.to_string()),
+            );
+        }
+        builder.finalize()
+    }
+
+    #[test]
+    fn test_return_route_all_replies_on_return_route() {
+        let message = message_with_return_route(Some("all"));
+
+        assert_eq!(Route::for_message(&message, true), Route::ReturnRoute);
+        assert_eq!(Route::for_message(&message, false), Route::Forward);
+    }
+
+    #[test]
+    fn test_other_return_routes_are_forwarded() {
+        for return_route in [None, Some("none"), Some("thread")] {
+            let message = message_with_return_route(return_route);
+            assert_eq!(Route::for_message(&message, true), Route::Forward);
+        }
+    }
+
+    #[test]
+    fn test_return_route_only_reaches_the_mediator() {
+        let mediator = Some("did:example:mediator");
+
+        assert_eq!(
+            Route::ReturnRoute.towards("did:example:mediator", mediator),
+            Route::ReturnRoute
+        );
+        assert_eq!(
+            Route::ReturnRoute.towards("did:example:bob", mediator),
+            Route::Forward
+        );
+        assert_eq!(
+            Route::ReturnRoute.towards("did:example:bob", None),
+            Route::Forward
+        );
+        assert_eq!(
+            Route::Forward.towards("did:example:mediator", mediator),
+            Route::Forward
+        );
+    }
 
     #[test]
     fn test_build_response() {
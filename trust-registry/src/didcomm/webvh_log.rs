@@ -0,0 +1,99 @@
+use std::{fs, path::PathBuf, sync::RwLock, time::SystemTime};
+
+use serde_json::Value;
+
+/// Modification time and length identify a version of the log file.
+type FileVersion = (SystemTime, u64);
+
+/// Serves the current DID document of a did:webvh DID from its `did.jsonl` log.
+///
+/// The log is re-read whenever the file changes, so updates appended to the log are
+/// published without a restart.
+pub struct WebvhLog {
+    path: PathBuf,
+    cached: RwLock<Option<(FileVersion, Value)>>,
+}
+
+impl WebvhLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// The DID document of the last entry in the log.
+    pub fn current_document(&self) -> Result<Value, String> {
+        let metadata = fs::metadata(&self.path)
+            .map_err(|e| format!("Failed to read {}: {e}", self.path.display()))?;
+        let version = (
+            metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            metadata.len(),
+        );
+
+        if let Some((cached_version, document)) = self.cached.read().unwrap().as_ref()
+            && *cached_version == version
+        {
+            return Ok(document.clone());
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {e}", self.path.display()))?;
+        let document = latest_document(&contents)?;
+        *self.cached.write().unwrap() = Some((version, document.clone()));
+        Ok(document)
+    }
+}
+
+/// Extracts the `state` of the last log entry.
+pub fn latest_document(log: &str) -> Result<Value, String> {
+    let (index, last_entry) = log
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .last()
+        .ok_or("The DID log is empty")?;
+    let entry: Value = serde_json::from_str(last_entry)
+        .map_err(|e| format!("DID log line {} is not valid JSON: {e}", index + 1))?;
+    entry
+        .get("state")
+        .filter(|state| state.is_object())
+        .cloned()
+        .ok_or_else(|| format!("DID log line {} has no DID document state", index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(version: u32, service_endpoint: &str) -> String {
+        json!({
+            "versionId": format!("{version}-Qm"),
+            "versionTime": "2025-01-01T00:00:00Z",
+            "parameters": {},
+            "state": {
+                "id": "did:webvh:Qm:example.com",
+                "service": [{ "id": "#didcomm", "serviceEndpoint": service_endpoint }]
+            },
+            "proof": []
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_latest_document_uses_last_entry() {
+        let log = format!("{}\n{}\n", entry(1, "did:web:old"), entry(2, "did:web:new"));
+
+        let document = latest_document(&log).unwrap();
+
+        assert_eq!(document["service"][0]["serviceEndpoint"], "did:web:new");
+    }
+
+    #[test]
+    fn test_latest_document_rejects_entry_without_state() {
+        let error = latest_document(r#"{"versionId":"1-Qm"}"#).unwrap_err();
+
+        assert!(error.contains("line 1"));
+    }
+}
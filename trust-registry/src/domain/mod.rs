@@ -1,10 +1,125 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+/// A DID of the `did:<method>:<method-specific-id>` shape from DID Core.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Did(String);
+
+impl Did {
+    pub fn parse(did: &str) -> Result<Self, DidError> {
+        let rest = did.strip_prefix("did:").ok_or(DidError::MissingPrefix)?;
+        let (method, id) = rest
+            .split_once(':')
+            .ok_or(DidError::MissingMethodSpecificId)?;
+        if method.is_empty()
+            || !method
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        {
+            return Err(DidError::InvalidMethod);
+        }
+        if id.is_empty() || id.ends_with(':') {
+            return Err(DidError::MissingMethodSpecificId);
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '%'))
+        {
+            return Err(DidError::InvalidMethodSpecificId);
+        }
+        Ok(Self(did.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn method(&self) -> &str {
+        self.0["did:".len()..].split(':').next().unwrap_or_default()
+    }
+}
+
+impl TryFrom<&str> for Did {
+    type Error = DidError;
+
+    fn try_from(did: &str) -> Result<Self, Self::Error> {
+        Self::parse(did)
+    }
+}
+
+impl fmt::Display for Did {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Rewrites equivalent spellings of a DID to one form, so they key the same record: the
+/// scheme and method are lowercased and, for `did:web`, the host is lowercased, the
+/// default port `%3A443` is dropped, percent-encodings are uppercased and trailing `/` or
+/// `:` are removed. Anything that isn't a DID is returned unchanged.
+pub fn canonicalize_did(value: &str) -> String {
+    let Some((scheme, rest)) = value.split_once(':') else {
+        return value.to_string();
+    };
+    let Some((method, id)) = rest.split_once(':') else {
+        return value.to_string();
+    };
+    if !scheme.eq_ignore_ascii_case("did") {
+        return value.to_string();
+    }
+    let method = method.to_ascii_lowercase();
+    let id = match method.as_str() {
+        "web" => canonicalize_web_id(id),
+        _ => id.to_string(),
+    };
+    format!("did:{method}:{id}")
+}
+
+fn canonicalize_web_id(id: &str) -> String {
+    let id = uppercase_percent_encodings(id.trim_end_matches(['/', ':']));
+    let (authority, path) = id.split_once(':').unwrap_or((&id, ""));
+    let (host, port) = authority.split_once("%3A").unwrap_or((authority, ""));
+    let mut canonical = host.to_ascii_lowercase();
+    if !port.is_empty() && port != "443" {
+        canonical.push_str("%3A");
+        canonical.push_str(port);
+    }
+    if !path.is_empty() {
+        canonical.push(':');
+        canonical.push_str(path);
+    }
+    canonical
+}
+
+fn uppercase_percent_encodings(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('%') {
+        out.push_str(&rest[..i]);
+        let encoded = rest.get(i..i + 3).unwrap_or(&rest[i..]);
+        if encoded.len() == 3 && encoded[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+            out.push_str(&encoded.to_ascii_uppercase());
+        } else {
+            out.push_str(encoded);
+        }
+        rest = &rest[i + encoded.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 pub struct EntityId(String);
 
+impl From<Did> for EntityId {
+    fn from(did: Did) -> Self {
+        Self(did.0)
+    }
+}
+
 impl EntityId {
     pub fn new(id: impl Into<String>) -> Self {
         Self(id.into())
@@ -21,9 +136,15 @@ impl fmt::Display for EntityId {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 pub struct AuthorityId(String);
 
+impl From<Did> for AuthorityId {
+    fn from(did: Did) -> Self {
+        Self(did.0)
+    }
+}
+
 impl AuthorityId {
     pub fn new(id: impl Into<String>) -> Self {
         Self(id.into())
@@ -40,7 +161,7 @@ impl fmt::Display for AuthorityId {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 pub struct Action(String);
 
 impl Action {
@@ -58,7 +179,7 @@ impl fmt::Display for Action {
         write!(f, "{}", self.0)
     }
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 pub struct Resource(String);
 
 impl Resource {
@@ -77,6 +198,29 @@ impl fmt::Display for Resource {
     }
 }
 
+/// How a context is merged into another, see `CONTEXT_MERGE_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextMergeMode {
+    /// Objects are merged key by key, any other value is overridden.
+    #[default]
+    Deep,
+    /// Like `Deep`, but `@context` entries are combined so the result keeps the terms
+    /// of both JSON-LD documents.
+    JsonLd,
+}
+
+impl std::str::FromStr for ContextMergeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deep" => Ok(Self::Deep),
+            "json-ld" | "jsonld" => Ok(Self::JsonLd),
+            _ => Err(format!("Invalid context merge mode: {s}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Context(serde_json::Value);
 
@@ -94,7 +238,30 @@ impl Context {
     }
 
     pub fn merge(self, additional: Context) -> Self {
-        Self(merge_json_values(self.0, additional.0))
+        self.merge_with(additional, ContextMergeMode::Deep)
+    }
+
+    pub fn merge_with(self, additional: Context, mode: ContextMergeMode) -> Self {
+        Self(merge_json_values(self.0, additional.0, mode))
+    }
+
+    /// Keeps only the fields addressed by the given JSON pointers (RFC 6901), with their
+    /// ancestors. Pointers that match nothing are ignored.
+    pub fn project(&self, pointers: &[String]) -> Self {
+        let paths: Vec<Vec<String>> = pointers.iter().map(|p| pointer_segments(p)).collect();
+        Self(project_json_value(&self.0, &paths).unwrap_or_else(|| json!({})))
+    }
+
+    /// Nesting depth of the context; scalars count as 0 and `{}` as 1.
+    pub fn depth(&self) -> usize {
+        fn depth(value: &serde_json::Value) -> usize {
+            match value {
+                serde_json::Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+                serde_json::Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+                _ => 0,
+            }
+        }
+        depth(&self.0)
     }
 }
 
@@ -104,6 +271,7 @@ impl Default for Context {
     }
 }
 
+/// Identifiers of a trust record as sent in TRQP requests, see [`TrustRecordQuery`](crate::storage::repository::TrustRecordQuery).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrustRecordIds {
     entity_id: EntityId,
@@ -113,6 +281,55 @@ pub struct TrustRecordIds {
 }
 
 impl TrustRecordIds {
+    pub fn new(
+        entity_id: EntityId,
+        authority_id: AuthorityId,
+        action: Action,
+        resource: Resource,
+    ) -> Self {
+        Self {
+            entity_id,
+            authority_id,
+            action,
+            resource,
+        }
+    }
+
+    /// URL-safe key of the record: each identifier is unpadded base64url encoded
+    /// and the four are joined with `.`, which the base64url alphabet never uses.
+    pub fn encode_key(&self) -> String {
+        [
+            self.entity_id.as_str(),
+            self.authority_id.as_str(),
+            self.action.as_str(),
+            self.resource.as_str(),
+        ]
+        .map(|part| URL_SAFE_NO_PAD.encode(part))
+        .join(".")
+    }
+
+    /// Inverse of [`TrustRecordIds::encode_key`].
+    pub fn decode_key(key: &str) -> Result<Self, RecordKeyError> {
+        let parts = key
+            .split('.')
+            .map(|part| {
+                let bytes = URL_SAFE_NO_PAD
+                    .decode(part)
+                    .map_err(|_| RecordKeyError::InvalidEncoding)?;
+                String::from_utf8(bytes).map_err(|_| RecordKeyError::InvalidEncoding)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let [entity_id, authority_id, action, resource] =
+            <[String; 4]>::try_from(parts).map_err(|_| RecordKeyError::WrongPartCount)?;
+
+        Ok(Self::new(
+            EntityId::new(entity_id),
+            AuthorityId::new(authority_id),
+            Action::new(action),
+            Resource::new(resource),
+        ))
+    }
+
     pub fn entity_id(&self) -> &EntityId {
         &self.entity_id
     }
@@ -141,6 +358,8 @@ impl TrustRecordIds {
     }
 }
 
+/// Kind of a trust record. Serialized as `assertion` for authorization records;
+/// this is a label only and not part of the record key.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RecordType {
     Authorization,
@@ -180,6 +399,47 @@ pub struct TrustRecord {
     authorized: Option<bool>,
     context: Context,
     record_type: RecordType,
+    /// The record stops answering TRQP queries after this instant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+    /// Free-form labels grouping records, e.g. `pilot`, independent of the record key.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Content of the DIDComm attachments the record was last written with. Only their
+    /// metadata goes into the context, so the content never reaches TRQP answers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Value>,
+    /// Incremented on every admin change to the record.
+    #[serde(default = "initial_version")]
+    version: u64,
+    /// Set by the admin handlers; `None` on records written before timestamps existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated_at: Option<DateTime<Utc>>,
+    /// Shape the record was stored in, see [`CURRENT_SCHEMA_VERSION`].
+    #[serde(default = "legacy_schema_version")]
+    schema_version: u32,
+}
+
+pub const INITIAL_VERSION: u64 = 1;
+
+/// Version of the stored record shape, written with every record.
+///
+/// - `1`: records stored before `schema_version` existed. Lifecycle fields
+///   (`expires_at`, `version`, `created_at`, `updated_at`) may be missing.
+/// - `2`: the current shape.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Assumed for stored records without a `schema_version`.
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+fn initial_version() -> u64 {
+    INITIAL_VERSION
+}
+
+fn legacy_schema_version() -> u32 {
+    LEGACY_SCHEMA_VERSION
 }
 
 impl TrustRecord {
@@ -203,6 +463,13 @@ impl TrustRecord {
             authorized: Some(authorized),
             context,
             record_type,
+            expires_at: None,
+            tags: Vec::new(),
+            attachments: Vec::new(),
+            version: INITIAL_VERSION,
+            created_at: None,
+            updated_at: None,
+            schema_version: CURRENT_SCHEMA_VERSION,
         }
     }
 
@@ -238,11 +505,161 @@ impl TrustRecord {
         self.authorized.unwrap_or_default()
     }
 
+    pub fn expires_at(&self) -> Option<&DateTime<Utc>> {
+        self.expires_at.as_ref()
+    }
+
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn attachments(&self) -> &[Value] {
+        &self.attachments
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn with_expires_at(mut self, expires_at: Option<DateTime<Utc>>) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_attachments(mut self, attachments: Vec<Value>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Drops the tags and attachment content, which are for admins only, before the record
+    /// is served to verifiers.
+    pub fn without_admin_fields(self) -> Self {
+        self.with_tags(Vec::new()).with_attachments(Vec::new())
+    }
+
+    pub fn with_entity_id(mut self, entity_id: EntityId) -> Self {
+        self.entity_id = entity_id;
+        self
+    }
+
+    pub fn with_authority_id(mut self, authority_id: AuthorityId) -> Self {
+        self.authority_id = authority_id;
+        self
+    }
+
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn created_at(&self) -> Option<&DateTime<Utc>> {
+        self.created_at.as_ref()
+    }
+
+    pub fn updated_at(&self) -> Option<&DateTime<Utc>> {
+        self.updated_at.as_ref()
+    }
+
+    pub fn with_created_at(mut self, created_at: Option<DateTime<Utc>>) -> Self {
+        self.created_at = created_at;
+        self
+    }
+
+    pub fn with_updated_at(mut self, updated_at: Option<DateTime<Utc>>) -> Self {
+        self.updated_at = updated_at;
+        self
+    }
+
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    pub fn with_schema_version(mut self, schema_version: u32) -> Self {
+        self.schema_version = schema_version;
+        self
+    }
+
+    /// Whether both records state the same thing, ignoring the key and the bookkeeping
+    /// fields (version, timestamps and schema version).
+    pub fn same_content(&self, other: &TrustRecord) -> bool {
+        self.is_recognized() == other.is_recognized()
+            && self.is_authorized() == other.is_authorized()
+            && self.context == other.context
+            && self.record_type == other.record_type
+            && self.expires_at == other.expires_at
+            && self.tags == other.tags
+    }
+
+    /// Upgrades a record read in an older shape to [`CURRENT_SCHEMA_VERSION`], so it is
+    /// written back in the current shape. Records from a newer shape are left as they are.
+    pub fn migrate(mut self) -> Self {
+        if self.schema_version < 2 {
+            // a record never updated since creation was last changed when it was created
+            self.updated_at = self.updated_at.or(self.created_at);
+            self.version = self.version.max(INITIAL_VERSION);
+        }
+        self.schema_version = self.schema_version.max(CURRENT_SCHEMA_VERSION);
+        self
+    }
+
     /// Merges additional_context into the given one.
     /// additional_context will OVERRIDE the existing one
-    pub fn merge_contexts(mut self, additional_context: Context) -> Self {
+    pub fn merge_contexts(self, additional_context: Context) -> Self {
+        self.merge_contexts_with(additional_context, ContextMergeMode::Deep)
+    }
+
+    pub fn merge_contexts_with(
+        mut self,
+        additional_context: Context,
+        mode: ContextMergeMode,
+    ) -> Self {
         let base_context = std::mem::take(&mut self.context);
-        self.context = base_context.merge(additional_context);
+        self.context = base_context.merge_with(additional_context, mode);
+        self
+    }
+
+    /// Merges the record's own context over `defaults`, so the record wins on conflicts.
+    /// A record without a context gets the defaults; non-object contexts are kept.
+    pub fn with_context_defaults(mut self, defaults: &Context, mode: ContextMergeMode) -> Self {
+        let own = std::mem::take(&mut self.context);
+        self.context = match own.as_value() {
+            Value::Object(_) => defaults.clone().merge_with(own, mode),
+            Value::Null => defaults.clone(),
+            _ => own,
+        };
+        self
+    }
+
+    pub fn project_context(mut self, pointers: &[String]) -> Self {
+        self.context = self.context.project(pointers);
+        self
+    }
+
+    pub fn with_authorized(mut self, authorized: bool) -> Self {
+        self.authorized = Some(authorized);
+        self
+    }
+
+    pub fn with_recognized(mut self, recognized: bool) -> Self {
+        self.recognized = Some(recognized);
         self
     }
 
@@ -257,12 +674,79 @@ impl TrustRecord {
     }
 }
 
-fn merge_json_values(base: Value, additional: Value) -> Value {
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn project_json_value(value: &Value, paths: &[Vec<String>]) -> Option<Value> {
+    if paths.iter().any(Vec::is_empty) {
+        return Some(value.clone());
+    }
+    let children = |key: &str| -> Vec<Vec<String>> {
+        paths
+            .iter()
+            .filter(|path| path[0] == key)
+            .map(|path| path[1..].to_vec())
+            .collect()
+    };
+    match value {
+        Value::Object(map) => Some(Value::Object(
+            map.iter()
+                .filter_map(|(key, child)| {
+                    let child_paths = children(key);
+                    if child_paths.is_empty() {
+                        return None;
+                    }
+                    project_json_value(child, &child_paths).map(|v| (key.clone(), v))
+                })
+                .collect(),
+        )),
+        Value::Array(items) => Some(Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .filter_map(|(index, child)| {
+                    let child_paths = children(&index.to_string());
+                    if child_paths.is_empty() {
+                        return None;
+                    }
+                    project_json_value(child, &child_paths)
+                })
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+/// Object nesting depth past which [`merge_json_values`] stops merging and takes the
+/// additional value as-is, so a hostile context cannot overflow the stack. Matches the
+/// nesting limit `serde_json` applies when parsing.
+const MAX_MERGE_DEPTH: usize = 128;
+
+fn merge_json_values(base: Value, additional: Value, mode: ContextMergeMode) -> Value {
+    merge_json_values_at(base, additional, mode, 0)
+}
+
+fn merge_json_values_at(
+    base: Value,
+    additional: Value,
+    mode: ContextMergeMode,
+    depth: usize,
+) -> Value {
     match (base, additional) {
-        (Value::Object(mut base_map), Value::Object(additional_map)) => {
+        (Value::Object(mut base_map), Value::Object(additional_map)) if depth < MAX_MERGE_DEPTH => {
             for (key, additional_value) in additional_map {
                 let merged_value = match base_map.remove(&key) {
-                    Some(base_value) => merge_json_values(base_value, additional_value),
+                    Some(base_value) if mode == ContextMergeMode::JsonLd && key == "@context" => {
+                        merge_json_ld_contexts(base_value, additional_value)
+                    }
+                    Some(base_value) => {
+                        merge_json_values_at(base_value, additional_value, mode, depth + 1)
+                    }
                     None => additional_value,
                 };
                 base_map.insert(key, merged_value);
@@ -273,6 +757,38 @@ fn merge_json_values(base: Value, additional: Value) -> Value {
     }
 }
 
+/// Combines two JSON-LD `@context` values into one defining the terms of both.
+///
+/// Context references (IRIs) of both are kept once each, in order. Embedded term
+/// definitions are combined into one object placed last, so it is processed after the
+/// referenced contexts; a term defined by both takes the additional definition.
+fn merge_json_ld_contexts(base: Value, additional: Value) -> Value {
+    fn entries(value: Value) -> Vec<Value> {
+        match value {
+            Value::Array(items) => items,
+            Value::Null => vec![],
+            value => vec![value],
+        }
+    }
+
+    let mut merged: Vec<Value> = vec![];
+    let mut terms = serde_json::Map::new();
+    for entry in entries(base).into_iter().chain(entries(additional)) {
+        match entry {
+            Value::Object(definitions) => terms.extend(definitions),
+            entry if !merged.contains(&entry) => merged.push(entry),
+            _ => {}
+        }
+    }
+    if !terms.is_empty() {
+        merged.push(Value::Object(terms));
+    }
+    match merged.len() {
+        1 => merged.remove(0),
+        _ => Value::Array(merged),
+    }
+}
+
 pub struct TrustRecordBuilder {
     entity_id: Option<EntityId>,
     authority_id: Option<AuthorityId>,
@@ -282,6 +798,8 @@ pub struct TrustRecordBuilder {
     context: Context,
     authorized: Option<bool>,
     record_type: Option<RecordType>,
+    expires_at: Option<DateTime<Utc>>,
+    tags: Vec<String>,
 }
 
 impl TrustRecordBuilder {
@@ -295,6 +813,8 @@ impl TrustRecordBuilder {
             context: Context::empty(),
             authorized: None,
             record_type: None,
+            expires_at: None,
+            tags: Vec::new(),
         }
     }
 
@@ -337,21 +857,67 @@ impl TrustRecordBuilder {
         self
     }
 
-    pub fn build(self) -> Result<TrustRecord, TrustRecordError> {
-        Ok(TrustRecord {
-            entity_id: self.entity_id.ok_or(TrustRecordError::MissingEntityId)?,
-            authority_id: self
-                .authority_id
-                .ok_or(TrustRecordError::MissingAuthorityId)?,
-            action: self.action.ok_or(TrustRecordError::MissingAction)?,
-            authorized: self.authorized,
-            recognized: self.recognized,
-            context: self.context,
-            resource: self.resource.ok_or(TrustRecordError::MissingResource)?,
-            record_type: self
-                .record_type
-                .ok_or(TrustRecordError::MissingRecordType)?,
-        })
+    pub fn expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Reports every missing field at once instead of stopping at the first.
+    pub fn build(self) -> Result<TrustRecord, TrustRecordErrors> {
+        let mut errors = Vec::new();
+        if self.entity_id.is_none() {
+            errors.push(TrustRecordError::MissingEntityId);
+        }
+        if self.authority_id.is_none() {
+            errors.push(TrustRecordError::MissingAuthorityId);
+        }
+        if self.action.is_none() {
+            errors.push(TrustRecordError::MissingAction);
+        }
+        if self.resource.is_none() {
+            errors.push(TrustRecordError::MissingResource);
+        }
+        if self.record_type.is_none() {
+            errors.push(TrustRecordError::MissingRecordType);
+        }
+
+        match (
+            self.entity_id,
+            self.authority_id,
+            self.action,
+            self.resource,
+            self.record_type,
+        ) {
+            (
+                Some(entity_id),
+                Some(authority_id),
+                Some(action),
+                Some(resource),
+                Some(record_type),
+            ) => Ok(TrustRecord {
+                entity_id,
+                authority_id,
+                action,
+                authorized: self.authorized,
+                recognized: self.recognized,
+                context: self.context,
+                resource,
+                record_type,
+                expires_at: self.expires_at,
+                tags: self.tags,
+                attachments: Vec::new(),
+                version: INITIAL_VERSION,
+                created_at: None,
+                updated_at: None,
+                schema_version: CURRENT_SCHEMA_VERSION,
+            }),
+            _ => Err(TrustRecordErrors(errors)),
+        }
     }
 }
 
@@ -390,9 +956,157 @@ impl fmt::Display for TrustRecordError {
 
 impl std::error::Error for TrustRecordError {}
 
+/// Every problem found while building a [`TrustRecord`], never empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustRecordErrors(Vec<TrustRecordError>);
+
+impl TrustRecordErrors {
+    pub fn new(errors: Vec<TrustRecordError>) -> Self {
+        Self(errors)
+    }
+
+    pub fn errors(&self) -> &[TrustRecordError] {
+        &self.0
+    }
+
+    pub fn into_errors(self) -> Vec<TrustRecordError> {
+        self.0
+    }
+}
+
+impl fmt::Display for TrustRecordErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for TrustRecordErrors {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordKeyError {
+    WrongPartCount,
+    InvalidEncoding,
+}
+
+impl fmt::Display for RecordKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongPartCount => write!(f, "Record key must have four `.` separated parts"),
+            Self::InvalidEncoding => write!(f, "Record key part is not base64url encoded UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for RecordKeyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DidError {
+    MissingPrefix,
+    InvalidMethod,
+    MissingMethodSpecificId,
+    InvalidMethodSpecificId,
+}
+
+impl fmt::Display for DidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrefix => write!(f, "DID must start with `did:`"),
+            Self::InvalidMethod => write!(f, "DID method must be lowercase letters and digits"),
+            Self::MissingMethodSpecificId => write!(f, "DID method-specific id is missing"),
+            Self::InvalidMethodSpecificId => {
+                write!(f, "DID method-specific id contains invalid characters")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DidError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_canonicalize_did_web_equivalents() {
+        let canonical = "did:web:example.com:users:alice";
+        for equivalent in [
+            "did:web:example.com:users:alice",
+            "did:web:Example.COM:users:alice",
+            "DID:WEB:example.com:users:alice",
+            "did:web:example.com%3A443:users:alice",
+            "did:web:example.com%3a443:users:alice",
+            "did:web:example.com:users:alice/",
+            "did:web:example.com:users:alice:",
+        ] {
+            assert_eq!(canonicalize_did(equivalent), canonical, "{equivalent}");
+        }
+
+        assert_eq!(
+            canonicalize_did("did:web:LocalHost%3a3232/"),
+            "did:web:localhost%3A3232"
+        );
+        assert_eq!(
+            canonicalize_did("did:web:example.com/"),
+            "did:web:example.com"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_did_keeps_distinct_dids_apart() {
+        // path segments and non-default ports identify different DIDs
+        assert_eq!(
+            canonicalize_did("did:web:example.com:Users:Alice"),
+            "did:web:example.com:Users:Alice"
+        );
+        assert_ne!(
+            canonicalize_did("did:web:example.com%3A8443"),
+            canonicalize_did("did:web:example.com")
+        );
+        // other methods only get their method lowercased
+        assert_eq!(canonicalize_did("did:KEY:z6MkAbC/"), "did:key:z6MkAbC/");
+        assert_eq!(canonicalize_did("not-a-did"), "not-a-did");
+        assert_eq!(
+            canonicalize_did("urn:web:Example.com"),
+            "urn:web:Example.com"
+        );
+    }
+
+    #[test]
+    fn test_did_parse_accepts_valid_dids() {
+        let did = Did::parse("did:web:localhost%3A3232:users:alice").unwrap();
+
+        assert_eq!(did.as_str(), "did:web:localhost%3A3232:users:alice");
+        assert_eq!(did.method(), "web");
+        assert_eq!(
+            Did::try_from("did:peer:2.Ez6LSb").unwrap().to_string(),
+            "did:peer:2.Ez6LSb"
+        );
+        assert_eq!(
+            EntityId::from(Did::parse("did:example:123").unwrap()).as_str(),
+            "did:example:123"
+        );
+    }
+
+    #[test]
+    fn test_did_parse_rejects_invalid_dids() {
+        assert_eq!(Did::parse("did;example:x"), Err(DidError::MissingPrefix));
+        assert_eq!(Did::parse("example:123"), Err(DidError::MissingPrefix));
+        assert_eq!(
+            Did::parse("did:example"),
+            Err(DidError::MissingMethodSpecificId)
+        );
+        assert_eq!(
+            Did::parse("did:example:"),
+            Err(DidError::MissingMethodSpecificId)
+        );
+        assert_eq!(Did::parse("did:Example:123"), Err(DidError::InvalidMethod));
+        assert_eq!(
+            Did::parse("did:example:a b"),
+            Err(DidError::InvalidMethodSpecificId)
+        );
+    }
 
     #[test]
     fn test_trust_record_creation() {
@@ -411,6 +1125,41 @@ mod tests {
         assert_eq!(record.record_type().to_string(), "assertion");
     }
 
+    #[test]
+    fn test_record_key_round_trips_dids() {
+        let ids = TrustRecordIds::new(
+            EntityId::new("did:web:issuer.example.com:users:alice"),
+            AuthorityId::new("did:peer:2.Vz6Mk.Ez6LS.SeyJ0Ijoi"),
+            Action::new("issue/credential"),
+            Resource::new("Résumé+Badge?v=1"),
+        );
+
+        let key = ids.encode_key();
+
+        assert!(
+            key.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)),
+            "key is not URL-safe: {key}"
+        );
+        let decoded = TrustRecordIds::decode_key(&key).unwrap();
+        assert_eq!(decoded.entity_id(), ids.entity_id());
+        assert_eq!(decoded.authority_id(), ids.authority_id());
+        assert_eq!(decoded.action(), ids.action());
+        assert_eq!(decoded.resource(), ids.resource());
+    }
+
+    #[test]
+    fn test_decode_record_key_rejects_malformed_keys() {
+        assert_eq!(
+            TrustRecordIds::decode_key("YQ.Yg.Yw").unwrap_err(),
+            RecordKeyError::WrongPartCount
+        );
+        assert_eq!(
+            TrustRecordIds::decode_key("YQ.Yg.Yw.!!").unwrap_err(),
+            RecordKeyError::InvalidEncoding
+        );
+    }
+
     #[test]
     fn test_builder_missing_fields() {
         let result = TrustRecordBuilder::new()
@@ -420,6 +1169,66 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_builder_reports_every_missing_field() {
+        let errors = TrustRecordBuilder::new()
+            .entity_id(EntityId::new("entity-123"))
+            .authority_id(AuthorityId::new("authority-456"))
+            .build()
+            .unwrap_err();
+
+        assert_eq!(
+            errors.errors(),
+            [
+                TrustRecordError::MissingAction,
+                TrustRecordError::MissingResource,
+                TrustRecordError::MissingRecordType,
+            ]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "Action is required; Resource is required; Record type is required"
+        );
+    }
+
+    #[test]
+    fn test_context_project_keeps_allowed_paths() {
+        let context = Context::new(json!({
+            "public": {"name": "Framework", "internal": "secret"},
+            "governance": {"owner": "ops"},
+            "a/b": 1,
+            "items": [{"id": 1, "note": "x"}, {"id": 2}]
+        }));
+        let pointers = ["/public/name", "/a~1b", "/items/0/id", "/missing/field"].map(String::from);
+
+        assert_eq!(
+            context.project(&pointers).as_value(),
+            &json!({
+                "public": {"name": "Framework"},
+                "a/b": 1,
+                "items": [{"id": 1}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_context_project_whole_subtree() {
+        let context = Context::new(json!({"public": {"a": 1, "b": 2}, "private": true}));
+
+        assert_eq!(
+            context.project(&["/public".to_string()]).as_value(),
+            &json!({"public": {"a": 1, "b": 2}})
+        );
+        assert_eq!(context.project(&["".to_string()]), context);
+    }
+
+    #[test]
+    fn test_context_depth() {
+        assert_eq!(Context::new(json!("scalar")).depth(), 0);
+        assert_eq!(Context::empty().depth(), 1);
+        assert_eq!(Context::new(json!({"a": {"b": [1, {"c": 2}]}})).depth(), 4);
+    }
+
     #[test]
     fn test_context_merge_overrides() {
         let base = Context::new(json!({
@@ -510,7 +1319,7 @@ mod tests {
             }
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(
             result,
@@ -533,7 +1342,7 @@ mod tests {
             "key": "value"
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         // When base is not an object, additional should completely replace it
         assert_eq!(result, json!({"key": "value"}));
@@ -546,7 +1355,7 @@ mod tests {
         });
         let additional = json!("replacement_string");
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         // When additional is not an object, it should completely replace base
         assert_eq!(result, json!("replacement_string"));
@@ -559,7 +1368,7 @@ mod tests {
             "new_key": "new_value"
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(result, json!({"new_key": "new_value"}));
     }
@@ -571,7 +1380,7 @@ mod tests {
         });
         let additional = json!({});
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(result, json!({"existing": "value"}));
     }
@@ -586,7 +1395,7 @@ mod tests {
             "array_field": [4, 5]
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(
             result,
@@ -620,7 +1429,7 @@ mod tests {
             }
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(
             result,
@@ -638,6 +1447,85 @@ mod tests {
         );
     }
 
+    fn nested(depth: usize, leaf: Value) -> Value {
+        (0..depth).fold(leaf, |inner, _| json!({ "n": inner }))
+    }
+
+    #[test]
+    fn test_merge_json_values_stops_at_depth_limit() {
+        let base = nested(MAX_MERGE_DEPTH + 2, json!({"keep": true}));
+        let additional = nested(MAX_MERGE_DEPTH + 2, json!({"added": true}));
+
+        let result = merge_json_values(base, additional.clone(), ContextMergeMode::Deep);
+
+        assert_eq!(result, additional);
+
+        let base = nested(MAX_MERGE_DEPTH - 1, json!({"keep": true}));
+        let additional = nested(MAX_MERGE_DEPTH - 1, json!({"added": true}));
+
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
+
+        assert_eq!(
+            result,
+            nested(MAX_MERGE_DEPTH - 1, json!({"keep": true, "added": true}))
+        );
+    }
+
+    fn arb_json() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            "[a-z]{0,6}".prop_map(Value::from),
+        ];
+        leaf.prop_recursive(6, 64, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::btree_map("[a-e]", inner, 0..6)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_merge_json_values_with_itself_is_idempotent(value in arb_json()) {
+            prop_assert_eq!(merge_json_values(value.clone(), value.clone(), ContextMergeMode::Deep), value);
+        }
+
+        #[test]
+        fn prop_merge_json_values_additional_keys_win(base in arb_json(), additional in arb_json()) {
+            let result = merge_json_values(base.clone(), additional.clone(), ContextMergeMode::Deep);
+            match (&base, &additional) {
+                (Value::Object(base_map), Value::Object(additional_map)) => {
+                    let result_map = result.as_object().unwrap();
+                    for (key, additional_value) in additional_map {
+                        let merged = &result_map[key];
+                        if additional_value.is_object() && base_map.get(key).is_some_and(Value::is_object) {
+                            prop_assert!(merged.is_object());
+                        } else {
+                            prop_assert_eq!(merged, additional_value);
+                        }
+                    }
+                    for (key, base_value) in base_map {
+                        if !additional_map.contains_key(key) {
+                            prop_assert_eq!(&result_map[key], base_value);
+                        }
+                    }
+                }
+                _ => prop_assert_eq!(result, additional),
+            }
+        }
+
+        #[test]
+        fn prop_merge_json_values_non_object_additional_replaces(
+            base in arb_json(),
+            additional in arb_json().prop_filter("non-object", |value| !value.is_object()),
+        ) {
+            prop_assert_eq!(merge_json_values(base, additional.clone(), ContextMergeMode::Deep), additional);
+        }
+    }
+
     #[test]
     fn test_merge_json_values_different_types_at_same_key() {
         let base = json!({
@@ -649,7 +1537,7 @@ mod tests {
             }
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         // Different types should result in complete replacement
         assert_eq!(
@@ -673,7 +1561,7 @@ mod tests {
             "new": null
         });
 
-        let result = merge_json_values(base, additional);
+        let result = merge_json_values(base, additional, ContextMergeMode::Deep);
 
         assert_eq!(
             result,
@@ -705,4 +1593,135 @@ mod tests {
         assert_eq!(RecordType::Authorization.to_string(), "assertion");
         assert_eq!(RecordType::Recognition.to_string(), "recognition");
     }
+
+    #[test]
+    fn test_migrate_upgrades_legacy_records() {
+        let created_at = Utc::now();
+        let record = TrustRecordBuilder::new()
+            .entity_id(EntityId::new("entity-123"))
+            .authority_id(AuthorityId::new("authority-456"))
+            .action(Action::new("action-789"))
+            .resource(Resource::new("resource-112"))
+            .record_type(RecordType::Authorization)
+            .build()
+            .unwrap()
+            .with_created_at(Some(created_at))
+            .with_version(0)
+            .with_schema_version(LEGACY_SCHEMA_VERSION);
+
+        let migrated = record.migrate();
+
+        assert_eq!(migrated.schema_version(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(migrated.version(), INITIAL_VERSION);
+        assert_eq!(migrated.updated_at(), Some(&created_at));
+        let newer = migrated.with_schema_version(CURRENT_SCHEMA_VERSION + 1);
+        assert_eq!(newer.migrate().schema_version(), CURRENT_SCHEMA_VERSION + 1);
+    }
+
+    #[test]
+    fn test_context_defaults_are_merged_under_record_context() {
+        let defaults = Context::new(json!({"framework": {"name": "Gov", "version": "1"}}));
+        let record = TrustRecordBuilder::new()
+            .entity_id(EntityId::new("entity-123"))
+            .authority_id(AuthorityId::new("authority-456"))
+            .action(Action::new("action-789"))
+            .resource(Resource::new("resource-112"))
+            .record_type(RecordType::Authorization)
+            .build()
+            .unwrap();
+
+        let plain = record
+            .clone()
+            .with_context_defaults(&defaults, ContextMergeMode::Deep);
+        assert_eq!(plain.context(), &defaults);
+
+        let own = record
+            .merge_contexts(Context::new(json!({"framework": {"version": "2"}, "x": 1})))
+            .with_context_defaults(&defaults, ContextMergeMode::Deep);
+        assert_eq!(
+            own.context().as_value(),
+            &json!({"framework": {"name": "Gov", "version": "2"}, "x": 1})
+        );
+    }
+
+    #[test]
+    fn test_json_ld_merge_keeps_context_terms_of_both() {
+        let base = Context::new(json!({
+            "@context": [
+                "https://www.w3.org/ns/credentials/v2",
+                { "Accreditation": "https://example.com/vocab#Accreditation" }
+            ],
+            "type": "Accreditation",
+            "level": 1
+        }));
+        let additional = Context::new(json!({
+            "@context": {
+                "level": "https://example.com/vocab#level",
+                "Accreditation": "https://example.org/vocab#Accreditation"
+            },
+            "level": 2
+        }));
+
+        let merged = base.merge_with(additional, ContextMergeMode::JsonLd);
+
+        assert_eq!(
+            merged.as_value(),
+            &json!({
+                "@context": [
+                    "https://www.w3.org/ns/credentials/v2",
+                    {
+                        "Accreditation": "https://example.org/vocab#Accreditation",
+                        "level": "https://example.com/vocab#level"
+                    }
+                ],
+                "type": "Accreditation",
+                "level": 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_ld_merge_unions_context_references() {
+        let base = Context::new(json!({
+            "@context": "https://www.w3.org/ns/credentials/v2",
+            "nested": { "@context": { "a": "https://example.com/a" } }
+        }));
+        let additional = Context::new(json!({
+            "@context": [
+                "https://www.w3.org/ns/credentials/v2",
+                "https://example.com/contexts/registry.jsonld"
+            ],
+            "nested": { "@context": { "b": "https://example.com/b" } }
+        }));
+
+        let json_ld = base
+            .clone()
+            .merge_with(additional.clone(), ContextMergeMode::JsonLd);
+        let deep = base.merge(additional.clone());
+
+        assert_eq!(
+            json_ld.as_value(),
+            &json!({
+                "@context": [
+                    "https://www.w3.org/ns/credentials/v2",
+                    "https://example.com/contexts/registry.jsonld"
+                ],
+                "nested": {
+                    "@context": {
+                        "a": "https://example.com/a",
+                        "b": "https://example.com/b"
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            deep.as_value()["@context"],
+            additional.as_value()["@context"]
+        );
+        assert_eq!(
+            "json-ld".parse::<ContextMergeMode>(),
+            Ok(ContextMergeMode::JsonLd)
+        );
+        assert!("merge".parse::<ContextMergeMode>().is_err());
+    }
 }
@@ -1,12 +1,16 @@
 use anyhow::Error;
 use axum::{
     Json,
+    extract::rejection::JsonRejection,
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
 use tracing::{error, warn};
 
+use super::trace_context::current_trace_id;
+use crate::storage::repository::RepositoryError;
+
 const LAST_WARNING_ERROR_CODE: u16 = 499;
 
 pub enum AppError {
@@ -14,14 +18,70 @@ pub enum AppError {
         internal_error: Error,
         details: Option<Value>,
     },
+    Unauthorized {
+        internal_error: Error,
+        details: Option<Value>,
+    },
     NotFound {
         internal_error: Error,
         details: Option<Value>,
     },
+    PayloadTooLarge {
+        internal_error: Error,
+        details: Option<Value>,
+    },
+    UnsupportedMediaType {
+        internal_error: Error,
+        details: Option<Value>,
+    },
+    Conflict {
+        internal_error: Error,
+        details: Option<Value>,
+    },
     Internal {
         internal_error: Error,
         details: Option<Value>,
     },
+    GatewayTimeout {
+        internal_error: Error,
+        details: Option<Value>,
+    },
+}
+
+/// Backend timeouts answer `504`, other storage failures `500`.
+impl From<RepositoryError> for AppError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::Timeout(_) => AppError::GatewayTimeout {
+                details: Some(json!([{ "issue": e.to_string() }])),
+                internal_error: e.into(),
+            },
+            e => AppError::Internal {
+                internal_error: e.into(),
+                details: None,
+            },
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        let details = Some(json!([{ "issue": rejection.body_text() }]));
+        match rejection {
+            JsonRejection::MissingJsonContentType(_) => AppError::UnsupportedMediaType {
+                details,
+                internal_error: rejection.into(),
+            },
+            _ if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge {
+                details,
+                internal_error: rejection.into(),
+            },
+            _ => AppError::BadRequest {
+                details,
+                internal_error: rejection.into(),
+            },
+        }
+    }
 }
 
 impl AppError {
@@ -37,6 +97,16 @@ impl AppError {
                 details,
                 internal_error,
             ),
+            AppError::Unauthorized {
+                internal_error,
+                details,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "The request lacks valid credentials",
+                details,
+                internal_error,
+            ),
             AppError::NotFound {
                 internal_error,
                 details,
@@ -47,6 +117,36 @@ impl AppError {
                 details,
                 internal_error,
             ),
+            AppError::PayloadTooLarge {
+                internal_error,
+                details,
+            } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "The request body exceeds the maximum allowed size",
+                details,
+                internal_error,
+            ),
+            AppError::UnsupportedMediaType {
+                internal_error,
+                details,
+            } => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "The request body must be sent as application/json",
+                details,
+                internal_error,
+            ),
+            AppError::Conflict {
+                internal_error,
+                details,
+            } => (
+                StatusCode::CONFLICT,
+                "conflict",
+                "The request conflicts with the current state of the resource",
+                details,
+                internal_error,
+            ),
             AppError::Internal {
                 internal_error,
                 details,
@@ -57,10 +157,53 @@ impl AppError {
                 details,
                 internal_error,
             ),
+            AppError::GatewayTimeout {
+                internal_error,
+                details,
+            } => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "gateway_timeout",
+                "The storage backend did not respond in time",
+                details,
+                internal_error,
+            ),
         }
     }
 }
 
+impl AppError {
+    pub fn internal_error(&self) -> &Error {
+        match self {
+            AppError::BadRequest { internal_error, .. }
+            | AppError::Unauthorized { internal_error, .. }
+            | AppError::NotFound { internal_error, .. }
+            | AppError::PayloadTooLarge { internal_error, .. }
+            | AppError::UnsupportedMediaType { internal_error, .. }
+            | AppError::Conflict { internal_error, .. }
+            | AppError::Internal { internal_error, .. }
+            | AppError::GatewayTimeout { internal_error, .. } => internal_error,
+        }
+    }
+
+    /// Problem body for this error, used where several results share one response.
+    pub fn into_problem(self) -> Value {
+        let (status, title, message, details, internal_error) = self.into_parts();
+        warn!(%internal_error, title, message, "Batch item failed. details: {:?}", details);
+        problem_body(status, title)
+    }
+}
+
+fn problem_body(status: StatusCode, title: &str) -> Value {
+    let mut payload = Map::new();
+    payload.insert("title".to_string(), Value::String(title.to_string()));
+    payload.insert("type".to_string(), Value::String("about:blank".to_string()));
+    payload.insert("code".to_string(), Value::Number(status.as_u16().into()));
+    if let Some(trace_id) = current_trace_id() {
+        payload.insert("trace_id".to_string(), Value::String(trace_id));
+    }
+    Value::Object(payload)
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, title, message, details, internal_error) = self.into_parts();
@@ -70,11 +213,6 @@ impl IntoResponse for AppError {
             warn!(%internal_error, title, message, "HTTP request failed with exception. details: {:?}", details);
         }
 
-        let mut payload = Map::new();
-        payload.insert("title".to_string(), Value::String(title.to_string()));
-        payload.insert("type".to_string(), Value::String("about:blank".to_string()));
-        payload.insert("code".to_string(), Value::Number(status.as_u16().into()));
-
-        (status, Json(Value::Object(payload))).into_response()
+        (status, Json(problem_body(status, title))).into_response()
     }
 }
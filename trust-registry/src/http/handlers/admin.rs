@@ -0,0 +1,547 @@
+use std::sync::Arc;
+
+use affinidi_tdk::didcomm::Message;
+use anyhow::anyhow;
+use axum::{
+    Json, Router,
+    extract::{Path, State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode, header, header::HeaderName},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+use crate::{
+    audit::model::{AuditLogBuilder, AuditOperation, AuditResource},
+    didcomm::{
+        handlers::admin::{
+            AdminMessagesHandler, CORRELATION_ID_FIELD, CREATE_RECORD_MESSAGE_TYPE,
+            DELETE_RECORD_MESSAGE_TYPE, READ_RECORD_MESSAGE_TYPE, UPDATE_RECORD_MESSAGE_TYPE,
+            messages::{RecordKeyBody, RecordResponse},
+        },
+        problem_report::{ProblemReport, codes},
+    },
+    domain::TrustRecordIds,
+    http::error::AppError,
+    storage::repository::{TrustRecordAdminRepository, TrustRecordQuery},
+    util::constant_time_eq,
+};
+
+/// Actor recorded in the audit log for operations authorized by the admin API key, and
+/// the admin DID its `ADMIN_AUTHORITY_ACL` entry is looked up under.
+pub const ADMIN_API_ACTOR: &str = "admin-api";
+/// Caller supplied request id, recorded as the audit entry's `correlation_id`.
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+
+/// Record administration over HTTP for tooling on a trusted network, for `Bearer` callers
+/// holding the admin API key. Requests run through the same [`AdminMessagesHandler`] as
+/// their DIDComm counterparts, so they are checked, limited, audited and announced to the
+/// webhook alike.
+pub struct AdminApi {
+    admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    api_key: String,
+}
+
+impl AdminApi {
+    pub fn new(
+        admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+        api_key: String,
+    ) -> Self {
+        Self { admin, api_key }
+    }
+
+    /// Checks the API key, auditing refused requests as unauthorized.
+    async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        operation: AuditOperation,
+    ) -> Result<(), AppError> {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if constant_time_eq(presented.as_bytes(), self.api_key.as_bytes()) {
+            return Ok(());
+        }
+
+        let reason = "missing or invalid admin API key";
+        self.admin
+            .audit_service
+            .log(
+                AuditLogBuilder::new()
+                    .operation(operation)
+                    .actor(ADMIN_API_ACTOR)
+                    .resource(AuditResource::empty())
+                    .correlation_id(correlation_id(headers))
+                    .build_unauthorized(reason),
+            )
+            .await;
+        Err(AppError::Unauthorized {
+            internal_error: anyhow!(reason),
+            details: None,
+        })
+    }
+
+    /// Runs the admin message of `message_type` with `body` and returns its response body.
+    async fn process(
+        &self,
+        headers: &HeaderMap,
+        message_type: &str,
+        body: Value,
+    ) -> Result<Value, AppError> {
+        let mut message = Message::build(
+            uuid::Uuid::new_v4().to_string(),
+            message_type.to_string(),
+            body,
+        );
+        if let Some(correlation_id) = correlation_id(headers) {
+            message = message.header(CORRELATION_ID_FIELD.into(), Value::String(correlation_id));
+        }
+        self.admin
+            .process(ADMIN_API_ACTOR, None, message.finalize())
+            .await
+            .map(|(_, body)| body)
+            .map_err(problem_error)
+    }
+
+    /// The stored record with the key of `body`, as returned after a create or update.
+    async fn stored(&self, body: Value) -> Result<RecordResponse, AppError> {
+        let key: RecordKeyBody = serde_json::from_value(body).map_err(internal)?;
+        let record = self
+            .admin
+            .repository
+            .read(TrustRecordQuery::from(key))
+            .await?;
+        Ok(RecordResponse::from(&record))
+    }
+}
+
+fn correlation_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// HTTP error of a refused or failed admin request, every problem listed in `details`.
+fn problem_error(report: ProblemReport) -> AppError {
+    let issues = report
+        .args
+        .clone()
+        .unwrap_or_else(|| vec![report.comment.clone()]);
+    let details = Some(json!(
+        issues
+            .iter()
+            .map(|issue| json!({ "issue": issue }))
+            .collect::<Vec<_>>()
+    ));
+    let internal_error = anyhow!(report.comment);
+    match report.code.as_str() {
+        codes::ERROR_BAD_REQUEST => AppError::BadRequest {
+            internal_error,
+            details,
+        },
+        codes::ERROR_UNAUTHORIZED => AppError::Unauthorized {
+            internal_error,
+            details,
+        },
+        codes::ERROR_NOT_FOUND => AppError::NotFound {
+            internal_error,
+            details,
+        },
+        codes::ERROR_CONFLICT | codes::ERROR_READ_ONLY => AppError::Conflict {
+            internal_error,
+            details,
+        },
+        codes::ERROR_TIMEOUT => AppError::GatewayTimeout {
+            internal_error,
+            details,
+        },
+        _ => AppError::Internal {
+            internal_error,
+            details,
+        },
+    }
+}
+
+fn internal(e: serde_json::Error) -> AppError {
+    AppError::Internal {
+        internal_error: e.into(),
+        details: None,
+    }
+}
+
+fn key_body(key: &str) -> Result<Value, AppError> {
+    let ids = TrustRecordIds::decode_key(key).map_err(|e| AppError::BadRequest {
+        details: Some(json!([{ "issue": e.to_string() }])),
+        internal_error: e.into(),
+    })?;
+    let query = TrustRecordQuery::from_ids(ids);
+    Ok(json!(RecordKeyBody {
+        entity_id: query.entity_id.to_string(),
+        authority_id: query.authority_id.to_string(),
+        action: query.action.to_string(),
+        resource: query.resource.to_string(),
+    }))
+}
+
+/// `POST /admin/records` with a create-record body, see `DIDCOMM_PROTOCOLS.md`.
+async fn handle_create(
+    State(admin): State<Arc<AdminApi>>,
+    headers: HeaderMap,
+    body: Result<Json<Value>, JsonRejection>,
+) -> Result<(StatusCode, Json<RecordResponse>), AppError> {
+    admin.authorize(&headers, AuditOperation::Create).await?;
+    let Json(body) = body?;
+    let key = admin
+        .process(&headers, CREATE_RECORD_MESSAGE_TYPE, body)
+        .await?;
+    Ok((StatusCode::CREATED, Json(admin.stored(key).await?)))
+}
+
+/// `PUT /admin/records` with an update-record body, optionally with `expected_version`.
+async fn handle_update(
+    State(admin): State<Arc<AdminApi>>,
+    headers: HeaderMap,
+    body: Result<Json<Value>, JsonRejection>,
+) -> Result<Json<RecordResponse>, AppError> {
+    admin.authorize(&headers, AuditOperation::Update).await?;
+    let Json(body) = body?;
+    let key = admin
+        .process(&headers, UPDATE_RECORD_MESSAGE_TYPE, body)
+        .await?;
+    Ok(Json(admin.stored(key).await?))
+}
+
+/// `GET /admin/records/{key}`, where `key` is [`TrustRecordIds::encode_key`] of the record.
+/// Unlike `GET /records/{key}`, expired records and their full context are returned.
+async fn handle_read(
+    State(admin): State<Arc<AdminApi>>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> Result<Json<RecordResponse>, AppError> {
+    admin.authorize(&headers, AuditOperation::Read).await?;
+    let record = admin
+        .process(&headers, READ_RECORD_MESSAGE_TYPE, key_body(&key)?)
+        .await?;
+    Ok(Json(serde_json::from_value(record).map_err(internal)?))
+}
+
+/// `DELETE /admin/records/{key}`.
+async fn handle_delete(
+    State(admin): State<Arc<AdminApi>>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> Result<StatusCode, AppError> {
+    admin.authorize(&headers, AuditOperation::Delete).await?;
+    admin
+        .process(&headers, DELETE_RECORD_MESSAGE_TYPE, key_body(&key)?)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn admin_routes(admin: Arc<AdminApi>) -> Router {
+    Router::new()
+        .route("/admin/records", post(handle_create).put(handle_update))
+        .route(
+            "/admin/records/{key}",
+            get(handle_read).delete(handle_delete),
+        )
+        .with_state(admin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ReadOnlyMode,
+        audit::model::{AuditLog, AuditLogger, AuditStatus},
+        configs::AdminConfig,
+        domain::*,
+        storage::adapters::local_storage::LocalStorage,
+    };
+    use axum::{
+        body::{Body, to_bytes},
+        http::{Method, Request},
+    };
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    const ENTITY: &str = "did:example:entity";
+    const AUTHORITY: &str = "did:example:authority";
+
+    #[derive(Default)]
+    struct RecordingAuditLogger {
+        logs: Mutex<Vec<AuditLog>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AuditLogger for RecordingAuditLogger {
+        async fn log(&self, audit_log: AuditLog) {
+            self.logs.lock().unwrap().push(audit_log);
+        }
+    }
+
+    fn router_with(audit_logger: Arc<RecordingAuditLogger>, admin_config: AdminConfig) -> Router {
+        let repository: Arc<dyn TrustRecordAdminRepository> = Arc::new(LocalStorage::new());
+        let admin = AdminMessagesHandler::new(repository, admin_config, audit_logger);
+        admin_routes(Arc::new(AdminApi::new(
+            Arc::new(admin),
+            "secret".to_string(),
+        )))
+    }
+
+    fn router(audit_logger: Arc<RecordingAuditLogger>, read_only: ReadOnlyMode) -> Router {
+        router_with(
+            audit_logger,
+            AdminConfig {
+                read_only,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn key() -> String {
+        TrustRecordIds::new(
+            EntityId::new(ENTITY),
+            AuthorityId::new(AUTHORITY),
+            Action::new("issue"),
+            Resource::new("credential"),
+        )
+        .encode_key()
+    }
+
+    fn record_body(authorized: bool) -> Value {
+        json!({
+            "entity_id": ENTITY,
+            "authority_id": AUTHORITY,
+            "action": "issue",
+            "resource": "credential",
+            "recognized": true,
+            "authorized": authorized,
+            "record_type": "assertion",
+            "context": { "level": 2 }
+        })
+    }
+
+    async fn send(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        api_key: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {api_key}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(CORRELATION_ID_HEADER, "req-1")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_crud_with_valid_api_key() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let app = router(audit_logger.clone(), ReadOnlyMode::default());
+        let record_uri = format!("/admin/records/{}", key());
+
+        let (status, created) = send(
+            &app,
+            Method::POST,
+            "/admin/records",
+            "secret",
+            Some(record_body(true)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["version"], 1);
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/admin/records",
+            "secret",
+            Some(record_body(true)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, updated) = send(
+            &app,
+            Method::PUT,
+            "/admin/records",
+            "secret",
+            Some(record_body(false)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(updated["version"], 2);
+
+        let (status, read) = send(&app, Method::GET, &record_uri, "secret", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(read["authorized"], false);
+        assert_eq!(read["context"], json!({ "level": 2 }));
+
+        let (status, _) = send(&app, Method::DELETE, &record_uri, "secret", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, problem) = send(&app, Method::GET, &record_uri, "secret", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(problem["title"], "not_found");
+        assert_eq!(problem["code"], 404);
+
+        let logs = audit_logger.logs.lock().unwrap();
+        let operations: Vec<String> = logs.iter().map(|log| log.operation.to_string()).collect();
+        assert_eq!(
+            operations,
+            ["CREATE", "CREATE", "UPDATE", "READ", "DELETE", "READ"]
+        );
+        assert!(logs.iter().all(|log| log.actor == ADMIN_API_ACTOR));
+        assert!(
+            logs.iter()
+                .all(|log| log.correlation_id.as_deref() == Some("req-1"))
+        );
+        assert!(matches!(logs[0].status, AuditStatus::Success));
+        assert!(matches!(logs[1].status, AuditStatus::Failure));
+        assert_eq!(
+            logs[4].resource.entity_id.as_ref().map(|e| e.as_str()),
+            Some(ENTITY)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_api_key_is_rejected_and_audited() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let app = router(audit_logger.clone(), ReadOnlyMode::default());
+        let record_uri = format!("/admin/records/{}", key());
+
+        let requests = [
+            (Method::POST, "/admin/records", Some(record_body(true))),
+            (Method::PUT, "/admin/records", Some(record_body(true))),
+            (Method::GET, record_uri.as_str(), None),
+            (Method::DELETE, record_uri.as_str(), None),
+        ];
+        for (method, uri, body) in requests {
+            let (status, problem) = send(&app, method, uri, "wrong", body).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(problem["title"], "unauthorized");
+        }
+
+        let logs = audit_logger.logs.lock().unwrap();
+        assert_eq!(logs.len(), 4);
+        assert!(
+            logs.iter()
+                .all(|log| matches!(log.status, AuditStatus::Unauthorized))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_record_is_rejected() {
+        let app = router(Arc::default(), ReadOnlyMode::default());
+        let mut body = record_body(true);
+        body["entity_id"] = json!("not-a-did");
+
+        let (status, problem) =
+            send(&app, Method::POST, "/admin/records", "secret", Some(body)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["title"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_refused_in_read_only_mode() {
+        let app = router(Arc::default(), ReadOnlyMode::new(true));
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/admin/records",
+            "secret",
+            Some(record_body(true)),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_context_size_limit_applies() {
+        let app = router_with(
+            Arc::default(),
+            AdminConfig {
+                max_context_bytes: 8,
+                ..Default::default()
+            },
+        );
+
+        let (status, problem) = send(
+            &app,
+            Method::POST,
+            "/admin/records",
+            "secret",
+            Some(record_body(true)),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["title"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn test_authority_acl_applies_to_the_api_key() {
+        let audit_logger = Arc::new(RecordingAuditLogger::default());
+        let app = router_with(
+            audit_logger.clone(),
+            AdminConfig {
+                authority_acl: HashMap::from([(
+                    ADMIN_API_ACTOR.to_string(),
+                    HashSet::from(["did:example:other".to_string()]),
+                )]),
+                ..Default::default()
+            },
+        );
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/admin/records",
+            "secret",
+            Some(record_body(true)),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let logs = audit_logger.logs.lock().unwrap();
+        assert_eq!(logs.len(), 1);
+        assert!(matches!(logs[0].status, AuditStatus::Unauthorized));
+    }
+
+    #[tokio::test]
+    async fn test_strict_requests_reject_unknown_fields() {
+        let app = router_with(
+            Arc::default(),
+            AdminConfig {
+                strict_requests: true,
+                ..Default::default()
+            },
+        );
+        let mut body = record_body(true);
+        body["authorised"] = json!(true);
+
+        let (status, problem) =
+            send(&app, Method::POST, "/admin/records", "secret", Some(body)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["title"], "bad_request");
+    }
+}
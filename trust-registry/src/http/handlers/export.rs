@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::{
+    Router,
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use futures::{Stream, stream};
+use serde_json::json;
+
+use crate::{
+    domain::TrustRecord,
+    http::error::AppError,
+    storage::repository::{RepositoryError, TrustRecordAdminRepository},
+    util::constant_time_eq,
+};
+
+/// Records serialized per streamed chunk.
+const EXPORT_PAGE_SIZE: usize = 500;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Streams every stored record as newline-delimited JSON, for `Bearer` callers holding the key.
+pub struct Export {
+    repository: Arc<dyn TrustRecordAdminRepository>,
+    api_key: String,
+    page_size: usize,
+}
+
+impl Export {
+    pub fn new(repository: Arc<dyn TrustRecordAdminRepository>, api_key: String) -> Self {
+        Self {
+            repository,
+            api_key,
+            page_size: EXPORT_PAGE_SIZE,
+        }
+    }
+
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        constant_time_eq(presented.as_bytes(), self.api_key.as_bytes())
+    }
+
+    /// One chunk per `page_size` records of a single [`TrustRecordAdminRepository::list`],
+    /// followed by a `{"summary": {"count": n}}` line. A failing list ends the stream
+    /// without the summary line.
+    fn stream(&self) -> impl Stream<Item = Result<Bytes, RepositoryError>> + use<> {
+        enum Cursor {
+            List,
+            Page(std::vec::IntoIter<TrustRecord>, usize),
+            Done,
+        }
+
+        let repository = self.repository.clone();
+        let page_size = self.page_size;
+        stream::unfold(Cursor::List, move |cursor| {
+            let repository = repository.clone();
+            async move {
+                let (mut records, count) = match cursor {
+                    Cursor::List => match repository.list().await {
+                        Ok(list) => {
+                            let records = list.into_records();
+                            let count = records.len();
+                            (records.into_iter(), count)
+                        }
+                        Err(e) => return Some((Err(e), Cursor::Done)),
+                    },
+                    Cursor::Page(records, count) => (records, count),
+                    Cursor::Done => return None,
+                };
+                let page: Vec<_> = records.by_ref().take(page_size).collect();
+                if page.is_empty() {
+                    let summary = format!("{}\n", json!({ "summary": { "count": count } }));
+                    return Some((Ok(Bytes::from(summary)), Cursor::Done));
+                }
+                let mut chunk = Vec::new();
+                for record in &page {
+                    if let Err(e) = serde_json::to_writer(&mut chunk, record) {
+                        let error = RepositoryError::SerializationFailed(e.to_string());
+                        return Some((Err(error), Cursor::Done));
+                    }
+                    chunk.push(b'\n');
+                }
+                Some((Ok(Bytes::from(chunk)), Cursor::Page(records, count)))
+            }
+        })
+    }
+}
+
+async fn handle_export(
+    State(export): State<Arc<Export>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if !export.is_authorized(&headers) {
+        return Err(AppError::Unauthorized {
+            internal_error: anyhow!("missing or invalid export API key"),
+            details: None,
+        });
+    }
+
+    let mut response = Body::from_stream(export.stream()).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(NDJSON_CONTENT_TYPE),
+    );
+    Ok(response)
+}
+
+pub fn export_routes(export: Arc<Export>) -> Router {
+    Router::new()
+        .route("/export", get(handle_export))
+        .with_state(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::record_builder;
+    use axum::body::to_bytes;
+    use axum::http::{Request, StatusCode};
+    use std::collections::HashSet;
+    use tower::ServiceExt;
+
+    fn record(entity: &str, resource: &str) -> TrustRecord {
+        record_builder(entity, "did:example:gov", "issue", resource)
+            .build()
+            .unwrap()
+    }
+
+    fn export(records: Vec<TrustRecord>, page_size: usize) -> Arc<Export> {
+        let mut export = Export::new(
+            Arc::new(LocalStorage::with_records(records)),
+            "secret".to_string(),
+        );
+        export.page_size = page_size;
+        Arc::new(export)
+    }
+
+    fn request(api_key: Option<&str>) -> Request<Body> {
+        let mut request = Request::get("/export");
+        if let Some(api_key) = api_key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {api_key}"));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_every_record_once_with_summary() {
+        let records: Vec<_> = (0..7)
+            .map(|i| record(&format!("did:example:entity-{i}"), "credential"))
+            .collect();
+
+        let response = export_routes(export(records, 3))
+            .oneshot(request(Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            NDJSON_CONTENT_TYPE
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let (summary, records) = lines.split_last().unwrap();
+        assert_eq!(summary, &json!({ "summary": { "count": 7 } }));
+        let entities: HashSet<_> = records
+            .iter()
+            .map(|record| record["entity_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(records.len(), 7);
+        assert_eq!(entities.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_api_key() {
+        let router = export_routes(export(vec![record("did:example:a", "credential")], 3));
+
+        for api_key in [None, Some("wrong")] {
+            let response = router.clone().oneshot(request(api_key)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::storage::repository::TrustRecordAdminRepository;
+
+pub const NO_RECORDS_WARNING: &str =
+    "No trust records are loaded. Every TRQP query will return not found until records are added.";
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReadinessReport {
+    pub status: &'static str,
+    pub record_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl ReadinessReport {
+    pub fn is_ready(&self) -> bool {
+        self.status == "ready"
+    }
+}
+
+/// Reports whether the registry is ready to serve queries based on the number of stored
+/// records, counted at most once per `ttl` so frequent probes don't scan the storage.
+pub struct Readiness {
+    repository: Arc<dyn TrustRecordAdminRepository>,
+    require_records: bool,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, ReadinessReport)>>,
+}
+
+impl Readiness {
+    pub fn new(
+        repository: Arc<dyn TrustRecordAdminRepository>,
+        require_records: bool,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            repository,
+            require_records,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Holding the lock while counting makes concurrent probes share one count.
+    pub async fn check(&self) -> ReadinessReport {
+        let mut cached = self.cached.lock().await;
+        if let Some((checked_at, report)) = cached.as_ref()
+            && checked_at.elapsed() < self.ttl
+        {
+            return report.clone();
+        }
+
+        let report = self.count_records().await;
+        *cached = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    async fn count_records(&self) -> ReadinessReport {
+        match self.repository.count().await {
+            Ok(0) => ReadinessReport {
+                status: if self.require_records {
+                    "not_ready"
+                } else {
+                    "ready"
+                },
+                record_count: Some(0),
+                warning: Some(NO_RECORDS_WARNING.to_string()),
+            },
+            Ok(count) => ReadinessReport {
+                status: "ready",
+                record_count: Some(count),
+                warning: None,
+            },
+            Err(e) => ReadinessReport {
+                status: "not_ready",
+                record_count: None,
+                warning: Some(format!("Failed to count trust records: {e}")),
+            },
+        }
+    }
+
+    /// Logs the record count once at startup, warning when the storage is empty.
+    pub async fn log_startup_state(&self) -> ReadinessReport {
+        let report = self.check().await;
+        match (&report.record_count, &report.warning) {
+            (Some(count), None) => info!("Trust registry started with {count} trust records"),
+            (_, Some(warning)) => warn!("{warning}"),
+            (None, None) => {}
+        }
+        if self.require_records && !report.is_ready() {
+            warn!(
+                "REQUIRE_RECORDS is enabled. /health/ready reports not ready until records exist"
+            );
+        }
+        report
+    }
+}
+
+async fn handle_health() -> impl IntoResponse {
+    Json(json!({ "status": "OK" }))
+}
+
+async fn handle_readiness(State(readiness): State<Arc<Readiness>>) -> impl IntoResponse {
+    let report = readiness.check().await;
+    let status = if report.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+pub fn health_routes(readiness: Arc<Readiness>) -> Router {
+    Router::new()
+        .route("/health", get(handle_health))
+        .route("/health/ready", get(handle_readiness))
+        .with_state(readiness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::{AUTHORITY, ENTITY, record_builder};
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    fn test_record() -> TrustRecord {
+        record_builder(ENTITY, AUTHORITY, "action", "resource")
+            .build()
+            .unwrap()
+    }
+
+    async fn get_ready(readiness: Readiness) -> (StatusCode, Value) {
+        let response = health_routes(Arc::new(readiness))
+            .oneshot(
+                Request::builder()
+                    .uri("/health/ready")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_startup_state_warns_on_zero_records() {
+        let readiness = Readiness::new(Arc::new(LocalStorage::new()), false, Duration::ZERO);
+
+        let report = readiness.log_startup_state().await;
+
+        assert!(report.is_ready());
+        assert_eq!(report.record_count, Some(0));
+        assert_eq!(report.warning.as_deref(), Some(NO_RECORDS_WARNING));
+    }
+
+    #[tokio::test]
+    async fn test_ready_with_zero_records_when_not_required() {
+        let readiness = Readiness::new(Arc::new(LocalStorage::new()), false, Duration::ZERO);
+
+        let (status, body) = get_ready(readiness).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["record_count"], 0);
+        assert_eq!(body["warning"], NO_RECORDS_WARNING);
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_with_zero_records_when_required() {
+        let readiness = Readiness::new(Arc::new(LocalStorage::new()), true, Duration::ZERO);
+
+        let (status, body) = get_ready(readiness).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["record_count"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_ready_once_records_exist_when_required() {
+        let storage = LocalStorage::with_records(vec![test_record()]);
+        let readiness = Readiness::new(Arc::new(storage), true, Duration::ZERO);
+
+        let (status, body) = get_ready(readiness).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["record_count"], 1);
+        assert!(body.get("warning").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_readiness_is_cached_within_ttl() {
+        let storage = Arc::new(LocalStorage::new());
+        let readiness = Readiness::new(storage.clone(), true, Duration::from_secs(60));
+        assert!(!readiness.check().await.is_ready());
+
+        storage.create(test_record()).await.unwrap();
+
+        assert!(!readiness.check().await.is_ready());
+        let uncached = Readiness::new(storage, true, Duration::ZERO);
+        assert!(uncached.check().await.is_ready());
+    }
+}
@@ -1,24 +1,80 @@
 use crate::SharedData;
+use crate::http::response_signing::sign_response;
+use crate::http::trace_context::propagate_trace_context;
 use crate::storage::repository::TrustRecordRepository;
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post},
 };
+use tower_http::{
+    compression::{CompressionLayer, predicate::SizeAbove},
+    limit::RequestBodyLimitLayer,
+};
 
+pub mod admin;
+pub mod export;
+pub mod health;
+pub mod records;
+pub mod stats;
 pub mod trqp;
 pub mod wellknown;
 
+/// Responses smaller than this are sent uncompressed even when compression is enabled.
+pub const COMPRESSION_MIN_BYTES: u16 = 1024;
+
 pub fn application_routes<R>(api_prefix: &str, shared_data: SharedData<R>) -> Router
 where
     R: TrustRecordRepository + Send + ?Sized + 'static,
 {
-    let all_handlers = Router::new()
+    let trqp_handlers = Router::new()
         .route("/authorization", post(trqp::handle_trqp_authorization::<R>))
         .route("/recognition", post(trqp::handle_trqp_recognition::<R>))
+        .route(
+            "/entity-status",
+            post(trqp::entity_status::handle_entity_status::<R>),
+        )
+        .route(
+            "/authorization/batch",
+            post(trqp::batch::handle_trqp_authorization_batch::<R>),
+        )
+        .route(
+            "/recognition/batch",
+            post(trqp::batch::handle_trqp_recognition_batch::<R>),
+        );
+    // signed inside the compression layer, so signatures cover the uncompressed body
+    let trqp_handlers = match &shared_data.response_signer {
+        Some(signer) => trqp_handlers.layer(middleware::from_fn_with_state(
+            signer.clone(),
+            sign_response,
+        )),
+        None => trqp_handlers,
+    };
+
+    let all_handlers = trqp_handlers
+        .route("/records/{key}", get(records::handle_get_record::<R>))
         .route(
             "/.well-known/did.json",
             get(wellknown::handle_wellknown_did_json::<R>),
-        );
+        )
+        // replace axum's default 2 MB limit with the configured one
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(
+            shared_data.config.server_config.max_body_bytes,
+        ));
+    let all_handlers = if shared_data.config.server_config.enable_compression {
+        all_handlers.layer(
+            CompressionLayer::new()
+                .gzip(true)
+                .deflate(true)
+                .compress_when(SizeAbove::new(COMPRESSION_MIN_BYTES)),
+        )
+    } else {
+        all_handlers
+    };
+
+    let all_handlers = all_handlers.layer(middleware::from_fn(propagate_trace_context));
 
     let router = if api_prefix.is_empty() || api_prefix == "/" {
         Router::new().merge(all_handlers)
@@ -0,0 +1,174 @@
+use anyhow::anyhow;
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::Utc;
+use serde_json::json;
+
+use crate::{
+    SharedData,
+    domain::{TrustRecord, TrustRecordIds},
+    http::{error::AppError, handlers::trqp::shape_response},
+    storage::repository::{TrustRecordQuery, TrustRecordRepository},
+};
+
+/// `GET /records/{key}`, where `key` is [`TrustRecordIds::encode_key`] of the record.
+/// The record is shaped like a TRQP answer without a request context: the evaluation
+/// policy decides it and the attachment content is left out.
+pub async fn handle_get_record<R>(
+    State(state): State<SharedData<R>>,
+    Path(key): Path<String>,
+) -> Result<Json<TrustRecord>, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let ids = TrustRecordIds::decode_key(&key).map_err(|e| AppError::BadRequest {
+        details: Some(json!([{ "issue": e.to_string() }])),
+        internal_error: anyhow!(e),
+    })?;
+
+    let trust_record = state
+        .repository
+        .find_by_query(TrustRecordQuery::from_ids(ids))
+        .await
+        .map_err(AppError::from)?
+        .filter(|record| !record.is_expired_at(Utc::now()))
+        .ok_or(AppError::NotFound {
+            internal_error: anyhow!("Trust record not found"),
+            details: None,
+        })?;
+
+    let trust_record = shape_response(&state, trust_record, None).without_admin_fields();
+
+    Ok(Json(trust_record))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::{
+        SharedData,
+        configs::TrsutRegistryConfig,
+        domain::*,
+        http::application_routes,
+        policy::{DefaultEvaluationPolicy, Evaluation, EvaluationPolicy},
+        storage::{adapters::local_storage::LocalStorage, repository::TrustRecordRepository},
+        test_support::record_builder,
+    };
+
+    const ENTITY: &str = "did:web:issuer.example.com:users:alice";
+    const AUTHORITY: &str = "did:peer:2.Vz6Mk.Ez6LS";
+
+    fn router() -> Router {
+        router_with_policy(Arc::new(DefaultEvaluationPolicy))
+    }
+
+    fn router_with_policy(evaluation_policy: Arc<dyn EvaluationPolicy>) -> Router {
+        let record = record_builder(ENTITY, AUTHORITY, "issue", "credential")
+            .authorized(false)
+            .tags(vec!["pilot".to_string()])
+            .build()
+            .unwrap()
+            .with_attachments(vec![serde_json::json!({"data": {"base64": "e30="}})]);
+        let shared_data = SharedData {
+            config: Arc::new(TrsutRegistryConfig::default()),
+            service_start_timestamp: chrono::Utc::now(),
+            repository: Arc::new(LocalStorage::with_records(vec![record]))
+                as Arc<dyn TrustRecordRepository>,
+            decision_cache: None,
+            read_only: Default::default(),
+            evaluation_policy,
+            did_resolver: None,
+            webvh_log: None,
+            response_signer: None,
+            query_log_sampler: Default::default(),
+        };
+        application_routes("", shared_data)
+    }
+
+    fn key(entity: &str, resource: &str) -> String {
+        TrustRecordIds::new(
+            EntityId::new(entity),
+            AuthorityId::new(AUTHORITY),
+            Action::new("issue"),
+            Resource::new(resource),
+        )
+        .encode_key()
+    }
+
+    async fn get(path: &str) -> (StatusCode, Value) {
+        get_from(router(), path).await
+    }
+
+    async fn get_from(router: Router, path: &str) -> (StatusCode, Value) {
+        let response = router
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_get_record_by_encoded_key() {
+        let (status, body) = get(&format!("/records/{}", key(ENTITY, "credential"))).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["entity_id"], ENTITY);
+        assert_eq!(body["authority_id"], AUTHORITY);
+        assert_eq!(body["recognized"], true);
+        assert_eq!(body["authorized"], false);
+        assert!(body.get("tags").is_none());
+        assert!(body.get("attachments").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_record_returns_not_found() {
+        let (status, _) = get(&format!("/records/{}", key(ENTITY, "badge"))).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_record_with_malformed_key_returns_bad_request() {
+        let (status, _) = get("/records/not-a-key").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    /// Recognizes nothing, whatever is stored.
+    struct RecognizeNothingPolicy;
+
+    impl EvaluationPolicy for RecognizeNothingPolicy {
+        fn evaluate(&self, record: &TrustRecord, _context: Option<&Context>) -> Evaluation {
+            Evaluation {
+                authorized: record.is_authorized(),
+                recognized: false,
+                annotations: Some(serde_json::json!({ "policy": "recognize-nothing" })),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_record_applies_the_evaluation_policy() {
+        let router = router_with_policy(Arc::new(RecognizeNothingPolicy));
+
+        let (status, body) =
+            get_from(router, &format!("/records/{}", key(ENTITY, "credential"))).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["recognized"], false);
+        assert_eq!(body["context"]["policy"], "recognize-nothing");
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use axum::{Json, Router, extract::State, routing::get};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    domain::TrustRecord,
+    http::error::AppError,
+    storage::repository::{RepositoryError, TrustRecordAdminRepository},
+};
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct RegistryStats {
+    pub total_records: usize,
+    pub authorized_records: usize,
+    pub recognized_records: usize,
+    pub unique_authorities: usize,
+    pub unique_entities: usize,
+}
+
+impl RegistryStats {
+    fn from_records(total_records: usize, records: &[TrustRecord]) -> Self {
+        let authorities: HashSet<_> = records.iter().map(TrustRecord::authority_id).collect();
+        let entities: HashSet<_> = records.iter().map(TrustRecord::entity_id).collect();
+        Self {
+            total_records,
+            authorized_records: records.iter().filter(|r| r.is_authorized()).count(),
+            recognized_records: records.iter().filter(|r| r.is_recognized()).count(),
+            unique_authorities: authorities.len(),
+            unique_entities: entities.len(),
+        }
+    }
+}
+
+/// Aggregates over all stored records, recomputed at most once per `ttl`.
+pub struct Stats {
+    repository: Arc<dyn TrustRecordAdminRepository>,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, RegistryStats)>>,
+}
+
+impl Stats {
+    pub fn new(repository: Arc<dyn TrustRecordAdminRepository>, ttl: Duration) -> Self {
+        Self {
+            repository,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Holding the lock while scanning makes concurrent requests share one scan.
+    pub async fn current(&self) -> Result<RegistryStats, RepositoryError> {
+        let mut cached = self.cached.lock().await;
+        if let Some((computed_at, stats)) = cached.as_ref()
+            && computed_at.elapsed() < self.ttl
+        {
+            return Ok(stats.clone());
+        }
+
+        let total_records = self.repository.count().await?;
+        let records = self.repository.list().await?;
+        let stats = RegistryStats::from_records(total_records, records.records());
+        *cached = Some((Instant::now(), stats.clone()));
+        Ok(stats)
+    }
+}
+
+async fn handle_stats(State(stats): State<Arc<Stats>>) -> Result<Json<RegistryStats>, AppError> {
+    stats
+        .current()
+        .await
+        .map(Json)
+        .map_err(|e| AppError::Internal {
+            internal_error: anyhow!(e),
+            details: None,
+        })
+}
+
+pub fn stats_routes(stats: Arc<Stats>) -> Router {
+    Router::new()
+        .route("/stats", get(handle_stats))
+        .with_state(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::record_builder;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    fn record(entity: &str, authority: &str, authorized: bool, recognized: bool) -> TrustRecord {
+        record_builder(
+            entity,
+            authority,
+            "issue",
+            &format!("{authorized}-{recognized}"),
+        )
+        .recognized(recognized)
+        .authorized(authorized)
+        .build()
+        .unwrap()
+    }
+
+    fn seeded() -> Arc<LocalStorage> {
+        Arc::new(LocalStorage::with_records(vec![
+            record("did:example:alice", "did:example:gov", true, true),
+            record("did:example:alice", "did:example:gov", false, true),
+            record("did:example:bob", "did:example:gov", true, false),
+            record("did:example:bob", "did:example:edu", false, false),
+            record("did:example:carol", "did:example:edu", true, true),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn test_stats_endpoint_returns_aggregates() {
+        let stats = Arc::new(Stats::new(seeded(), Duration::from_secs(60)));
+
+        let response = stats_routes(stats)
+            .oneshot(Request::get("/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "total_records": 5,
+                "authorized_records": 3,
+                "recognized_records": 3,
+                "unique_authorities": 2,
+                "unique_entities": 3
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stats_are_cached_within_ttl() {
+        let storage = seeded();
+        let stats = Stats::new(storage.clone(), Duration::from_secs(60));
+        assert_eq!(stats.current().await.unwrap().total_records, 5);
+
+        storage
+            .create(record("did:example:dave", "did:example:gov", true, true))
+            .await
+            .unwrap();
+
+        assert_eq!(stats.current().await.unwrap().total_records, 5);
+        let uncached = Stats::new(storage, Duration::ZERO);
+        assert_eq!(uncached.current().await.unwrap().total_records, 6);
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::anyhow;
+use axum::{
+    Json,
+    extract::{State, rejection::JsonRejection},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{
+    InputDto, OutputDto, authority_did_document, authorization_output, find_trust_record,
+    recognition_output,
+};
+use crate::{
+    SharedData,
+    domain::TrustRecord,
+    http::error::AppError,
+    storage::repository::{Determination, TrustRecordRepository},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchInputDto {
+    queries: Vec<InputDto>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchOutputDto {
+    /// One entry per query, in request order. Failed lookups carry a problem body instead.
+    results: Vec<Value>,
+}
+
+/// Rejects requests that would trigger more backend lookups than `max_fan_out` allows.
+pub fn check_fan_out(lookups: usize, max_fan_out: usize) -> Result<(), AppError> {
+    if lookups > max_fan_out {
+        let issue = format!(
+            "Request triggers {lookups} lookups, exceeding the maximum fan-out of {max_fan_out}"
+        );
+        return Err(AppError::BadRequest {
+            internal_error: anyhow!(issue.clone()),
+            details: Some(json!([{ "issue": issue }])),
+        });
+    }
+    Ok(())
+}
+
+async fn handle_batch<R>(
+    state: SharedData<R>,
+    payload: Result<Json<BatchInputDto>, JsonRejection>,
+    determination: Determination,
+    build_output: fn(TrustRecord, DateTime<Utc>) -> OutputDto,
+) -> Result<Json<BatchOutputDto>, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let requested_at = Utc::now();
+    let body = payload?;
+    let queries = body.0.queries;
+    check_fan_out(queries.len(), state.config.server_config.max_fan_out)?;
+
+    let mut results = Vec::with_capacity(queries.len());
+    for input in queries {
+        let include_authority_did_document = input.include_authority_did_document;
+        let output = find_trust_record(&state, input, determination)
+            .await
+            .map(|record| build_output(record, requested_at));
+        let output = match output {
+            Ok(mut output) if include_authority_did_document => {
+                authority_did_document(&state, output.trust_record.authority_id())
+                    .await
+                    .map(|document| {
+                        output.authority_did_document = Some(document);
+                        output
+                    })
+            }
+            output => output,
+        };
+        let result = match output {
+            Ok(output) => serde_json::to_value(output).map_err(|e| AppError::Internal {
+                internal_error: e.into(),
+                details: None,
+            })?,
+            Err(e) => e.into_problem(),
+        };
+        results.push(result);
+    }
+
+    Ok(Json(BatchOutputDto { results }))
+}
+
+pub async fn handle_trqp_authorization_batch<R>(
+    State(state): State<SharedData<R>>,
+    payload: Result<Json<BatchInputDto>, JsonRejection>,
+) -> Result<Json<BatchOutputDto>, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    handle_batch(
+        state,
+        payload,
+        Determination::Authorization,
+        authorization_output,
+    )
+    .await
+}
+
+pub async fn handle_trqp_recognition_batch<R>(
+    State(state): State<SharedData<R>>,
+    payload: Result<Json<BatchInputDto>, JsonRejection>,
+) -> Result<Json<BatchOutputDto>, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    handle_batch(
+        state,
+        payload,
+        Determination::Recognition,
+        recognition_output,
+    )
+    .await
+}
@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{InputDto, OutputDto, batch::check_fan_out, respond};
+use crate::{
+    SharedData,
+    domain::{Action, AuthorityId, Context, EntityId, Resource, TrustRecord, TrustRecordIds},
+    http::error::AppError,
+    storage::repository::{Determination, TrustRecordRepository},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// TRQP input whose `action` or `resource` may list several values, to check an entity
+/// against each of them in one call.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryInputDto {
+    entity_id: EntityId,
+    authority_id: AuthorityId,
+    action: OneOrMany<Action>,
+    resource: OneOrMany<Resource>,
+    context: Option<Context>,
+    as_of: Option<DateTime<Utc>>,
+    #[serde(default)]
+    include_authority_did_document: bool,
+}
+
+/// A plain TRQP query, or one query per listed value keyed by that value.
+pub enum TrqpQuery {
+    Single(InputDto),
+    Composite(Vec<(String, InputDto)>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompositeOutputDto {
+    /// One entry per listed value. Failed lookups carry a problem body instead.
+    results: BTreeMap<String, Value>,
+}
+
+fn bad_request(issue: &str) -> AppError {
+    AppError::BadRequest {
+        internal_error: anyhow!(issue.to_string()),
+        details: Some(json!([{ "issue": issue }])),
+    }
+}
+
+impl QueryInputDto {
+    pub fn into_query(self) -> Result<TrqpQuery, AppError> {
+        let input = |action: Action, resource: Resource| InputDto {
+            ids: TrustRecordIds::new(
+                self.entity_id.clone(),
+                self.authority_id.clone(),
+                action,
+                resource,
+            ),
+            context: self.context.clone(),
+            as_of: self.as_of,
+            include_authority_did_document: self.include_authority_did_document,
+        };
+        let inputs = match (self.action.clone(), self.resource.clone()) {
+            (OneOrMany::One(action), OneOrMany::One(resource)) => {
+                return Ok(TrqpQuery::Single(input(action, resource)));
+            }
+            (OneOrMany::Many(_), OneOrMany::Many(_)) => {
+                return Err(bad_request(
+                    "Only one of action and resource can list several values",
+                ));
+            }
+            (OneOrMany::One(action), OneOrMany::Many(resources)) => resources
+                .into_iter()
+                .map(|resource| (resource.to_string(), input(action.clone(), resource)))
+                .collect::<Vec<_>>(),
+            (OneOrMany::Many(actions), OneOrMany::One(resource)) => actions
+                .into_iter()
+                .map(|action| (action.to_string(), input(action, resource.clone())))
+                .collect(),
+        };
+        if inputs.is_empty() {
+            return Err(bad_request("The listed actions or resources are empty"));
+        }
+        let mut listed = HashSet::new();
+        if let Some((duplicate, _)) = inputs.iter().find(|(key, _)| !listed.insert(key)) {
+            return Err(bad_request(&format!(
+                "'{duplicate}' is listed more than once"
+            )));
+        }
+        Ok(TrqpQuery::Composite(inputs))
+    }
+}
+
+/// Evaluates every query of a composite input like a single one.
+pub async fn respond_all<R>(
+    state: &SharedData<R>,
+    inputs: Vec<(String, InputDto)>,
+    determination: Determination,
+    build_output: fn(TrustRecord, DateTime<Utc>) -> OutputDto,
+) -> Result<CompositeOutputDto, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    check_fan_out(inputs.len(), state.config.server_config.max_fan_out)?;
+
+    let mut results = BTreeMap::new();
+    for (key, input) in inputs {
+        let result = match respond(state, input, determination, build_output).await {
+            Ok(output) => serde_json::to_value(output).map_err(|e| AppError::Internal {
+                internal_error: e.into(),
+                details: None,
+            })?,
+            Err(e) => e.into_problem(),
+        };
+        results.insert(key, result);
+    }
+    Ok(CompositeOutputDto { results })
+}
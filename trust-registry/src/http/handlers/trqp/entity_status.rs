@@ -0,0 +1,216 @@
+use std::collections::BTreeSet;
+
+use anyhow::anyhow;
+use axum::{
+    Json,
+    extract::{State, rejection::JsonRejection},
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SharedData,
+    domain::{AuthorityId, EntityId, TrustRecord},
+    http::error::AppError,
+    storage::repository::TrustRecordRepository,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntityStatusInputDto {
+    entity_id: EntityId,
+    authority_id: AuthorityId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EntityStatusOutputDto {
+    entity_id: EntityId,
+    authority_id: AuthorityId,
+    /// True when at least one record authorizes or recognizes the entity.
+    active: bool,
+    count_authorized: usize,
+    count_recognized: usize,
+    /// Distinct actions across all records, sorted.
+    actions: Vec<String>,
+    time_requested: String,
+    time_evaluated: String,
+}
+
+impl EntityStatusOutputDto {
+    fn aggregate(
+        input: EntityStatusInputDto,
+        records: &[TrustRecord],
+        time_requested: String,
+    ) -> Self {
+        let count_authorized = records.iter().filter(|r| r.is_authorized()).count();
+        let count_recognized = records.iter().filter(|r| r.is_recognized()).count();
+        let actions: BTreeSet<String> = records.iter().map(|r| r.action().to_string()).collect();
+
+        Self {
+            entity_id: input.entity_id,
+            authority_id: input.authority_id,
+            active: count_authorized > 0 || count_recognized > 0,
+            count_authorized,
+            count_recognized,
+            actions: actions.into_iter().collect(),
+            time_requested,
+            time_evaluated: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+/// Summarizes every record of an entity under an authority, across actions and resources.
+pub async fn handle_entity_status<R>(
+    State(state): State<SharedData<R>>,
+    payload: Result<Json<EntityStatusInputDto>, JsonRejection>,
+) -> Result<Json<EntityStatusOutputDto>, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let time_requested = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let input = payload?.0;
+
+    let now = Utc::now();
+    let records: Vec<TrustRecord> = state
+        .repository
+        .find_by_entity_and_authority(&input.entity_id, &input.authority_id)
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .filter(|record| !record.is_expired_at(now))
+        .collect();
+
+    if records.is_empty() {
+        return Err(AppError::NotFound {
+            internal_error: anyhow!("No trust records found for entity"),
+            details: None,
+        });
+    }
+
+    Ok(Json(EntityStatusOutputDto::aggregate(
+        input,
+        &records,
+        time_requested,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::test_support::{AUTHORITY, record_builder};
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::{
+        SharedData,
+        configs::TrsutRegistryConfig,
+        domain::*,
+        http::application_routes,
+        policy::DefaultEvaluationPolicy,
+        storage::{adapters::local_storage::LocalStorage, repository::TrustRecordRepository},
+    };
+
+    fn record(
+        entity: &str,
+        action: &str,
+        resource: &str,
+        authorized: bool,
+        recognized: bool,
+    ) -> TrustRecord {
+        record_builder(entity, AUTHORITY, action, resource)
+            .recognized(recognized)
+            .authorized(authorized)
+            .build()
+            .unwrap()
+    }
+
+    fn router(records: Vec<TrustRecord>) -> Router {
+        let shared_data = SharedData {
+            config: Arc::new(TrsutRegistryConfig::default()),
+            service_start_timestamp: chrono::Utc::now(),
+            repository: Arc::new(LocalStorage::with_records(records))
+                as Arc<dyn TrustRecordRepository>,
+            decision_cache: None,
+            read_only: Default::default(),
+            evaluation_policy: Arc::new(DefaultEvaluationPolicy),
+            did_resolver: None,
+            webvh_log: None,
+            response_signer: None,
+            query_log_sampler: Default::default(),
+        };
+        application_routes("", shared_data)
+    }
+
+    async fn post_status(router: Router, entity: &str) -> (StatusCode, Value) {
+        let body = serde_json::json!({
+            "entity_id": entity,
+            "authority_id": "did:example:authority"
+        });
+        let response = router
+            .oneshot(
+                Request::post("/entity-status")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_entity_status_aggregates_records_for_entity() {
+        let app = router(vec![
+            record("did:example:entity", "issue", "credential", true, true),
+            record("did:example:entity", "issue", "badge", false, true),
+            record("did:example:entity", "verify", "credential", true, false),
+            record("did:example:other", "revoke", "credential", true, true),
+        ]);
+
+        let (status, body) = post_status(app, "did:example:entity").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["entity_id"], "did:example:entity");
+        assert_eq!(body["active"], true);
+        assert_eq!(body["count_authorized"], 2);
+        assert_eq!(body["count_recognized"], 2);
+        assert_eq!(body["actions"], serde_json::json!(["issue", "verify"]));
+    }
+
+    #[tokio::test]
+    async fn test_entity_status_inactive_when_nothing_granted() {
+        let app = router(vec![
+            record("did:example:entity", "issue", "credential", false, false),
+            record("did:example:entity", "verify", "credential", false, false),
+        ]);
+
+        let (status, body) = post_status(app, "did:example:entity").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["active"], false);
+        assert_eq!(body["count_authorized"], 0);
+        assert_eq!(body["count_recognized"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_entity_status_unknown_entity_is_not_found() {
+        let app = router(vec![record(
+            "did:example:entity",
+            "issue",
+            "credential",
+            true,
+            true,
+        )]);
+
+        let (status, _) = post_status(app, "did:example:unknown").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -1,18 +1,43 @@
 use crate::{
-    domain::{Context, TrustRecord, TrustRecordIds},
-    storage::repository::{TrustRecordQuery, TrustRecordRepository},
+    did_resolution::public_document,
+    domain::{AuthorityId, Context, TrustRecord, TrustRecordIds},
+    policy,
+    storage::{
+        decision_cache::context_hash,
+        normalizing::normalize_query,
+        repository::{Determination, RepositoryError, TrustRecordQuery, TrustRecordRepository},
+    },
 };
 use anyhow::anyhow;
 use axum::{
     Json,
     extract::{State, rejection::JsonRejection},
+    response::{IntoResponse, Response},
 };
-use chrono::{SecondsFormat, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Value, json};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::info;
 
 use super::SharedData;
 use crate::http::error::AppError;
+use composite::{QueryInputDto, TrqpQuery, respond_all};
+
+/// Maximum nesting depth accepted for a request context before it is merged into a record.
+pub const MAX_CONTEXT_DEPTH: usize = 32;
+/// `tracing` target of the per-query events enabled by `LOG_TRQP_QUERIES`.
+pub const TRQP_QUERY_LOG_TARGET: &str = "trqp_query";
+
+pub mod batch;
+pub mod composite;
+pub mod entity_status;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OutputDto {
@@ -21,6 +46,9 @@ pub struct OutputDto {
     time_requested: String,
     time_evaluated: String,
     message: String,
+    /// Public parts of the authority's DID document, when `include_authority_did_document` was set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    authority_did_document: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,52 +56,339 @@ pub struct InputDto {
     #[serde(flatten)]
     ids: TrustRecordIds,
     context: Option<Context>,
+    /// Answers from the record as it stood at this time instead of its current state.
+    as_of: Option<DateTime<Utc>>,
+    /// Embeds the authority's resolved DID document in the response.
+    #[serde(default)]
+    include_authority_did_document: bool,
 }
 
 async fn handle_trqp<R>(
-    state: SharedData<R>,
-    payload: Result<Json<InputDto>, JsonRejection>,
+    state: &SharedData<R>,
+    input: InputDto,
+    determination: Determination,
 ) -> Result<TrustRecord, AppError>
 where
     R: TrustRecordRepository + Send + ?Sized + 'static,
 {
-    let body = payload.map_err(|e| AppError::BadRequest {
-        details: Some(json!([{ "issue": e.body_text() }])),
-        internal_error: e.into(),
+    if !state.config.server_config.log_trqp_queries {
+        return find_trust_record(state, input, determination).await;
+    }
+
+    let ids = input.ids.clone();
+    let started_at = Instant::now();
+    let result = find_trust_record(state, input, determination).await;
+    let sample_rate = match &result {
+        Ok(_) => state.query_log_sampler.rate(),
+        Err(_) => 1,
+    };
+    if result.is_err() || state.query_log_sampler.sample() {
+        log_query_outcome(
+            determination,
+            &ids,
+            &result,
+            started_at.elapsed(),
+            sample_rate,
+        );
+    }
+    result
+}
+
+/// Picks which successful TRQP queries are logged when `LOG_TRQP_SAMPLE_RATE` is above 1:
+/// the first of every `rate` queries. Not-found and failed queries are always logged.
+/// Clones share the same counter.
+#[derive(Debug, Clone)]
+pub struct QueryLogSampler {
+    rate: u64,
+    successes: Arc<AtomicU64>,
+}
+
+impl QueryLogSampler {
+    /// A `rate` of 0 is treated as 1, logging every query.
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            successes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Counts a successful query and tells whether it's one to log.
+    pub fn sample(&self) -> bool {
+        self.successes
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.rate)
+    }
+}
+
+impl Default for QueryLogSampler {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Analytics event for a TRQP decision, separate from the admin audit log.
+fn log_query_outcome(
+    determination: Determination,
+    ids: &TrustRecordIds,
+    result: &Result<TrustRecord, AppError>,
+    latency: Duration,
+    sample_rate: u64,
+) {
+    let query = match determination {
+        Determination::Authorization => "authorization",
+        Determination::Recognition => "recognition",
+    };
+    let (found, decision, failed) = match result {
+        Ok(record) => (true, determination.value(record), false),
+        Err(AppError::NotFound { .. }) => (false, false, false),
+        Err(_) => (false, false, true),
+    };
+    info!(
+        target: TRQP_QUERY_LOG_TARGET,
+        query,
+        entity_id = %ids.entity_id(),
+        authority_id = %ids.authority_id(),
+        action = %ids.action(),
+        resource = %ids.resource(),
+        found,
+        decision,
+        failed,
+        latency_us = latency.as_micros() as u64,
+        sample_rate,
+        "TRQP query evaluated"
+    );
+}
+
+async fn find_trust_record<R>(
+    state: &SharedData<R>,
+    input: InputDto,
+    determination: Determination,
+) -> Result<TrustRecord, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    if let Some(depth) = input.context.as_ref().map(Context::depth)
+        && depth > MAX_CONTEXT_DEPTH
+    {
+        let issue =
+            format!("Context nesting depth {depth} exceeds the maximum of {MAX_CONTEXT_DEPTH}");
+        return Err(AppError::BadRequest {
+            internal_error: anyhow!(issue.clone()),
+            details: Some(json!([{ "issue": issue }])),
+        });
+    }
+
+    if let Err(issue) = state
+        .config
+        .server_config
+        .trqp_query_allowlist
+        .check(input.ids.action().as_str(), input.ids.resource().as_str())
+    {
+        return Err(AppError::BadRequest {
+            internal_error: anyhow!(issue.clone()),
+            details: Some(json!([{ "issue": issue }])),
+        });
+    }
+
+    let mut query = TrustRecordQuery::from_ids(input.ids);
+    if state.config.server_config.normalize_dids {
+        // decisions are cached by the normalized query the repository sees
+        query = normalize_query(query);
+    }
+    if let Some(as_of) = input.as_of {
+        return find_trust_record_as_of(state, query, determination, as_of, input.context).await;
+    }
+    let cache_key = state
+        .decision_cache
+        .as_ref()
+        .map(|_| context_hash(input.context.as_ref()));
+    if let (Some(cache), Some(key)) = (&state.decision_cache, &cache_key)
+        && let Some(cached) = cache.get(&query, key)
+    {
+        return reject_expired(cached);
+    }
+
+    let evaluated = state
+        .repository
+        .evaluate(query.clone(), determination, None)
+        .await?;
+    let trust_record = evaluated.record.ok_or(AppError::NotFound {
+        internal_error: anyhow!("Trust record not found"),
+        details: None,
     })?;
-    let input = body.0;
-    let query_result = state
+    let trust_record = shape_response(state, trust_record, input.context);
+
+    if let (Some(cache), Some(key)) = (&state.decision_cache, cache_key) {
+        cache.insert(&query, key, trust_record.clone());
+    }
+
+    Ok(trust_record)
+}
+
+/// Past decisions skip the decision cache, which only holds current ones.
+async fn find_trust_record_as_of<R>(
+    state: &SharedData<R>,
+    query: TrustRecordQuery,
+    determination: Determination,
+    as_of: DateTime<Utc>,
+    context: Option<Context>,
+) -> Result<TrustRecord, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let evaluated = state
         .repository
-        .find_by_query(TrustRecordQuery::from_ids(input.ids))
+        .evaluate(query, determination, Some(as_of))
         .await;
-    let mut trust_record = query_result
-        .map_err(|e| AppError::Internal {
-            internal_error: e.into(),
-            details: None,
-        })?
-        .ok_or(AppError::NotFound {
-            internal_error: anyhow!("Trust record not found"),
-            details: None,
-        })?;
+    let trust_record = match evaluated {
+        Ok(evaluated) => evaluated.record,
+        Err(e @ RepositoryError::HistoryUnavailable(_)) => {
+            return Err(AppError::BadRequest {
+                details: Some(json!([{ "issue": e.to_string() }])),
+                internal_error: e.into(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    }
+    .ok_or(AppError::NotFound {
+        internal_error: anyhow!("Trust record not found at {as_of}"),
+        details: None,
+    })?;
+    Ok(shape_response(state, trust_record, context))
+}
+
+/// Applies the default context, the evaluation policy and the context allowlist.
+pub(crate) fn shape_response<R>(
+    state: &SharedData<R>,
+    mut trust_record: TrustRecord,
+    context: Option<Context>,
+) -> TrustRecord
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let merge_mode = state.config.server_config.context_merge_mode;
+    if let Some(defaults) = &state.config.server_config.trqp_default_context {
+        trust_record = trust_record.with_context_defaults(defaults, merge_mode);
+    }
 
-    if let Some(c) = input.context {
-        trust_record = trust_record.merge_contexts(c);
+    trust_record = policy::apply(
+        state.evaluation_policy.as_ref(),
+        trust_record,
+        context,
+        merge_mode,
+        state.config.server_config.context_provenance,
+    );
+    let allowlist = &state.config.server_config.trqp_context_allowlist;
+    if !allowlist.is_empty() {
+        trust_record = trust_record.project_context(allowlist);
     }
+    trust_record
+}
 
+/// Resolves the authority's DID document for `include_authority_did_document`, through the
+/// cached resolver so repeated queries don't repeat the lookup.
+async fn authority_did_document<R>(
+    state: &SharedData<R>,
+    authority_id: &AuthorityId,
+) -> Result<Value, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let Some(resolver) = &state.did_resolver else {
+        let issue = "DID resolution is not configured, authority DID documents are unavailable";
+        return Err(AppError::BadRequest {
+            internal_error: anyhow!(issue),
+            details: Some(json!([{ "issue": issue }])),
+        });
+    };
+    let document =
+        resolver
+            .resolve(authority_id.as_str())
+            .await
+            .map_err(|e| AppError::Internal {
+                details: Some(json!([{ "issue": e.to_string() }])),
+                internal_error: e.into(),
+            })?;
+    Ok(public_document(document))
+}
+
+/// Builds the response for `input`, with the authority's DID document when it was asked for.
+async fn respond<R>(
+    state: &SharedData<R>,
+    input: InputDto,
+    determination: Determination,
+    build_output: fn(TrustRecord, DateTime<Utc>) -> OutputDto,
+) -> Result<OutputDto, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let requested_at = Utc::now();
+    let include_authority_did_document = input.include_authority_did_document;
+    let trust_record = handle_trqp(state, input, determination).await?;
+    let mut output = build_output(trust_record, requested_at);
+    if include_authority_did_document {
+        output.authority_did_document =
+            Some(authority_did_document(state, output.trust_record.authority_id()).await?);
+    }
+    Ok(output)
+}
+
+/// Expired records answer like missing ones.
+fn reject_expired(trust_record: TrustRecord) -> Result<TrustRecord, AppError> {
+    if trust_record.is_expired_at(Utc::now()) {
+        return Err(AppError::NotFound {
+            internal_error: anyhow!("Trust record expired"),
+            details: None,
+        });
+    }
     Ok(trust_record)
 }
 
+/// Answers a single query with its output, and a composite one with the output per listed value.
+async fn handle_query<R>(
+    state: SharedData<R>,
+    payload: Result<Json<QueryInputDto>, JsonRejection>,
+    determination: Determination,
+    build_output: fn(TrustRecord, DateTime<Utc>) -> OutputDto,
+) -> Result<Response, AppError>
+where
+    R: TrustRecordRepository + Send + ?Sized + 'static,
+{
+    let body = payload?;
+    match body.0.into_query()? {
+        TrqpQuery::Single(input) => {
+            let output = respond(&state, input, determination, build_output).await?;
+            Ok(Json(output).into_response())
+        }
+        TrqpQuery::Composite(inputs) => {
+            let output = respond_all(&state, inputs, determination, build_output).await?;
+            Ok(Json(output).into_response())
+        }
+    }
+}
+
 pub async fn handle_trqp_authorization<R>(
     State(state): State<SharedData<R>>,
-    payload: Result<Json<InputDto>, JsonRejection>,
-) -> Result<Json<OutputDto>, AppError>
+    payload: Result<Json<QueryInputDto>, JsonRejection>,
+) -> Result<Response, AppError>
 where
     R: TrustRecordRepository + Send + ?Sized + 'static,
 {
-    let requested_at = Utc::now();
-    let mut trust_record = handle_trqp(state, payload).await?;
-    // in order to follow spec remove this field from output
-    trust_record = trust_record.none_recognized();
+    handle_query(
+        state,
+        payload,
+        Determination::Authorization,
+        authorization_output,
+    )
+    .await
+}
+
+fn authorization_output(trust_record: TrustRecord, requested_at: DateTime<Utc>) -> OutputDto {
+    let trust_record = Determination::Authorization.isolate(trust_record);
     let message = format!(
         "{} authorized to {}+{} by {}",
         trust_record.entity_id(),
@@ -83,25 +398,33 @@ where
     );
     let evaluated_at = Utc::now();
 
-    Ok(Json(OutputDto {
+    OutputDto {
         trust_record,
         time_requested: requested_at.to_rfc3339_opts(SecondsFormat::Secs, true),
         time_evaluated: evaluated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
         message,
-    }))
+        authority_did_document: None,
+    }
 }
 
 pub async fn handle_trqp_recognition<R>(
     State(state): State<SharedData<R>>,
-    payload: Result<Json<InputDto>, JsonRejection>,
-) -> Result<Json<OutputDto>, AppError>
+    payload: Result<Json<QueryInputDto>, JsonRejection>,
+) -> Result<Response, AppError>
 where
     R: TrustRecordRepository + Send + ?Sized + 'static,
 {
-    let requested_at = Utc::now();
-    let mut trust_record = handle_trqp(state, payload).await?;
-    // in order to follow spec remove this field from output
-    trust_record = trust_record.none_authorized();
+    handle_query(
+        state,
+        payload,
+        Determination::Recognition,
+        recognition_output,
+    )
+    .await
+}
+
+fn recognition_output(trust_record: TrustRecord, requested_at: DateTime<Utc>) -> OutputDto {
+    let trust_record = Determination::Recognition.isolate(trust_record);
     let message = format!(
         "{} recognized by {}",
         trust_record.entity_id(),
@@ -109,10 +432,971 @@ where
     );
     let evaluated_at = Utc::now();
 
-    Ok(Json(OutputDto {
+    OutputDto {
         trust_record,
         time_requested: requested_at.to_rfc3339_opts(SecondsFormat::Secs, true),
         time_evaluated: evaluated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
         message,
-    }))
+        authority_did_document: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::QueryLogSampler;
+    use crate::{
+        SharedData,
+        configs::{QueryAllowlist, ServerConfig, TrsutRegistryConfig},
+        did_resolution::{CachingDidResolver, DidResolutionError, DidResolver},
+        domain::*,
+        http::application_routes,
+        policy::{DefaultEvaluationPolicy, Evaluation, EvaluationPolicy},
+        storage::{
+            adapters::local_storage::LocalStorage,
+            decision_cache::{DecisionCache, InvalidatingRepository},
+            history::HistoryRepository,
+            repository::*,
+            timeout::TimeoutRepository,
+        },
+        test_support::{AUTHORITY, CountingRepository, SlowRepository, record_builder, record_for},
+    };
+
+    fn router(
+        repository: Arc<dyn TrustRecordAdminRepository>,
+        decision_cache: Option<Arc<DecisionCache>>,
+    ) -> Router {
+        router_with_config(repository, decision_cache, TrsutRegistryConfig::default())
+    }
+
+    fn router_with_config(
+        repository: Arc<dyn TrustRecordAdminRepository>,
+        decision_cache: Option<Arc<DecisionCache>>,
+        config: TrsutRegistryConfig,
+    ) -> Router {
+        router_with_policy(
+            repository,
+            decision_cache,
+            config,
+            Arc::new(DefaultEvaluationPolicy),
+        )
+    }
+
+    fn router_with_policy(
+        repository: Arc<dyn TrustRecordAdminRepository>,
+        decision_cache: Option<Arc<DecisionCache>>,
+        config: TrsutRegistryConfig,
+        evaluation_policy: Arc<dyn EvaluationPolicy>,
+    ) -> Router {
+        let shared_data = shared_data(repository, decision_cache, config, evaluation_policy);
+        application_routes("", shared_data)
+    }
+
+    fn shared_data(
+        repository: Arc<dyn TrustRecordAdminRepository>,
+        decision_cache: Option<Arc<DecisionCache>>,
+        config: TrsutRegistryConfig,
+        evaluation_policy: Arc<dyn EvaluationPolicy>,
+    ) -> SharedData<dyn TrustRecordRepository> {
+        let query_log_sampler = QueryLogSampler::new(config.server_config.log_trqp_sample_rate);
+        SharedData {
+            config: Arc::new(config),
+            service_start_timestamp: chrono::Utc::now(),
+            repository: repository as Arc<dyn TrustRecordRepository>,
+            decision_cache,
+            read_only: Default::default(),
+            evaluation_policy,
+            did_resolver: None,
+            webvh_log: None,
+            response_signer: None,
+            query_log_sampler,
+        }
+    }
+
+    async fn post(router: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    fn request_body() -> Value {
+        json!({
+            "entity_id": "did:example:entity",
+            "authority_id": "did:example:authority",
+            "action": "issue",
+            "resource": "credential",
+            "context": {"purpose": "test"}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_decision_cache_hit_skips_backend_and_update_invalidates() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let cache = Arc::new(DecisionCache::new(Duration::from_secs(60), 100));
+        let repository: Arc<dyn TrustRecordAdminRepository> =
+            Arc::new(InvalidatingRepository::new(backend.clone(), cache.clone()));
+        let app = router(repository.clone(), Some(cache));
+
+        let (status, first) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["authorized"], true);
+        let (_, second) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(second["authorized"], true);
+        assert_eq!(second["context"], json!({"purpose": "test"}));
+        assert_eq!(backend.lookups(), 1);
+
+        repository
+            .update(record_for("credential", false))
+            .await
+            .unwrap();
+
+        let (_, third) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(third["authorized"], false);
+        assert_eq!(backend.lookups(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_different_context_is_a_cache_miss() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let cache = Arc::new(DecisionCache::new(Duration::from_secs(60), 100));
+        let app = router(backend.clone(), Some(cache));
+
+        post(&app, "/recognition", request_body()).await;
+        let mut other = request_body();
+        other["context"] = json!({"purpose": "other"});
+        let (_, body) = post(&app, "/recognition", other).await;
+
+        assert_eq!(body["context"], json!({"purpose": "other"}));
+        assert_eq!(backend.lookups(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_as_of_answers_from_the_version_active_then() {
+        let history = Arc::new(HistoryRepository::new(Arc::new(LocalStorage::new())));
+        let created = chrono::Utc::now() - chrono::Duration::hours(2);
+        let updated = chrono::Utc::now() - chrono::Duration::hours(1);
+        history
+            .create(
+                record_for("credential", true)
+                    .with_created_at(Some(created))
+                    .with_updated_at(Some(created)),
+            )
+            .await
+            .unwrap();
+        history
+            .update(
+                record_for("credential", false)
+                    .with_created_at(Some(created))
+                    .with_updated_at(Some(updated)),
+            )
+            .await
+            .unwrap();
+        let app = router(history, None);
+
+        let mut between = request_body();
+        between["as_of"] = json!(created + chrono::Duration::minutes(30));
+        let (status, body) = post(&app, "/authorization", between).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["authorized"], true);
+
+        let mut before = request_body();
+        before["as_of"] = json!(created - chrono::Duration::minutes(30));
+        let (status, _) = post(&app, "/authorization", before).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, current) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(current["authorized"], false);
+    }
+
+    #[tokio::test]
+    async fn test_without_decision_cache_every_request_hits_backend() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router(backend.clone(), None);
+
+        post(&app, "/authorization", request_body()).await;
+        post(&app, "/authorization", request_body()).await;
+
+        assert_eq!(backend.lookups(), 2);
+    }
+
+    fn config_with_max_fan_out(max_fan_out: usize) -> TrsutRegistryConfig {
+        TrsutRegistryConfig {
+            server_config: ServerConfig {
+                max_fan_out,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_exceeding_fan_out_is_rejected() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router_with_config(backend.clone(), None, config_with_max_fan_out(2));
+
+        let body = json!({ "queries": [request_body(), request_body(), request_body()] });
+        let (status, response) = post(&app, "/authorization/batch", body).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["title"], "bad_request");
+        assert_eq!(backend.lookups(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_within_fan_out_returns_results_in_order() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router_with_config(backend.clone(), None, config_with_max_fan_out(2));
+
+        let mut missing = request_body();
+        missing["entity_id"] = json!("did:example:unknown");
+        let body = json!({ "queries": [request_body(), missing] });
+        let (status, response) = post(&app, "/recognition/batch", body).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = response["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["recognized"], true);
+        assert!(results[0].get("authorized").is_none());
+        assert_eq!(results[1]["code"], 404);
+        assert_eq!(backend.lookups(), 2);
+    }
+
+    fn config_with_max_body_bytes(max_body_bytes: usize) -> TrsutRegistryConfig {
+        TrsutRegistryConfig {
+            server_config: ServerConfig {
+                max_body_bytes,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected_with_413() {
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            config_with_max_body_bytes(1024),
+        );
+
+        let mut body = request_body();
+        body["context"] = json!({ "padding": "x".repeat(2048) });
+        let (status, _) = post(&app, "/authorization", body).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let small = post(&app, "/authorization", request_body()).await;
+        assert_eq!(small.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_declared_oversized_body_is_rejected_with_413() {
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            config_with_max_body_bytes(1024),
+        );
+
+        let mut body = request_body();
+        body["context"] = json!({ "padding": "x".repeat(2048) });
+        let body = body.to_string();
+        // rejected from the content-length header alone, before the body is read
+        let response = app
+            .oneshot(
+                Request::post("/authorization")
+                    .header("content-type", "application/json")
+                    .header("content-length", body.len())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_content_type_is_rejected_with_415() {
+        let app = router(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+        );
+
+        for content_type in [Some("text/plain"), None] {
+            let mut request = Request::post("/authorization");
+            if let Some(content_type) = content_type {
+                request = request.header("content-type", content_type);
+            }
+            let response = app
+                .clone()
+                .oneshot(
+                    request
+                        .body(Body::from(request_body().to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["title"], "unsupported_media_type");
+            assert_eq!(body["code"], 415);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_rejected_with_400() {
+        let app = router(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+        );
+
+        let response = app
+            .oneshot(
+                Request::post("/authorization")
+                    .header("content-type", "application/json")
+                    .body(Body::from("{\"entity_id\": "))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["title"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn test_deeply_nested_context_is_rejected() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router(backend.clone(), None);
+
+        let mut context = json!("leaf");
+        for _ in 0..=super::MAX_CONTEXT_DEPTH {
+            context = json!({ "nested": context });
+        }
+        let mut body = request_body();
+        body["context"] = context;
+        let (status, response) = post(&app, "/authorization", body).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["title"], "bad_request");
+        assert_eq!(backend.lookups(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_record_is_not_found() {
+        let expired = record_for("credential", true)
+            .with_expires_at(Some(chrono::Utc::now() - Duration::from_secs(1)));
+        let app = router(Arc::new(LocalStorage::with_records(vec![expired])), None);
+
+        let (status, _) = post(&app, "/authorization", request_body()).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_query_outside_allowlist_is_rejected_before_storage() {
+        let backend = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let config = TrsutRegistryConfig {
+            server_config: ServerConfig {
+                trqp_query_allowlist: QueryAllowlist {
+                    actions: Some(["issue".to_string()].into()),
+                    resources: None,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let app = router_with_config(backend.clone(), None, config);
+
+        let mut disallowed = request_body();
+        disallowed["action"] = json!("revoke");
+        let (status, body) = post(&app, "/authorization", disallowed).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["title"], "bad_request");
+        assert_eq!(backend.lookups(), 0);
+
+        let (status, body) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["authorized"], true);
+        assert_eq!(backend.lookups(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_default_context_is_merged_under_record_context() {
+        let plain = record_for("credential", true);
+        let own = record_builder("did:example:other", AUTHORITY, "issue", "credential")
+            .context(Context::new(json!({"framework": {"version": "2.0"}})))
+            .build()
+            .unwrap();
+        let config = TrsutRegistryConfig {
+            server_config: ServerConfig {
+                trqp_default_context: Some(Context::new(json!({
+                    "framework": {"name": "Gov", "version": "1.0"}
+                }))),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![plain, own])),
+            None,
+            config,
+        );
+        let mut body = request_body();
+        body.as_object_mut().unwrap().remove("context");
+
+        let (status, response) = post(&app, "/authorization", body.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response["context"],
+            json!({"framework": {"name": "Gov", "version": "1.0"}})
+        );
+
+        body["entity_id"] = json!("did:example:other");
+        let (status, response) = post(&app, "/recognition", body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response["context"],
+            json!({"framework": {"name": "Gov", "version": "2.0"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_allowlist_strips_disallowed_fields() {
+        let stored = record_for("credential", true).merge_contexts(Context::new(json!({
+            "framework": {"name": "Healthcare", "internal_owner": "ops"},
+            "internal_notes": "do not share"
+        })));
+        let config = TrsutRegistryConfig {
+            server_config: ServerConfig {
+                trqp_context_allowlist: vec!["/framework/name".to_string(), "/purpose".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![stored])),
+            None,
+            config,
+        );
+
+        for uri in ["/authorization", "/recognition"] {
+            let (status, body) = post(&app, uri, request_body()).await;
+
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(
+                body["context"],
+                json!({"framework": {"name": "Healthcare"}, "purpose": "test"})
+            );
+        }
+    }
+
+    async fn content_encoding(app: Router, context: Value) -> Option<String> {
+        let mut body = request_body();
+        body["context"] = context;
+        let response = app
+            .oneshot(
+                Request::post("/authorization")
+                    .header("content-type", "application/json")
+                    .header("accept-encoding", "gzip")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response
+            .headers()
+            .get("content-encoding")
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    fn config_with_compression() -> TrsutRegistryConfig {
+        TrsutRegistryConfig {
+            server_config: ServerConfig {
+                enable_compression: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_response_is_gzipped() {
+        let repository = Arc::new(LocalStorage::with_records(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router_with_config(repository, None, config_with_compression());
+
+        let encoding = content_encoding(app, json!({ "notes": "x".repeat(4096) })).await;
+
+        assert_eq!(encoding.as_deref(), Some("gzip"));
+    }
+
+    #[tokio::test]
+    async fn test_small_response_is_not_compressed() {
+        let repository = Arc::new(LocalStorage::with_records(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router_with_config(repository, None, config_with_compression());
+
+        let encoding = content_encoding(app, json!({ "purpose": "test" })).await;
+
+        assert_eq!(encoding, None);
+    }
+
+    #[tokio::test]
+    async fn test_compression_disabled_by_default() {
+        let repository = Arc::new(LocalStorage::with_records(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router(repository, None);
+
+        let encoding = content_encoding(app, json!({ "notes": "x".repeat(4096) })).await;
+
+        assert_eq!(encoding, None);
+    }
+
+    /// Denies unless the caller asserts `approved: true` in the request context.
+    struct ApprovalRequiredPolicy;
+
+    impl EvaluationPolicy for ApprovalRequiredPolicy {
+        fn evaluate(&self, record: &TrustRecord, context: Option<&Context>) -> Evaluation {
+            let approved = context
+                .and_then(|c| c.as_value().get("approved"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            Evaluation {
+                authorized: approved && record.is_authorized(),
+                recognized: approved && record.is_recognized(),
+                annotations: Some(json!({ "policy": "approval-required" })),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_policy_denies_unless_context_flag_is_set() {
+        let app = router_with_policy(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            TrsutRegistryConfig::default(),
+            Arc::new(ApprovalRequiredPolicy),
+        );
+
+        let (status, denied) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(denied["authorized"], false);
+        assert_eq!(denied["context"]["policy"], "approval-required");
+
+        let mut body = request_body();
+        body["context"]["approved"] = json!(true);
+        let (status, allowed) = post(&app, "/authorization", body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(allowed["authorized"], true);
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedEvents(Arc<std::sync::Mutex<Vec<HashMap<String, String>>>>);
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(
+                field.name().to_string(),
+                format!("{value:?}").replace('"', ""),
+            );
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CapturedEvents {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if event.metadata().target() == super::TRQP_QUERY_LOG_TARGET {
+                let mut fields = HashMap::new();
+                event.record(&mut FieldVisitor(&mut fields));
+                self.0.lock().unwrap().push(fields);
+            }
+        }
+    }
+
+    fn logging_config(log_trqp_queries: bool) -> TrsutRegistryConfig {
+        TrsutRegistryConfig {
+            server_config: ServerConfig {
+                log_trqp_queries,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_outcome_event_is_emitted_per_query() {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            logging_config(true),
+        );
+
+        post(&app, "/authorization", request_body()).await;
+        let mut missing = request_body();
+        missing["entity_id"] = json!("did:example:unknown");
+        post(&app, "/recognition", missing).await;
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["query"], "authorization");
+        assert_eq!(events[0]["entity_id"], "did:example:entity");
+        assert_eq!(events[0]["authority_id"], "did:example:authority");
+        assert_eq!(events[0]["action"], "issue");
+        assert_eq!(events[0]["resource"], "credential");
+        assert_eq!(events[0]["found"], "true");
+        assert_eq!(events[0]["decision"], "true");
+        assert!(events[0].contains_key("latency_us"));
+        assert_eq!(events[1]["query"], "recognition");
+        assert_eq!(events[1]["entity_id"], "did:example:unknown");
+        assert_eq!(events[1]["found"], "false");
+        assert_eq!(events[1]["decision"], "false");
+    }
+
+    #[tokio::test]
+    async fn test_successes_are_sampled_but_failures_always_logged() {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+        let mut config = logging_config(true);
+        config.server_config.log_trqp_sample_rate = 3;
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            config,
+        );
+        let mut missing = request_body();
+        missing["entity_id"] = json!("did:example:unknown");
+
+        for _ in 0..6 {
+            post(&app, "/authorization", request_body()).await;
+            post(&app, "/authorization", missing.clone()).await;
+        }
+
+        let events = events.0.lock().unwrap();
+        let found: Vec<_> = events.iter().filter(|e| e["found"] == "true").collect();
+        let not_found: Vec<_> = events.iter().filter(|e| e["found"] == "false").collect();
+        assert_eq!(found.len(), 2, "one in three successful queries is logged");
+        assert!(found.iter().all(|e| e["sample_rate"] == "3"));
+        assert_eq!(
+            not_found.len(),
+            6,
+            "not-found queries are never sampled out"
+        );
+        assert!(not_found.iter().all(|e| e["sample_rate"] == "1"));
+    }
+
+    #[test]
+    fn test_sampler_logs_first_of_every_rate_successes() {
+        let sampler = QueryLogSampler::new(4);
+        let shared = sampler.clone();
+
+        let sampled: Vec<bool> = (0..8)
+            .map(|i| {
+                if i % 2 == 0 {
+                    sampler.sample()
+                } else {
+                    shared.sample()
+                }
+            })
+            .collect();
+
+        assert_eq!(
+            sampled,
+            [true, false, false, false, true, false, false, false]
+        );
+        assert!(QueryLogSampler::new(0).sample() && QueryLogSampler::new(0).sample());
+    }
+
+    #[tokio::test]
+    async fn test_query_outcome_is_not_logged_by_default() {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+        let app = router_with_config(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            logging_config(false),
+        );
+
+        post(&app, "/authorization", request_body()).await;
+
+        assert!(events.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_trace_id_is_returned_in_error_body() {
+        let app = router(Arc::new(LocalStorage::with_records(vec![])), None);
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+        let response = app
+            .oneshot(
+                Request::post("/authorization")
+                    .header("content-type", "application/json")
+                    .header("traceparent", traceparent)
+                    .body(Body::from(request_body().to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["traceparent"], traceparent);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[tokio::test]
+    async fn test_trace_id_is_generated_without_traceparent() {
+        let app = router(Arc::new(LocalStorage::with_records(vec![])), None);
+
+        let (status, body) = post(&app, "/authorization", request_body()).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["trace_id"].as_str().unwrap().len(), 32);
+    }
+
+    struct StaticResolver {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl DidResolver for StaticResolver {
+        async fn resolve(&self, did: &str) -> Result<Value, DidResolutionError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({
+                "id": did,
+                "verificationMethod": [{
+                    "id": format!("{did}#key-0"),
+                    "publicKeyJwk": { "kty": "OKP", "crv": "Ed25519", "x": "public", "d": "secret" }
+                }]
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authority_did_document_is_embedded_only_when_requested() {
+        let resolver = Arc::new(StaticResolver {
+            calls: AtomicUsize::new(0),
+        });
+        let mut shared_data = shared_data(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+            TrsutRegistryConfig::default(),
+            Arc::new(DefaultEvaluationPolicy),
+        );
+        shared_data.did_resolver = Some(Arc::new(CachingDidResolver::new(
+            resolver.clone(),
+            Duration::from_secs(60),
+            10,
+        )));
+        let app = application_routes("", shared_data);
+
+        let (status, plain) = post(&app, "/authorization", request_body()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(plain.get("authority_did_document").is_none());
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+
+        let mut body = request_body();
+        body["include_authority_did_document"] = json!(true);
+        let (status, first) = post(&app, "/authorization", body.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let document = &first["authority_did_document"];
+        assert_eq!(document["id"], "did:example:authority");
+        assert_eq!(
+            document["verificationMethod"][0]["publicKeyJwk"],
+            json!({ "kty": "OKP", "crv": "Ed25519", "x": "public" })
+        );
+
+        let (_, second) = post(&app, "/recognition", body).await;
+        assert_eq!(second["authority_did_document"], *document);
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_authority_did_document_without_resolver_is_rejected() {
+        let app = router(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+        );
+        let mut body = request_body();
+        body["include_authority_did_document"] = json!(true);
+
+        let (status, _) = post(&app, "/authorization", body).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_storage_timeout_returns_504() {
+        let backend = Arc::new(SlowRepository::hanging(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router(
+            Arc::new(TimeoutRepository::new(backend, Duration::from_millis(20))),
+            None,
+        );
+
+        let (status, body) = post(&app, "/authorization", request_body()).await;
+
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(body["title"], "gateway_timeout");
+        assert_eq!(body["code"], 504);
+    }
+
+    #[tokio::test]
+    async fn test_composite_query_returns_result_per_resource() {
+        let app = router(
+            Arc::new(LocalStorage::with_records(vec![
+                record_for("credential", true),
+                record_for("badge", false),
+            ])),
+            None,
+        );
+        let mut body = request_body();
+        body["resource"] = json!(["credential", "badge", "unknown"]);
+
+        let (status, response) = post(&app, "/authorization", body).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = &response["results"];
+        assert_eq!(results["credential"]["authorized"], true);
+        assert_eq!(results["badge"]["authorized"], false);
+        assert_eq!(results["unknown"]["code"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_composite_query_rejects_several_actions_and_resources() {
+        let app = router(
+            Arc::new(LocalStorage::with_records(vec![record_for(
+                "credential",
+                true,
+            )])),
+            None,
+        );
+        let mut body = request_body();
+        body["action"] = json!(["issue", "verify"]);
+        body["resource"] = json!(["credential", "badge"]);
+
+        let (status, _) = post(&app, "/recognition", body).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_composite_query_rejects_duplicate_values() {
+        let repository = Arc::new(CountingRepository::new(vec![record_for(
+            "credential",
+            true,
+        )]));
+        let app = router(repository.clone(), None);
+        let mut body = request_body();
+        body["resource"] = json!(["credential", "credential"]);
+
+        let (status, _) = post(&app, "/authorization", body).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(repository.lookups(), 0);
+    }
 }
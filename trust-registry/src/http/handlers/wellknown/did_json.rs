@@ -7,6 +7,15 @@ pub async fn handle_wellknown_did_json<R>(State(state): State<SharedData<R>>) ->
 where
     R: TrustRecordRepository + Send + ?Sized + 'static,
 {
+    if let Some(webvh_log) = &state.webvh_log {
+        match webvh_log.current_document() {
+            Ok(document) => return (StatusCode::OK, Json(document)),
+            Err(e) => {
+                warn!("Failed to read the did:webvh log, serving the static DID document: {e}")
+            }
+        }
+    }
+
     let did_doc = state.config.didcomm_config.did_document.clone();
 
     let did_doc_value = serde_json::from_str::<serde_json::Value>(&did_doc).unwrap_or_else(|e| {
@@ -17,3 +26,193 @@ where
 
     (StatusCode::OK, Json(did_doc_value))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use tempfile::NamedTempFile;
+    use tower::ServiceExt;
+
+    use affinidi_tdk::secrets_resolver::secrets::Secret;
+
+    use crate::{
+        SharedData,
+        configs::{DidcommConfig, ProfileConfig, TrsutRegistryConfig},
+        didcomm::{did_document::build_did_document, webvh_log::WebvhLog},
+        http::application_routes,
+        policy::DefaultEvaluationPolicy,
+        storage::{adapters::local_storage::LocalStorage, repository::TrustRecordRepository},
+    };
+
+    fn router(webvh_log: Option<Arc<WebvhLog>>) -> Router {
+        router_with(
+            DidcommConfig {
+                did_document: json!({ "id": "did:web:static.example.com" }).to_string(),
+                ..Default::default()
+            },
+            webvh_log,
+        )
+    }
+
+    fn router_with(didcomm_config: DidcommConfig, webvh_log: Option<Arc<WebvhLog>>) -> Router {
+        let config = TrsutRegistryConfig {
+            didcomm_config,
+            ..Default::default()
+        };
+        let shared_data = SharedData {
+            config: Arc::new(config),
+            service_start_timestamp: chrono::Utc::now(),
+            repository: Arc::new(LocalStorage::with_records(vec![]))
+                as Arc<dyn TrustRecordRepository>,
+            decision_cache: None,
+            read_only: Default::default(),
+            evaluation_policy: Arc::new(DefaultEvaluationPolicy),
+            did_resolver: None,
+            webvh_log,
+            response_signer: None,
+            query_log_sampler: Default::default(),
+        };
+        application_routes("", shared_data)
+    }
+
+    async fn get_did_json(router: &Router) -> Value {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/.well-known/did.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn log_entry(version: u32) -> String {
+        json!({
+            "versionId": format!("{version}-Qm"),
+            "versionTime": "2025-01-01T00:00:00Z",
+            "parameters": {},
+            "state": { "id": "did:webvh:Qm:example.com", "version": version },
+            "proof": []
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_static_document_without_webvh_log() {
+        let document = get_did_json(&router(None)).await;
+
+        assert_eq!(document["id"], "did:web:static.example.com");
+    }
+
+    #[tokio::test]
+    async fn test_webvh_document_follows_appended_log_entries() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "{}", log_entry(1)).unwrap();
+        let app = router(Some(Arc::new(WebvhLog::new(file.path()))));
+        assert_eq!(get_did_json(&app).await["version"], 1);
+
+        writeln!(file, "{}", log_entry(2)).unwrap();
+
+        let document = get_did_json(&app).await;
+        assert_eq!(document["id"], "did:webvh:Qm:example.com");
+        assert_eq!(document["version"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_webvh_log_falls_back_to_static_document() {
+        let app = router(Some(Arc::new(WebvhLog::new("/nonexistent/did.jsonl"))));
+
+        assert_eq!(get_did_json(&app).await["id"], "did:web:static.example.com");
+    }
+
+    fn contains_private_field(value: &Value) -> bool {
+        match value {
+            Value::Object(map) => map.iter().any(|(key, value)| {
+                key == "d"
+                    || key.to_lowercase().contains("private")
+                    || contains_private_field(value)
+            }),
+            Value::Array(values) => values.iter().any(contains_private_field),
+            _ => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_document_derived_from_profile_has_only_public_keys() {
+        let secrets: Vec<Secret> = serde_json::from_value(json!([
+            {
+                "id": "did:web:registry.example.com#key-0",
+                "type": "JsonWebKey2020",
+                "privateKeyJwk": {
+                    "crv": "P-256",
+                    // not real, just copy of x
+                    "d": "ctKLNB9cXUO3yD-jMCaRi680RmHOFuS30nVogmEhkx4",
+                    "kty": "EC",
+                    "x": "ctKLNB9cXUO3yD-jMCaRi680RmHOFuS30nVogmEhkx4",
+                    "y": "1GDFw4zkTPdVWwqxRhSnEVCdkZyfmViJR8Nq5ad2V9w"
+                }
+            },
+            {
+                "id": "did:web:registry.example.com#key-1",
+                "type": "JsonWebKey2020",
+                "privateKeyJwk": {
+                    "crv": "Ed25519",
+                    // not real, just copy of x
+                    "d": "DfRiO5mCASvWyPxr20GQEfzOmFFh50spyP7KHMjvGQo",
+                    "kty": "OKP",
+                    "x": "DfRiO5mCASvWyPxr20GQEfzOmFFh50spyP7KHMjvGQo"
+                }
+            }
+        ]))
+        .unwrap();
+        let profile = ProfileConfig {
+            did: "did:web:registry.example.com".to_string(),
+            alias: "registry".to_string(),
+            secrets,
+            mediator_did: None,
+        };
+        // what the config builds when DID_DOCUMENT is not set
+        let didcomm_config = DidcommConfig {
+            did_document: build_did_document(&profile, "did:web:mediator.example.com"),
+            profile_config: profile,
+            ..Default::default()
+        };
+
+        let document = get_did_json(&router_with(didcomm_config, None)).await;
+
+        assert_eq!(document["id"], "did:web:registry.example.com");
+        let methods = document["verificationMethod"].as_array().unwrap();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0]["id"], "did:web:registry.example.com#key-0");
+        assert_eq!(
+            methods[0]["publicKeyJwk"],
+            json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": "ctKLNB9cXUO3yD-jMCaRi680RmHOFuS30nVogmEhkx4",
+                "y": "1GDFw4zkTPdVWwqxRhSnEVCdkZyfmViJR8Nq5ad2V9w"
+            })
+        );
+        assert_eq!(methods[1]["id"], "did:web:registry.example.com#key-1");
+        assert_eq!(
+            methods[1]["publicKeyJwk"],
+            json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": "DfRiO5mCASvWyPxr20GQEfzOmFFh50spyP7KHMjvGQo"
+            })
+        );
+        assert!(!contains_private_field(&document));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod error;
 pub mod handlers;
+pub mod response_signing;
+pub mod tls;
+pub mod trace_context;
 pub use handlers::application_routes;
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use affinidi_tdk::secrets_resolver::{jwk::Params, secrets::SecretMaterial};
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header::HeaderName},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use ed25519_dalek::{Signer, SigningKey};
+use tracing::error;
+
+use crate::{configs::ProfileConfig, didcomm::did_document::verification_method_id};
+
+/// Detached JWS over the response body, see [`ResponseSigner::sign`].
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-jws-signature");
+
+/// Signs TRQP responses with the registry's Ed25519 key when `SIGN_RESPONSES` is set, so
+/// verifiers can check a response came from the registry with the key in its DID document.
+pub struct ResponseSigner {
+    key_id: String,
+    signing_key: SigningKey,
+}
+
+impl ResponseSigner {
+    /// Uses the first Ed25519 secret of the profile, the key the DID document lists
+    /// under `assertionMethod`.
+    pub fn from_profile(profile_config: &ProfileConfig) -> Result<Self, String> {
+        let (index, secret, private_key) = profile_config
+            .secrets
+            .iter()
+            .enumerate()
+            .find_map(|(index, secret)| match &secret.secret_material {
+                SecretMaterial::JWK(jwk) => match &jwk.params {
+                    Params::OKP(params) if params.curve == "Ed25519" => {
+                        Some((index, secret, params.d.clone()?))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .ok_or_else(|| {
+                format!(
+                    "SIGN_RESPONSES needs an Ed25519 secret in the profile of {}",
+                    profile_config.did
+                )
+            })?;
+
+        let private_key: [u8; 32] = URL_SAFE_NO_PAD
+            .decode(private_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Secret {} is not a valid Ed25519 key", secret.id))?;
+
+        Ok(Self {
+            key_id: verification_method_id(profile_config, secret, index),
+            signing_key: SigningKey::from_bytes(&private_key),
+        })
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Compact JWS with the payload left out, `<header>..<signature>` (RFC 7515 appendix F).
+    /// Verifiers put the base64url encoded body back between the dots.
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let header = serde_json::json!({ "alg": "EdDSA", "kid": self.key_id });
+        let header = URL_SAFE_NO_PAD.encode(header.to_string());
+        let signing_input = format!("{header}.{}", URL_SAFE_NO_PAD.encode(payload));
+        let signature = self.signing_key.sign(signing_input.as_bytes());
+        format!("{header}..{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+}
+
+/// Adds [`SIGNATURE_HEADER`] to every response of the routes it wraps.
+pub async fn sign_response(
+    State(signer): State<Arc<ResponseSigner>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = next.run(request).await.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read the response body to sign: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let signature = signer.sign(&bytes);
+    parts.headers.insert(
+        SIGNATURE_HEADER,
+        HeaderValue::from_str(&signature).expect("JWS is base64url and dots"),
+    );
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::didcomm::did_document::build_did_document;
+    use affinidi_tdk::secrets_resolver::secrets::Secret;
+    use axum::{Router, middleware, routing::post};
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    fn profile(signing_key: &SigningKey) -> ProfileConfig {
+        let secrets: Vec<Secret> = serde_json::from_value(json!([
+            {
+                "id": "did:web:registry.example.com#key-0",
+                "type": "JsonWebKey2020",
+                "privateKeyJwk": {
+                    "crv": "X25519",
+                    // not real, just copy of x
+                    "d": "8G7Ygt6Kqcq0h7wwIr4gTX-ke_0MbpqgYGXVymVCm0E",
+                    "kty": "OKP",
+                    "x": "8G7Ygt6Kqcq0h7wwIr4gTX-ke_0MbpqgYGXVymVCm0E"
+                }
+            },
+            {
+                "id": "did:web:registry.example.com#key-1",
+                "type": "JsonWebKey2020",
+                "privateKeyJwk": {
+                    "crv": "Ed25519",
+                    "d": URL_SAFE_NO_PAD.encode(signing_key.to_bytes()),
+                    "kty": "OKP",
+                    "x": URL_SAFE_NO_PAD.encode(signing_key.verifying_key().to_bytes())
+                }
+            }
+        ]))
+        .unwrap();
+        ProfileConfig {
+            did: "did:web:registry.example.com".to_string(),
+            alias: "registry".to_string(),
+            secrets,
+            mediator_did: None,
+        }
+    }
+
+    /// The verifying key published in the DID document under `kid`.
+    fn published_key(document: &Value, kid: &str) -> VerifyingKey {
+        let method = document["verificationMethod"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|method| method["id"] == kid)
+            .unwrap();
+        let x = URL_SAFE_NO_PAD
+            .decode(method["publicKeyJwk"]["x"].as_str().unwrap())
+            .unwrap();
+        VerifyingKey::from_bytes(&x.try_into().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_signature_verifies_against_published_key() {
+        let profile = profile(&SigningKey::from_bytes(&[7; 32]));
+        let signer = Arc::new(ResponseSigner::from_profile(&profile).unwrap());
+        let app = Router::new()
+            .route(
+                "/authorization",
+                post(|| async { axum::Json(json!({ "authorized": true })) }),
+            )
+            .layer(middleware::from_fn_with_state(signer, sign_response));
+
+        let response = app
+            .oneshot(Request::post("/authorization").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let jws = response.headers()[SIGNATURE_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({ "authorized": true })
+        );
+
+        let (header, signature) = jws.split_once("..").unwrap();
+        let header: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap();
+        assert_eq!(header["alg"], "EdDSA");
+        assert_eq!(header["kid"], "did:web:registry.example.com#key-1");
+
+        let document: Value = serde_json::from_str(&build_did_document(
+            &profile,
+            "did:web:mediator.example.com",
+        ))
+        .unwrap();
+        let key = published_key(&document, header["kid"].as_str().unwrap());
+        let signing_input = format!(
+            "{}.{}",
+            jws.split_once('.').unwrap().0,
+            URL_SAFE_NO_PAD.encode(&body)
+        );
+        let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+        assert!(key.verify(signing_input.as_bytes(), &signature).is_ok());
+        assert!(
+            key.verify(b"tampered.payload", &signature).is_err(),
+            "signature must be bound to the body"
+        );
+    }
+
+    #[test]
+    fn test_profile_without_ed25519_key_cannot_sign() {
+        let mut profile = profile(&SigningKey::from_bytes(&[7; 32]));
+        profile.secrets.truncate(1);
+
+        let error = ResponseSigner::from_profile(&profile).err().unwrap();
+
+        assert_eq!(
+            error,
+            "SIGN_RESPONSES needs an Ed25519 secret in the profile of did:web:registry.example.com"
+        );
+    }
+}
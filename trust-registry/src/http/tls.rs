@@ -0,0 +1,92 @@
+use std::{io, net::TcpListener};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+
+/// Loads the PEM certificate chain and private key served by the HTTP server.
+pub async fn load_tls_config(cert_path: &str, key_path: &str) -> Result<RustlsConfig, String> {
+    // other dependencies enable ring as well, so rustls can't pick a provider on its own
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+    RustlsConfig::from_pem_file(cert_path, key_path)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to load TLS certificate {cert_path} and key {key_path}: {e}. \
+                 Check TLS_CERT_PATH and TLS_KEY_PATH point to PEM files."
+            )
+        })
+}
+
+/// Serves `router` over HTTPS when `tls` is set, plain HTTP otherwise.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    tls: Option<RustlsConfig>,
+) -> io::Result<()> {
+    match tls {
+        Some(tls) => {
+            listener.set_nonblocking(true)?;
+            axum_server::from_tcp_rustls(listener, tls)
+                .serve(router.into_make_service())
+                .await
+        }
+        None => {
+            listener.set_nonblocking(true)?;
+            axum::serve(tokio::net::TcpListener::from_std(listener)?, router).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::handlers::health::{Readiness, health_routes},
+        storage::adapters::local_storage::LocalStorage,
+    };
+    use std::sync::Arc;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/tls/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[tokio::test]
+    async fn test_load_tls_config_reports_missing_files() {
+        let error = load_tls_config("/nonexistent/cert.pem", &fixture("key.pem"))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(error.contains("/nonexistent/cert.pem"));
+        assert!(error.contains("TLS_CERT_PATH"));
+    }
+
+    #[tokio::test]
+    async fn test_serves_health_over_tls() {
+        let tls = load_tls_config(&fixture("cert.pem"), &fixture("key.pem"))
+            .await
+            .unwrap();
+        let readiness = Arc::new(Readiness::new(
+            Arc::new(LocalStorage::new()),
+            false,
+            std::time::Duration::ZERO,
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener, health_routes(readiness), Some(tls)));
+
+        let cert = std::fs::read(fixture("cert.pem")).unwrap();
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(&cert).unwrap())
+            .build()
+            .unwrap();
+        let response = client
+            .get(format!("https://localhost:{port}/health"))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+}
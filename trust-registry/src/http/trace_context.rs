@@ -0,0 +1,137 @@
+use axum::{
+    extract::Request,
+    http::{HeaderValue, header::HeaderName},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{Instrument, info_span};
+use uuid::Uuid;
+
+pub const TRACEPARENT_HEADER: HeaderName = HeaderName::from_static("traceparent");
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// W3C Trace Context of a request, see <https://www.w3.org/TR/trace-context/>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_id: String,
+    pub flags: String,
+}
+
+impl TraceContext {
+    /// Parses a version `00` `traceparent` header. All-zero ids are invalid per the spec.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, parent_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let valid = version == "00"
+            && parts.next().is_none()
+            && is_lower_hex(trace_id, 32)
+            && is_lower_hex(parent_id, 16)
+            && is_lower_hex(flags, 2);
+        valid.then(|| Self {
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: flags.to_string(),
+        })
+    }
+
+    /// Starts a new trace for requests without a valid `traceparent`.
+    pub fn generate() -> Self {
+        Self {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            parent_id: Uuid::new_v4().simple().to_string()[..16].to_string(),
+            flags: "01".to_string(),
+        }
+    }
+
+    pub fn to_header(&self) -> String {
+        format!("00-{}-{}-{}", self.trace_id, self.parent_id, self.flags)
+    }
+}
+
+fn is_lower_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        && value.chars().any(|c| c != '0')
+}
+
+/// Trace id of the request being handled, if it runs inside [`propagate_trace_context`].
+pub fn current_trace_id() -> Option<String> {
+    TRACE_ID.try_with(String::clone).ok()
+}
+
+/// Runs the request in a span carrying its trace id and echoes `traceparent` on the response.
+pub async fn propagate_trace_context(mut request: Request, next: Next) -> Response {
+    let trace_context = request
+        .headers()
+        .get(&TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::parse)
+        .unwrap_or_else(TraceContext::generate);
+    let span = info_span!(
+        "http_request",
+        trace_id = %trace_context.trace_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    request.extensions_mut().insert(trace_context.clone());
+
+    let mut response = TRACE_ID
+        .scope(
+            trace_context.trace_id.clone(),
+            next.run(request).instrument(span),
+        )
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&trace_context.to_header()) {
+        response.headers_mut().insert(TRACEPARENT_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_traceparent() {
+        let context =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_id, "00f067aa0ba902b7");
+        assert_eq!(
+            context.to_header(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_traceparent() {
+        assert!(
+            TraceContext::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-01").is_none());
+    }
+
+    #[test]
+    fn test_generated_context_is_valid() {
+        let context = TraceContext::generate();
+
+        assert_eq!(TraceContext::parse(&context.to_header()), Some(context));
+    }
+}
@@ -1,14 +1,51 @@
-use crate::storage::repository::TrustRecordRepository;
+use crate::did_resolution::DidResolver;
+use crate::didcomm::webvh_log::WebvhLog;
+use crate::http::{handlers::trqp::QueryLogSampler, response_signing::ResponseSigner};
+use crate::policy::EvaluationPolicy;
+use crate::storage::{decision_cache::DecisionCache, repository::TrustRecordRepository};
 use chrono::{DateTime, Utc};
-use std::{fmt, sync::Arc};
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 pub mod audit;
+pub mod client;
 pub mod configs;
+pub mod did_resolution;
 pub mod didcomm;
 pub mod domain;
 pub mod http;
+pub mod policy;
 pub mod server;
 pub mod storage;
+pub mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod util;
+pub mod webhook;
+
+/// Runtime switch that blocks admin mutations while keeping reads and TRQP queries available.
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOnlyMode(Arc<AtomicBool>);
+
+impl ReadOnlyMode {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::SeqCst);
+    }
+}
 
 pub struct SharedData<R>
 where
@@ -17,6 +54,17 @@ where
     pub config: Arc<configs::TrsutRegistryConfig>,
     pub service_start_timestamp: DateTime<Utc>,
     pub repository: Arc<R>,
+    pub decision_cache: Option<Arc<DecisionCache>>,
+    pub read_only: ReadOnlyMode,
+    pub evaluation_policy: Arc<dyn EvaluationPolicy>,
+    /// Cached DID resolution for verifying authority proofs. `None` when no resolver is set.
+    pub did_resolver: Option<Arc<dyn DidResolver>>,
+    /// Source of the well-known DID document for did:webvh. `None` serves the static document.
+    pub webvh_log: Option<Arc<WebvhLog>>,
+    /// Signs TRQP responses when `SIGN_RESPONSES` is set.
+    pub response_signer: Option<Arc<ResponseSigner>>,
+    /// Picks the successful TRQP queries logged under `LOG_TRQP_QUERIES`.
+    pub query_log_sampler: QueryLogSampler,
 }
 
 impl<R: TrustRecordRepository> fmt::Debug for SharedData<R> {
@@ -24,6 +72,12 @@ impl<R: TrustRecordRepository> fmt::Debug for SharedData<R> {
         f.debug_struct("SharedData")
             .field("config", &self.config)
             .field("service_start_timestamp", &self.service_start_timestamp)
+            .field("decision_cache", &self.decision_cache.is_some())
+            .field("read_only", &self.read_only.is_enabled())
+            .field("did_resolver", &self.did_resolver.is_some())
+            .field("webvh_log", &self.webvh_log.is_some())
+            .field("response_signer", &self.response_signer.is_some())
+            .field("query_log_sampler", &self.query_log_sampler.rate())
             .finish()
     }
 }
@@ -37,6 +91,13 @@ where
             config: self.config.clone(),
             service_start_timestamp: self.service_start_timestamp,
             repository: Arc::clone(&self.repository),
+            decision_cache: self.decision_cache.clone(),
+            read_only: self.read_only.clone(),
+            evaluation_policy: Arc::clone(&self.evaluation_policy),
+            did_resolver: self.did_resolver.clone(),
+            webvh_log: self.webvh_log.clone(),
+            response_signer: self.response_signer.clone(),
+            query_log_sampler: self.query_log_sampler.clone(),
         }
     }
 }
@@ -0,0 +1,169 @@
+use serde_json::{Map, Value, json};
+
+use crate::domain::{Context, ContextMergeMode, TrustRecord};
+
+/// Context key of the map recording where each top-level key of a merged context came
+/// from, see `CONTEXT_PROVENANCE`.
+pub const CONTEXT_PROVENANCE_KEY: &str = "_provenance";
+
+/// Final determination for a TRQP query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub authorized: bool,
+    pub recognized: bool,
+    /// Merged into the response context after the request context.
+    pub annotations: Option<Value>,
+}
+
+/// Decides the TRQP answer for a stored record, so deployments can apply rules beyond
+/// the stored booleans. Runs after expired records have been rejected.
+pub trait EvaluationPolicy: Send + Sync {
+    fn evaluate(&self, record: &TrustRecord, context: Option<&Context>) -> Evaluation;
+}
+
+/// Answers with the stored `authorized` and `recognized` values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultEvaluationPolicy;
+
+impl EvaluationPolicy for DefaultEvaluationPolicy {
+    fn evaluate(&self, record: &TrustRecord, _context: Option<&Context>) -> Evaluation {
+        Evaluation {
+            authorized: record.is_authorized(),
+            recognized: record.is_recognized(),
+            annotations: None,
+        }
+    }
+}
+
+/// Records `source` for every top-level key of `context`, replacing earlier sources.
+fn record_sources(sources: &mut Map<String, Value>, context: &Context, source: &str) {
+    if let Value::Object(map) = context.as_value() {
+        for key in map.keys() {
+            sources.insert(key.clone(), Value::String(source.to_string()));
+        }
+    }
+}
+
+/// Applies the policy's determination and annotations to the record.
+/// The request context is merged before the annotations, so annotations win on conflicts.
+///
+/// With `provenance`, the merged context gets a [`CONTEXT_PROVENANCE_KEY`] map naming the
+/// source of each top-level key: `record`, `request` or `policy`. A key set by several
+/// sources is attributed to the last one merged, whose value won.
+pub fn apply(
+    policy: &dyn EvaluationPolicy,
+    record: TrustRecord,
+    context: Option<Context>,
+    merge_mode: ContextMergeMode,
+    provenance: bool,
+) -> TrustRecord {
+    let evaluation = policy.evaluate(&record, context.as_ref());
+    let mut record = record
+        .with_authorized(evaluation.authorized)
+        .with_recognized(evaluation.recognized);
+    let mut sources = Map::new();
+    record_sources(&mut sources, record.context(), "record");
+    if let Some(context) = context {
+        record_sources(&mut sources, &context, "request");
+        record = record.merge_contexts_with(context, merge_mode);
+    }
+    if let Some(annotations) = evaluation.annotations {
+        let annotations = Context::new(annotations);
+        record_sources(&mut sources, &annotations, "policy");
+        record = record.merge_contexts_with(annotations, merge_mode);
+    }
+    if provenance && record.context().as_value().is_object() {
+        let annotated = json!({ CONTEXT_PROVENANCE_KEY: sources });
+        record = record.merge_contexts_with(Context::new(annotated), ContextMergeMode::Deep);
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::test_support::record_builder;
+    use serde_json::json;
+
+    fn record() -> TrustRecord {
+        record_builder("entity-1", "authority-1", "action-1", "resource-1")
+            .authorized(false)
+            .context(Context::new(json!({"stored": true})))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_policy_keeps_record_values() {
+        let record = apply(
+            &DefaultEvaluationPolicy,
+            record(),
+            Some(Context::new(json!({"purpose": "test"}))),
+            ContextMergeMode::Deep,
+            false,
+        );
+
+        assert!(!record.is_authorized());
+        assert!(record.is_recognized());
+        assert_eq!(
+            record.context().as_value(),
+            &json!({"stored": true, "purpose": "test"})
+        );
+    }
+
+    struct AnnotatingPolicy;
+
+    impl EvaluationPolicy for AnnotatingPolicy {
+        fn evaluate(&self, _record: &TrustRecord, _context: Option<&Context>) -> Evaluation {
+            Evaluation {
+                authorized: true,
+                recognized: false,
+                annotations: Some(json!({"purpose": "annotated", "policy": "custom"})),
+            }
+        }
+    }
+
+    #[test]
+    fn test_annotations_are_merged_after_request_context() {
+        let record = apply(
+            &AnnotatingPolicy,
+            record(),
+            Some(Context::new(json!({"purpose": "test"}))),
+            ContextMergeMode::Deep,
+            false,
+        );
+
+        assert!(record.is_authorized());
+        assert!(!record.is_recognized());
+        assert_eq!(
+            record.context().as_value(),
+            &json!({"stored": true, "purpose": "annotated", "policy": "custom"})
+        );
+    }
+
+    #[test]
+    fn test_provenance_attributes_colliding_keys_to_the_request() {
+        let record = apply(
+            &DefaultEvaluationPolicy,
+            record().with_context(Context::new(json!({"stored": true, "purpose": "stored"}))),
+            Some(Context::new(json!({"purpose": "test", "requested": 1}))),
+            ContextMergeMode::Deep,
+            true,
+        );
+
+        assert_eq!(
+            record.context().as_value(),
+            &json!({
+                "stored": true,
+                "purpose": "test",
+                "requested": 1,
+                "_provenance": {
+                    "stored": "record",
+                    "purpose": "request",
+                    "requested": "request",
+                },
+            })
+        );
+    }
+}
@@ -1,74 +1,245 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::storage::{
+    caching::CachingRepository,
+    decision_cache::{DecisionCache, InvalidatingRepository},
+    encryption::{ContextCipher, EncryptingRepository},
     factory::TrustStorageRepoFactory,
+    history::{HistoryRepository, HistoryRetention},
+    normalizing::NormalizingRepository,
     repository::{TrustRecordAdminRepository, TrustRecordRepository},
+    wildcard::WildcardRepository,
 };
-use axum::{Json, Router, routing::get};
+use axum_server::tls_rustls::RustlsConfig;
 use dotenvy::dotenv;
-use serde_json::json;
 use tower_http::cors::CorsLayer;
 use tracing::{debug, error, info, warn};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    SharedData,
-    configs::{Configs, DidcommConfig, TrsutRegistryConfig},
-    didcomm::listener::start_didcomm_listener,
-    http::application_routes,
+    ReadOnlyMode, SharedData,
+    audit::model::AuditLogger,
+    configs::{DidcommConfig, TrsutRegistryConfig, loaders::environment::optional_env},
+    did_resolution::{CachingDidResolver, DidResolver},
+    didcomm::{
+        dead_letter::DeadLetterQueue,
+        handlers::{admin::AdminMessagesHandler, build::configured_audit_logger},
+        listener::start_didcomm_listener,
+        webvh_log::WebvhLog,
+    },
+    http::{
+        application_routes,
+        handlers::{
+            admin::{AdminApi, admin_routes},
+            export::{Export, export_routes},
+            health::{Readiness, health_routes},
+            stats::{Stats, stats_routes},
+            trqp::QueryLogSampler,
+        },
+        response_signing::ResponseSigner,
+        tls::{load_tls_config, serve},
+    },
+    policy::{DefaultEvaluationPolicy, EvaluationPolicy},
+    telemetry::OtlpTelemetry,
 };
 
-fn setup_logging() {
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        // .with_max_level(tracing::Level::DEBUG)
-        .with_env_filter(EnvFilter::from_default_env()) // reads RUST_LOG
+/// Also exports spans over OTLP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+fn setup_logging() -> Option<OtlpTelemetry> {
+    let endpoint = optional_env("OTEL_EXPORTER_OTLP_ENDPOINT");
+    let telemetry = OtlpTelemetry::init(endpoint.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Failed to initialize OTLP export, continuing without it: {e}");
+        None
+    });
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(false)
         .with_level(true)
-        .with_thread_ids(true)
+        .with_thread_ids(true);
+    let _ = tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env()) // reads RUST_LOG
+        .with(fmt_layer)
+        .with(telemetry.as_ref().map(OtlpTelemetry::layer))
         .try_init();
+
+    if let Some(endpoint) = endpoint.filter(|_| telemetry.is_some()) {
+        info!("Exporting traces and metrics over OTLP to {endpoint}");
+    }
+    telemetry
+}
+
+/// Opens the dead letter queue shared by every profile listener and starts its retry task.
+async fn open_dead_letters(config: &DidcommConfig) -> Option<Arc<DeadLetterQueue>> {
+    let dead_letter_config = config.dead_letter_config.as_ref()?;
+    let queue = match DeadLetterQueue::open(dead_letter_config).await {
+        Ok(queue) => Arc::new(queue),
+        Err(e) => {
+            error!(
+                "Failed to open dead letter store {}: {e}. Undelivered responses are dropped",
+                dead_letter_config.store
+            );
+            return None;
+        }
+    };
+    info!(
+        "Retrying undelivered responses every {}s, {} queued",
+        dead_letter_config.retry_interval_sec,
+        queue.depth()
+    );
+    queue.spawn_retry_task(Duration::from_secs(dead_letter_config.retry_interval_sec));
+    Some(queue)
 }
 
 async fn start_didcomm_server(
     config: DidcommConfig,
     repository: Arc<dyn TrustRecordAdminRepository>,
+    admin: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    evaluation_policy: Arc<dyn EvaluationPolicy>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    start_didcomm_listener(config, repository).await?;
+    let dead_letters = open_dead_letters(&config).await;
+    start_didcomm_listener(config, repository, admin, evaluation_policy, dead_letters).await?;
 
     Ok(())
 }
 
 /// The main purpose is just to handle health check of container
+#[allow(clippy::too_many_arguments)]
 async fn start_http_server(
     config: Arc<TrsutRegistryConfig>,
-    repository: Arc<dyn TrustRecordRepository>,
+    repository: Arc<dyn TrustRecordAdminRepository>,
+    decision_cache: Option<Arc<DecisionCache>>,
+    read_only: ReadOnlyMode,
+    evaluation_policy: Arc<dyn EvaluationPolicy>,
+    did_resolver: Option<Arc<dyn DidResolver>>,
+    admin_handler: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>>,
+    tls: Option<RustlsConfig>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let listen_address = config.server_config.listen_address.clone();
 
+    let readiness = Arc::new(Readiness::new(
+        repository.clone(),
+        config.server_config.require_records,
+        Duration::from_secs(config.server_config.readiness_cache_ttl_sec),
+    ));
+    readiness.log_startup_state().await;
+    let stats = config.server_config.enable_stats.then(|| {
+        Arc::new(Stats::new(
+            repository.clone(),
+            Duration::from_secs(config.server_config.stats_cache_ttl_sec),
+        ))
+    });
+    let export = config
+        .server_config
+        .export_api_key
+        .clone()
+        .map(|api_key| Arc::new(Export::new(repository.clone(), api_key)));
+    let admin = config
+        .server_config
+        .admin_api_key
+        .clone()
+        .map(|api_key| Arc::new(AdminApi::new(admin_handler, api_key)));
+
+    let didcomm_config = &config.didcomm_config;
+    let webvh_log = match &didcomm_config.did_webvh_log {
+        Some(path) if didcomm_config.profile_config.did.starts_with("did:webvh:") => {
+            info!("Serving the DID document from did:webvh log {path}");
+            Some(Arc::new(WebvhLog::new(path)))
+        }
+        Some(_) => {
+            warn!("DID_WEBVH_LOG is ignored because the profile DID is not a did:webvh");
+            None
+        }
+        None => None,
+    };
+
+    let response_signer = if config.server_config.sign_responses {
+        let signer = ResponseSigner::from_profile(&didcomm_config.profile_config)?;
+        info!("Signing TRQP responses with {}", signer.key_id());
+        Some(Arc::new(signer))
+    } else {
+        None
+    };
+
     let shared_data = SharedData {
         config: config.clone(),
         service_start_timestamp: chrono::Utc::now(),
-        repository,
+        repository: repository as Arc<dyn TrustRecordRepository>,
+        decision_cache,
+        read_only,
+        evaluation_policy,
+        did_resolver,
+        webvh_log,
+        response_signer,
+        query_log_sampler: QueryLogSampler::new(config.server_config.log_trqp_sample_rate),
     };
 
     let cors = build_cors_layer(&config.server_config.cors_allowed_origins);
 
-    let health_route =
-        Router::new().route("/health", get(|| async { Json(json!({ "status": "OK" })) }));
-
-    let main_router = health_route
-        .merge(application_routes("", shared_data))
-        .layer(cors);
+    let mut main_router = health_routes(readiness).merge(application_routes("", shared_data));
+    if let Some(stats) = stats {
+        main_router = main_router.merge(stats_routes(stats));
+    }
+    if let Some(export) = export {
+        main_router = main_router.merge(export_routes(export));
+    }
+    if let Some(admin) = admin {
+        main_router = main_router.merge(admin_routes(admin));
+    }
+    let main_router = main_router.layer(cors);
 
-    info!("HTTP server is starting on {}...", listen_address);
+    let scheme = if tls.is_some() { "HTTPS" } else { "HTTP" };
+    info!("{scheme} server is starting on {}...", listen_address);
     debug!("CONFIGS: {:?}", &config);
 
-    let listener = tokio::net::TcpListener::bind(&listen_address).await?;
-    axum::serve(listener, main_router).await?;
+    let listener = std::net::TcpListener::bind(&listen_address)?;
+    serve(listener, main_router, tls).await?;
 
     Ok(())
 }
 
+/// Flushes and stops the OTLP exporters, if any.
+/// Exporter shutdown blocks while flushing, so it runs off the async workers.
+async fn shutdown_telemetry(telemetry: Option<OtlpTelemetry>) {
+    if let Some(telemetry) = telemetry {
+        let _ = tokio::task::spawn_blocking(move || telemetry.shutdown()).await;
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Writes out mutations the storage backend still buffers before the process exits.
+async fn flush_repository(repository: &dyn TrustRecordAdminRepository) {
+    if let Err(e) = repository.flush().await {
+        error!("Failed to flush trust records on shutdown: {e}");
+    }
+}
+
 fn build_cors_layer(allowed_origins: &[String]) -> CorsLayer {
     if allowed_origins.is_empty() {
         info!("CORS: No allowed origins configured, allowing all origins");
@@ -93,22 +264,101 @@ fn build_cors_layer(allowed_origins: &[String]) -> CorsLayer {
         .allow_headers(tower_http::cors::Any)
 }
 
+/// Starts an embedded registry with custom extension points.
+/// Anything not set falls back to what the environment configures.
+#[derive(Default)]
+pub struct ServerBuilder {
+    audit_logger: Option<Arc<dyn AuditLogger>>,
+    evaluation_policy: Option<Arc<dyn EvaluationPolicy>>,
+    did_resolver: Option<Arc<dyn DidResolver>>,
+    env_prefix: Option<String>,
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the audit logger configured through `AUDIT_LOG_FORMAT` and `AUDIT_LOG_FILE`.
+    pub fn audit_logger(mut self, audit_logger: Arc<dyn AuditLogger>) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Decides TRQP answers over HTTP and DIDComm instead of [`DefaultEvaluationPolicy`].
+    pub fn evaluation_policy(mut self, evaluation_policy: Arc<dyn EvaluationPolicy>) -> Self {
+        self.evaluation_policy = Some(evaluation_policy);
+        self
+    }
+
+    /// Resolver for authority DIDs, cached per `DID_CACHE_TTL_SEC` and `DID_CACHE_MAX_ENTRIES`.
+    pub fn did_resolver(mut self, did_resolver: Arc<dyn DidResolver>) -> Self {
+        self.did_resolver = Some(did_resolver);
+        self
+    }
+
+    /// Reads `{prefix}NAME` environment variables before `NAME`, e.g. `TR1_LISTEN_ADDRESS`,
+    /// to run several instances from one environment.
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    pub async fn start(self) {
+        run(self).await
+    }
+}
+
 pub async fn start() {
+    ServerBuilder::new().start().await
+}
+
+/// Starts the registry with a custom audit logger for admin operations,
+/// replacing the one configured through `AUDIT_LOG_FORMAT`.
+pub async fn start_with_audit_logger(audit_logger: Arc<dyn AuditLogger>) {
+    ServerBuilder::new()
+        .audit_logger(audit_logger)
+        .start()
+        .await
+}
+
+async fn run(builder: ServerBuilder) {
+    let ServerBuilder {
+        audit_logger,
+        evaluation_policy,
+        did_resolver,
+        env_prefix,
+    } = builder;
+    let evaluation_policy = evaluation_policy.unwrap_or_else(|| Arc::new(DefaultEvaluationPolicy));
+
     // resources section
     dotenv().ok();
 
-    setup_logging();
+    let telemetry = setup_logging();
 
-    let config = match TrsutRegistryConfig::load().await {
-        Ok(c) => Arc::new(c),
-        Err(e) => {
-            error!(
-                "Failed to load configs. End of work. Original error is: {}",
-                e
-            );
-            panic!("Failed to load configs");
-        }
-    };
+    let config =
+        match TrsutRegistryConfig::load_with_prefix(env_prefix.as_deref().unwrap_or("")).await {
+            Ok(c) => Arc::new(c),
+            Err(e) => {
+                error!(
+                    "Failed to load configs. End of work. Original error is: {}",
+                    e
+                );
+                std::process::exit(1);
+            }
+        };
+
+    if let Err(e) = config.validate().await {
+        error!("{e}");
+        std::process::exit(1);
+    }
+    if config.server_config.log_effective_config {
+        info!(
+            version = env!("CARGO_PKG_VERSION"),
+            config = %config.effective_config(),
+            "Trust Registry starting with effective configuration"
+        );
+    }
 
     let repository_factory = TrustStorageRepoFactory::new(Arc::clone(&config));
 
@@ -116,34 +366,190 @@ pub async fn start() {
         Ok(r) => r,
         Err(e) => {
             error!("Failed to initialize trust record repository: {e}");
-            panic!("Failed to initialize trust record repository: {e}");
+            std::process::exit(1);
         }
     };
 
-    // tasks section
-    let http_task = tokio::spawn(start_http_server(config.clone(), repository.clone()));
+    // innermost, so contexts are bound to the record keys as stored
+    let repository: Arc<dyn TrustRecordAdminRepository> =
+        match &config.server_config.context_encryption_key {
+            Some(key) => {
+                let cipher = ContextCipher::from_base64(key).unwrap_or_else(|e| {
+                    error!("{e}");
+                    std::process::exit(1);
+                });
+                info!(
+                    "Context encryption at rest enabled with key {}",
+                    cipher.key_id()
+                );
+                Arc::new(EncryptingRepository::new(repository, cipher))
+            }
+            None => repository,
+        };
 
-    if config.didcomm_config.is_enabled {
-        let didcomm_task = tokio::spawn(start_didcomm_server(
-            config.didcomm_config.clone(),
+    let repository: Arc<dyn TrustRecordAdminRepository> =
+        if config.server_config.enable_record_history {
+            info!("Record history enabled for as_of queries");
+            let history = Arc::new(HistoryRepository::new(repository));
+            let server_config = &config.server_config;
+            if server_config.history_max_versions > 0 {
+                info!(
+                    "Record history compacted every {}s to {} versions per record",
+                    server_config.history_compaction_interval_sec,
+                    server_config.history_max_versions
+                );
+                history.spawn_compaction_task(
+                    HistoryRetention {
+                        max_versions: server_config.history_max_versions,
+                        keep_within: (server_config.history_retention_sec > 0)
+                            .then(|| Duration::from_secs(server_config.history_retention_sec)),
+                    },
+                    Duration::from_secs(server_config.history_compaction_interval_sec),
+                );
+            }
+            history
+        } else {
+            repository
+        };
+
+    let repository: Arc<dyn TrustRecordAdminRepository> = if config.server_config.enable_wildcards {
+        info!("Wildcard actions and resources enabled");
+        Arc::new(WildcardRepository::new(repository))
+    } else {
+        repository
+    };
+
+    let repository: Arc<dyn TrustRecordAdminRepository> = if config.server_config.cache_enabled {
+        info!(
+            "Record cache enabled with TTL {}s and {} entries",
+            config.server_config.cache_ttl_sec, config.server_config.cache_max_entries
+        );
+        Arc::new(CachingRepository::new(
             repository,
-        ));
+            Duration::from_secs(config.server_config.cache_ttl_sec),
+            config.server_config.cache_max_entries,
+        ))
+    } else {
+        repository
+    };
 
-        tokio::select! {
-            result = didcomm_task => {
-                error!("didcomm_task failed: {:?}", result);
+    let decision_cache = (config.server_config.decision_cache_ttl_sec > 0).then(|| {
+        info!(
+            "Decision cache enabled with TTL {}s",
+            config.server_config.decision_cache_ttl_sec
+        );
+        Arc::new(DecisionCache::new(
+            Duration::from_secs(config.server_config.decision_cache_ttl_sec),
+            config.server_config.decision_cache_max_entries,
+        ))
+    });
+    // admin mutations must go through the wrapper so cached decisions are invalidated
+    let repository: Arc<dyn TrustRecordAdminRepository> = match &decision_cache {
+        Some(cache) => Arc::new(InvalidatingRepository::new(repository, cache.clone())),
+        None => repository,
+    };
+    // outermost, so caches and invalidation only ever see canonical DIDs
+    let repository: Arc<dyn TrustRecordAdminRepository> = if config.server_config.normalize_dids {
+        info!("DID normalization enabled");
+        Arc::new(NormalizingRepository::new(repository))
+    } else {
+        repository
+    };
+
+    let did_resolver = did_resolver.map(|resolver| -> Arc<dyn DidResolver> {
+        Arc::new(CachingDidResolver::new(
+            resolver,
+            Duration::from_secs(config.server_config.did_cache_ttl_sec),
+            config.server_config.did_cache_max_entries,
+        ))
+    });
+
+    let server_config = &config.server_config;
+    let tls = match (&server_config.tls_cert_path, &server_config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => match load_tls_config(cert_path, key_path).await {
+            Ok(tls) => Some(tls),
+            Err(e) => {
+                error!("{e}");
+                std::process::exit(1);
             }
-            result = http_task => {
-                error!("http_task failed: {:?}", result);
+        },
+        _ => None,
+    };
+
+    let read_only = ReadOnlyMode::new(config.server_config.read_only);
+    if read_only.is_enabled() {
+        warn!("Registry is in read-only mode. Admin mutations are rejected.");
+    }
+
+    // one admin handler for both transports, so they share the audit log, the webhook and
+    // the mutation limit
+    let audit_logger =
+        audit_logger.unwrap_or_else(|| configured_audit_logger(&config.didcomm_config));
+    let mut admin_config = config.didcomm_config.admin_config.clone();
+    admin_config.read_only = read_only.clone();
+    let admin_handler: Arc<AdminMessagesHandler<dyn TrustRecordAdminRepository>> = Arc::new(
+        AdminMessagesHandler::new(repository.clone(), admin_config, audit_logger),
+    );
+
+    // tasks section
+    let http_task = tokio::spawn(start_http_server(
+        config.clone(),
+        repository.clone(),
+        decision_cache,
+        read_only.clone(),
+        evaluation_policy.clone(),
+        did_resolver,
+        admin_handler.clone(),
+        tls,
+    ));
+
+    let shutdown_repository = repository.clone();
+    let tasks = async move {
+        if config.didcomm_config.is_enabled {
+            let mut didcomm_config = config.didcomm_config.clone();
+            didcomm_config.admin_config.read_only = read_only;
+            didcomm_config.trqp_default_context = config.server_config.trqp_default_context.clone();
+            didcomm_config.trqp_context_allowlist =
+                config.server_config.trqp_context_allowlist.clone();
+            didcomm_config.trqp_context_merge_mode = config.server_config.context_merge_mode;
+            didcomm_config.trqp_context_provenance = config.server_config.context_provenance;
+            didcomm_config.trqp_query_allowlist = config.server_config.trqp_query_allowlist.clone();
+            didcomm_config.trqp_max_fan_out = config.server_config.max_fan_out;
+            let didcomm_task = tokio::spawn(start_didcomm_server(
+                didcomm_config,
+                repository,
+                admin_handler,
+                evaluation_policy,
+            ));
+
+            tokio::select! {
+                result = didcomm_task => {
+                    error!("didcomm_task failed: {:?}", result);
+                }
+                result = http_task => {
+                    error!("http_task failed: {:?}", result);
+                }
+            }
+        } else {
+            warn!("DIDComm server is disabled.");
+
+            if let Err(e) = http_task.await {
+                error!("http_task failed: {:?}", e);
             }
         }
-    } else {
-        warn!("DIDComm server is disabled.");
+    };
 
-        if let Err(e) = http_task.await {
-            error!("http_task failed: {:?}", e);
+    tokio::select! {
+        _ = tasks => {}
+        _ = shutdown_signal() => {
+            info!("Shutdown signal received");
+            flush_repository(shutdown_repository.as_ref()).await;
+            shutdown_telemetry(telemetry).await;
+            std::process::exit(0);
         }
     }
 
+    flush_repository(shutdown_repository.as_ref()).await;
+    shutdown_telemetry(telemetry).await;
     std::process::exit(1);
 }
@@ -1,20 +1,25 @@
+use crate::configs::DuplicateKeyPolicy;
 use crate::domain::*;
 use crate::storage::repository::*;
 use anyhow::anyhow;
 use base64::Engine as _;
 use base64::engine::general_purpose::STANDARD as base64;
+use chrono::DateTime;
 use serde_json::Value;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, hash_map::Entry},
     path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
     time::{Duration, SystemTime},
 };
 
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct RecordKey {
@@ -35,26 +40,109 @@ impl RecordKey {
     }
 }
 
+/// Columns of the file, in the order rows are written.
+const CSV_COLUMNS: [&str; 15] = [
+    "entity_id",
+    "authority_id",
+    "action",
+    "resource",
+    "recognized",
+    "authorized",
+    "context",
+    "record_type",
+    "expires_at",
+    "tags",
+    "attachments",
+    "version",
+    "created_at",
+    "updated_at",
+    "schema_version",
+];
+
+/// Columns no row can be read without.
+const REQUIRED_COLUMNS: [&str; 7] = [
+    "entity_id",
+    "authority_id",
+    "action",
+    "resource",
+    "recognized",
+    "authorized",
+    "record_type",
+];
+
+/// How a CSV document is turned into records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CsvLoadOptions {
+    /// Fail the whole load on a malformed row instead of skipping it.
+    pub strict: bool,
+    /// What to do with a row whose record key an earlier row already used.
+    pub on_duplicate: DuplicateKeyPolicy,
+}
+
+/// Coalesces mutations into one file write, see [`FileStorage::with_write_batching`].
+#[derive(Debug, Clone, Copy)]
+struct WriteBatching {
+    window: Duration,
+    max_pending: usize,
+}
+
 #[derive(Clone)]
 pub struct FileStorage {
     file_path: PathBuf,
     update_interval: Duration,
     records: Arc<RwLock<HashMap<RecordKey, TrustRecord>>>,
     last_modified: Arc<RwLock<Option<SystemTime>>>,
+    options: CsvLoadOptions,
+    batching: Option<WriteBatching>,
+    /// Mutations applied in memory but not written to the file yet.
+    pending_writes: Arc<AtomicUsize>,
+    flush_scheduled: Arc<AtomicBool>,
+    /// Keeps file writes in order, so an older snapshot never overwrites a newer one.
+    write_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl FileStorage {
     pub async fn try_new<P: Into<PathBuf>>(
         file_path: P,
         update_interval_sec: u64,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::try_new_with_strict(file_path, update_interval_sec, false).await
+    }
+
+    /// Like [`FileStorage::try_new`]; with `strict` one malformed row fails the load,
+    /// otherwise malformed rows are logged and skipped.
+    pub async fn try_new_with_strict<P: Into<PathBuf>>(
+        file_path: P,
+        update_interval_sec: u64,
+        strict: bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let options = CsvLoadOptions {
+            strict,
+            ..Default::default()
+        };
+        Self::try_new_with_options(file_path, update_interval_sec, options).await
+    }
+
+    /// Like [`FileStorage::try_new`], parsing the file as `options` say.
+    pub async fn try_new_with_options<P: Into<PathBuf>>(
+        file_path: P,
+        update_interval_sec: u64,
+        options: CsvLoadOptions,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let file_path = file_path.into();
         let update_interval = Duration::from_secs(update_interval_sec);
 
+        if Self::is_blank(&file_path).await? {
+            info!(path = %file_path.display(), "Creating trust records file with a header row");
+            tokio::fs::write(&file_path, format!("{}\n", CSV_COLUMNS.join(",")))
+                .await
+                .map_err(|e| format!("file_path: {file_path:?}, error: {e}"))?;
+        }
+
         let records = Arc::new(RwLock::new(HashMap::new()));
         let last_modified = Arc::new(RwLock::new(None));
 
-        let (initial_records, modified) = Self::load_if_modified(&file_path, None)
+        let (initial_records, modified) = Self::load_if_modified(&file_path, None, options)
             .await?
             .ok_or_else(|| {
                 anyhow!("unable to load trust records from {}", file_path.display())
@@ -75,6 +163,11 @@ impl FileStorage {
             update_interval,
             records: Arc::clone(&records),
             last_modified: Arc::clone(&last_modified),
+            options,
+            batching: None,
+            pending_writes: Arc::new(AtomicUsize::new(0)),
+            flush_scheduled: Arc::new(AtomicBool::new(false)),
+            write_lock: Arc::new(tokio::sync::Mutex::new(())),
         };
 
         storage.spawn_sync_task();
@@ -82,21 +175,39 @@ impl FileStorage {
         Ok(storage)
     }
 
+    /// Buffers mutations instead of rewriting the file on each one. The file is written
+    /// `window` after the first buffered mutation, or once `max_pending` have accumulated,
+    /// whichever comes first. Reads see mutations immediately; call
+    /// [`TrustRecordAdminRepository::flush`] before shutting down.
+    pub fn with_write_batching(mut self, window: Duration, max_pending: usize) -> Self {
+        self.batching = Some(WriteBatching {
+            window,
+            max_pending: max_pending.max(1),
+        });
+        self
+    }
+
     fn spawn_sync_task(&self) {
         let file_path = self.file_path.clone();
         let update_interval = self.update_interval;
         let records = Arc::clone(&self.records);
         let last_modified = Arc::clone(&self.last_modified);
+        let pending_writes = Arc::clone(&self.pending_writes);
+        let options = self.options;
 
         tokio::spawn(async move {
             loop {
                 sleep(update_interval).await;
+                // reloading now would drop buffered mutations
+                if pending_writes.load(Ordering::SeqCst) > 0 {
+                    continue;
+                }
 
                 info!(path = %file_path.display(), "Syncing trust records from file");
 
                 let previous = { *last_modified.read().unwrap() };
 
-                match Self::load_if_modified(&file_path, previous).await {
+                match Self::load_if_modified(&file_path, previous, options).await {
                     Ok(Some((new_records, modified))) => {
                         {
                             let mut guard = records.write().unwrap();
@@ -120,9 +231,47 @@ impl FileStorage {
         });
     }
 
+    /// Whether the file is missing or empty, so [`FileStorage::try_new`] starts it with a header.
+    async fn is_blank(path: &Path) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(contents) => Ok(contents.trim().is_empty()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(format!("file_path: {path:?}, error: {e}").into()),
+        }
+    }
+
+    /// Reads and parses the file without starting the refresh task. Returns the record count.
+    /// A missing or empty file counts as empty when its directory exists, since it's
+    /// created on startup.
+    pub async fn check_file(
+        path: &Path,
+        options: CsvLoadOptions,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        if Self::is_blank(path).await? {
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            return match tokio::fs::metadata(dir).await {
+                Ok(metadata) if metadata.is_dir() => Ok(0),
+                _ => Err(
+                    format!("file_path: {path:?}, error: directory {dir:?} doesn't exist").into(),
+                ),
+            };
+        }
+        let (records, _) = Self::load_if_modified(path, None, options)
+            .await?
+            .ok_or_else(|| {
+                anyhow!("unable to load trust records from {}", path.display())
+                    .into_boxed_dyn_error()
+            })?;
+        Ok(records.len())
+    }
+
     async fn load_if_modified(
         path: &Path,
         last_seen: Option<SystemTime>,
+        options: CsvLoadOptions,
     ) -> Result<
         Option<(HashMap<RecordKey, TrustRecord>, SystemTime)>,
         Box<dyn std::error::Error + Send + Sync>,
@@ -148,26 +297,91 @@ impl FileStorage {
         );
         let contents = tokio::fs::read_to_string(path).await?.trim().to_string();
 
-        let records = Self::parse_csv(&contents)?;
+        let records = Self::parse_csv(&contents, options)?;
 
         Ok(Some((records, modified)))
     }
 
+    /// Parses CSV content that didn't come from a file, see [`RemoteStorage`](super::remote_storage::RemoteStorage).
+    pub(crate) fn parse_records(
+        contents: &str,
+        options: CsvLoadOptions,
+    ) -> Result<Vec<TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self::parse_csv(contents.trim(), options)?
+            .into_values()
+            .collect())
+    }
+
     fn parse_csv(
         contents: &str,
+        options: CsvLoadOptions,
     ) -> Result<HashMap<RecordKey, TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
         let mut reader = csv::ReaderBuilder::new()
             .has_headers(true)
             .trim(csv::Trim::All)
             .from_reader(contents.as_bytes());
+        let headers = reader.headers()?.clone();
+        if headers.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let missing: Vec<&str> = REQUIRED_COLUMNS
+            .into_iter()
+            .filter(|column| !headers.iter().any(|header| header == *column))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "malformed CSV header, missing columns: {}",
+                missing.join(", ")
+            )
+            .into());
+        }
 
         let mut records = HashMap::new();
 
-        for result in reader.deserialize::<TrustRecordCsvRow>() {
-            let row = result?;
-            let record = row.into_record()?;
-            let key = RecordKey::from_record(&record);
-            records.insert(key, record);
+        for result in reader.records() {
+            let (line, parsed) = match result {
+                Ok(row) => (
+                    row.position().map(csv::Position::line),
+                    row.deserialize::<TrustRecordCsvRow>(Some(&headers))
+                        .map_err(Into::into)
+                        .and_then(TrustRecordCsvRow::into_record),
+                ),
+                Err(e) => (e.position().map(csv::Position::line), Err(e.into())),
+            };
+            match parsed {
+                Ok(record) => {
+                    let mut existing = match records.entry(RecordKey::from_record(&record)) {
+                        Entry::Vacant(slot) => {
+                            slot.insert(record);
+                            continue;
+                        }
+                        Entry::Occupied(existing) => existing,
+                    };
+                    let key = existing.key();
+                    let conflict = format!(
+                        "{}|{}|{}|{}",
+                        key.entity_id, key.authority_id, key.action, key.resource
+                    );
+                    match options.on_duplicate {
+                        DuplicateKeyPolicy::LastWins => {
+                            warn!(line, key = %conflict, "Duplicate record key, the later row wins");
+                            existing.insert(record);
+                        }
+                        DuplicateKeyPolicy::FirstWins => {
+                            warn!(line, key = %conflict, "Duplicate record key, the earlier row wins");
+                        }
+                        DuplicateKeyPolicy::Error => {
+                            let at = line.map(|l| format!(" at line {l}")).unwrap_or_default();
+                            return Err(format!("duplicate record key {conflict}{at}").into());
+                        }
+                    }
+                }
+                Err(e) if options.strict => {
+                    let at = line.map(|l| format!(" at line {l}")).unwrap_or_default();
+                    return Err(format!("malformed CSV row{at}: {e}").into());
+                }
+                Err(e) => warn!(line, error = %e, "Skipping malformed CSV row"),
+            }
         }
 
         Ok(records)
@@ -180,6 +394,48 @@ impl FileStorage {
             && record.resource() == &query.resource
     }
 
+    /// Writes the file after a mutation, or buffers the write when batching is enabled.
+    async fn persist(&self) -> Result<(), RepositoryError> {
+        let Some(batching) = self.batching else {
+            let _guard = self.write_lock.lock().await;
+            return self.write_to_file().await;
+        };
+
+        let pending = self.pending_writes.fetch_add(1, Ordering::SeqCst) + 1;
+        if pending >= batching.max_pending {
+            return self.flush_pending().await;
+        }
+        if !self.flush_scheduled.swap(true, Ordering::SeqCst) {
+            let storage = self.clone();
+            tokio::spawn(async move {
+                sleep(batching.window).await;
+                storage.flush_scheduled.store(false, Ordering::SeqCst);
+                if let Err(e) = storage.flush_pending().await {
+                    error!(
+                        error = %e,
+                        path = %storage.file_path.display(),
+                        "Failed to flush buffered trust record writes"
+                    );
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn flush_pending(&self) -> Result<(), RepositoryError> {
+        let _guard = self.write_lock.lock().await;
+        let pending = self.pending_writes.swap(0, Ordering::SeqCst);
+        if pending == 0 {
+            return Ok(());
+        }
+        let result = self.write_to_file().await;
+        if result.is_err() {
+            // keep the mutations pending so the next flush retries them
+            self.pending_writes.fetch_add(pending, Ordering::SeqCst);
+        }
+        result
+    }
+
     async fn write_to_file(&self) -> Result<(), RepositoryError> {
         let records_clone = {
             let records = self.records.read().unwrap();
@@ -192,6 +448,11 @@ impl FileStorage {
         }
 
         let mut wtr = csv::Writer::from_writer(vec![]);
+        if csv_records.is_empty() {
+            // serializing rows writes the header, without rows it has to be written here
+            wtr.write_record(CSV_COLUMNS)
+                .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+        }
         for row in csv_records {
             wtr.serialize(&row)
                 .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
@@ -236,6 +497,27 @@ impl TrustRecordRepository for FileStorage {
 
         Ok(result)
     }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| {
+                record.entity_id() == entity_id && record.authority_id() == authority_id
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        let records = self.records.read().unwrap();
+        let records_vec: Vec<TrustRecord> = records.values().cloned().collect();
+        Ok(TrustRecordList::new(records_vec))
+    }
 }
 
 #[async_trait::async_trait]
@@ -255,7 +537,7 @@ impl TrustRecordAdminRepository for FileStorage {
             }
             records.insert(key, record);
         }
-        self.write_to_file().await
+        self.persist().await
     }
 
     async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
@@ -273,7 +555,20 @@ impl TrustRecordAdminRepository for FileStorage {
             }
             records.insert(key, record);
         }
-        self.write_to_file().await
+        self.persist().await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let key = RecordKey::from_record(&record);
+        let outcome = {
+            let mut records = self.records.write().unwrap();
+            match records.insert(key, record) {
+                Some(_) => UpsertOutcome::Updated,
+                None => UpsertOutcome::Created,
+            }
+        };
+        self.persist().await?;
+        Ok(outcome)
     }
 
     async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
@@ -292,13 +587,11 @@ impl TrustRecordAdminRepository for FileStorage {
                 )));
             }
         }
-        self.write_to_file().await
+        self.persist().await
     }
 
-    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
-        let records = self.records.read().unwrap();
-        let records_vec: Vec<TrustRecord> = records.values().cloned().collect();
-        Ok(TrustRecordList::new(records_vec))
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.flush_pending().await
     }
 
     async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
@@ -315,6 +608,15 @@ impl TrustRecordAdminRepository for FileStorage {
             ))
         })
     }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| record.has_tag(tag))
+            .cloned()
+            .collect())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -327,6 +629,23 @@ struct TrustRecordCsvRow {
     authorized: bool,
     context: Option<String>,
     record_type: String,
+    /// RFC 3339 timestamp; the column is optional so older files keep loading.
+    #[serde(default)]
+    expires_at: Option<String>,
+    /// JSON array of the record's tags; the column is optional like `expires_at`.
+    #[serde(default)]
+    tags: Option<String>,
+    /// JSON array of the record's attachment content, optional like `tags`.
+    #[serde(default)]
+    attachments: Option<String>,
+    #[serde(default)]
+    version: Option<u64>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    schema_version: Option<u32>,
 }
 
 impl TrustRecordCsvRow {
@@ -364,11 +683,28 @@ impl TrustRecordCsvRow {
             authorized: record.is_authorized(),
             context,
             record_type: record.record_type().to_string(),
+            expires_at: record.expires_at().map(DateTime::to_rfc3339),
+            tags: (!record.tags().is_empty())
+                .then(|| serde_json::to_string(record.tags()).unwrap_or_default()),
+            attachments: (!record.attachments().is_empty())
+                .then(|| serde_json::to_string(record.attachments()).unwrap_or_default()),
+            version: Some(record.version()),
+            created_at: record.created_at().map(DateTime::to_rfc3339),
+            updated_at: record.updated_at().map(DateTime::to_rfc3339),
+            schema_version: Some(record.schema_version()),
         }
     }
 
     fn into_record(self) -> Result<TrustRecord, Box<dyn std::error::Error + Send + Sync>> {
         let ctx = TrustRecordCsvRow::parse_context(self.context);
+        let tags: Vec<String> = match self.tags.filter(|s| !s.is_empty()) {
+            Some(tags) => serde_json::from_str(&tags)?,
+            None => Vec::new(),
+        };
+        let attachments: Vec<Value> = match self.attachments.filter(|s| !s.is_empty()) {
+            Some(attachments) => serde_json::from_str(&attachments)?,
+            None => Vec::new(),
+        };
         let mut builder = TrustRecordBuilder::new()
             .entity_id(EntityId::new(self.entity_id))
             .authority_id(AuthorityId::new(self.authority_id))
@@ -376,21 +712,44 @@ impl TrustRecordCsvRow {
             .resource(Resource::new(self.resource))
             .recognized(self.recognized)
             .authorized(self.authorized)
-            .record_type(RecordType::from_str(&self.record_type)?);
+            .record_type(RecordType::from_str(&self.record_type)?)
+            .tags(tags);
 
         if let Some(c) = ctx {
             builder = builder.context(Context::new(c));
         }
+        if let Some(expires_at) = self.expires_at.filter(|s| !s.is_empty()) {
+            builder = builder.expires_at(DateTime::parse_from_rfc3339(&expires_at)?.to_utc());
+        }
 
-        builder
+        let mut record = builder
             .build()
-            .map_err(|err| anyhow!("invalid trust record: {err}").into())
+            .map_err(|err| anyhow!("invalid trust record: {err}"))?
+            .with_attachments(attachments)
+            .with_created_at(Self::parse_timestamp(self.created_at)?)
+            .with_updated_at(Self::parse_timestamp(self.updated_at)?);
+        if let Some(version) = self.version {
+            record = record.with_version(version);
+        }
+        Ok(record
+            .with_schema_version(self.schema_version.unwrap_or(LEGACY_SCHEMA_VERSION))
+            .migrate())
+    }
+
+    fn parse_timestamp(
+        value: Option<String>,
+    ) -> Result<Option<DateTime<chrono::Utc>>, Box<dyn std::error::Error + Send + Sync>> {
+        match value.filter(|s| !s.is_empty()) {
+            Some(value) => Ok(Some(DateTime::parse_from_rfc3339(&value)?.to_utc())),
+            None => Ok(None),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::record_builder;
     use std::io::Write;
     use tempfile::NamedTempFile;
     use tokio::time::{Duration, sleep};
@@ -411,6 +770,65 @@ mod tests {
         csv
     }
 
+    fn strict() -> CsvLoadOptions {
+        CsvLoadOptions {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    /// Two rows for e1 authorized `true` then `false`, around a row for e2.
+    fn duplicate_csv() -> String {
+        format!(
+            "{}e1,a1,ac1,r1,true,true,e30=,assertion\ne2,a2,ac2,r2,true,true,e30=,assertion\ne1,a1,ac1,r1,true,false,e30=,assertion\n",
+            csv_header()
+        )
+    }
+
+    fn load_duplicates(
+        on_duplicate: DuplicateKeyPolicy,
+    ) -> Result<HashMap<RecordKey, TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let options = CsvLoadOptions {
+            on_duplicate,
+            ..Default::default()
+        };
+        FileStorage::parse_csv(duplicate_csv().trim(), options)
+    }
+
+    fn e1_authorized(records: &HashMap<RecordKey, TrustRecord>) -> bool {
+        records
+            .values()
+            .find(|record| record.entity_id().as_str() == "e1")
+            .unwrap()
+            .is_authorized()
+    }
+
+    #[test]
+    fn duplicate_key_last_wins_by_default() {
+        let records = load_duplicates(DuplicateKeyPolicy::default()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert!(!e1_authorized(&records));
+    }
+
+    #[test]
+    fn duplicate_key_first_wins_keeps_earlier_row() {
+        let records = load_duplicates(DuplicateKeyPolicy::FirstWins).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert!(e1_authorized(&records));
+    }
+
+    #[test]
+    fn duplicate_key_error_fails_the_load() {
+        let error = load_duplicates(DuplicateKeyPolicy::Error).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "duplicate record key e1|a1|ac1|r1 at line 4"
+        );
+    }
+
     #[tokio::test]
     async fn fails_when_initial_load_fails() {
         let result = FileStorage::try_new("/does/not/exist.csv", 1).await;
@@ -443,6 +861,89 @@ mod tests {
         assert_eq!(*record.record_type(), RecordType::Authorization);
     }
 
+    #[tokio::test]
+    async fn skips_malformed_rows_unless_strict() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}e2,a2,ac2,r2,not-a-bool,true,e30=,assertion\n{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")]),
+            sample_csv(&[("e3", "a3", "ac3", "r3", "recognition")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let entities: Vec<String> = storage
+            .list()
+            .await
+            .unwrap()
+            .into_records()
+            .iter()
+            .map(|record| record.entity_id().to_string())
+            .collect();
+        assert_eq!(entities, vec!["e1", "e3"]);
+
+        let error = FileStorage::try_new_with_strict(file.path(), 60, true)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("line 3"));
+    }
+
+    fn numbered_record(i: usize) -> TrustRecord {
+        record_builder(&format!("e{i}"), "a1", "ac1", "r1")
+            .build()
+            .unwrap()
+    }
+
+    fn rows_in_file(file: &NamedTempFile) -> usize {
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        contents.lines().skip(1).filter(|l| !l.is_empty()).count()
+    }
+
+    #[tokio::test]
+    async fn batched_writes_coalesce_rapid_creates() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        file.flush().unwrap();
+        let storage = FileStorage::try_new(file.path(), 60)
+            .await
+            .unwrap()
+            .with_write_batching(Duration::from_secs(60), 50);
+
+        for i in 0..120 {
+            storage.create(numbered_record(i)).await.unwrap();
+        }
+
+        // reads see every mutation while only two size-triggered writes happened
+        assert_eq!(storage.count().await.unwrap(), 120);
+        assert_eq!(rows_in_file(&file), 100);
+
+        storage.flush().await.unwrap();
+        assert_eq!(rows_in_file(&file), 120);
+    }
+
+    #[tokio::test]
+    async fn batched_writes_flush_after_window() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        file.flush().unwrap();
+        let storage = FileStorage::try_new(file.path(), 60)
+            .await
+            .unwrap()
+            .with_write_batching(Duration::from_millis(50), 100);
+
+        for i in 0..3 {
+            storage.create(numbered_record(i)).await.unwrap();
+        }
+        assert_eq!(rows_in_file(&file), 0);
+
+        sleep(Duration::from_millis(300)).await;
+        assert_eq!(rows_in_file(&file), 3);
+    }
+
     #[tokio::test]
     async fn reloads_when_file_changes() {
         let mut file = NamedTempFile::new().unwrap();
@@ -483,4 +984,270 @@ mod tests {
         assert_eq!(result.clone().unwrap().entity_id().as_str(), "e2");
         assert_eq!(*result.unwrap().record_type(), RecordType::Recognition);
     }
+
+    #[tokio::test]
+    async fn persists_expiry_and_version_columns() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let query = TrustRecordQuery::new(
+            EntityId::new("e1"),
+            AuthorityId::new("a1"),
+            Action::new("ac1"),
+            Resource::new("r1"),
+        );
+        let legacy = storage.read(query.clone()).await.unwrap();
+        assert_eq!(legacy.expires_at(), None);
+        assert_eq!(legacy.version(), INITIAL_VERSION);
+
+        let expires_at = DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        storage
+            .update(legacy.with_expires_at(Some(expires_at)).with_version(2))
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        let record = reloaded.values().next().unwrap();
+        assert_eq!(record.expires_at(), Some(&expires_at));
+        assert_eq!(record.version(), 2);
+    }
+
+    #[tokio::test]
+    async fn persists_timestamp_columns() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let query = TrustRecordQuery::new(
+            EntityId::new("e1"),
+            AuthorityId::new("a1"),
+            Action::new("ac1"),
+            Resource::new("r1"),
+        );
+        let legacy = storage.read(query).await.unwrap();
+        assert_eq!(legacy.created_at(), None);
+        assert_eq!(legacy.updated_at(), None);
+
+        let created_at = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let updated_at = DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        storage
+            .update(
+                legacy
+                    .with_created_at(Some(created_at))
+                    .with_updated_at(Some(updated_at)),
+            )
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        let record = reloaded.values().next().unwrap();
+        assert_eq!(record.created_at(), Some(&created_at));
+        assert_eq!(record.updated_at(), Some(&updated_at));
+    }
+
+    #[tokio::test]
+    async fn upgrades_legacy_rows_to_current_schema_on_write() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let record = storage.list().await.unwrap().into_records().remove(0);
+        assert_eq!(record.schema_version(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(record.version(), INITIAL_VERSION);
+        assert_eq!(record.expires_at(), None);
+
+        storage.update(record).await.unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        assert!(
+            contents
+                .lines()
+                .next()
+                .unwrap()
+                .ends_with(",schema_version")
+        );
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        let record = reloaded.values().next().unwrap();
+        assert_eq!(record.schema_version(), CURRENT_SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn tags_survive_a_write_and_reload() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[
+                ("e1", "a1", "ac1", "r1", "assertion"),
+                ("e2", "a2", "ac2", "r2", "assertion"),
+            ])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let record = storage
+            .list()
+            .await
+            .unwrap()
+            .into_records()
+            .into_iter()
+            .find(|record| record.entity_id().as_str() == "e1")
+            .unwrap();
+        assert!(record.tags().is_empty());
+        let tagged = record.with_tags(vec!["kyc".to_string(), "eu".to_string()]);
+        storage.update(tagged).await.unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        let tags_of = |entity: &str| {
+            reloaded
+                .values()
+                .find(|record| record.entity_id().as_str() == entity)
+                .unwrap()
+                .tags()
+                .to_vec()
+        };
+        assert_eq!(tags_of("e1"), ["kyc", "eu"]);
+        assert!(tags_of("e2").is_empty());
+    }
+
+    #[tokio::test]
+    async fn attachments_survive_a_write_and_reload() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+        let record = storage.list().await.unwrap().into_records().remove(0);
+        let attachment = serde_json::json!({"id": "schema-1", "data": {"base64": "e30="}});
+        storage
+            .update(record.with_attachments(vec![attachment.clone()]))
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        let record = reloaded.values().next().unwrap();
+        assert_eq!(record.attachments(), [attachment]);
+    }
+
+    #[tokio::test]
+    async fn upsert_inserts_then_overwrites() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", csv_header()).unwrap();
+        write!(
+            file,
+            "{}",
+            sample_csv(&[("e1", "a1", "ac1", "r1", "assertion")])
+        )
+        .unwrap();
+        file.flush().unwrap();
+        let storage = FileStorage::try_new(file.path(), 60).await.unwrap();
+
+        let record = |entity: &str, authorized: bool| {
+            record_builder(entity, "a1", "ac1", "r1")
+                .authorized(authorized)
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(
+            storage.upsert(record("e2", true)).await.unwrap(),
+            UpsertOutcome::Created
+        );
+        assert_eq!(
+            storage.upsert(record("e1", false)).await.unwrap(),
+            UpsertOutcome::Updated
+        );
+
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        let reloaded = FileStorage::parse_csv(contents.trim(), strict()).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(
+            !reloaded
+                .values()
+                .find(|r| r.entity_id().as_str() == "e1")
+                .unwrap()
+                .is_authorized()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_or_empty_file_is_created_with_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("trust_records.csv");
+        let empty = NamedTempFile::new().unwrap();
+
+        for path in [missing.as_path(), empty.path()] {
+            assert_eq!(FileStorage::check_file(path, strict()).await.unwrap(), 0);
+            let storage = FileStorage::try_new(path, 60).await.unwrap();
+
+            let contents = std::fs::read_to_string(path).unwrap();
+            assert_eq!(contents, format!("{}\n", CSV_COLUMNS.join(",")));
+            assert!(storage.list().await.unwrap().records().is_empty());
+        }
+
+        let storage = FileStorage::try_new(&missing, 60).await.unwrap();
+        let record = record_builder("e1", "a1", "ac1", "r1").build().unwrap();
+        storage.create(record).await.unwrap();
+        assert_eq!(
+            FileStorage::check_file(&missing, strict()).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_header_is_rejected() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "entity,authority,action,resource\ne1,a1,ac1,r1\n").unwrap();
+
+        let error = FileStorage::try_new(file.path(), 60).await.err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "malformed CSV header, missing columns: entity_id, authority_id, recognized, authorized, record_type"
+        );
+        assert_eq!(
+            std::fs::read_to_string(file.path()).unwrap(),
+            "entity,authority,action,resource\ne1,a1,ac1,r1\n"
+        );
+    }
 }
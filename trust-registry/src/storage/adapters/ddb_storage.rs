@@ -2,16 +2,20 @@ use std::collections::HashMap;
 
 use anyhow::Result as AnyResult;
 use aws_config::BehaviorVersion;
-use aws_sdk_dynamodb::{Client, types::AttributeValue};
+use aws_sdk_dynamodb::{
+    Client,
+    operation::put_item::PutItemError,
+    types::{AttributeValue, ReturnValue, ReturnValuesOnConditionCheckFailure},
+};
 use aws_types::region::Region;
 use tracing::debug;
 
 use crate::{
     configs::DynamoDbStorageConfig,
-    domain::TrustRecord,
+    domain::{AuthorityId, EntityId, INITIAL_VERSION, TrustRecord},
     storage::repository::{
         RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
-        TrustRecordRepository,
+        TrustRecordRepository, UpsertOutcome, check_version,
     },
 };
 
@@ -45,11 +49,20 @@ impl DynamoDbStorage {
 
         let shared_config = loader.load().await;
         let client = Client::new(&shared_config);
-        // TODO: describe table to check connection to fail fast?
 
         Ok(Self::with_client(client, config.table_name))
     }
 
+    /// Describes the table, failing if it is missing or DynamoDB can't be reached.
+    pub async fn check_table(&self) -> AnyResult<()> {
+        self.client
+            .describe_table()
+            .table_name(&self.table_name)
+            .send()
+            .await?;
+        Ok(())
+    }
+
     pub fn with_client(client: Client, table_name: impl Into<String>) -> Self {
         Self {
             client,
@@ -116,12 +129,85 @@ impl TrustRecordRepository for DynamoDbStorage {
 
         if let Some(item) = response.item {
             let trust_record: TrustRecord = serde_dynamo::from_item(item)
+                .map(TrustRecord::migrate)
                 .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
             return Ok(Some(trust_record));
         }
 
         Ok(None)
     }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        debug!(
+            entity = entity_id.as_str(),
+            authority = authority_id.as_str(),
+            "Scanning trust records for entity in DynamoDB"
+        );
+
+        let mut records = Vec::new();
+        let mut start_key = None;
+        loop {
+            let response = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("entity_id = :entity_id AND authority_id = :authority_id")
+                .expression_attribute_values(":entity_id", AttributeValue::S(entity_id.to_string()))
+                .expression_attribute_values(
+                    ":authority_id",
+                    AttributeValue::S(authority_id.to_string()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|err| {
+                    RepositoryError::QueryFailed(format!("Failed to scan table: {err}"))
+                })?;
+
+            for item in response.items.unwrap_or_default() {
+                let record: TrustRecord =
+                    serde_dynamo::from_item(item)
+                        .map(TrustRecord::migrate)
+                        .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+                records.push(record);
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(records)
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        debug!("Listing all trust records from DynamoDB");
+
+        let response = self
+            .client
+            .scan()
+            .table_name(&self.table_name)
+            .send()
+            .await
+            .map_err(|err| RepositoryError::QueryFailed(format!("Failed to scan table: {err}")))?;
+
+        let items = response.items.unwrap_or_default();
+        let mut records = Vec::with_capacity(items.len());
+
+        for item in items {
+            let record: TrustRecord = serde_dynamo::from_item(item)
+                .map(TrustRecord::migrate)
+                .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+            records.push(record);
+        }
+
+        Ok(TrustRecordList::new(records))
+    }
 }
 
 #[async_trait::async_trait]
@@ -222,6 +308,130 @@ impl TrustRecordAdminRepository for DynamoDbStorage {
         Ok(())
     }
 
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        debug!(
+            entity = record.entity_id().as_str(),
+            authority = record.authority_id().as_str(),
+            action = record.action().as_str(),
+            resource = record.resource().as_str(),
+            expected_version,
+            "Conditionally updating trust record in DynamoDB"
+        );
+
+        let mut item: HashMap<String, AttributeValue> = serde_dynamo::to_item(&record)
+            .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+
+        let key_value = format!(
+            "{}|{}|{}|{}",
+            record.entity_id(),
+            record.authority_id(),
+            record.action(),
+            record.resource()
+        );
+        item.insert(PK_ATTR.to_string(), AttributeValue::S(key_value.clone()));
+        item.insert(SK_ATTR.to_string(), AttributeValue::S(key_value));
+
+        // legacy items without a version read as the initial one
+        let condition = if expected_version == INITIAL_VERSION {
+            "attribute_exists(PK) AND (attribute_not_exists(#version) OR #version <= :expected)"
+        } else {
+            "attribute_exists(PK) AND #version = :expected"
+        };
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .condition_expression(condition)
+            .expression_attribute_names("#version", "version")
+            .expression_attribute_values(
+                ":expected",
+                AttributeValue::N(expected_version.to_string()),
+            )
+            .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) => match err.as_service_error() {
+                Some(PutItemError::ConditionalCheckFailedException(failed)) => {
+                    match failed.item() {
+                        Some(current) if !current.is_empty() => {
+                            let current: TrustRecord = serde_dynamo::from_item(current.clone())
+                                .map(TrustRecord::migrate)
+                                .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+                            check_version(&current, expected_version)?;
+                            // the item matched once read back, so it changed in between
+                            Err(RepositoryError::VersionConflict(format!(
+                                "{}|{}|{}|{} changed while it was being updated",
+                                record.entity_id(),
+                                record.authority_id(),
+                                record.action(),
+                                record.resource()
+                            )))
+                        }
+                        _ => Err(RepositoryError::RecordNotFound(format!(
+                            "Record not found: {}|{}|{}|{}",
+                            record.entity_id(),
+                            record.authority_id(),
+                            record.action(),
+                            record.resource()
+                        ))),
+                    }
+                }
+                _ => Err(RepositoryError::QueryFailed(format!(
+                    "Failed to update record: {err}"
+                ))),
+            },
+        }
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        debug!(
+            entity = record.entity_id().as_str(),
+            authority = record.authority_id().as_str(),
+            action = record.action().as_str(),
+            resource = record.resource().as_str(),
+            "Upserting trust record in DynamoDB"
+        );
+
+        let mut item: HashMap<String, AttributeValue> = serde_dynamo::to_item(&record)
+            .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+
+        let key_value = format!(
+            "{}|{}|{}|{}",
+            record.entity_id(),
+            record.authority_id(),
+            record.action(),
+            record.resource()
+        );
+        item.insert(PK_ATTR.to_string(), AttributeValue::S(key_value.clone()));
+        item.insert(SK_ATTR.to_string(), AttributeValue::S(key_value));
+
+        // unconditional put; the previous item tells us whether it was an overwrite
+        let response = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|err| {
+                RepositoryError::QueryFailed(format!("Failed to upsert record: {err}"))
+            })?;
+
+        Ok(match response.attributes {
+            Some(previous) if !previous.is_empty() => UpsertOutcome::Updated,
+            _ => UpsertOutcome::Created,
+        })
+    }
+
     async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
         debug!(
             entity = query.entity_id.as_str(),
@@ -254,29 +464,6 @@ impl TrustRecordAdminRepository for DynamoDbStorage {
         Ok(())
     }
 
-    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
-        debug!("Listing all trust records from DynamoDB");
-
-        let response = self
-            .client
-            .scan()
-            .table_name(&self.table_name)
-            .send()
-            .await
-            .map_err(|err| RepositoryError::QueryFailed(format!("Failed to scan table: {err}")))?;
-
-        let items = response.items.unwrap_or_default();
-        let mut records = Vec::with_capacity(items.len());
-
-        for item in items {
-            let record: TrustRecord = serde_dynamo::from_item(item)
-                .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
-            records.push(record);
-        }
-
-        Ok(TrustRecordList::new(records))
-    }
-
     async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
         debug!(
             entity = query.entity_id.as_str(),
@@ -303,6 +490,7 @@ impl TrustRecordAdminRepository for DynamoDbStorage {
 
         if let Some(item) = response.item {
             let trust_record: TrustRecord = serde_dynamo::from_item(item)
+                .map(TrustRecord::migrate)
                 .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
             return Ok(trust_record);
         }
@@ -312,4 +500,40 @@ impl TrustRecordAdminRepository for DynamoDbStorage {
             query.entity_id, query.authority_id, query.action, query.resource
         )))
     }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        debug!(tag, "Scanning trust records by tag in DynamoDB");
+
+        let mut records = Vec::new();
+        let mut start_key = None;
+        loop {
+            let response = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .filter_expression("contains(tags, :tag)")
+                .expression_attribute_values(":tag", AttributeValue::S(tag.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|err| {
+                    RepositoryError::QueryFailed(format!("Failed to scan table: {err}"))
+                })?;
+
+            for item in response.items.unwrap_or_default() {
+                let record: TrustRecord =
+                    serde_dynamo::from_item(item)
+                        .map(TrustRecord::migrate)
+                        .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+                records.push(record);
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(records)
+    }
 }
@@ -0,0 +1,506 @@
+use crate::domain::*;
+use crate::storage::repository::*;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tokio::time::sleep;
+use tracing::{error, info};
+
+type RecordKey = (EntityId, AuthorityId, Action, Resource);
+
+fn record_key(record: &TrustRecord) -> RecordKey {
+    (
+        record.entity_id().clone(),
+        record.authority_id().clone(),
+        record.action().clone(),
+        record.resource().clone(),
+    )
+}
+
+fn query_key(query: &TrustRecordQuery) -> RecordKey {
+    (
+        query.entity_id.clone(),
+        query.authority_id.clone(),
+        query.action.clone(),
+        query.resource.clone(),
+    )
+}
+
+fn not_found(query: &TrustRecordQuery) -> RepositoryError {
+    RepositoryError::RecordNotFound(format!(
+        "Record not found: {}|{}|{}|{}",
+        query.entity_id, query.authority_id, query.action, query.resource
+    ))
+}
+
+/// Stores trust records as a JSON array with contexts as plain JSON objects.
+///
+/// Uses the same columns as the CSV file and reloads the file when it changes, like
+/// [`FileStorage`](super::csv_file_storage::FileStorage).
+#[derive(Clone)]
+pub struct JsonFileStorage {
+    file_path: PathBuf,
+    update_interval: Duration,
+    records: Arc<RwLock<HashMap<RecordKey, TrustRecord>>>,
+    last_modified: Arc<RwLock<Option<SystemTime>>>,
+}
+
+impl JsonFileStorage {
+    pub async fn try_new<P: Into<PathBuf>>(
+        file_path: P,
+        update_interval_sec: u64,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file_path = file_path.into();
+        let (records, modified) =
+            Self::load_if_modified(&file_path, None)
+                .await?
+                .ok_or_else(|| {
+                    anyhow!("unable to load trust records from {}", file_path.display())
+                        .into_boxed_dyn_error()
+                })?;
+
+        let storage = Self {
+            file_path,
+            update_interval: Duration::from_secs(update_interval_sec),
+            records: Arc::new(RwLock::new(records)),
+            last_modified: Arc::new(RwLock::new(Some(modified))),
+        };
+        storage.spawn_sync_task();
+
+        Ok(storage)
+    }
+
+    fn spawn_sync_task(&self) {
+        let file_path = self.file_path.clone();
+        let update_interval = self.update_interval;
+        let records = Arc::clone(&self.records);
+        let last_modified = Arc::clone(&self.last_modified);
+
+        tokio::spawn(async move {
+            loop {
+                sleep(update_interval).await;
+
+                let previous = { *last_modified.read().unwrap() };
+                match Self::load_if_modified(&file_path, previous).await {
+                    Ok(Some((new_records, modified))) => {
+                        *records.write().unwrap() = new_records;
+                        *last_modified.write().unwrap() = Some(modified);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        error!(
+                            error = %err,
+                            path = %file_path.display(),
+                            "Failed to sync trust records from file"
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    /// Reads and parses the file without starting the refresh task. Returns the record count.
+    pub async fn check_file(
+        path: &Path,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let (records, _) = Self::load_if_modified(path, None).await?.ok_or_else(|| {
+            anyhow!("unable to load trust records from {}", path.display()).into_boxed_dyn_error()
+        })?;
+        Ok(records.len())
+    }
+
+    async fn load_if_modified(
+        path: &Path,
+        last_seen: Option<SystemTime>,
+    ) -> Result<
+        Option<(HashMap<RecordKey, TrustRecord>, SystemTime)>,
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| format!("file_path: {path:?}, error: {e}"))?;
+        let modified = metadata.modified()?;
+
+        if let Some(previous) = last_seen
+            && modified <= previous
+        {
+            return Ok(None);
+        }
+
+        info!(
+            path = %path.display(),
+            "Changes detected in trust records file, reloading"
+        );
+        let contents = tokio::fs::read_to_string(path).await?;
+
+        Ok(Some((Self::parse_json(&contents)?, modified)))
+    }
+
+    /// Parses JSON content that didn't come from a file, see [`RemoteStorage`](super::remote_storage::RemoteStorage).
+    pub(crate) fn parse_records(
+        contents: &str,
+    ) -> Result<Vec<TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self::parse_json(contents)?.into_values().collect())
+    }
+
+    fn parse_json(
+        contents: &str,
+    ) -> Result<HashMap<RecordKey, TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows: Vec<TrustRecordJsonRow> = if contents.trim().is_empty() {
+            vec![]
+        } else {
+            serde_json::from_str(contents)?
+        };
+
+        let mut records = HashMap::new();
+        for row in rows {
+            let record = row.into_record()?;
+            records.insert(record_key(&record), record);
+        }
+        Ok(records)
+    }
+
+    async fn write_to_file(&self) -> Result<(), RepositoryError> {
+        let rows: Vec<TrustRecordJsonRow> = {
+            let records = self.records.read().unwrap();
+            records
+                .values()
+                .map(TrustRecordJsonRow::from_record)
+                .collect()
+        };
+        let json = serde_json::to_vec_pretty(&rows)
+            .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+
+        tokio::fs::write(&self.file_path, json)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Failed to write JSON file: {e}")))?;
+
+        // Update last_modified to prevent reload
+        let modified = tokio::fs::metadata(&self.file_path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| {
+                RepositoryError::QueryFailed(format!("Failed to get modified time: {e}"))
+            })?;
+        *self.last_modified.write().unwrap() = Some(modified);
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for JsonFileStorage {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        Ok(self
+            .records
+            .read()
+            .unwrap()
+            .get(&query_key(&query))
+            .cloned())
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| {
+                record.entity_id() == entity_id && record.authority_id() == authority_id
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(TrustRecordList::new(records.values().cloned().collect()))
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for JsonFileStorage {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let key = record_key(&record);
+        {
+            let mut records = self.records.write().unwrap();
+            if records.contains_key(&key) {
+                return Err(RepositoryError::RecordAlreadyExists(format!(
+                    "Record already exists: {}|{}|{}|{}",
+                    record.entity_id(),
+                    record.authority_id(),
+                    record.action(),
+                    record.resource(),
+                )));
+            }
+            records.insert(key, record);
+        }
+        self.write_to_file().await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let key = record_key(&record);
+        {
+            let mut records = self.records.write().unwrap();
+            if !records.contains_key(&key) {
+                return Err(RepositoryError::RecordNotFound(format!(
+                    "Record not found: {}|{}|{}|{}",
+                    record.entity_id(),
+                    record.authority_id(),
+                    record.action(),
+                    record.resource()
+                )));
+            }
+            records.insert(key, record);
+        }
+        self.write_to_file().await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let outcome = match self
+            .records
+            .write()
+            .unwrap()
+            .insert(record_key(&record), record)
+        {
+            Some(_) => UpsertOutcome::Updated,
+            None => UpsertOutcome::Created,
+        };
+        self.write_to_file().await?;
+        Ok(outcome)
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        if self
+            .records
+            .write()
+            .unwrap()
+            .remove(&query_key(&query))
+            .is_none()
+        {
+            return Err(not_found(&query));
+        }
+        self.write_to_file().await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.records
+            .read()
+            .unwrap()
+            .get(&query_key(&query))
+            .cloned()
+            .ok_or_else(|| not_found(&query))
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| record.has_tag(tag))
+            .cloned()
+            .collect())
+    }
+}
+
+/// One element of the JSON array, with the same fields as a CSV row.
+#[derive(Debug, Deserialize, Serialize)]
+struct TrustRecordJsonRow {
+    entity_id: String,
+    authority_id: String,
+    action: String,
+    resource: String,
+    recognized: bool,
+    authorized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context: Option<Value>,
+    record_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema_version: Option<u32>,
+}
+
+impl TrustRecordJsonRow {
+    fn from_record(record: &TrustRecord) -> Self {
+        let context = record.context().as_value();
+        Self {
+            entity_id: record.entity_id().to_string(),
+            authority_id: record.authority_id().to_string(),
+            action: record.action().to_string(),
+            resource: record.resource().to_string(),
+            recognized: record.is_recognized(),
+            authorized: record.is_authorized(),
+            context: (context.is_object() || context.is_array()).then(|| context.clone()),
+            record_type: record.record_type().to_string(),
+            expires_at: record.expires_at().copied(),
+            tags: record.tags().to_vec(),
+            attachments: record.attachments().to_vec(),
+            version: Some(record.version()),
+            created_at: record.created_at().copied(),
+            updated_at: record.updated_at().copied(),
+            schema_version: Some(record.schema_version()),
+        }
+    }
+
+    fn into_record(self) -> Result<TrustRecord, Box<dyn std::error::Error + Send + Sync>> {
+        let mut builder = TrustRecordBuilder::new()
+            .entity_id(EntityId::new(self.entity_id))
+            .authority_id(AuthorityId::new(self.authority_id))
+            .action(Action::new(self.action))
+            .resource(Resource::new(self.resource))
+            .recognized(self.recognized)
+            .authorized(self.authorized)
+            .record_type(RecordType::from_str(&self.record_type)?)
+            .tags(self.tags);
+        if let Some(context) = self.context {
+            builder = builder.context(Context::new(context));
+        }
+        if let Some(expires_at) = self.expires_at {
+            builder = builder.expires_at(expires_at);
+        }
+
+        let mut record = builder
+            .build()
+            .map_err(|err| anyhow!("invalid trust record: {err}"))?
+            .with_attachments(self.attachments)
+            .with_created_at(self.created_at)
+            .with_updated_at(self.updated_at);
+        if let Some(version) = self.version {
+            record = record.with_version(version);
+        }
+        Ok(record
+            .with_schema_version(self.schema_version.unwrap_or(LEGACY_SCHEMA_VERSION))
+            .migrate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn query(entity: &str) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new(entity),
+            AuthorityId::new("a1"),
+            Action::new("ac1"),
+            Resource::new("r1"),
+        )
+    }
+
+    fn row(entity: &str, record_type: &str) -> Value {
+        json!({
+            "entity_id": entity,
+            "authority_id": "a1",
+            "action": "ac1",
+            "resource": "r1",
+            "recognized": true,
+            "authorized": true,
+            "context": { "framework": { "name": "example", "levels": [1, 2] } },
+            "record_type": record_type
+        })
+    }
+
+    fn json_file(rows: Vec<Value>) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", Value::Array(rows)).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn finds_records_from_initial_load() {
+        let file = json_file(vec![row("e1", "assertion")]);
+
+        let storage = JsonFileStorage::try_new(file.path(), 60).await.unwrap();
+
+        let record = storage.find_by_query(query("e1")).await.unwrap().unwrap();
+        assert_eq!(*record.record_type(), RecordType::Authorization);
+        assert_eq!(
+            record.context().as_value()["framework"]["levels"],
+            json!([1, 2])
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_invalid_json() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "[{{\"entity_id\": ").unwrap();
+
+        assert!(JsonFileStorage::try_new(file.path(), 60).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reloads_when_file_changes() {
+        let file = json_file(vec![row("e1", "recognition")]);
+        let storage = JsonFileStorage::try_new(file.path(), 1).await.unwrap();
+
+        sleep(Duration::from_secs(1)).await;
+        std::fs::write(
+            file.path(),
+            Value::Array(vec![row("e1", "recognition"), row("e2", "recognition")]).to_string(),
+        )
+        .unwrap();
+
+        // Wait for sync task to detect and process changes
+        sleep(Duration::from_secs(2)).await;
+
+        let record = storage.find_by_query(query("e2")).await.unwrap().unwrap();
+        assert_eq!(*record.record_type(), RecordType::Recognition);
+    }
+
+    #[tokio::test]
+    async fn round_trips_nested_context_as_plain_json() {
+        let file = json_file(vec![]);
+        let storage = JsonFileStorage::try_new(file.path(), 60).await.unwrap();
+        let context = json!({
+            "framework": { "name": "example", "levels": [1, 2] },
+            "notes": "a,b;\"quoted\""
+        });
+        let record = TrustRecordBuilder::new()
+            .entity_id(EntityId::new("e1"))
+            .authority_id(AuthorityId::new("a1"))
+            .action(Action::new("ac1"))
+            .resource(Resource::new("r1"))
+            .recognized(true)
+            .authorized(false)
+            .context(Context::new(context.clone()))
+            .record_type(RecordType::Authorization)
+            .build()
+            .unwrap();
+
+        storage.create(record.clone()).await.unwrap();
+
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(written[0]["context"], context);
+        let reloaded =
+            JsonFileStorage::parse_json(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(reloaded.values().next().unwrap(), &record);
+    }
+}
@@ -54,6 +54,15 @@ impl LocalStorage {
         storage
     }
 
+    /// Swaps the whole record set in one step, so readers never see a partial set.
+    pub fn replace_all(&self, records: Vec<TrustRecord>) {
+        let records = records
+            .into_iter()
+            .map(|record| (RecordKey::from_record(&record), record))
+            .collect();
+        *self.records.write().unwrap() = records;
+    }
+
     pub fn clear(&self) {
         self.records.write().unwrap().clear();
     }
@@ -85,6 +94,27 @@ impl TrustRecordRepository for LocalStorage {
             .cloned();
         Ok(result)
     }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| {
+                record.entity_id() == entity_id && record.authority_id() == authority_id
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        let records = self.records.read().unwrap();
+        let records_vec: Vec<TrustRecord> = records.values().cloned().collect();
+        Ok(TrustRecordList::new(records_vec))
+    }
 }
 
 #[async_trait::async_trait]
@@ -121,6 +151,36 @@ impl TrustRecordAdminRepository for LocalStorage {
         Ok(())
     }
 
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        let key = RecordKey::from_record(&record);
+        let mut records = self.records.write().unwrap();
+        let Some(current) = records.get(&key) else {
+            return Err(RepositoryError::RecordNotFound(format!(
+                "Record not found: {}|{}|{}|{}",
+                record.entity_id(),
+                record.authority_id(),
+                record.action(),
+                record.resource()
+            )));
+        };
+        check_version(current, expected_version)?;
+        records.insert(key, record);
+        Ok(())
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let key = RecordKey::from_record(&record);
+        let mut records = self.records.write().unwrap();
+        Ok(match records.insert(key, record) {
+            Some(_) => UpsertOutcome::Updated,
+            None => UpsertOutcome::Created,
+        })
+    }
+
     async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
         let key = RecordKey {
             entity_id: query.entity_id.clone(),
@@ -138,12 +198,6 @@ impl TrustRecordAdminRepository for LocalStorage {
         Ok(())
     }
 
-    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
-        let records = self.records.read().unwrap();
-        let records_vec: Vec<TrustRecord> = records.values().cloned().collect();
-        Ok(TrustRecordList::new(records_vec))
-    }
-
     async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
         let records = self.records.read().unwrap();
         let result = records
@@ -158,6 +212,15 @@ impl TrustRecordAdminRepository for LocalStorage {
             ))
         })
     }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .values()
+            .filter(|record| record.has_tag(tag))
+            .cloned()
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -223,4 +286,51 @@ mod tests {
         assert_eq!(record.action().as_str(), "action-1");
         assert_eq!(record.resource().as_str(), "resource-1");
     }
+
+    #[tokio::test]
+    async fn test_list_returns_records_in_stable_key_order() {
+        let keys = [
+            ("entity-2", "authority-1", "action-1", "resource-1"),
+            ("entity-1", "authority-2", "action-1", "resource-1"),
+            ("entity-1", "authority-1", "action-2", "resource-1"),
+            ("entity-1", "authority-1", "action-1", "resource-2"),
+            ("entity-1", "authority-1", "action-1", "resource-1"),
+        ];
+        let storage = LocalStorage::with_records(
+            keys.iter()
+                .map(|(e, a, ac, r)| create_test_record(e, a, ac, r, true, true, "assertion"))
+                .collect(),
+        );
+
+        let key_of = |record: &TrustRecord| {
+            format!(
+                "{}|{}|{}|{}",
+                record.entity_id(),
+                record.authority_id(),
+                record.action(),
+                record.resource()
+            )
+        };
+        let first: Vec<String> = storage
+            .list()
+            .await
+            .unwrap()
+            .records()
+            .iter()
+            .map(key_of)
+            .collect();
+        let second: Vec<String> = storage
+            .list()
+            .await
+            .unwrap()
+            .records()
+            .iter()
+            .map(key_of)
+            .collect();
+
+        assert_eq!(first, second);
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+    }
 }
@@ -1,4 +1,6 @@
 pub mod csv_file_storage;
 pub mod ddb_storage;
+pub mod json_file_storage;
 pub mod local_storage;
 pub mod redis_storage;
+pub mod remote_storage;
@@ -6,12 +6,83 @@ use tracing::{debug, error, info};
 use crate::domain::*;
 use crate::storage::repository::*;
 
+const SCAN_COUNT: usize = 1_000;
+
+/// What a create, update, upsert or delete waits for before reporting success.
+/// The default waits for nothing, leaving durability to the Redis persistence config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteDurability {
+    /// Replicas that must acknowledge the write, via `WAIT`.
+    pub replicas: u32,
+    /// Also wait for the write to be fsynced to the local AOF, via `WAITAOF`.
+    pub fsync: bool,
+    /// How long to wait for the acknowledgements. `0` waits forever.
+    pub timeout_ms: u64,
+}
+
+impl WriteDurability {
+    fn is_enabled(&self) -> bool {
+        self.replicas > 0 || self.fsync
+    }
+
+    /// `WAIT` or `WAITAOF` issued after each write, `None` when nothing is awaited.
+    fn command(&self) -> Option<redis::Cmd> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut cmd = if self.fsync {
+            let mut cmd = redis::cmd("WAITAOF");
+            cmd.arg(1);
+            cmd
+        } else {
+            redis::cmd("WAIT")
+        };
+        cmd.arg(self.replicas).arg(self.timeout_ms);
+        Some(cmd)
+    }
+
+    /// Checks the reply of [`WriteDurability::command`]: a replica count for `WAIT`,
+    /// `[local, replicas]` for `WAITAOF`.
+    fn check_reply(&self, reply: &redis::Value) -> Result<(), RepositoryError> {
+        let count = |value: &redis::Value| match value {
+            redis::Value::Int(n) => Some(*n),
+            _ => None,
+        };
+        let (local, replicas) = match reply {
+            redis::Value::Array(values) if values.len() == 2 => {
+                (count(&values[0]), count(&values[1]))
+            }
+            value => (Some(1), count(value)),
+        };
+        let (Some(local), Some(replicas)) = (local, replicas) else {
+            return Err(RepositoryError::QueryFailed(format!(
+                "Unexpected Redis WAIT reply: {reply:?}"
+            )));
+        };
+        if local < 1 {
+            return Err(RepositoryError::QueryFailed(
+                "Write was not fsynced to the AOF before the WAITAOF timeout".to_string(),
+            ));
+        }
+        if replicas < i64::from(self.replicas) {
+            return Err(RepositoryError::QueryFailed(format!(
+                "Write acknowledged by {replicas} of {} replicas before the WAIT timeout",
+                self.replicas
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Redis storage adapter for Trust Registry
-/// Keys are formatted as: entity_id|authority_id|action|resource
+/// Keys are formatted as: tr:{prefix}:entity_id|authority_id|action|resource,
+/// or entity_id|authority_id|action|resource when no prefix is configured.
 /// Values are JSON-serialized TrustRecord objects
 #[derive(Clone)]
 pub struct RedisStorage {
     connection: Arc<RwLock<MultiplexedConnection>>,
+    key_namespace: String,
+    durability: WriteDurability,
 }
 
 impl RedisStorage {
@@ -22,17 +93,66 @@ impl RedisStorage {
 
         Ok(Self {
             connection: Arc::new(RwLock::new(connection)),
+            key_namespace: String::new(),
+            durability: WriteDurability::default(),
         })
     }
 
+    /// Makes writes wait for replication or AOF persistence, trading latency for durability.
+    pub fn with_durability(mut self, durability: WriteDurability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Waits for the configured durability after a write on `conn`.
+    async fn await_durability(
+        &self,
+        conn: &mut MultiplexedConnection,
+    ) -> Result<(), RepositoryError> {
+        let Some(cmd) = self.durability.command() else {
+            return Ok(());
+        };
+        let reply: redis::Value = cmd
+            .query_async(conn)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis WAIT failed: {e}")))?;
+        self.durability.check_reply(&reply)
+    }
+
+    /// Keeps this registry's records under `tr:{prefix}:` so several registries can
+    /// share one Redis. An empty prefix keeps the unprefixed key layout.
+    pub fn with_key_prefix(mut self, prefix: &str) -> Self {
+        self.key_namespace = Self::namespace(prefix);
+        self
+    }
+
+    fn namespace(prefix: &str) -> String {
+        if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("tr:{prefix}:")
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), RepositoryError> {
+        let mut conn = self.connection.write().await;
+        redis::cmd("PING")
+            .query_async::<String>(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|e| RepositoryError::ConnectionFailed(format!("Redis PING failed: {e}")))
+    }
+
     fn generate_key(
+        &self,
         entity_id: &EntityId,
         authority_id: &AuthorityId,
         action: &Action,
         resource: &Resource,
     ) -> String {
         format!(
-            "{}|{}|{}|{}",
+            "{}{}|{}|{}|{}",
+            self.key_namespace,
             entity_id.as_str(),
             authority_id.as_str(),
             action.as_str(),
@@ -40,8 +160,8 @@ impl RedisStorage {
         )
     }
 
-    fn key_from_record(record: &TrustRecord) -> String {
-        Self::generate_key(
+    fn key_from_record(&self, record: &TrustRecord) -> String {
+        self.generate_key(
             record.entity_id(),
             record.authority_id(),
             record.action(),
@@ -49,8 +169,8 @@ impl RedisStorage {
         )
     }
 
-    fn key_from_query(query: &TrustRecordQuery) -> String {
-        Self::generate_key(
+    fn key_from_query(&self, query: &TrustRecordQuery) -> String {
+        self.generate_key(
             &query.entity_id,
             &query.authority_id,
             &query.action,
@@ -64,10 +184,59 @@ impl RedisStorage {
         })
     }
 
+    /// Escapes glob metacharacters so ids can be used in a `MATCH` pattern.
+    fn escape_pattern(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// `MATCH` pattern for keys of this namespace, with `suffix` appended unescaped.
+    fn key_pattern(&self, suffix: &str) -> String {
+        format!("{}{suffix}", Self::escape_pattern(&self.key_namespace))
+    }
+
+    /// Collects matching keys with `SCAN`, which unlike `KEYS` doesn't block Redis
+    /// while walking the keyspace.
+    async fn scan_keys(
+        conn: &mut MultiplexedConnection,
+        pattern: &str,
+    ) -> Result<Vec<String>, RepositoryError> {
+        let mut keys = Vec::new();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next_cursor, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(conn)
+                .await
+                .map_err(|e| RepositoryError::QueryFailed(format!("Redis SCAN failed: {e}")))?;
+            keys.extend(batch);
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+        // SCAN may return a key more than once
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
     fn deserialize_record(data: &str) -> Result<TrustRecord, RepositoryError> {
-        serde_json::from_str(data).map_err(|e| {
-            RepositoryError::SerializationFailed(format!("Failed to deserialize record: {e}"))
-        })
+        serde_json::from_str(data)
+            .map(TrustRecord::migrate)
+            .map_err(|e| {
+                RepositoryError::SerializationFailed(format!("Failed to deserialize record: {e}"))
+            })
     }
 }
 
@@ -77,7 +246,7 @@ impl TrustRecordRepository for RedisStorage {
         &self,
         query: TrustRecordQuery,
     ) -> Result<Option<TrustRecord>, RepositoryError> {
-        let key = Self::key_from_query(&query);
+        let key = self.key_from_query(&query);
         debug!("Finding record by key: {}", key);
 
         let mut conn = self.connection.write().await;
@@ -94,12 +263,78 @@ impl TrustRecordRepository for RedisStorage {
             None => Ok(None),
         }
     }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let pattern = self.key_pattern(&format!(
+            "{}|{}|*|*",
+            Self::escape_pattern(entity_id.as_str()),
+            Self::escape_pattern(authority_id.as_str())
+        ));
+        debug!("Finding records matching: {}", pattern);
+
+        let mut conn = self.connection.write().await;
+        let keys = Self::scan_keys(&mut conn, &pattern).await?;
+
+        let mut records = Vec::with_capacity(keys.len());
+        for key in keys {
+            let data: Option<String> = conn
+                .get(&key)
+                .await
+                .map_err(|e| RepositoryError::QueryFailed(format!("Redis GET failed: {e}")))?;
+            if let Some(data) = data {
+                let record = Self::deserialize_record(&data)?;
+                // guard against ids containing the key separator
+                if record.entity_id() == entity_id && record.authority_id() == authority_id {
+                    records.push(record);
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        debug!("Listing all records");
+
+        let mut conn = self.connection.write().await;
+
+        let keys = Self::scan_keys(&mut conn, &self.key_pattern("*|*|*|*")).await?;
+
+        let mut records = Vec::new();
+
+        for key in keys {
+            // an unprefixed pattern also matches other registries' namespaces
+            if self.key_namespace.is_empty() && key.starts_with("tr:") {
+                continue;
+            }
+            let data: Option<String> = conn
+                .get(&key)
+                .await
+                .map_err(|e| RepositoryError::QueryFailed(format!("Redis GET failed: {e}")))?;
+
+            if let Some(data) = data {
+                match Self::deserialize_record(&data) {
+                    Ok(record) => records.push(record),
+                    Err(e) => {
+                        error!("Failed to deserialize record for key {}: {}", key, e);
+                    }
+                }
+            }
+        }
+
+        info!("Listed {} records", records.len());
+        Ok(TrustRecordList::new(records))
+    }
 }
 
 #[async_trait::async_trait]
 impl TrustRecordAdminRepository for RedisStorage {
     async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
-        let key = Self::key_from_record(&record);
+        let key = self.key_from_record(&record);
         debug!("Creating record with key: {}", key);
 
         let mut conn = self.connection.write().await;
@@ -125,13 +360,14 @@ impl TrustRecordAdminRepository for RedisStorage {
             .set(&key, value)
             .await
             .map_err(|e| RepositoryError::QueryFailed(format!("Redis SET failed: {e}")))?;
+        self.await_durability(&mut conn).await?;
 
         info!("Record created successfully: {}", key);
         Ok(())
     }
 
     async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
-        let key = Self::key_from_record(&record);
+        let key = self.key_from_record(&record);
         debug!("Updating record with key: {}", key);
 
         let mut conn = self.connection.write().await;
@@ -157,67 +393,174 @@ impl TrustRecordAdminRepository for RedisStorage {
             .set(&key, value)
             .await
             .map_err(|e| RepositoryError::QueryFailed(format!("Redis SET failed: {e}")))?;
+        self.await_durability(&mut conn).await?;
 
         info!("Record updated successfully: {}", key);
         Ok(())
     }
 
-    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
-        let key = Self::key_from_query(&query);
-        debug!("Deleting record with key: {}", key);
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        let key = self.key_from_record(&record);
+        debug!(
+            "Updating record with key: {} at version {}",
+            key, expected_version
+        );
 
+        let value = Self::serialize_record(&record)?;
         let mut conn = self.connection.write().await;
 
-        let deleted: i32 = conn
-            .del(&key)
+        // the SET below is discarded if the key changes between WATCH and EXEC
+        let _: () = redis::cmd("WATCH")
+            .arg(&key)
+            .query_async(&mut *conn)
             .await
-            .map_err(|e| RepositoryError::QueryFailed(format!("Redis DEL failed: {e}")))?;
-
-        if deleted == 0 {
-            return Err(RepositoryError::RecordNotFound(format!(
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis WATCH failed: {e}")))?;
+        let checked = match conn.get::<_, Option<String>>(&key).await {
+            Ok(Some(data)) => Self::deserialize_record(&data)
+                .and_then(|current| check_version(&current, expected_version)),
+            Ok(None) => Err(RepositoryError::RecordNotFound(format!(
                 "Record not found: {}|{}|{}|{}",
-                query.entity_id, query.authority_id, query.action, query.resource
+                record.entity_id(),
+                record.authority_id(),
+                record.action(),
+                record.resource()
+            ))),
+            Err(e) => Err(RepositoryError::QueryFailed(format!(
+                "Redis GET failed: {e}"
+            ))),
+        };
+        if let Err(e) = checked {
+            let _: Result<(), _> = redis::cmd("UNWATCH").query_async(&mut *conn).await;
+            return Err(e);
+        }
+
+        let executed: Option<()> = redis::pipe()
+            .atomic()
+            .set(&key, value)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis SET failed: {e}")))?;
+        if executed.is_none() {
+            return Err(RepositoryError::VersionConflict(format!(
+                "{key} changed while it was being updated"
             )));
         }
+        self.await_durability(&mut conn).await?;
 
-        info!("Record deleted successfully: {}", key);
+        info!("Record updated successfully: {}", key);
         Ok(())
     }
 
-    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
-        debug!("Listing all records");
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        if queries.is_empty() {
+            return Ok(vec![]);
+        }
+        let keys: Vec<String> = queries
+            .iter()
+            .map(|query| self.key_from_query(query))
+            .collect();
+        debug!("Reading {} records with MGET", keys.len());
 
         let mut conn = self.connection.write().await;
+        let values: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis MGET failed: {e}")))?;
+
+        values
+            .into_iter()
+            .map(|value| {
+                value
+                    .map(|data| Self::deserialize_record(&data))
+                    .transpose()
+            })
+            .collect()
+    }
 
-        let keys: Vec<String> = conn
-            .keys("*|*|*|*")
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        if queries.is_empty() {
+            return Ok(vec![]);
+        }
+        debug!("Deleting {} records in one transaction", queries.len());
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for query in &queries {
+            pipe.del(self.key_from_query(query));
+        }
+        let mut conn = self.connection.write().await;
+        let deleted: Vec<i32> = pipe
+            .query_async(&mut *conn)
             .await
-            .map_err(|e| RepositoryError::QueryFailed(format!("Redis KEYS failed: {e}")))?;
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis DEL failed: {e}")))?;
+        self.await_durability(&mut conn).await?;
 
-        let mut records = Vec::new();
+        Ok(deleted.into_iter().map(|count| count > 0).collect())
+    }
 
-        for key in keys {
-            let data: Option<String> = conn
-                .get(&key)
-                .await
-                .map_err(|e| RepositoryError::QueryFailed(format!("Redis GET failed: {e}")))?;
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let key = self.key_from_record(&record);
+        debug!("Upserting record with key: {}", key);
 
-            if let Some(data) = data {
-                match Self::deserialize_record(&data) {
-                    Ok(record) => records.push(record),
-                    Err(e) => {
-                        error!("Failed to deserialize record for key {}: {}", key, e);
-                    }
-                }
-            }
+        let value = Self::serialize_record(&record)?;
+        let mut conn = self.connection.write().await;
+
+        // EXISTS and SET run in one MULTI/EXEC transaction
+        let (existed,): (bool,) = redis::pipe()
+            .atomic()
+            .exists(&key)
+            .set(&key, value)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis upsert failed: {e}")))?;
+        self.await_durability(&mut conn).await?;
+
+        info!("Record upserted successfully: {}", key);
+        Ok(if existed {
+            UpsertOutcome::Updated
+        } else {
+            UpsertOutcome::Created
+        })
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        let key = self.key_from_query(&query);
+        debug!("Deleting record with key: {}", key);
+
+        let mut conn = self.connection.write().await;
+
+        let deleted: i32 = conn
+            .del(&key)
+            .await
+            .map_err(|e| RepositoryError::QueryFailed(format!("Redis DEL failed: {e}")))?;
+
+        if deleted == 0 {
+            return Err(RepositoryError::RecordNotFound(format!(
+                "Record not found: {}|{}|{}|{}",
+                query.entity_id, query.authority_id, query.action, query.resource
+            )));
         }
+        self.await_durability(&mut conn).await?;
 
-        info!("Listed {} records", records.len());
-        Ok(TrustRecordList::new(records))
+        info!("Record deleted successfully: {}", key);
+        Ok(())
     }
 
     async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
-        let key = Self::key_from_query(&query);
+        let key = self.key_from_query(&query);
         debug!("Reading record with key: {}", key);
 
         let mut conn = self.connection.write().await;
@@ -281,6 +624,78 @@ mod tests {
         let _: Result<(), _> = redis::cmd("FLUSHDB").query_async(&mut *conn).await;
     }
 
+    #[test]
+    fn test_durability_command() {
+        let packed = |durability: WriteDurability| {
+            durability
+                .command()
+                .map(|cmd| String::from_utf8_lossy(&cmd.get_packed_command()).into_owned())
+        };
+
+        assert!(packed(WriteDurability::default()).is_none());
+        assert_eq!(
+            packed(WriteDurability {
+                replicas: 2,
+                fsync: false,
+                timeout_ms: 500,
+            })
+            .unwrap(),
+            "*3\r\n$4\r\nWAIT\r\n$1\r\n2\r\n$3\r\n500\r\n"
+        );
+        assert_eq!(
+            packed(WriteDurability {
+                replicas: 0,
+                fsync: true,
+                timeout_ms: 500,
+            })
+            .unwrap(),
+            "*4\r\n$7\r\nWAITAOF\r\n$1\r\n1\r\n$1\r\n0\r\n$3\r\n500\r\n"
+        );
+    }
+
+    #[test]
+    fn test_durability_reply_must_cover_replicas_and_fsync() {
+        let durability = WriteDurability {
+            replicas: 2,
+            fsync: false,
+            timeout_ms: 500,
+        };
+        assert!(durability.check_reply(&redis::Value::Int(2)).is_ok());
+        assert!(durability.check_reply(&redis::Value::Int(1)).is_err());
+
+        let durability = WriteDurability {
+            fsync: true,
+            ..durability
+        };
+        let reply = |local, replicas| {
+            redis::Value::Array(vec![redis::Value::Int(local), redis::Value::Int(replicas)])
+        };
+        assert!(durability.check_reply(&reply(1, 2)).is_ok());
+        assert!(durability.check_reply(&reply(0, 2)).is_err());
+        assert!(durability.check_reply(&reply(1, 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_fails_when_replicas_do_not_acknowledge() {
+        let Some(storage) = get_test_storage().await else {
+            return;
+        };
+        // a standalone test Redis has no replicas to acknowledge the write
+        let storage = storage
+            .with_key_prefix("durability-test")
+            .with_durability(WriteDurability {
+                replicas: 1,
+                fsync: false,
+                timeout_ms: 50,
+            });
+
+        let record = create_test_record("e-wait", "a-wait", "ac", "r", true, true, "assertion");
+        let err = storage.create(record.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("WAIT"), "{err}");
+
+        let _ = storage.delete(TrustRecordQuery::from(&record)).await;
+    }
+
     #[tokio::test]
     async fn test_create_and_read_record() {
         let Some(storage) = get_test_storage().await else {
@@ -388,6 +803,39 @@ mod tests {
         cleanup_test_data(&storage).await;
     }
 
+    #[tokio::test]
+    async fn test_stale_conditional_update_is_rejected() {
+        let Some(storage) = get_test_storage().await else {
+            return;
+        };
+        cleanup_test_data(&storage).await;
+
+        let record = create_test_record(
+            "did:example:entity1",
+            "did:example:authority1",
+            "issue",
+            "VerifiableCredential",
+            true,
+            true,
+            "assertion",
+        );
+        storage.create(record.clone()).await.unwrap();
+        let query = TrustRecordQuery::from(&record);
+
+        let first = record.clone().with_authorized(false).with_version(2);
+        storage.update_if_version(first, 1).await.unwrap();
+
+        let stale = record.with_version(2);
+        let result = storage.update_if_version(stale, 1).await;
+        assert!(matches!(result, Err(RepositoryError::VersionConflict(_))));
+
+        let retrieved = storage.read(query).await.unwrap();
+        assert_eq!(retrieved.version(), 2);
+        assert!(!retrieved.is_authorized());
+
+        cleanup_test_data(&storage).await;
+    }
+
     #[tokio::test]
     async fn test_delete_record() {
         let Some(storage) = get_test_storage().await else {
@@ -459,6 +907,106 @@ mod tests {
         cleanup_test_data(&storage).await;
     }
 
+    #[test]
+    fn test_deserializes_v1_record_with_defaults() {
+        let v1 = r#"{
+            "entity_id": "did:example:entity1",
+            "authority_id": "did:example:authority1",
+            "action": "issue",
+            "resource": "VerifiableCredential",
+            "recognized": true,
+            "authorized": false,
+            "context": {},
+            "record_type": "Authorization"
+        }"#;
+
+        let record = RedisStorage::deserialize_record(v1).unwrap();
+
+        assert_eq!(record.entity_id().as_str(), "did:example:entity1");
+        assert!(record.is_recognized());
+        assert!(!record.is_authorized());
+        assert_eq!(record.version(), INITIAL_VERSION);
+        assert_eq!(record.expires_at(), None);
+        assert_eq!(record.created_at(), None);
+        assert_eq!(record.updated_at(), None);
+        assert_eq!(record.schema_version(), CURRENT_SCHEMA_VERSION);
+
+        let written: serde_json::Value =
+            serde_json::from_str(&RedisStorage::serialize_record(&record).unwrap()).unwrap();
+        assert_eq!(written["schema_version"], CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_namespace_and_pattern_escaping() {
+        assert_eq!(RedisStorage::namespace(""), "");
+        assert_eq!(RedisStorage::namespace("registry-a"), "tr:registry-a:");
+        assert_eq!(RedisStorage::escape_pattern("tr:[a]*:"), "tr:\\[a\\]\\*:");
+    }
+
+    #[tokio::test]
+    async fn test_list_only_returns_own_prefix() {
+        let Some(storage) = get_test_storage().await else {
+            return;
+        };
+        cleanup_test_data(&storage).await;
+        let registry_a = storage.clone().with_key_prefix("registry-a");
+        let registry_b = storage.clone().with_key_prefix("registry-b");
+
+        let record_a = create_test_record(
+            "did:example:entity1",
+            "did:example:authority1",
+            "issue",
+            "VerifiableCredential",
+            true,
+            true,
+            "assertion",
+        );
+        let record_b = create_test_record(
+            "did:example:entity2",
+            "did:example:authority2",
+            "verify",
+            "DriverLicense",
+            true,
+            false,
+            "recognition",
+        );
+        let record_unprefixed = create_test_record(
+            "did:example:entity3",
+            "did:example:authority3",
+            "verify",
+            "DriverLicense",
+            false,
+            false,
+            "recognition",
+        );
+        registry_a.create(record_a).await.unwrap();
+        registry_b.create(record_b.clone()).await.unwrap();
+        storage.create(record_unprefixed).await.unwrap();
+
+        let list_a = registry_a.list().await.unwrap().into_records();
+        assert_eq!(list_a.len(), 1);
+        assert_eq!(list_a[0].entity_id().as_str(), "did:example:entity1");
+        let list_b = registry_b.list().await.unwrap().into_records();
+        assert_eq!(list_b.len(), 1);
+        assert_eq!(list_b[0].entity_id().as_str(), "did:example:entity2");
+        let unprefixed = storage.list().await.unwrap().into_records();
+        assert_eq!(unprefixed.len(), 1);
+        assert_eq!(unprefixed[0].entity_id().as_str(), "did:example:entity3");
+
+        // the same record id in another namespace is a different record
+        registry_a.create(record_b).await.unwrap();
+        let found = registry_b
+            .find_by_entity_and_authority(
+                &EntityId::new("did:example:entity2"),
+                &AuthorityId::new("did:example:authority2"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        cleanup_test_data(&storage).await;
+    }
+
     #[tokio::test]
     async fn test_find_by_query() {
         let Some(storage) = get_test_storage().await else {
@@ -0,0 +1,291 @@
+use crate::domain::*;
+use crate::storage::adapters::{
+    csv_file_storage::{CsvLoadOptions, FileStorage},
+    json_file_storage::JsonFileStorage,
+    local_storage::LocalStorage,
+};
+use crate::storage::repository::*;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the document served at the seed URL is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedFormat {
+    Csv(CsvLoadOptions),
+    Json,
+}
+
+/// Records fetched over HTTP(S) at startup into memory, for deployments without a
+/// writable volume. With a refresh interval the URL is re-fetched in the background and
+/// the fetched set replaces the stored one, including records changed through the admin
+/// protocol since the last fetch.
+#[derive(Clone)]
+pub struct RemoteStorage {
+    records: LocalStorage,
+}
+
+impl RemoteStorage {
+    /// Whether `FILE_STORAGE_PATH` points at a URL instead of a file.
+    pub fn is_remote(path: &str) -> bool {
+        path.starts_with("https://") || path.starts_with("http://")
+    }
+
+    /// Fetches the records once, failing if the URL can't be fetched or parsed.
+    /// `update_interval_sec` of `0` never re-fetches.
+    pub async fn try_new(
+        url: &str,
+        format: SeedFormat,
+        update_interval_sec: u64,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+        let initial = Self::fetch(&client, url, format).await?;
+        info!(url, count = initial.len(), "Loaded trust records from URL");
+
+        let storage = Self {
+            records: LocalStorage::with_records(initial),
+        };
+        if update_interval_sec > 0 {
+            storage.spawn_refresh_task(
+                client,
+                url.to_string(),
+                format,
+                Duration::from_secs(update_interval_sec),
+            );
+        }
+        Ok(storage)
+    }
+
+    /// Fetches and parses the records without keeping them. Returns the record count.
+    pub async fn check_url(
+        url: &str,
+        format: SeedFormat,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+        Ok(Self::fetch(&client, url, format).await?.len())
+    }
+
+    async fn fetch(
+        client: &reqwest::Client,
+        url: &str,
+        format: SeedFormat,
+    ) -> Result<Vec<TrustRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let contents = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("unable to fetch trust records from {url}: {e}"))?
+            .text()
+            .await
+            .map_err(|e| format!("unable to read trust records from {url}: {e}"))?;
+
+        let records = match format {
+            SeedFormat::Csv(options) => FileStorage::parse_records(&contents, options),
+            SeedFormat::Json => JsonFileStorage::parse_records(&contents),
+        };
+        records.map_err(|e| format!("unable to parse trust records from {url}: {e}").into())
+    }
+
+    fn spawn_refresh_task(
+        &self,
+        client: reqwest::Client,
+        url: String,
+        format: SeedFormat,
+        interval: Duration,
+    ) {
+        let records = self.records.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(interval).await;
+                info!(url, "Refreshing trust records from URL");
+                match Self::fetch(&client, &url, format).await {
+                    Ok(fetched) => records.replace_all(fetched),
+                    Err(err) => {
+                        error!(error = %err, url, "Failed to refresh trust records from URL")
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for RemoteStorage {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.records.find_by_query(query).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.records
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.records.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for RemoteStorage {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.records.create(record).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.records.update(record).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.records
+            .update_if_version(record, expected_version)
+            .await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.records.upsert(record).await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.records.delete(query).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.records.read(query).await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.records.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, routing::get};
+    use std::sync::{Arc, Mutex};
+
+    const CSV_HEADER: &str =
+        "entity_id,authority_id,action,resource,recognized,authorized,context,record_type\n";
+
+    fn csv(entities: &[&str]) -> String {
+        let mut csv = CSV_HEADER.to_string();
+        for entity in entities {
+            csv.push_str(&format!("{entity},a1,ac1,r1,true,true,e30=,assertion\n"));
+        }
+        csv
+    }
+
+    fn query(entity: &str) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new(entity),
+            AuthorityId::new("a1"),
+            Action::new("ac1"),
+            Resource::new("r1"),
+        )
+    }
+
+    /// Serves whatever `body` holds at `/records.csv`, returning the URL.
+    async fn serve(body: Arc<Mutex<String>>) -> String {
+        let app = Router::new()
+            .route(
+                "/records.csv",
+                get(move || {
+                    let body = body.lock().unwrap().clone();
+                    async move { body }
+                }),
+            )
+            .route("/missing.csv", get(|| async { StatusCode::NOT_FOUND }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn detects_remote_paths() {
+        assert!(RemoteStorage::is_remote("https://example.com/records.csv"));
+        assert!(RemoteStorage::is_remote(
+            "http://localhost:8080/records.csv"
+        ));
+        assert!(!RemoteStorage::is_remote("trust_records.csv"));
+        assert!(!RemoteStorage::is_remote("/data/https.csv"));
+    }
+
+    #[tokio::test]
+    async fn loads_csv_records_from_url() {
+        let base = serve(Arc::new(Mutex::new(csv(&["e1", "e2"])))).await;
+
+        let storage = RemoteStorage::try_new(
+            &format!("{base}/records.csv"),
+            SeedFormat::Csv(CsvLoadOptions {
+                strict: true,
+                ..Default::default()
+            }),
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(storage.list().await.unwrap().records().len(), 2);
+        let record = storage.find_by_query(query("e1")).await.unwrap().unwrap();
+        assert!(record.is_recognized());
+    }
+
+    #[tokio::test]
+    async fn fails_when_url_returns_an_error() {
+        let base = serve(Arc::new(Mutex::new(String::new()))).await;
+        let url = format!("{base}/missing.csv");
+
+        let err = RemoteStorage::try_new(
+            &url,
+            SeedFormat::Csv(CsvLoadOptions {
+                strict: false,
+                ..Default::default()
+            }),
+            0,
+        )
+        .await
+        .err()
+        .unwrap();
+
+        assert!(err.to_string().contains(&url), "{err}");
+        assert!(err.to_string().contains("404"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn refreshes_records_on_interval() {
+        let body = Arc::new(Mutex::new(csv(&["e1"])));
+        let base = serve(body.clone()).await;
+        let storage = RemoteStorage::try_new(
+            &format!("{base}/records.csv"),
+            SeedFormat::Csv(CsvLoadOptions {
+                strict: true,
+                ..Default::default()
+            }),
+            1,
+        )
+        .await
+        .unwrap();
+
+        *body.lock().unwrap() = csv(&["e2"]);
+        sleep(Duration::from_millis(1500)).await;
+
+        assert!(storage.find_by_query(query("e1")).await.unwrap().is_none());
+        assert!(storage.find_by_query(query("e2")).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    domain::{AuthorityId, EntityId, TrustRecord},
+    storage::{
+        repository::{
+            QueryKey, RepositoryError, TrustRecordAdminRepository, TrustRecordList,
+            TrustRecordQuery, TrustRecordRepository, UpsertOutcome,
+        },
+        wildcard::is_wildcard_query,
+    },
+};
+
+struct CachedLookup {
+    record: Option<TrustRecord>,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+/// Bounded LRU of `find_by_query` results, misses included.
+struct LookupCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: HashMap<QueryKey, CachedLookup>,
+    /// Keys by the tick they were last used, oldest first.
+    recency: BTreeMap<u64, QueryKey>,
+    tick: u64,
+}
+
+impl LookupCache {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &QueryKey) -> Option<Option<TrustRecord>> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires_at <= Instant::now() {
+            self.remove(key);
+            return None;
+        }
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(tick, key.clone());
+        entry.last_used = tick;
+        Some(entry.record.clone())
+    }
+
+    fn insert(&mut self, key: QueryKey, record: Option<TrustRecord>) {
+        if self.max_entries == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.entries.len() >= self.max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        let tick = self.next_tick();
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            CachedLookup {
+                record,
+                expires_at: Instant::now() + self.ttl,
+                last_used: tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &QueryKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+
+    fn invalidate(&mut self, query: &TrustRecordQuery) {
+        if is_wildcard_query(query) {
+            // lookups for concrete queries may have been answered by this wildcard record
+            let (entity_id, authority_id) = (query.entity_id.as_str(), query.authority_id.as_str());
+            let stale: Vec<_> = self
+                .entries
+                .keys()
+                .filter(|key| key.0 == entity_id && key.1 == authority_id)
+                .cloned()
+                .collect();
+            for key in stale {
+                self.remove(&key);
+            }
+        } else {
+            self.remove(&query.key());
+        }
+    }
+}
+
+/// Repository wrapper memoizing `find_by_query` in a bounded LRU with a TTL.
+///
+/// Admin mutations are passed to the inner repository and drop the cached lookups of the
+/// affected record, so writes made through this wrapper are visible immediately. Writes made
+/// elsewhere, e.g. by another instance sharing the backend, show up once the TTL expires.
+pub struct CachingRepository<R: ?Sized + TrustRecordAdminRepository> {
+    inner: Arc<R>,
+    cache: Mutex<LookupCache>,
+}
+
+impl<R: ?Sized + TrustRecordAdminRepository> CachingRepository<R> {
+    pub fn new(inner: Arc<R>, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(LookupCache {
+                ttl,
+                max_entries,
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    fn invalidate(&self, query: &TrustRecordQuery) {
+        self.cache.lock().unwrap().invalidate(query);
+    }
+}
+
+#[async_trait::async_trait]
+impl<R: ?Sized + TrustRecordAdminRepository> TrustRecordRepository for CachingRepository<R> {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        let key = query.key();
+        if let Some(record) = self.cache.lock().unwrap().get(&key) {
+            return Ok(record);
+        }
+        let record = self.inner.find_by_query(query).await?;
+        self.cache.lock().unwrap().insert(key, record.clone());
+        Ok(record)
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        // past states aren't cached
+        self.inner.find_as_of(query, as_of).await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl<R: ?Sized + TrustRecordAdminRepository> TrustRecordAdminRepository for CachingRepository<R> {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let query = TrustRecordQuery::from(&record);
+        self.inner.create(record).await?;
+        self.invalidate(&query);
+        Ok(())
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let query = TrustRecordQuery::from(&record);
+        self.inner.update(record).await?;
+        self.invalidate(&query);
+        Ok(())
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        let query = TrustRecordQuery::from(&record);
+        self.inner
+            .update_if_version(record, expected_version)
+            .await?;
+        self.invalidate(&query);
+        Ok(())
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let query = TrustRecordQuery::from(&record);
+        let outcome = self.inner.upsert(record).await?;
+        self.invalidate(&query);
+        Ok(outcome)
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query.clone()).await?;
+        self.invalidate(&query);
+        Ok(())
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner.read_many(queries).await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        let deleted = self.inner.delete_many(queries.clone()).await?;
+        for (query, _) in queries
+            .iter()
+            .zip(&deleted)
+            .filter(|(_, deleted)| **deleted)
+        {
+            self.invalidate(query);
+        }
+        Ok(deleted)
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{CountingRepository, record_for};
+
+    fn caching(
+        records: Vec<TrustRecord>,
+        ttl: Duration,
+        max_entries: usize,
+    ) -> (
+        Arc<CountingRepository>,
+        CachingRepository<CountingRepository>,
+    ) {
+        let storage = Arc::new(CountingRepository::new(records));
+        let repository = CachingRepository::new(storage.clone(), ttl, max_entries);
+        (storage, repository)
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_backend() {
+        let (storage, repository) = caching(
+            vec![record_for("credential", true)],
+            Duration::from_secs(60),
+            10,
+        );
+        let query = TrustRecordQuery::from(&record_for("credential", true));
+
+        let first = repository.find_by_query(query.clone()).await.unwrap();
+        let second = repository.find_by_query(query).await.unwrap();
+
+        assert_eq!(first, second);
+        assert!(second.unwrap().is_authorized());
+        assert_eq!(storage.lookups(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_invalidates_cached_lookup() {
+        let (storage, repository) = caching(
+            vec![record_for("credential", true)],
+            Duration::from_secs(60),
+            10,
+        );
+        let query = TrustRecordQuery::from(&record_for("credential", true));
+        repository.find_by_query(query.clone()).await.unwrap();
+
+        repository
+            .update(record_for("credential", false))
+            .await
+            .unwrap();
+        let found = repository.find_by_query(query).await.unwrap().unwrap();
+
+        assert!(!found.is_authorized());
+        assert_eq!(storage.lookups(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_create_invalidates_cached_miss() {
+        let (_, repository) = caching(vec![], Duration::from_secs(60), 10);
+        let query = TrustRecordQuery::from(&record_for("credential", true));
+        assert!(
+            repository
+                .find_by_query(query.clone())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        repository
+            .create(record_for("credential", true))
+            .await
+            .unwrap();
+
+        assert!(repository.find_by_query(query).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let (storage, repository) = caching(
+            vec![
+                record_for("a", true),
+                record_for("b", true),
+                record_for("c", true),
+            ],
+            Duration::from_secs(60),
+            2,
+        );
+        let query = |resource: &str| TrustRecordQuery::from(&record_for(resource, true));
+
+        repository.find_by_query(query("a")).await.unwrap();
+        repository.find_by_query(query("b")).await.unwrap();
+        repository.find_by_query(query("a")).await.unwrap();
+        repository.find_by_query(query("c")).await.unwrap();
+        assert_eq!(storage.lookups(), 3);
+
+        repository.find_by_query(query("a")).await.unwrap();
+        assert_eq!(storage.lookups(), 3);
+        repository.find_by_query(query("b")).await.unwrap();
+        assert_eq!(storage.lookups(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_expired_lookups_go_to_backend() {
+        let (storage, repository) =
+            caching(vec![record_for("credential", true)], Duration::ZERO, 10);
+        let query = TrustRecordQuery::from(&record_for("credential", true));
+
+        repository.find_by_query(query.clone()).await.unwrap();
+        repository.find_by_query(query).await.unwrap();
+
+        assert_eq!(storage.lookups(), 2);
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    domain::{AuthorityId, Context, EntityId, TrustRecord},
+    storage::repository::{
+        QueryKey, RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+    storage::wildcard::is_wildcard_query,
+};
+
+/// Hash of the request context, so identical requests share one cache entry.
+/// `serde_json` maps are ordered, which makes the serialized form canonical.
+pub fn context_hash(context: Option<&Context>) -> String {
+    match context {
+        Some(c) => sha256::digest(c.as_value().to_string()),
+        None => String::new(),
+    }
+}
+
+struct CachedDecision {
+    record: TrustRecord,
+    expires_at: Instant,
+}
+
+/// Short-lived cache of TRQP decisions keyed on the query and a hash of the request context.
+///
+/// Only found records are cached; updates and deletes drop every cached context for the
+/// affected record. Creates invalidate too, since with wildcards enabled a new exact
+/// record shadows the wildcard record a cached decision came from.
+pub struct DecisionCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: RwLock<HashMap<QueryKey, HashMap<String, CachedDecision>>>,
+}
+
+impl DecisionCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, query: &TrustRecordQuery, context_hash: &str) -> Option<TrustRecord> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(&query.key())
+            .and_then(|contexts| contexts.get(context_hash))
+            .filter(|cached| cached.expires_at > Instant::now())
+            .map(|cached| cached.record.clone())
+    }
+
+    pub fn insert(&self, query: &TrustRecordQuery, context_hash: String, record: TrustRecord) {
+        let mut entries = self.entries.write().unwrap();
+        if Self::len(&entries) >= self.max_entries {
+            let now = Instant::now();
+            entries.retain(|_, contexts| {
+                contexts.retain(|_, cached| cached.expires_at > now);
+                !contexts.is_empty()
+            });
+            if Self::len(&entries) >= self.max_entries {
+                return;
+            }
+        }
+        entries.entry(query.key()).or_default().insert(
+            context_hash,
+            CachedDecision {
+                record,
+                expires_at: Instant::now() + self.ttl,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, query: &TrustRecordQuery) {
+        let mut entries = self.entries.write().unwrap();
+        if is_wildcard_query(query) {
+            // decisions for concrete queries may have been answered by this wildcard record
+            let (entity_id, authority_id) = (query.entity_id.as_str(), query.authority_id.as_str());
+            entries.retain(|key, _| key.0 != entity_id || key.1 != authority_id);
+        } else {
+            entries.remove(&query.key());
+        }
+    }
+
+    fn len(entries: &HashMap<QueryKey, HashMap<String, CachedDecision>>) -> usize {
+        entries.values().map(HashMap::len).sum()
+    }
+}
+
+/// Repository wrapper that invalidates the decision cache after successful admin mutations.
+pub struct InvalidatingRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+    cache: Arc<DecisionCache>,
+}
+
+impl InvalidatingRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>, cache: Arc<DecisionCache>) -> Self {
+        Self { inner, cache }
+    }
+
+    fn query_for(record: &TrustRecord) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            record.entity_id().clone(),
+            record.authority_id().clone(),
+            record.action().clone(),
+            record.resource().clone(),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for InvalidatingRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner.find_by_query(query).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner.find_as_of(query, as_of).await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for InvalidatingRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let query = Self::query_for(&record);
+        self.inner.create(record).await?;
+        self.cache.invalidate(&query);
+        Ok(())
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        let query = Self::query_for(&record);
+        self.inner.update(record).await?;
+        self.cache.invalidate(&query);
+        Ok(())
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        let query = Self::query_for(&record);
+        self.inner
+            .update_if_version(record, expected_version)
+            .await?;
+        self.cache.invalidate(&query);
+        Ok(())
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let query = Self::query_for(&record);
+        let outcome = self.inner.upsert(record).await?;
+        self.cache.invalidate(&query);
+        Ok(outcome)
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query.clone()).await?;
+        self.cache.invalidate(&query);
+        Ok(())
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner.read_many(queries).await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        let deleted = self.inner.delete_many(queries.clone()).await?;
+        for (query, _) in queries
+            .iter()
+            .zip(&deleted)
+            .filter(|(_, deleted)| **deleted)
+        {
+            self.cache.invalidate(query);
+        }
+        Ok(deleted)
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::test_support::record_builder;
+    use serde_json::json;
+
+    fn query() -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new("entity-1"),
+            AuthorityId::new("authority-1"),
+            Action::new("action-1"),
+            Resource::new("resource-1"),
+        )
+    }
+
+    fn record() -> TrustRecord {
+        record_builder("entity-1", "authority-1", "action-1", "resource-1")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_context_hash_is_stable_and_distinguishes_contexts() {
+        let a = Context::new(json!({"a": 1, "b": 2}));
+        let b = Context::new(json!({"b": 2, "a": 1}));
+        let c = Context::new(json!({"a": 2}));
+
+        assert_eq!(context_hash(Some(&a)), context_hash(Some(&b)));
+        assert_ne!(context_hash(Some(&a)), context_hash(Some(&c)));
+        assert_ne!(context_hash(Some(&a)), context_hash(None));
+    }
+
+    #[test]
+    fn test_get_respects_context_hash() {
+        let cache = DecisionCache::new(Duration::from_secs(60), 10);
+        cache.insert(&query(), "ctx-1".to_string(), record());
+
+        assert!(cache.get(&query(), "ctx-1").is_some());
+        assert!(cache.get(&query(), "ctx-2").is_none());
+    }
+
+    #[test]
+    fn test_expired_entries_are_not_returned() {
+        let cache = DecisionCache::new(Duration::ZERO, 10);
+        cache.insert(&query(), String::new(), record());
+
+        assert!(cache.get(&query(), "").is_none());
+    }
+
+    #[test]
+    fn test_invalidate_drops_all_contexts_for_record() {
+        let cache = DecisionCache::new(Duration::from_secs(60), 10);
+        cache.insert(&query(), "ctx-1".to_string(), record());
+        cache.insert(&query(), "ctx-2".to_string(), record());
+
+        cache.invalidate(&query());
+
+        assert!(cache.get(&query(), "ctx-1").is_none());
+        assert!(cache.get(&query(), "ctx-2").is_none());
+    }
+
+    #[test]
+    fn test_invalidating_wildcard_record_drops_concrete_decisions() {
+        let cache = DecisionCache::new(Duration::from_secs(60), 10);
+        cache.insert(&query(), String::new(), record());
+        let mut wildcard = query();
+        wildcard.action = Action::new("*");
+
+        cache.invalidate(&wildcard);
+
+        assert!(cache.get(&query(), "").is_none());
+    }
+
+    #[test]
+    fn test_insert_is_skipped_when_full() {
+        let cache = DecisionCache::new(Duration::from_secs(60), 1);
+        cache.insert(&query(), "ctx-1".to_string(), record());
+        cache.insert(&query(), "ctx-2".to_string(), record());
+
+        assert!(cache.get(&query(), "ctx-1").is_some());
+        assert!(cache.get(&query(), "ctx-2").is_none());
+    }
+}
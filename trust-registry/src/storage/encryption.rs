@@ -0,0 +1,451 @@
+use std::sync::Arc;
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    domain::{AuthorityId, Context, EntityId, TrustRecord},
+    storage::repository::{
+        RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+/// Only field of a context stored encrypted, holding the envelope.
+pub const ENVELOPE_FIELD: &str = "$encrypted";
+const ENVELOPE_VERSION: u64 = 1;
+const NONCE_LEN: usize = 12;
+
+fn record_key(record: &TrustRecord) -> String {
+    format!(
+        "{}|{}|{}|{}",
+        record.entity_id(),
+        record.authority_id(),
+        record.action(),
+        record.resource()
+    )
+}
+
+fn envelope(context: &Context) -> Option<&Value> {
+    let object = context.as_value().as_object()?;
+    object.get(ENVELOPE_FIELD).filter(|_| object.len() == 1)
+}
+
+fn decode(envelope: &Value, field: &str) -> Result<Vec<u8>, String> {
+    let value = envelope[field]
+        .as_str()
+        .ok_or_else(|| format!("envelope has no {field}"))?;
+    STANDARD
+        .decode(value)
+        .map_err(|e| format!("envelope {field} is not base64: {e}"))
+}
+
+/// Envelope encryption of record contexts with the key of `CONTEXT_ENCRYPTION_KEY`.
+///
+/// Every context is encrypted with its own random data key, bound to the record key so
+/// it can't be moved to another record, and the data key is stored wrapped with the
+/// configured key. The envelope names the configured key by an id derived from it.
+pub struct ContextCipher {
+    key_id: String,
+    key: Aes256Gcm,
+}
+
+impl ContextCipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        let digest = Sha256::digest(key);
+        Self {
+            key_id: digest[..8].iter().map(|b| format!("{b:02x}")).collect(),
+            key: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        }
+    }
+
+    /// `key` is 32 bytes, base64 encoded.
+    pub fn from_base64(key: &str) -> Result<Self, String> {
+        let key: [u8; 32] = STANDARD
+            .decode(key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("CONTEXT_ENCRYPTION_KEY must be 32 bytes, base64 encoded")?;
+        Ok(Self::new(key))
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Replaces the record's context with its envelope.
+    pub fn encrypt(&self, record: TrustRecord) -> Result<TrustRecord, RepositoryError> {
+        if record
+            .context()
+            .as_value()
+            .as_object()
+            .is_some_and(|object| object.contains_key(ENVELOPE_FIELD))
+        {
+            return Err(RepositoryError::ValidationError(format!(
+                "context field {ENVELOPE_FIELD} is reserved"
+            )));
+        }
+        let failed = |e| RepositoryError::SerializationFailed(format!("context encryption: {e}"));
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let key_nonce = Aes256Gcm::generate_nonce(OsRng);
+        let wrapped_key = self
+            .key
+            .encrypt(
+                &key_nonce,
+                Payload {
+                    msg: &data_key,
+                    aad: self.key_id.as_bytes(),
+                },
+            )
+            .map_err(failed)?;
+
+        let plaintext = serde_json::to_vec(record.context().as_value())
+            .map_err(|e| RepositoryError::SerializationFailed(e.to_string()))?;
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = Aes256Gcm::new(&data_key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: record_key(&record).as_bytes(),
+                },
+            )
+            .map_err(failed)?;
+
+        let envelope = json!({
+            ENVELOPE_FIELD: {
+                "v": ENVELOPE_VERSION,
+                "kid": self.key_id,
+                "key": STANDARD.encode([key_nonce.as_slice(), &wrapped_key].concat()),
+                "nonce": STANDARD.encode(nonce),
+                "ciphertext": STANDARD.encode(ciphertext),
+            }
+        });
+        Ok(record.with_context(Context::new(envelope)))
+    }
+
+    /// Restores the record's context from its envelope. Records stored before encryption
+    /// was enabled are returned as they are.
+    pub fn decrypt(&self, record: TrustRecord) -> Result<TrustRecord, RepositoryError> {
+        let Some(envelope) = envelope(record.context()) else {
+            return Ok(record);
+        };
+        let context = self.open(envelope, &record_key(&record)).map_err(|e| {
+            RepositoryError::SerializationFailed(format!(
+                "context of {} can't be decrypted: {e}",
+                record_key(&record)
+            ))
+        })?;
+        Ok(record.with_context(context))
+    }
+
+    fn open(&self, envelope: &Value, record_key: &str) -> Result<Context, String> {
+        if envelope["v"].as_u64() != Some(ENVELOPE_VERSION) {
+            return Err(format!("unsupported envelope version {}", envelope["v"]));
+        }
+        let key_id = envelope["kid"].as_str().unwrap_or_default();
+        if key_id != self.key_id {
+            return Err(format!(
+                "encrypted with key {key_id}, the configured key is {}",
+                self.key_id
+            ));
+        }
+
+        let wrapped_key = decode(envelope, "key")?;
+        if wrapped_key.len() <= NONCE_LEN {
+            return Err("envelope key is truncated".to_string());
+        }
+        let (key_nonce, wrapped_key) = wrapped_key.split_at(NONCE_LEN);
+        let data_key = self
+            .key
+            .decrypt(
+                Nonce::from_slice(key_nonce),
+                Payload {
+                    msg: wrapped_key,
+                    aad: self.key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| "data key can't be unwrapped".to_string())?;
+        let data_key =
+            Key::<Aes256Gcm>::from_exact_iter(data_key).ok_or("data key has the wrong length")?;
+
+        let nonce = decode(envelope, "nonce")?;
+        if nonce.len() != NONCE_LEN {
+            return Err("envelope nonce has the wrong length".to_string());
+        }
+        let plaintext = Aes256Gcm::new(&data_key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &decode(envelope, "ciphertext")?,
+                    aad: record_key.as_bytes(),
+                },
+            )
+            .map_err(|_| "ciphertext doesn't belong to this record or was altered".to_string())?;
+        serde_json::from_slice(&plaintext)
+            .map(Context::new)
+            .map_err(|e| format!("decrypted context is not JSON: {e}"))
+    }
+}
+
+/// Repository wrapper storing record contexts encrypted, see [`ContextCipher`]. Entity,
+/// authority, action and resource stay in plaintext so lookups work as before. Meant to
+/// sit right on top of the backend, so the record keys it binds contexts to are the
+/// stored ones.
+pub struct EncryptingRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+    cipher: ContextCipher,
+}
+
+impl EncryptingRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>, cipher: ContextCipher) -> Self {
+        Self { inner, cipher }
+    }
+
+    fn decrypt_all(&self, records: Vec<TrustRecord>) -> Result<Vec<TrustRecord>, RepositoryError> {
+        records
+            .into_iter()
+            .map(|record| self.cipher.decrypt(record))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for EncryptingRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_query(query)
+            .await?
+            .map(|record| self.cipher.decrypt(record))
+            .transpose()
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self
+            .inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await?;
+        self.decrypt_all(records)
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_as_of(query, as_of)
+            .await?
+            .map(|record| self.cipher.decrypt(record))
+            .transpose()
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        let records = self.inner.list().await?.into_records();
+        Ok(TrustRecordList::new(self.decrypt_all(records)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for EncryptingRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(self.cipher.encrypt(record)?).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(self.cipher.encrypt(record)?).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.inner
+            .update_if_version(self.cipher.encrypt(record)?, expected_version)
+            .await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query).await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.inner.upsert(self.cipher.encrypt(record)?).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.cipher.decrypt(self.inner.read(query).await?)
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner
+            .read_many(queries)
+            .await?
+            .into_iter()
+            .map(|record| record.map(|record| self.cipher.decrypt(record)).transpose())
+            .collect()
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        self.inner.delete_many(queries).await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let records = self.inner.find_by_tag(tag).await?;
+        self.decrypt_all(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::{
+        json_file_storage::JsonFileStorage, local_storage::LocalStorage,
+    };
+    use crate::test_support::{AUTHORITY, record_builder};
+    use std::io::Write;
+
+    const SECRET: &str = "board-resolution-2024-17";
+
+    fn record(entity: &str) -> TrustRecord {
+        record_builder(entity, AUTHORITY, "issue", "credential")
+            .context(Context::new(json!({ "governance": { "ref": SECRET } })))
+            .build()
+            .unwrap()
+    }
+
+    fn cipher() -> ContextCipher {
+        ContextCipher::new([7; 32])
+    }
+
+    #[tokio::test]
+    async fn test_context_round_trips_and_is_stored_encrypted() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "[]").unwrap();
+        let storage = Arc::new(JsonFileStorage::try_new(file.path(), 60).await.unwrap());
+        let repository = EncryptingRepository::new(storage, cipher());
+
+        repository
+            .create(record("did:example:entity"))
+            .await
+            .unwrap();
+
+        let stored = std::fs::read_to_string(file.path()).unwrap();
+        assert!(!stored.contains(SECRET), "context stored in plaintext");
+        assert!(stored.contains(ENVELOPE_FIELD));
+        assert!(
+            stored.contains("did:example:entity"),
+            "keys stay in plaintext"
+        );
+
+        let reloaded = EncryptingRepository::new(
+            Arc::new(JsonFileStorage::try_new(file.path(), 60).await.unwrap()),
+            cipher(),
+        );
+        let found = reloaded
+            .read(TrustRecordQuery::from(&record("did:example:entity")))
+            .await
+            .unwrap();
+        assert_eq!(found.context(), record("did:example:entity").context());
+        assert_eq!(
+            reloaded.list().await.unwrap().records(),
+            &[record("did:example:entity")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_legacy_records_still_load() {
+        let storage = Arc::new(LocalStorage::with_records(vec![record(
+            "did:example:legacy",
+        )]));
+        let repository = EncryptingRepository::new(storage, cipher());
+
+        let found = repository
+            .find_by_query(TrustRecordQuery::from(&record("did:example:legacy")))
+            .await
+            .unwrap();
+
+        assert_eq!(found, Some(record("did:example:legacy")));
+    }
+
+    #[tokio::test]
+    async fn test_context_moved_to_another_record_is_rejected() {
+        let storage = Arc::new(LocalStorage::new());
+        let repository = EncryptingRepository::new(storage.clone(), cipher());
+        repository.create(record("did:example:a")).await.unwrap();
+        let stored = storage
+            .read(TrustRecordQuery::from(&record("did:example:a")))
+            .await
+            .unwrap();
+        storage
+            .create(record("did:example:b").with_context(stored.context().clone()))
+            .await
+            .unwrap();
+
+        let result = repository
+            .read(TrustRecordQuery::from(&record("did:example:b")))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(RepositoryError::SerializationFailed(message))
+                if message.contains("doesn't belong to this record")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_other_key_cannot_decrypt() {
+        let storage = Arc::new(LocalStorage::new());
+        EncryptingRepository::new(storage.clone(), cipher())
+            .create(record("did:example:entity"))
+            .await
+            .unwrap();
+        let other = EncryptingRepository::new(storage, ContextCipher::new([8; 32]));
+
+        let result = other
+            .read(TrustRecordQuery::from(&record("did:example:entity")))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(RepositoryError::SerializationFailed(message))
+                if message.contains("the configured key is")
+        ));
+    }
+
+    #[test]
+    fn test_key_must_be_32_bytes_of_base64() {
+        assert!(ContextCipher::from_base64(&STANDARD.encode([1; 32])).is_ok());
+        assert!(ContextCipher::from_base64(&STANDARD.encode([1; 16])).is_err());
+        assert!(ContextCipher::from_base64("not base64").is_err());
+    }
+}
@@ -1,18 +1,30 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc, time::Duration};
 
 use anyhow::anyhow;
 
 use crate::{
-    configs::{TrsutRegistryConfig, TrustStorageBackend},
+    configs::{FileStorageConfig, TrsutRegistryConfig, TrustStorageBackend},
     storage::{
         adapters::{
-            csv_file_storage::FileStorage, ddb_storage::DynamoDbStorage,
-            redis_storage::RedisStorage,
+            csv_file_storage::{CsvLoadOptions, FileStorage},
+            ddb_storage::DynamoDbStorage,
+            json_file_storage::JsonFileStorage,
+            redis_storage::{RedisStorage, WriteDurability},
+            remote_storage::{RemoteStorage, SeedFormat},
         },
+        layered::LayeredRepository,
         repository::TrustRecordAdminRepository,
+        timeout::TimeoutRepository,
     },
 };
 
+fn csv_load_options(config: &FileStorageConfig) -> CsvLoadOptions {
+    CsvLoadOptions {
+        strict: config.csv_strict,
+        on_duplicate: config.csv_on_duplicate,
+    }
+}
+
 pub struct TrustStorageRepoFactory {
     config: Arc<TrsutRegistryConfig>,
 }
@@ -24,32 +36,158 @@ impl TrustStorageRepoFactory {
     pub async fn create(
         &self,
     ) -> Result<Arc<dyn TrustRecordAdminRepository>, Box<dyn std::error::Error>> {
-        let repository: Arc<dyn TrustRecordAdminRepository> =
-            match self.config.storage_config.storage_backend {
-                TrustStorageBackend::Csv => {
-                    let config = self.config.storage_config.file_storage_config.clone();
-                    let file_storage =
-                        FileStorage::try_new(config.path, config.update_interval_sec)
-                            .await
-                            .map_err(|e| anyhow!(e.to_string()))?;
-                    Arc::new(file_storage)
-                }
-                TrustStorageBackend::DynamoDb => {
-                    let ddb_config = self.config.storage_config.ddb_storage_config.clone();
-                    let ddb = DynamoDbStorage::new(ddb_config)
-                        .await
-                        .map_err(|e| anyhow!(e.to_string()))?;
-                    Arc::new(ddb)
+        if let Some((url, format)) = self.remote_seed() {
+            let update_interval_sec = self
+                .config
+                .storage_config
+                .file_storage_config
+                .update_interval_sec;
+            let remote = RemoteStorage::try_new(url, format, update_interval_sec)
+                .await
+                .map_err(|e| anyhow!(e.to_string()))?;
+            return Ok(Arc::new(remote));
+        }
+
+        let storage_config = &self.config.storage_config;
+        let primary = self.create_backend(storage_config.storage_backend).await?;
+        let Some(secondary) = storage_config.secondary_backend else {
+            return Ok(primary);
+        };
+        let secondary = self.create_backend(secondary).await?;
+        Ok(Arc::new(LayeredRepository::new(primary, secondary)))
+    }
+
+    async fn create_backend(
+        &self,
+        backend: TrustStorageBackend,
+    ) -> Result<Arc<dyn TrustRecordAdminRepository>, Box<dyn std::error::Error>> {
+        let repository: Arc<dyn TrustRecordAdminRepository> = match backend {
+            TrustStorageBackend::Csv => {
+                let config = self.config.storage_config.file_storage_config.clone();
+                let mut file_storage = FileStorage::try_new_with_options(
+                    &config.path,
+                    config.update_interval_sec,
+                    csv_load_options(&config),
+                )
+                .await
+                .map_err(|e| anyhow!(e.to_string()))?;
+                if config.csv_write_batch_ms > 0 {
+                    file_storage = file_storage.with_write_batching(
+                        Duration::from_millis(config.csv_write_batch_ms),
+                        config.csv_write_batch_max,
+                    );
                 }
-                TrustStorageBackend::Redis => {
-                    let redis_config = self.config.storage_config.redis_storage_config.clone();
-                    let redis = RedisStorage::new(&redis_config.redis_url)
+                Arc::new(file_storage)
+            }
+            TrustStorageBackend::Json => {
+                let config = self.config.storage_config.file_storage_config.clone();
+                let file_storage =
+                    JsonFileStorage::try_new(config.path, config.update_interval_sec)
                         .await
                         .map_err(|e| anyhow!(e.to_string()))?;
-                    Arc::new(redis)
-                }
-            };
+                Arc::new(file_storage)
+            }
+            TrustStorageBackend::DynamoDb => {
+                let ddb_config = self.config.storage_config.ddb_storage_config.clone();
+                let ddb = DynamoDbStorage::new(ddb_config)
+                    .await
+                    .map_err(|e| anyhow!(e.to_string()))?;
+                Arc::new(ddb)
+            }
+            TrustStorageBackend::Redis => {
+                let redis_config = self.config.storage_config.redis_storage_config.clone();
+                let redis = RedisStorage::new(&redis_config.redis_url)
+                    .await
+                    .map_err(|e| anyhow!(e.to_string()))?
+                    .with_key_prefix(&redis_config.key_prefix)
+                    .with_durability(WriteDurability {
+                        replicas: redis_config.wait_replicas,
+                        fsync: redis_config.fsync,
+                        timeout_ms: redis_config.wait_timeout_ms,
+                    });
+                Arc::new(redis)
+            }
+        };
+
+        let op_timeout_ms = self.config.storage_config.op_timeout_ms;
+        if op_timeout_ms == 0 {
+            return Ok(repository);
+        }
+        Ok(Arc::new(TimeoutRepository::new(
+            repository,
+            Duration::from_millis(op_timeout_ms),
+        )))
+    }
+
+    /// The URL and format to seed from when `FILE_STORAGE_PATH` is an `http(s)://` URL.
+    fn remote_seed(&self) -> Option<(&str, SeedFormat)> {
+        let config = &self.config.storage_config.file_storage_config;
+        if !RemoteStorage::is_remote(&config.path)
+            || self.config.storage_config.secondary_backend.is_some()
+        {
+            return None;
+        }
+        let format = match self.config.storage_config.storage_backend {
+            TrustStorageBackend::Csv => SeedFormat::Csv(csv_load_options(config)),
+            TrustStorageBackend::Json => SeedFormat::Json,
+            _ => return None,
+        };
+        Some((&config.path, format))
+    }
+
+    /// Checks that the configured backends can be reached, without keeping a connection.
+    pub async fn check(&self) -> Result<(), String> {
+        let storage_config = &self.config.storage_config;
+        if let Some((url, format)) = self.remote_seed() {
+            return RemoteStorage::check_url(url, format)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string());
+        }
+        self.check_backend(storage_config.storage_backend).await?;
+        match storage_config.secondary_backend {
+            Some(secondary) => self.check_backend(secondary).await,
+            None => Ok(()),
+        }
+    }
 
-        Ok(repository)
+    async fn check_backend(&self, backend: TrustStorageBackend) -> Result<(), String> {
+        let storage_config = &self.config.storage_config;
+        match backend {
+            TrustStorageBackend::Csv => {
+                let path = &storage_config.file_storage_config.path;
+                FileStorage::check_file(
+                    Path::new(path),
+                    csv_load_options(&storage_config.file_storage_config),
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("CSV file {path} can't be loaded: {e}"))
+            }
+            TrustStorageBackend::Json => {
+                let path = &storage_config.file_storage_config.path;
+                JsonFileStorage::check_file(Path::new(path))
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("JSON file {path} can't be loaded: {e}"))
+            }
+            TrustStorageBackend::DynamoDb => {
+                let ddb_config = storage_config.ddb_storage_config.clone();
+                let table_name = ddb_config.table_name.clone();
+                let ddb = DynamoDbStorage::new(ddb_config)
+                    .await
+                    .map_err(|e| format!("DynamoDB client can't be created: {e}"))?;
+                ddb.check_table()
+                    .await
+                    .map_err(|e| format!("DynamoDB table {table_name} is not reachable: {e}"))
+            }
+            TrustStorageBackend::Redis => {
+                let redis_url = &storage_config.redis_storage_config.redis_url;
+                let redis = RedisStorage::new(redis_url)
+                    .await
+                    .map_err(|e| format!("Redis at {redis_url} is not reachable: {e}"))?;
+                redis.ping().await.map_err(|e| e.to_string())
+            }
+        }
     }
 }
@@ -0,0 +1,414 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+use crate::{
+    domain::{AuthorityId, EntityId, TrustRecord},
+    storage::repository::{
+        QueryKey, RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+/// One state of a record, in effect from `valid_from` until the next version.
+#[derive(Debug, Clone)]
+pub struct RecordVersion {
+    pub valid_from: DateTime<Utc>,
+    /// `None` once the record was deleted.
+    pub record: Option<TrustRecord>,
+}
+
+/// Which versions [`HistoryRepository::compact`] keeps of each record.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryRetention {
+    /// The latest versions always kept, at least one.
+    pub max_versions: usize,
+    /// Also keeps every version still in effect within this long before compaction runs,
+    /// so `as_of` queries inside the window keep their answers.
+    pub keep_within: Option<Duration>,
+}
+
+/// Repository wrapper keeping every version written through it, so
+/// [`find_as_of`](TrustRecordRepository::find_as_of) can answer for any time since the
+/// registry started. History is held in memory; earlier times fall back to the current
+/// record, see the default `find_as_of`.
+pub struct HistoryRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+    versions: RwLock<HashMap<QueryKey, Vec<RecordVersion>>>,
+}
+
+impl HistoryRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>) -> Self {
+        Self {
+            inner,
+            versions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Versions of the record kept so far, oldest first.
+    pub fn versions(&self, query: &TrustRecordQuery) -> Vec<RecordVersion> {
+        self.versions
+            .read()
+            .unwrap()
+            .get(&query.key())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn push(&self, query: &TrustRecordQuery, version: RecordVersion) {
+        let mut versions = self.versions.write().unwrap();
+        let history = versions.entry(query.key()).or_default();
+        // keep versions ordered even when a write carries an older timestamp
+        let at = history.partition_point(|v| v.valid_from <= version.valid_from);
+        history.insert(at, version);
+    }
+
+    /// Drops the versions of each record `retention` doesn't keep, returning how many
+    /// were dropped. Queries for times before the oldest kept version fall back to the
+    /// inner repository, like times before startup.
+    ///
+    /// Each record's history is trimmed under the write lock, so a version written
+    /// concurrently is either trimmed along with the rest or counted as the latest.
+    pub fn compact(&self, retention: HistoryRetention, now: DateTime<Utc>) -> usize {
+        let cutoff = retention
+            .keep_within
+            .and_then(|window| chrono::Duration::from_std(window).ok())
+            .map(|window| now - window);
+        let mut versions = self.versions.write().unwrap();
+        let mut dropped = 0;
+        for history in versions.values_mut() {
+            let mut keep_from = history.len().saturating_sub(retention.max_versions.max(1));
+            if let Some(cutoff) = cutoff {
+                // the version in effect at the cutoff answers for the start of the window
+                let in_effect = history
+                    .partition_point(|v| v.valid_from <= cutoff)
+                    .saturating_sub(1);
+                keep_from = keep_from.min(in_effect);
+            }
+            history.drain(..keep_from);
+            dropped += keep_from;
+        }
+        dropped
+    }
+
+    /// Compacts the history every `interval`.
+    pub fn spawn_compaction_task(
+        self: &Arc<Self>,
+        retention: HistoryRetention,
+        interval: Duration,
+    ) {
+        let repository = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let dropped = repository.compact(retention, Utc::now());
+                if dropped > 0 {
+                    info!(dropped, "Compacted record history");
+                }
+            }
+        });
+    }
+
+    /// Records the version as stored, which may differ from what was written.
+    async fn record_write(&self, written: TrustRecord) {
+        let query = TrustRecordQuery::from(&written);
+        let stored = self.inner.read(query.clone()).await.unwrap_or(written);
+        let valid_from = stored
+            .updated_at()
+            .or(stored.created_at())
+            .copied()
+            .unwrap_or_else(Utc::now);
+        self.push(
+            &query,
+            RecordVersion {
+                valid_from,
+                record: Some(stored),
+            },
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for HistoryRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner.find_by_query(query).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: DateTime<Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        let versions = self.versions(&query);
+        match versions.iter().rev().find(|v| v.valid_from <= as_of) {
+            Some(version) => Ok(version.record.clone()),
+            None => self.inner.find_as_of(query, as_of).await,
+        }
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for HistoryRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(record.clone()).await?;
+        self.record_write(record).await;
+        Ok(())
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(record.clone()).await?;
+        self.record_write(record).await;
+        Ok(())
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.inner
+            .update_if_version(record.clone(), expected_version)
+            .await?;
+        self.record_write(record).await;
+        Ok(())
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        let outcome = self.inner.upsert(record.clone()).await?;
+        self.record_write(record).await;
+        Ok(outcome)
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query.clone()).await?;
+        self.push(
+            &query,
+            RecordVersion {
+                valid_from: Utc::now(),
+                record: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner.read_many(queries).await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        let deleted = self.inner.delete_many(queries.clone()).await?;
+        let now = Utc::now();
+        for (query, _) in queries
+            .iter()
+            .zip(&deleted)
+            .filter(|(_, deleted)| **deleted)
+        {
+            self.push(
+                query,
+                RecordVersion {
+                    valid_from: now,
+                    record: None,
+                },
+            );
+        }
+        Ok(deleted)
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::record_for;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, hour, 0, 0).unwrap()
+    }
+
+    fn record(
+        authorized: bool,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> TrustRecord {
+        record_for("VerifiableCredential", authorized)
+            .with_created_at(Some(created_at))
+            .with_updated_at(Some(updated_at))
+    }
+
+    fn query() -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new("did:example:entity"),
+            AuthorityId::new("did:example:authority"),
+            Action::new("issue"),
+            Resource::new("VerifiableCredential"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_as_of_resolves_the_version_active_at_the_time() {
+        let repository = HistoryRepository::new(Arc::new(LocalStorage::new()));
+        repository.create(record(true, at(8), at(8))).await.unwrap();
+        repository
+            .update(record(false, at(8), at(12)))
+            .await
+            .unwrap();
+
+        let before = repository.find_as_of(query(), at(7)).await.unwrap();
+        let between = repository.find_as_of(query(), at(10)).await.unwrap();
+        let after = repository.find_as_of(query(), at(13)).await.unwrap();
+
+        assert!(before.is_none());
+        assert!(between.unwrap().is_authorized());
+        assert!(!after.unwrap().is_authorized());
+        assert_eq!(repository.versions(&query()).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_as_of_after_delete_finds_nothing() {
+        let repository = HistoryRepository::new(Arc::new(LocalStorage::new()));
+        repository.create(record(true, at(8), at(8))).await.unwrap();
+        let before_delete = Utc::now();
+        repository.delete(query()).await.unwrap();
+
+        assert!(
+            repository
+                .find_as_of(query(), Utc::now())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repository
+                .find_as_of(query(), before_delete)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    async fn with_versions(hours: std::ops::RangeInclusive<u32>) -> HistoryRepository {
+        let repository = HistoryRepository::new(Arc::new(LocalStorage::new()));
+        repository
+            .create(record(true, at(*hours.start()), at(*hours.start())))
+            .await
+            .unwrap();
+        for hour in hours.skip(1) {
+            repository
+                .update(record(hour % 2 == 0, at(0), at(hour)))
+                .await
+                .unwrap();
+        }
+        repository
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_the_latest_versions() {
+        let repository = with_versions(0..=19).await;
+        assert_eq!(repository.versions(&query()).len(), 20);
+
+        let retention = HistoryRetention {
+            max_versions: 5,
+            keep_within: None,
+        };
+        let dropped = repository.compact(retention, at(20));
+
+        let versions = repository.versions(&query());
+        assert_eq!(dropped, 15);
+        assert_eq!(versions.len(), 5);
+        assert_eq!(versions[0].valid_from, at(15));
+        assert_eq!(versions[4].valid_from, at(19));
+        assert_eq!(repository.compact(retention, at(20)), 0);
+        assert!(
+            repository
+                .find_as_of(query(), at(16))
+                .await
+                .unwrap()
+                .unwrap()
+                .is_authorized()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_versions_within_the_retention_window() {
+        let repository = with_versions(0..=19).await;
+
+        let dropped = repository.compact(
+            HistoryRetention {
+                max_versions: 2,
+                keep_within: Some(Duration::from_secs(6 * 3600 + 1800)),
+            },
+            at(20),
+        );
+
+        // 13:30 is the window start, answered by the version written at 13:00
+        let versions = repository.versions(&query());
+        assert_eq!(dropped, 13);
+        assert_eq!(versions.first().unwrap().valid_from, at(13));
+        assert!(
+            !repository
+                .find_as_of(query(), at(13))
+                .await
+                .unwrap()
+                .unwrap()
+                .is_authorized()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_without_history_only_the_current_record_answers() {
+        let storage = LocalStorage::with_records(vec![record(false, at(8), at(12))]);
+
+        assert!(storage.find_as_of(query(), at(7)).await.unwrap().is_none());
+        assert!(storage.find_as_of(query(), at(13)).await.unwrap().is_some());
+        assert!(matches!(
+            storage.find_as_of(query(), at(10)).await,
+            Err(RepositoryError::HistoryUnavailable(_))
+        ));
+    }
+}
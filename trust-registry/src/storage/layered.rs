@@ -0,0 +1,342 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use tracing::warn;
+
+use crate::{
+    domain::{AuthorityId, EntityId, TrustRecord},
+    storage::repository::{
+        QueryKey, RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+/// Records of both layers, the primary's copy winning for keys found in both.
+fn merge(primary: Vec<TrustRecord>, secondary: Vec<TrustRecord>) -> Vec<TrustRecord> {
+    let mut merged: BTreeMap<QueryKey, TrustRecord> = secondary
+        .into_iter()
+        .map(|record| (TrustRecordQuery::from(&record).key(), record))
+        .collect();
+    for record in primary {
+        merged.insert(TrustRecordQuery::from(&record).key(), record);
+    }
+    merged.into_values().collect()
+}
+
+/// Repository reading through a primary backend to a secondary one, for migrating
+/// between backends, see `TR_STORAGE_LAYERED`.
+///
+/// Lookups the primary can't answer go to the secondary, and a record found there is
+/// promoted into the primary so later reads stop at the primary. Creates and updates only
+/// go to the primary. Deletes go to both layers, the secondary first, so a deleted record
+/// can't be served and promoted again; a delete the secondary fails leaves both untouched.
+pub struct LayeredRepository {
+    primary: Arc<dyn TrustRecordAdminRepository>,
+    secondary: Arc<dyn TrustRecordAdminRepository>,
+}
+
+impl LayeredRepository {
+    pub fn new(
+        primary: Arc<dyn TrustRecordAdminRepository>,
+        secondary: Arc<dyn TrustRecordAdminRepository>,
+    ) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Copies the record into the primary. A failed copy is logged, not returned, since
+    /// the record can still be served from the secondary.
+    async fn promote(&self, record: &TrustRecord) {
+        if let Err(e) = self.primary.upsert(record.clone()).await {
+            warn!(
+                entity_id = %record.entity_id(),
+                authority_id = %record.authority_id(),
+                "Failed to promote record into the primary storage: {e}"
+            );
+        }
+    }
+
+    /// Makes sure a record only the secondary holds is in the primary before it's changed there.
+    async fn promote_for_write(&self, query: &TrustRecordQuery) -> Result<(), RepositoryError> {
+        if self.primary.find_by_query(query.clone()).await?.is_some() {
+            return Ok(());
+        }
+        if let Some(record) = self.secondary.find_by_query(query.clone()).await? {
+            self.primary.upsert(record).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for LayeredRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        if let Some(record) = self.primary.find_by_query(query.clone()).await? {
+            return Ok(Some(record));
+        }
+        let record = self.secondary.find_by_query(query).await?;
+        if let Some(record) = &record {
+            self.promote(record).await;
+        }
+        Ok(record)
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let primary = self
+            .primary
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await?;
+        let secondary = self
+            .secondary
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await?;
+        Ok(merge(primary, secondary))
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        match self.primary.find_as_of(query.clone(), as_of).await? {
+            Some(record) => Ok(Some(record)),
+            None => self.secondary.find_as_of(query, as_of).await,
+        }
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        let primary = self.primary.list().await?.into_records();
+        let secondary = self.secondary.list().await?.into_records();
+        Ok(TrustRecordList::new(merge(primary, secondary)))
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for LayeredRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        if self
+            .secondary
+            .find_by_query(TrustRecordQuery::from(&record))
+            .await?
+            .is_some()
+        {
+            return Err(RepositoryError::RecordAlreadyExists(format!(
+                "Record exists in the secondary storage: {}|{}|{}|{}",
+                record.entity_id(),
+                record.authority_id(),
+                record.action(),
+                record.resource()
+            )));
+        }
+        self.primary.create(record).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.promote_for_write(&TrustRecordQuery::from(&record))
+            .await?;
+        self.primary.update(record).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.promote_for_write(&TrustRecordQuery::from(&record))
+            .await?;
+        self.primary
+            .update_if_version(record, expected_version)
+            .await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        let in_secondary = match self.secondary.delete(query.clone()).await {
+            Ok(()) => true,
+            Err(RepositoryError::RecordNotFound(_)) => false,
+            Err(e) => return Err(e),
+        };
+        match self.primary.delete(query).await {
+            Err(RepositoryError::RecordNotFound(_)) if in_secondary => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        let in_secondary = self.secondary.delete_many(queries.clone()).await?;
+        let in_primary = self.primary.delete_many(queries).await?;
+        Ok(in_primary
+            .into_iter()
+            .zip(in_secondary)
+            .map(|(primary, secondary)| primary || secondary)
+            .collect())
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.promote_for_write(&TrustRecordQuery::from(&record))
+            .await?;
+        self.primary.upsert(record).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.find_by_query(query.clone()).await?.ok_or_else(|| {
+            RepositoryError::RecordNotFound(format!(
+                "Record not found: {}|{}|{}|{}",
+                query.entity_id, query.authority_id, query.action, query.resource
+            ))
+        })
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.primary.flush().await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        let primary = self.primary.find_by_tag(tag).await?;
+        let secondary = self.secondary.find_by_tag(tag).await?;
+        Ok(merge(primary, secondary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::record_for;
+
+    fn layered(
+        primary: Vec<TrustRecord>,
+        secondary: Vec<TrustRecord>,
+    ) -> (Arc<LocalStorage>, Arc<LocalStorage>, LayeredRepository) {
+        let primary = Arc::new(LocalStorage::with_records(primary));
+        let secondary = Arc::new(LocalStorage::with_records(secondary));
+        let repository = LayeredRepository::new(primary.clone(), secondary.clone());
+        (primary, secondary, repository)
+    }
+
+    #[tokio::test]
+    async fn test_read_missing_in_primary_is_served_and_promoted() {
+        let (primary, _, repository) = layered(vec![], vec![record_for("legacy", true)]);
+        let query = TrustRecordQuery::from(&record_for("legacy", true));
+        assert!(
+            primary
+                .find_by_query(query.clone())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let found = repository.find_by_query(query.clone()).await.unwrap();
+
+        assert_eq!(found, Some(record_for("legacy", true)));
+        assert_eq!(
+            primary.find_by_query(query).await.unwrap(),
+            Some(record_for("legacy", true)),
+            "record served by the secondary must be promoted into the primary"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_primary_wins_over_secondary() {
+        let (_, _, repository) = layered(
+            vec![record_for("both", true)],
+            vec![record_for("both", false)],
+        );
+
+        let found = repository
+            .read(TrustRecordQuery::from(&record_for("both", true)))
+            .await
+            .unwrap();
+        let listed = repository.list().await.unwrap();
+
+        assert!(found.is_authorized());
+        assert_eq!(listed.records(), &[record_for("both", true)]);
+    }
+
+    #[tokio::test]
+    async fn test_writes_only_reach_the_primary() {
+        let (primary, secondary, repository) = layered(vec![], vec![record_for("legacy", true)]);
+
+        repository
+            .update(record_for("legacy", false))
+            .await
+            .unwrap();
+        repository.create(record_for("new", true)).await.unwrap();
+
+        assert_eq!(primary.list().await.unwrap().records().len(), 2);
+        assert!(
+            !primary
+                .read(TrustRecordQuery::from(&record_for("legacy", true)))
+                .await
+                .unwrap()
+                .is_authorized()
+        );
+        assert_eq!(
+            secondary.list().await.unwrap().records(),
+            &[record_for("legacy", true)]
+        );
+        assert!(matches!(
+            repository.create(record_for("legacy", true)).await,
+            Err(RepositoryError::RecordAlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deleted_record_is_not_served_from_the_secondary() {
+        let (primary, secondary, repository) = layered(
+            vec![record_for("both", true)],
+            vec![record_for("both", true), record_for("legacy", true)],
+        );
+        let both = TrustRecordQuery::from(&record_for("both", true));
+        let legacy = TrustRecordQuery::from(&record_for("legacy", true));
+        repository.find_by_query(legacy.clone()).await.unwrap();
+
+        repository.delete(both.clone()).await.unwrap();
+        let deleted = repository
+            .delete_many(vec![legacy.clone(), both.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(deleted, [true, false]);
+        for query in [both, legacy] {
+            assert_eq!(repository.find_by_query(query.clone()).await.unwrap(), None);
+            assert_eq!(primary.find_by_query(query).await.unwrap(), None);
+        }
+        assert!(secondary.list().await.unwrap().records().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_of_secondary_only_record_succeeds() {
+        let (_, _, repository) = layered(vec![], vec![record_for("legacy", true)]);
+        let legacy = TrustRecordQuery::from(&record_for("legacy", true));
+
+        repository.delete(legacy.clone()).await.unwrap();
+
+        assert_eq!(
+            repository.find_by_query(legacy.clone()).await.unwrap(),
+            None
+        );
+        assert!(matches!(
+            repository.delete(legacy).await,
+            Err(RepositoryError::RecordNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_in_both_layers_is_not_found() {
+        let (_, _, repository) = layered(vec![], vec![]);
+
+        let result = repository
+            .read(TrustRecordQuery::from(&record_for("absent", true)))
+            .await;
+
+        assert!(matches!(result, Err(RepositoryError::RecordNotFound(_))));
+    }
+}
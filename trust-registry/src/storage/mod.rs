@@ -1,3 +1,11 @@
 pub mod adapters;
+pub mod caching;
+pub mod decision_cache;
+pub mod encryption;
 pub mod factory;
+pub mod history;
+pub mod layered;
+pub mod normalizing;
 pub mod repository;
+pub mod timeout;
+pub mod wildcard;
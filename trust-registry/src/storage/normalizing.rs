@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::{
+    domain::{AuthorityId, EntityId, TrustRecord, canonicalize_did},
+    storage::repository::{
+        RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+pub fn normalize_query(query: TrustRecordQuery) -> TrustRecordQuery {
+    TrustRecordQuery::new(
+        EntityId::new(canonicalize_did(query.entity_id.as_str())),
+        AuthorityId::new(canonicalize_did(query.authority_id.as_str())),
+        query.action,
+        query.resource,
+    )
+}
+
+fn normalize_record(record: TrustRecord) -> TrustRecord {
+    let entity_id = EntityId::new(canonicalize_did(record.entity_id().as_str()));
+    let authority_id = AuthorityId::new(canonicalize_did(record.authority_id().as_str()));
+    record
+        .with_entity_id(entity_id)
+        .with_authority_id(authority_id)
+}
+
+/// Repository wrapper storing and looking up records by their canonical entity and
+/// authority DIDs, see [`canonicalize_did`]. Records stored before normalization was
+/// enabled are only found when they were already written in canonical form.
+pub struct NormalizingRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+}
+
+impl NormalizingRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for NormalizingRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner.find_by_query(normalize_query(query)).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_entity_and_authority(
+                &EntityId::new(canonicalize_did(entity_id.as_str())),
+                &AuthorityId::new(canonicalize_did(authority_id.as_str())),
+            )
+            .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.inner.find_as_of(normalize_query(query), as_of).await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for NormalizingRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(normalize_record(record)).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(normalize_record(record)).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.inner
+            .update_if_version(normalize_record(record), expected_version)
+            .await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.inner.upsert(normalize_record(record)).await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(normalize_query(query)).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(normalize_query(query)).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner
+            .read_many(queries.into_iter().map(normalize_query).collect())
+            .await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        self.inner
+            .delete_many(queries.into_iter().map(normalize_query).collect())
+            .await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::record_builder;
+
+    fn record(entity: &str, authority: &str) -> TrustRecord {
+        record_builder(entity, authority, "issue", "VerifiableCredential")
+            .build()
+            .unwrap()
+    }
+
+    fn query(entity: &str, authority: &str) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new(entity),
+            AuthorityId::new(authority),
+            Action::new("issue"),
+            Resource::new("VerifiableCredential"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_dids_address_the_same_record() {
+        let repository = NormalizingRepository::new(Arc::new(LocalStorage::new()));
+        repository
+            .create(record(
+                "did:web:Example.com%3a443:users:alice/",
+                "did:web:authority.example.com",
+            ))
+            .await
+            .unwrap();
+
+        let found = repository
+            .find_by_query(query(
+                "did:web:example.com:users:alice",
+                "did:web:AUTHORITY.example.com:",
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            found.entity_id().as_str(),
+            "did:web:example.com:users:alice"
+        );
+
+        assert!(
+            repository
+                .create(record(
+                    "did:web:example.com:users:alice",
+                    "did:web:authority.example.com"
+                ))
+                .await
+                .is_err()
+        );
+
+        repository
+            .delete(query(
+                "DID:WEB:EXAMPLE.COM:users:alice",
+                "did:web:authority.example.com",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(repository.count().await.unwrap(), 0);
+    }
+}
@@ -4,6 +4,49 @@ use serde::{Deserialize, Serialize};
 
 use crate::domain::*;
 
+/// Context field listing the credential schemas an authority endorses through a record,
+/// see [`collect_schemas`].
+pub const CREDENTIAL_SCHEMAS_CONTEXT_KEY: &str = "credential_schemas";
+
+/// Entries of the [`CREDENTIAL_SCHEMAS_CONTEXT_KEY`] field of the record's context, `None`
+/// when it has none.
+pub fn endorsed_schemas(record: &TrustRecord) -> Option<Vec<serde_json::Value>> {
+    match record
+        .context()
+        .as_value()
+        .get(CREDENTIAL_SCHEMAS_CONTEXT_KEY)?
+    {
+        serde_json::Value::Array(entries) => Some(entries.clone()),
+        entry => Some(vec![entry.clone()]),
+    }
+}
+
+/// Credential schemas the authority endorses through those of `records` unexpired at `now`,
+/// without duplicates, in record order.
+pub fn collect_schemas<'a>(
+    records: impl IntoIterator<Item = &'a TrustRecord>,
+    authority_id: &AuthorityId,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<serde_json::Value> {
+    let mut schemas = Vec::new();
+    for record in records {
+        if record.authority_id() != authority_id || record.is_expired_at(now) {
+            continue;
+        }
+        for schema in endorsed_schemas(record).unwrap_or_default() {
+            if !schemas.contains(&schema) {
+                schemas.push(schema);
+            }
+        }
+    }
+    schemas
+}
+
+/// Lookup key for a trust record.
+///
+/// Records are keyed on the TRQP v2 four-tuple `(entity, authority, action, resource)`.
+/// There is no separate assertion-keyed model: an "assertion" is expressed as an
+/// `action` + `resource` pair, so every query path uses this type.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrustRecordQuery {
     pub entity_id: EntityId,
@@ -18,7 +61,10 @@ pub struct TrustRecordList {
 }
 
 impl TrustRecordList {
-    pub fn new(records: Vec<TrustRecord>) -> Self {
+    /// Sorts records by `(entity, authority, action, resource)` so list responses are
+    /// stable regardless of the backend's iteration order.
+    pub fn new(mut records: Vec<TrustRecord>) -> Self {
+        records.sort_by(|a, b| sort_key(a).cmp(&sort_key(b)));
         Self { records }
     }
 
@@ -31,6 +77,33 @@ impl TrustRecordList {
     }
 }
 
+/// Fails with [`RepositoryError::VersionConflict`] unless `current` is at `expected_version`.
+pub fn check_version(current: &TrustRecord, expected_version: u64) -> Result<(), RepositoryError> {
+    if current.version() == expected_version {
+        return Ok(());
+    }
+    Err(RepositoryError::VersionConflict(format!(
+        "{}|{}|{}|{} is at version {}, expected {expected_version}",
+        current.entity_id(),
+        current.authority_id(),
+        current.action(),
+        current.resource(),
+        current.version()
+    )))
+}
+
+fn sort_key(record: &TrustRecord) -> (&EntityId, &AuthorityId, &Action, &Resource) {
+    (
+        record.entity_id(),
+        record.authority_id(),
+        record.action(),
+        record.resource(),
+    )
+}
+
+/// Entity, authority, action and resource of a [`TrustRecordQuery`], see [`TrustRecordQuery::key`].
+pub type QueryKey = (String, String, String, String);
+
 impl TrustRecordQuery {
     pub fn new(
         entity_id: EntityId,
@@ -46,6 +119,16 @@ impl TrustRecordQuery {
         }
     }
 
+    /// Owned identifiers of the query, for keying maps of records.
+    pub fn key(&self) -> QueryKey {
+        (
+            self.entity_id.to_string(),
+            self.authority_id.to_string(),
+            self.action.to_string(),
+            self.resource.to_string(),
+        )
+    }
+
     pub fn from_ids(ids: TrustRecordIds) -> Self {
         let (entity_id, authority_id, action, resource) = ids.into_parts();
         Self {
@@ -57,6 +140,71 @@ impl TrustRecordQuery {
     }
 }
 
+impl From<&TrustRecord> for TrustRecordQuery {
+    fn from(record: &TrustRecord) -> Self {
+        Self::new(
+            record.entity_id().clone(),
+            record.authority_id().clone(),
+            record.action().clone(),
+            record.resource().clone(),
+        )
+    }
+}
+
+/// Which branch of an upsert was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Created,
+    Updated,
+}
+
+/// The TRQP determination a query asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Determination {
+    Authorization,
+    Recognition,
+}
+
+impl Determination {
+    pub fn value(self, record: &TrustRecord) -> bool {
+        match self {
+            Self::Authorization => record.is_authorized(),
+            Self::Recognition => record.is_recognized(),
+        }
+    }
+
+    /// Drops the other determination from the record, as TRQP responses must only carry
+    /// one, along with the fields for admins only, see [`TrustRecord::without_admin_fields`].
+    pub fn isolate(self, record: TrustRecord) -> TrustRecord {
+        let record = record.without_admin_fields();
+        match self {
+            Self::Authorization => record.none_recognized(),
+            Self::Recognition => record.none_authorized(),
+        }
+    }
+}
+
+/// Result of evaluating a single determination. Missing and expired records are `false`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluated {
+    pub value: bool,
+    pub record_found: bool,
+    /// The record the determination was read from, for handlers shaping a response from it.
+    pub record: Option<TrustRecord>,
+}
+
+impl Evaluated {
+    fn new(determination: Determination, record: Option<TrustRecord>) -> Self {
+        Self {
+            value: record
+                .as_ref()
+                .is_some_and(|record| determination.value(record)),
+            record_found: record.is_some(),
+            record,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RepositoryError {
     ConnectionFailed(String),
@@ -65,6 +213,12 @@ pub enum RepositoryError {
     RecordNotFound(String),
     RecordAlreadyExists(String),
     ValidationError(String),
+    /// The state asked for predates what the repository can still tell.
+    HistoryUnavailable(String),
+    /// A conditional write found the record at another version than expected.
+    VersionConflict(String),
+    /// The backend didn't complete the operation within `STORAGE_OP_TIMEOUT_MS`.
+    Timeout(String),
 }
 
 impl fmt::Display for RepositoryError {
@@ -76,6 +230,9 @@ impl fmt::Display for RepositoryError {
             Self::RecordNotFound(msg) => write!(f, "Record not found: {msg}"),
             Self::RecordAlreadyExists(msg) => write!(f, "Record already exists: {msg}"),
             Self::ValidationError(msg) => write!(f, "Validation error: {msg}"),
+            Self::HistoryUnavailable(msg) => write!(f, "History unavailable: {msg}"),
+            Self::VersionConflict(msg) => write!(f, "Version conflict: {msg}"),
+            Self::Timeout(msg) => write!(f, "Operation timed out: {msg}"),
         }
     }
 }
@@ -89,6 +246,103 @@ pub trait TrustRecordRepository: Send + Sync {
         &self,
         query: TrustRecordQuery,
     ) -> Result<Option<TrustRecord>, RepositoryError>;
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError>;
+
+    /// Partial match returning every record of the entity under the authority,
+    /// across all actions and resources, ordered like [`Self::list`]. The default scans
+    /// [`Self::list`]; backends that can look up the pair directly override it.
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        Ok(self
+            .list()
+            .await?
+            .into_records()
+            .into_iter()
+            .filter(|record| {
+                record.entity_id() == entity_id && record.authority_id() == authority_id
+            })
+            .collect())
+    }
+
+    /// The record as it stood at `as_of`. Without earlier versions only the current
+    /// record can answer: it does when it was last changed at or before `as_of`, and a
+    /// record created after `as_of` didn't exist yet. A record deleted since is not found.
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        let Some(record) = self.find_by_query(query).await? else {
+            return Ok(None);
+        };
+        if record
+            .created_at()
+            .is_some_and(|created_at| *created_at > as_of)
+        {
+            return Ok(None);
+        }
+        match record.updated_at().or(record.created_at()) {
+            Some(changed_at) if *changed_at <= as_of => Ok(Some(record)),
+            _ => Err(RepositoryError::HistoryUnavailable(format!(
+                "record changed after {as_of} and no earlier version is kept"
+            ))),
+        }
+    }
+
+    /// Whether the record of `query` authorizes it, at `as_of` or now. Expired records
+    /// count as missing.
+    async fn evaluate_authorization(
+        &self,
+        query: TrustRecordQuery,
+        as_of: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Evaluated, RepositoryError> {
+        let record = find_unexpired(self, query, as_of).await?;
+        Ok(Evaluated::new(Determination::Authorization, record))
+    }
+
+    /// Whether the record of `query` recognizes it, at `as_of` or now. Expired records
+    /// count as missing.
+    async fn evaluate_recognition(
+        &self,
+        query: TrustRecordQuery,
+        as_of: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Evaluated, RepositoryError> {
+        let record = find_unexpired(self, query, as_of).await?;
+        Ok(Evaluated::new(Determination::Recognition, record))
+    }
+
+    async fn evaluate(
+        &self,
+        query: TrustRecordQuery,
+        determination: Determination,
+        as_of: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Evaluated, RepositoryError> {
+        match determination {
+            Determination::Authorization => self.evaluate_authorization(query, as_of).await,
+            Determination::Recognition => self.evaluate_recognition(query, as_of).await,
+        }
+    }
+}
+
+/// The record of `query` as it stands at `as_of` or now, unless it has expired by then.
+async fn find_unexpired<R>(
+    repository: &R,
+    query: TrustRecordQuery,
+    as_of: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Option<TrustRecord>, RepositoryError>
+where
+    R: TrustRecordRepository + ?Sized,
+{
+    let record = match as_of {
+        Some(as_of) => repository.find_as_of(query, as_of).await?,
+        None => repository.find_by_query(query).await?,
+    };
+    let evaluated_at = as_of.unwrap_or_else(chrono::Utc::now);
+    Ok(record.filter(|record| !record.is_expired_at(evaluated_at)))
 }
 
 /// Write operations for trust record administration
@@ -96,14 +350,80 @@ pub trait TrustRecordRepository: Send + Sync {
 pub trait TrustRecordAdminRepository: TrustRecordRepository {
     async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError>;
     async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError>;
+
+    /// Updates the record only if the stored one is still at `expected_version`, failing
+    /// with [`RepositoryError::VersionConflict`] otherwise. The default reads then writes,
+    /// so a concurrent writer can slip in between; backends that can check and write in
+    /// one step override it.
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        let current = self.read(TrustRecordQuery::from(&record)).await?;
+        check_version(&current, expected_version)?;
+        self.update(record).await
+    }
+
     async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError>;
-    async fn list(&self) -> Result<TrustRecordList, RepositoryError>;
+    /// Creates the record, or replaces it if one with the same key exists, in one step.
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError>;
     async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError>;
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        Ok(self.list().await?.records().len())
+    }
+
+    /// Persists mutations the backend has buffered. Write-through backends have nothing to do.
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    /// Looks up several records at once. The result has one entry per query, in order.
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        let mut records = Vec::with_capacity(queries.len());
+        for query in queries {
+            records.push(self.find_by_query(query).await?);
+        }
+        Ok(records)
+    }
+
+    /// Deletes several records at once. The result has one entry per query, in order,
+    /// `false` where no record matched. The default deletes one record at a time.
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        let mut deleted = Vec::with_capacity(queries.len());
+        for query in queries {
+            match self.delete(query).await {
+                Ok(()) => deleted.push(true),
+                Err(RepositoryError::RecordNotFound(_)) => deleted.push(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Records carrying `tag`, ordered like [`Self::list`]. The default scans [`Self::list`].
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        Ok(self
+            .list()
+            .await?
+            .into_records()
+            .into_iter()
+            .filter(|record| record.has_tag(tag))
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::record_builder;
 
     #[test]
     fn test_trust_record_creation() {
@@ -142,4 +462,179 @@ mod tests {
 
         assert_eq!(query.entity_id.as_str(), "entity-123");
     }
+
+    fn query(entity: &str) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new(entity),
+            AuthorityId::new("authority-456"),
+            Action::new("action-789"),
+            Resource::new("resource-012"),
+        )
+    }
+
+    fn storage() -> crate::storage::adapters::local_storage::LocalStorage {
+        let record = |entity: &str| {
+            record_builder(entity, "authority-456", "action-789", "resource-012").recognized(false)
+        };
+        crate::storage::adapters::local_storage::LocalStorage::with_records(vec![
+            record("entity-123").build().unwrap(),
+            record("entity-expired")
+                .expires_at(chrono::Utc::now() - chrono::Duration::seconds(1))
+                .build()
+                .unwrap(),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_authorization_reports_only_authorized() {
+        let evaluated = storage()
+            .evaluate_authorization(query("entity-123"), None)
+            .await
+            .unwrap();
+
+        assert!(evaluated.value);
+        assert!(evaluated.record_found);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_recognition_reports_only_recognized() {
+        let evaluated = storage()
+            .evaluate_recognition(query("entity-123"), None)
+            .await
+            .unwrap();
+
+        assert!(!evaluated.value);
+        assert!(evaluated.record_found);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_treats_missing_and_expired_records_alike() {
+        let storage = storage();
+        for entity in ["entity-unknown", "entity-expired"] {
+            let evaluated = storage
+                .evaluate(query(entity), Determination::Authorization, None)
+                .await
+                .unwrap();
+
+            assert_eq!((evaluated.value, evaluated.record_found), (false, false));
+            assert_eq!(evaluated.record, None);
+        }
+    }
+
+    #[test]
+    fn test_isolate_drops_other_determination() {
+        let record = TrustRecordBuilder::new()
+            .entity_id(EntityId::new("entity-123"))
+            .authority_id(AuthorityId::new("authority-456"))
+            .action(Action::new("action-789"))
+            .resource(Resource::new("resource-012"))
+            .recognized(true)
+            .authorized(true)
+            .record_type(RecordType::Authorization)
+            .build()
+            .unwrap()
+            .with_tags(vec!["pilot".to_string()])
+            .with_attachments(vec![serde_json::json!({"data": {"base64": "e30="}})]);
+
+        let authorization =
+            serde_json::to_value(Determination::Authorization.isolate(record.clone())).unwrap();
+        let recognition = serde_json::to_value(Determination::Recognition.isolate(record)).unwrap();
+
+        assert_eq!(authorization["authorized"], true);
+        assert!(authorization.get("recognized").is_none());
+        assert_eq!(recognition["recognized"], true);
+        assert!(recognition.get("authorized").is_none());
+        assert!(authorization.get("attachments").is_none());
+        assert!(recognition.get("attachments").is_none());
+        assert!(authorization.get("tags").is_none());
+        assert!(recognition.get("tags").is_none());
+    }
+
+    #[test]
+    fn test_collect_schemas_collects_schemas_of_the_authority() {
+        let schema_record = |entity: &str, authority: &str, schemas: serde_json::Value| {
+            record_builder(entity, authority, "issue", "credential")
+                .context(Context::new(
+                    serde_json::json!({ CREDENTIAL_SCHEMAS_CONTEXT_KEY: schemas }),
+                ))
+                .build()
+                .unwrap()
+        };
+        let records = vec![
+            schema_record(
+                "entity-1",
+                "authority-456",
+                serde_json::json!(["https://schema/a"]),
+            ),
+            schema_record(
+                "entity-2",
+                "authority-456",
+                serde_json::json!(["https://schema/a", "https://schema/b"]),
+            ),
+            schema_record(
+                "entity-3",
+                "authority-other",
+                serde_json::json!(["https://schema/c"]),
+            ),
+        ];
+
+        let schemas = collect_schemas(
+            &records,
+            &AuthorityId::new("authority-456"),
+            chrono::Utc::now(),
+        );
+
+        assert_eq!(
+            schemas,
+            vec![
+                serde_json::json!("https://schema/a"),
+                serde_json::json!("https://schema/b")
+            ]
+        );
+    }
+
+    /// Implements only the required read methods, so the defaults answer.
+    struct ListOnly(crate::storage::adapters::local_storage::LocalStorage);
+
+    #[async_trait::async_trait]
+    impl TrustRecordRepository for ListOnly {
+        async fn find_by_query(
+            &self,
+            query: TrustRecordQuery,
+        ) -> Result<Option<TrustRecord>, RepositoryError> {
+            self.0.find_by_query(query).await
+        }
+
+        async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+            self.0.list().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_find_by_entity_and_authority_scans_list() {
+        let record = |entity: &str, authority: &str, action: &str| {
+            record_builder(entity, authority, action, "resource-012")
+                .build()
+                .unwrap()
+        };
+        let storage = ListOnly(
+            crate::storage::adapters::local_storage::LocalStorage::with_records(vec![
+                record("entity-123", "authority-456", "issue"),
+                record("entity-123", "authority-456", "verify"),
+                record("entity-123", "authority-other", "issue"),
+                record("entity-other", "authority-456", "issue"),
+            ]),
+        );
+
+        let records = storage
+            .find_by_entity_and_authority(
+                &EntityId::new("entity-123"),
+                &AuthorityId::new("authority-456"),
+            )
+            .await
+            .unwrap();
+
+        let actions: Vec<_> = records.iter().map(|r| r.action().as_str()).collect();
+        assert_eq!(actions, ["issue", "verify"]);
+    }
 }
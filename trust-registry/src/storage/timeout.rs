@@ -0,0 +1,179 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use crate::{
+    domain::{AuthorityId, EntityId, TrustRecord},
+    storage::repository::{
+        RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+/// Repository wrapper failing operations of a backend that don't complete within the
+/// timeout of `STORAGE_OP_TIMEOUT_MS` with [`RepositoryError::Timeout`], so a hung
+/// backend call doesn't block a request indefinitely. An operation that times out may
+/// still have been applied by the backend.
+pub struct TimeoutRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+    timeout: Duration,
+}
+
+impl TimeoutRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    async fn within<T>(
+        &self,
+        operation: &str,
+        future: impl Future<Output = Result<T, RepositoryError>>,
+    ) -> Result<T, RepositoryError> {
+        tokio::time::timeout(self.timeout, future)
+            .await
+            .unwrap_or_else(|_| {
+                Err(RepositoryError::Timeout(format!(
+                    "{operation} did not complete within {:?}",
+                    self.timeout
+                )))
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for TimeoutRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.within("find_by_query", self.inner.find_by_query(query))
+            .await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.within(
+            "find_by_entity_and_authority",
+            self.inner
+                .find_by_entity_and_authority(entity_id, authority_id),
+        )
+        .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.within("find_as_of", self.inner.find_as_of(query, as_of))
+            .await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.within("list", self.inner.list()).await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for TimeoutRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.within("create", self.inner.create(record)).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.within("update", self.inner.update(record)).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.within(
+            "update_if_version",
+            self.inner.update_if_version(record, expected_version),
+        )
+        .await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.within("delete", self.inner.delete(query)).await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.within("upsert", self.inner.upsert(record)).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.within("read", self.inner.read(query)).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.within("count", self.inner.count()).await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.within("flush", self.inner.flush()).await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.within("read_many", self.inner.read_many(queries))
+            .await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        self.within("delete_many", self.inner.delete_many(queries))
+            .await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.within("find_by_tag", self.inner.find_by_tag(tag))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{SlowRepository, record_for};
+
+    fn repository(delay: Duration, timeout: Duration) -> TimeoutRepository {
+        let slow = Arc::new(SlowRepository::new(
+            vec![record_for("credential", true)],
+            delay,
+        ));
+        TimeoutRepository::new(slow, timeout)
+    }
+
+    #[tokio::test]
+    async fn test_slow_backend_call_times_out() {
+        let repository = repository(Duration::from_secs(5), Duration::from_millis(20));
+
+        let found = repository
+            .find_by_query(TrustRecordQuery::from(&record_for("credential", true)))
+            .await;
+        let listed = repository.list().await;
+
+        assert!(matches!(found, Err(RepositoryError::Timeout(_))));
+        assert!(matches!(listed, Err(RepositoryError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_backend_call_within_timeout_succeeds() {
+        let repository = repository(Duration::from_millis(1), Duration::from_secs(5));
+
+        let found = repository
+            .find_by_query(TrustRecordQuery::from(&record_for("credential", true)))
+            .await
+            .unwrap();
+
+        assert_eq!(found, Some(record_for("credential", true)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use crate::{
+    domain::{Action, AuthorityId, EntityId, Resource, TrustRecord},
+    storage::repository::{
+        RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+        TrustRecordRepository, UpsertOutcome,
+    },
+};
+
+/// Stored as a record's action or resource to match any value in queries.
+pub const WILDCARD: &str = "*";
+
+pub fn is_wildcard_query(query: &TrustRecordQuery) -> bool {
+    query.action.as_str() == WILDCARD || query.resource.as_str() == WILDCARD
+}
+
+/// Queries tried after the exact one, most specific first: any action on the
+/// resource, then the action on any resource, then anything.
+fn fallback_queries(query: &TrustRecordQuery) -> Vec<TrustRecordQuery> {
+    let with = |action: &Action, resource: &Resource| {
+        TrustRecordQuery::new(
+            query.entity_id.clone(),
+            query.authority_id.clone(),
+            action.clone(),
+            resource.clone(),
+        )
+    };
+    let any_action = Action::new(WILDCARD);
+    let any_resource = Resource::new(WILDCARD);
+    let mut fallbacks = vec![];
+    if query.action != any_action {
+        fallbacks.push(with(&any_action, &query.resource));
+    }
+    if query.resource != any_resource {
+        fallbacks.push(with(&query.action, &any_resource));
+    }
+    if query.action != any_action && query.resource != any_resource {
+        fallbacks.push(with(&any_action, &any_resource));
+    }
+    fallbacks
+}
+
+/// Repository wrapper answering queries with `*` action or resource records when no
+/// record matches exactly. Admin operations always address records by their exact key.
+pub struct WildcardRepository {
+    inner: Arc<dyn TrustRecordAdminRepository>,
+}
+
+impl WildcardRepository {
+    pub fn new(inner: Arc<dyn TrustRecordAdminRepository>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for WildcardRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        let fallbacks = fallback_queries(&query);
+        if let Some(record) = self.inner.find_by_query(query).await? {
+            return Ok(Some(record));
+        }
+        for fallback in fallbacks {
+            if let Some(record) = self.inner.find_by_query(fallback).await? {
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn find_as_of(
+        &self,
+        query: TrustRecordQuery,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        let fallbacks = fallback_queries(&query);
+        if let Some(record) = self.inner.find_as_of(query, as_of).await? {
+            return Ok(Some(record));
+        }
+        for fallback in fallbacks {
+            if let Some(record) = self.inner.find_as_of(fallback, as_of).await? {
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for WildcardRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(record).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(record).await
+    }
+
+    async fn update_if_version(
+        &self,
+        record: TrustRecord,
+        expected_version: u64,
+    ) -> Result<(), RepositoryError> {
+        self.inner.update_if_version(record, expected_version).await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.inner.upsert(record).await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+
+    async fn count(&self) -> Result<usize, RepositoryError> {
+        self.inner.count().await
+    }
+
+    async fn flush(&self) -> Result<(), RepositoryError> {
+        self.inner.flush().await
+    }
+
+    async fn read_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<Option<TrustRecord>>, RepositoryError> {
+        self.inner.read_many(queries).await
+    }
+
+    async fn delete_many(
+        &self,
+        queries: Vec<TrustRecordQuery>,
+    ) -> Result<Vec<bool>, RepositoryError> {
+        self.inner.delete_many(queries).await
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.inner.find_by_tag(tag).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::storage::adapters::local_storage::LocalStorage;
+    use crate::test_support::{AUTHORITY, ENTITY, record_builder};
+
+    fn record(action: &str, resource: &str, authorized: bool) -> TrustRecord {
+        record_builder(ENTITY, AUTHORITY, action, resource)
+            .authorized(authorized)
+            .build()
+            .unwrap()
+    }
+
+    fn query(action: &str, resource: &str) -> TrustRecordQuery {
+        TrustRecordQuery::new(
+            EntityId::new("did:example:entity"),
+            AuthorityId::new("did:example:authority"),
+            Action::new(action),
+            Resource::new(resource),
+        )
+    }
+
+    fn repository(records: Vec<TrustRecord>) -> WildcardRepository {
+        WildcardRepository::new(Arc::new(LocalStorage::with_records(records)))
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_action_matches_any_action() {
+        let repository = repository(vec![record("*", "VerifiableCredential", true)]);
+
+        let found = repository
+            .find_by_query(query("issue", "VerifiableCredential"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(found.action().as_str(), "*");
+        assert!(
+            repository
+                .find_by_query(query("issue", "DriverLicense"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exact_match_takes_precedence_over_wildcards() {
+        let repository = repository(vec![
+            record("*", "*", true),
+            record("*", "VerifiableCredential", true),
+            record("issue", "*", true),
+            record("issue", "VerifiableCredential", false),
+        ]);
+
+        let exact = repository
+            .find_by_query(query("issue", "VerifiableCredential"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exact.action().as_str(), "issue");
+        assert_eq!(exact.resource().as_str(), "VerifiableCredential");
+        assert!(!exact.is_authorized());
+
+        let any_action = repository
+            .find_by_query(query("verify", "VerifiableCredential"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(any_action.action().as_str(), "*");
+        assert_eq!(any_action.resource().as_str(), "VerifiableCredential");
+
+        let any_resource = repository
+            .find_by_query(query("issue", "DriverLicense"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(any_resource.action().as_str(), "issue");
+        assert_eq!(any_resource.resource().as_str(), "*");
+
+        let anything = repository
+            .find_by_query(query("verify", "DriverLicense"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(anything.action().as_str(), "*");
+        assert_eq!(anything.resource().as_str(), "*");
+    }
+
+    #[test]
+    fn test_fallbacks_skip_wildcards_already_in_query() {
+        assert_eq!(fallback_queries(&query("issue", "credential")).len(), 3);
+        assert_eq!(fallback_queries(&query("*", "credential")).len(), 1);
+        assert!(fallback_queries(&query("*", "*")).is_empty());
+    }
+}
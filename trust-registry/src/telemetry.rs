@@ -0,0 +1,111 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, metrics::SdkMeterProvider, trace::SdkTracerProvider};
+use tracing::Subscriber;
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+use crate::configs::loaders::environment::optional_env;
+
+/// Exports traces and metrics over OTLP/HTTP to `OTEL_EXPORTER_OTLP_ENDPOINT`.
+pub struct OtlpTelemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl OtlpTelemetry {
+    /// `None` when no endpoint is configured, so nothing is exported and no
+    /// connection is ever opened. Exporters connect lazily on the first export.
+    /// Installs the meter provider as the process-wide one.
+    pub fn init(endpoint: Option<&str>) -> Result<Option<Self>, ExporterBuildError> {
+        let telemetry = Self::build(endpoint)?;
+        if let Some(telemetry) = &telemetry {
+            opentelemetry::global::set_meter_provider(telemetry.meter_provider.clone());
+        }
+        Ok(telemetry)
+    }
+
+    /// [`Self::init`] without touching the global meter provider.
+    fn build(endpoint: Option<&str>) -> Result<Option<Self>, ExporterBuildError> {
+        let Some(endpoint) = endpoint
+            .map(|e| e.trim_end_matches('/'))
+            .filter(|e| !e.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let resource = match optional_env("OTEL_SERVICE_NAME") {
+            Some(_) => Resource::builder().build(),
+            None => Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        };
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/traces"))
+            .build()?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/metrics"))
+            .build()?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+
+        Ok(Some(Self {
+            tracer_provider,
+            meter_provider,
+        }))
+    }
+
+    /// Forwards `tracing` spans to the OTLP trace exporter.
+    pub fn layer<S>(&self) -> impl Layer<S> + use<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let tracer = self.tracer_provider.tracer(env!("CARGO_PKG_NAME"));
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    }
+
+    /// Flushes buffered spans and metrics. Call before the process exits.
+    pub fn shutdown(&self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to shut down OTLP trace export: {e}");
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Failed to shut down OTLP metric export: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_init_is_a_no_op_without_endpoint() {
+        assert!(OtlpTelemetry::init(None).unwrap().is_none());
+        assert!(OtlpTelemetry::init(Some("")).unwrap().is_none());
+    }
+
+    // builds without installing the meter provider, which other tests share
+    #[test]
+    fn test_build_with_endpoint_builds_exporters() {
+        let telemetry = OtlpTelemetry::build(Some("http://127.0.0.1:4318/"))
+            .unwrap()
+            .unwrap();
+
+        let subscriber = tracing_subscriber::registry().with(telemetry.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("otlp-smoke-test").in_scope(|| tracing::info!("exported"));
+        });
+        telemetry.shutdown();
+    }
+}
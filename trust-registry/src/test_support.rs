@@ -0,0 +1,216 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use affinidi_tdk::{
+    common::TDKSharedState,
+    messaging::{ATM, config::ATMConfig, profiles::ATMProfile},
+};
+
+use crate::{
+    domain::{
+        Action, AuthorityId, EntityId, RecordType, Resource, TrustRecord, TrustRecordBuilder,
+    },
+    storage::{
+        adapters::local_storage::LocalStorage,
+        repository::{
+            RepositoryError, TrustRecordAdminRepository, TrustRecordList, TrustRecordQuery,
+            TrustRecordRepository, UpsertOutcome,
+        },
+    },
+};
+
+/// An ATM and a profile named `alias` without a mediator, enough to dispatch messages
+/// offline. Sending anything through it fails.
+pub async fn offline_atm(alias: &str) -> (Arc<ATM>, Arc<ATMProfile>) {
+    let atm = ATM::new(
+        ATMConfig::builder().build().unwrap(),
+        Arc::new(TDKSharedState::default().await),
+    )
+    .await
+    .unwrap();
+    let profile = ATMProfile::new(
+        &atm,
+        Some(alias.to_string()),
+        format!("did:example:{alias}"),
+        None,
+    )
+    .await
+    .unwrap();
+    (Arc::new(atm), Arc::new(profile))
+}
+
+/// Entity of the records built by [`record_for`].
+pub const ENTITY: &str = "did:example:entity";
+/// Authority of the records built by [`record_for`].
+pub const AUTHORITY: &str = "did:example:authority";
+
+/// A recognized and authorized record, for tests to adjust before `build`.
+pub fn record_builder(
+    entity: &str,
+    authority: &str,
+    action: &str,
+    resource: &str,
+) -> TrustRecordBuilder {
+    TrustRecordBuilder::new()
+        .entity_id(EntityId::new(entity))
+        .authority_id(AuthorityId::new(authority))
+        .action(Action::new(action))
+        .resource(Resource::new(resource))
+        .recognized(true)
+        .authorized(true)
+        .record_type(RecordType::Authorization)
+}
+
+/// A recognized record of [`ENTITY`] by [`AUTHORITY`] to `issue` the resource.
+pub fn record_for(resource: &str, authorized: bool) -> TrustRecord {
+    record_builder(ENTITY, AUTHORITY, "issue", resource)
+        .authorized(authorized)
+        .build()
+        .unwrap()
+}
+
+/// Local storage counting the lookups reaching it.
+pub struct CountingRepository {
+    pub inner: LocalStorage,
+    lookups: AtomicUsize,
+}
+
+impl CountingRepository {
+    pub fn new(records: Vec<TrustRecord>) -> Self {
+        Self {
+            inner: LocalStorage::with_records(records),
+            lookups: AtomicUsize::new(0),
+        }
+    }
+
+    /// `find_by_query` and `find_by_entity_and_authority` calls so far.
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for CountingRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.inner.find_by_query(query).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for CountingRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(record).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(record).await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query).await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.inner.upsert(record).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+}
+
+/// Local storage taking `delay` for every read.
+pub struct SlowRepository {
+    inner: LocalStorage,
+    delay: Duration,
+}
+
+impl SlowRepository {
+    pub fn new(records: Vec<TrustRecord>, delay: Duration) -> Self {
+        Self {
+            inner: LocalStorage::with_records(records),
+            delay,
+        }
+    }
+
+    /// Reads that outlast any test.
+    pub fn hanging(records: Vec<TrustRecord>) -> Self {
+        Self::new(records, Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordRepository for SlowRepository {
+    async fn find_by_query(
+        &self,
+        query: TrustRecordQuery,
+    ) -> Result<Option<TrustRecord>, RepositoryError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.find_by_query(query).await
+    }
+
+    async fn find_by_entity_and_authority(
+        &self,
+        entity_id: &EntityId,
+        authority_id: &AuthorityId,
+    ) -> Result<Vec<TrustRecord>, RepositoryError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner
+            .find_by_entity_and_authority(entity_id, authority_id)
+            .await
+    }
+
+    async fn list(&self) -> Result<TrustRecordList, RepositoryError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.list().await
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustRecordAdminRepository for SlowRepository {
+    async fn create(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.create(record).await
+    }
+
+    async fn update(&self, record: TrustRecord) -> Result<(), RepositoryError> {
+        self.inner.update(record).await
+    }
+
+    async fn delete(&self, query: TrustRecordQuery) -> Result<(), RepositoryError> {
+        self.inner.delete(query).await
+    }
+
+    async fn upsert(&self, record: TrustRecord) -> Result<UpsertOutcome, RepositoryError> {
+        self.inner.upsert(record).await
+    }
+
+    async fn read(&self, query: TrustRecordQuery) -> Result<TrustRecord, RepositoryError> {
+        self.inner.read(query).await
+    }
+}
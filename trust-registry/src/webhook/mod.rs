@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+use crate::{
+    audit::model::{AuditOperation, AuditResource},
+    configs::WebhookConfig,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Trust-Registry-Signature";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecordChangeEvent {
+    pub operation: String,
+    /// `entity|authority|action|resource` of the changed record. Absent on truncate
+    /// events, which cover the whole registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Number of records removed by a truncate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<usize>,
+    pub timestamp: String,
+}
+
+impl RecordChangeEvent {
+    pub fn new(operation: AuditOperation, resource: &AuditResource) -> Self {
+        let part = |value: Option<String>| value.unwrap_or_default();
+        let key = [
+            part(resource.entity_id.as_ref().map(ToString::to_string)),
+            part(resource.authority_id.as_ref().map(ToString::to_string)),
+            part(resource.action.as_ref().map(ToString::to_string)),
+            part(resource.resource.as_ref().map(ToString::to_string)),
+        ]
+        .join("|");
+
+        Self {
+            operation: operation.to_string(),
+            key: Some(key),
+            deleted: None,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    /// One event for a truncate, instead of one per deleted record.
+    pub fn truncated(deleted: usize) -> Self {
+        Self {
+            operation: AuditOperation::Truncate.to_string(),
+            key: None,
+            deleted: Some(deleted),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    /// The changed key, or `*` for events covering the whole registry.
+    fn subject(&self) -> &str {
+        self.key.as_deref().unwrap_or("*")
+    }
+}
+
+/// Returns the `sha256=<hex>` HMAC signature of `body` sent in [`SIGNATURE_HEADER`].
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+/// Posts record change events to the configured webhook.
+///
+/// Delivery runs in the background with bounded retries, so a slow or
+/// unavailable webhook never affects the admin operation that triggered it.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { client, config }
+    }
+
+    pub fn notify(&self, event: RecordChangeEvent) {
+        let client = self.client.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            deliver(&client, &config, &event).await;
+        });
+    }
+}
+
+/// Sends the event, retrying up to `max_retries` times. Returns whether delivery succeeded.
+pub async fn deliver(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    event: &RecordChangeEvent,
+) -> bool {
+    let body = match serde_json::to_vec(event) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to serialize webhook event: {e}");
+            return false;
+        }
+    };
+    let signature = sign(&config.secret, &body);
+    let mut delay = INITIAL_RETRY_DELAY;
+
+    for attempt in 0..=config.max_retries {
+        let result = client
+            .post(&config.url)
+            .header("content-type", "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                debug!("Webhook delivered: {} {}", event.operation, event.subject());
+                return true;
+            }
+            Ok(response) => warn!(
+                "Webhook attempt {} returned status {}",
+                attempt + 1,
+                response.status()
+            ),
+            Err(e) => warn!("Webhook attempt {} failed: {e}", attempt + 1),
+        }
+
+        if attempt < config.max_retries {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    warn!(
+        "Giving up on webhook event {} {} after {} attempts",
+        event.operation,
+        event.subject(),
+        config.max_retries + 1
+    );
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Action, AuthorityId, EntityId, Resource};
+
+    #[test]
+    fn test_sign_matches_rfc4231_vector() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_event_key_joins_record_ids() {
+        let resource = AuditResource::new(
+            Some(EntityId::new("entity")),
+            Some(AuthorityId::new("authority")),
+            Some(Action::new("action")),
+            Some(Resource::new("resource")),
+        );
+
+        let event = RecordChangeEvent::new(AuditOperation::Create, &resource);
+
+        assert_eq!(event.operation, "CREATE");
+        assert_eq!(
+            event.key.as_deref(),
+            Some("entity|authority|action|resource")
+        );
+    }
+
+    #[test]
+    fn test_truncate_event_has_no_key() {
+        let event = serde_json::to_value(RecordChangeEvent::truncated(3)).unwrap();
+
+        assert_eq!(event["operation"], "TRUNCATE");
+        assert_eq!(event["deleted"], 3);
+        assert!(event.get("key").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up_when_webhook_is_down() {
+        let config = WebhookConfig {
+            url: "http://127.0.0.1:9/unreachable".to_string(),
+            secret: "secret".to_string(),
+            max_retries: 1,
+        };
+        let event = RecordChangeEvent::new(AuditOperation::Delete, &AuditResource::empty());
+
+        assert!(!deliver(&reqwest::Client::new(), &config, &event).await);
+    }
+}
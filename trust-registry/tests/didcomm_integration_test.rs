@@ -11,16 +11,25 @@ use affinidi_tdk::{
 use serde_json::{Value, json};
 use std::{env, sync::Arc, time::Duration, vec};
 use tokio::sync::OnceCell;
+use trust_registry::configs::DEFAULT_REGISTRY_NAME;
 use trust_registry::didcomm::{
+    MediatorConnectOptions,
     handlers::{
         admin::{
-            CREATE_RECORD_MESSAGE_TYPE, CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
+            ACK_MESSAGE_TYPE, CREATE_RECORD_MESSAGE_TYPE, CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
             DELETE_RECORD_MESSAGE_TYPE, DELETE_RECORD_RESPONSE_MESSAGE_TYPE,
             LIST_RECORDS_MESSAGE_TYPE, LIST_RECORDS_RESPONSE_MESSAGE_TYPE,
             READ_RECORD_MESSAGE_TYPE, READ_RECORD_RESPONSE_MESSAGE_TYPE,
             UPDATE_RECORD_MESSAGE_TYPE, UPDATE_RECORD_RESPONSE_MESSAGE_TYPE,
         },
-        trqp::{QUERY_RECOGNITION_MESSAGE_TYPE, QUERY_RECOGNITION_RESPONSE_MESSAGE_TYPE},
+        registry_info::{REGISTRY_INFO_MESSAGE_TYPE, REGISTRY_INFO_RESPONSE_MESSAGE_TYPE},
+        schemas::{QUERY_SCHEMAS_MESSAGE_TYPE, QUERY_SCHEMAS_RESPONSE_MESSAGE_TYPE},
+        trqp::{
+            QUERY_AUTHORIZATION_BATCH_MESSAGE_TYPE,
+            QUERY_AUTHORIZATION_BATCH_RESPONSE_MESSAGE_TYPE, QUERY_RECOGNITION_MESSAGE_TYPE,
+            QUERY_RECOGNITION_RESPONSE_MESSAGE_TYPE,
+        },
+        trust_ping::{TRUST_PING_MESSAGE_TYPE, TRUST_PING_RESPONSE_MESSAGE_TYPE},
     },
     prepare_atm_and_profile,
 };
@@ -274,10 +283,16 @@ async fn setup_test_environment(
 ) -> (Arc<ATM>, Arc<ATMProfile>, Arc<Protocols>) {
     let protocols = Arc::new(Protocols::new());
     let secrets: Vec<Secret> = serde_json::from_str(secrets).unwrap();
-    let (atm, profile) =
-        prepare_atm_and_profile("test-client", client_did, mediator_did, secrets, false)
-            .await
-            .unwrap();
+    let (atm, profile) = prepare_atm_and_profile(
+        "test-client",
+        client_did,
+        mediator_did,
+        secrets,
+        false,
+        &MediatorConnectOptions::default(),
+    )
+    .await
+    .unwrap();
 
     tokio::time::sleep(Duration::from_secs(5)).await;
 
@@ -541,6 +556,243 @@ async fn test_trqp_handler() {
     assert_eq!(response_body["authorized"].as_bool(), Some(true));
 }
 
+#[tokio::test]
+async fn test_trust_ping() {
+    let (atm_test_context, config) = get_test_context().await;
+
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &json!({ "response_requested": true }),
+        TRUST_PING_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+
+    let response_body = fetch_and_verify_response_with_retry(
+        &atm_test_context.atm,
+        &atm_test_context.profile,
+        TRUST_PING_RESPONSE_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response_body, json!({}));
+}
+
+#[tokio::test]
+async fn test_ack_arrives_before_create_response() {
+    let (atm_test_context, config) = get_test_context().await;
+    let mut body = create_message_with_defaults(&format!("ack_{}", Uuid::new_v4()));
+    body["ack_requested"] = json!(true);
+
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &body,
+        CREATE_RECORD_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+
+    let (atm, profile) = (&atm_test_context.atm, &atm_test_context.profile);
+    for attempt in 0..3 {
+        tokio::time::sleep(Duration::from_secs(
+            config.message_wait_duration_secs + attempt * 2,
+        ))
+        .await;
+        let fetched = atm
+            .fetch_messages(profile, &create_fetch_options(INITIAL_FETCH_LIMIT))
+            .await
+            .unwrap();
+        let mut unpacked = Vec::new();
+        for element in &fetched.success {
+            if let Some(message) = &element.msg {
+                unpacked.push(atm.unpack(message).await.unwrap());
+            }
+        }
+
+        let Some(ack_position) = unpacked
+            .iter()
+            .position(|(msg, _)| msg.type_ == ACK_MESSAGE_TYPE)
+        else {
+            continue;
+        };
+        let thid = unpacked[ack_position].0.thid.clone();
+        let Some(response_position) = unpacked.iter().position(|(msg, _)| {
+            msg.type_ == CREATE_RECORD_RESPONSE_MESSAGE_TYPE && msg.thid == thid
+        }) else {
+            continue;
+        };
+
+        assert_eq!(unpacked[ack_position].0.body["status"], "OK");
+        assert!(ack_position < response_position);
+        let hashes = [ack_position, response_position]
+            .map(|i| unpacked[i].1.sha256_hash.clone())
+            .to_vec();
+        delete_message(atm, profile, hashes).await;
+        return;
+    }
+    panic!("ack and create-record response not both received");
+}
+
+#[tokio::test]
+async fn test_registry_info() {
+    let (atm_test_context, config) = get_test_context().await;
+    let expected_name =
+        env::var("REGISTRY_NAME").unwrap_or_else(|_| DEFAULT_REGISTRY_NAME.to_string());
+
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &json!({}),
+        REGISTRY_INFO_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+
+    let response_body = fetch_and_verify_response_with_retry(
+        &atm_test_context.atm,
+        &atm_test_context.profile,
+        REGISTRY_INFO_RESPONSE_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response_body["name"], expected_name);
+    assert_eq!(response_body["did"], config.trust_registry_did);
+    assert_eq!(response_body["record_model"], "action-resource");
+}
+
+#[tokio::test]
+async fn test_query_schemas() {
+    let (atm_test_context, config) = get_test_context().await;
+    let mut body = create_message_with_defaults(&format!("schemas_{}", Uuid::new_v4()));
+    let schema = "https://example.com/schemas/degree.json";
+    body["context"] = json!({ "credential_schemas": [schema] });
+
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &body,
+        CREATE_RECORD_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+    let _ = fetch_and_verify_response_with_retry(
+        &atm_test_context.atm,
+        &atm_test_context.profile,
+        CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
+    )
+    .await;
+
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &json!({ "authority_id": body["authority_id"] }),
+        QUERY_SCHEMAS_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+
+    let response_body = fetch_and_verify_response_with_retry(
+        &atm_test_context.atm,
+        &atm_test_context.profile,
+        QUERY_SCHEMAS_RESPONSE_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response_body["authority_id"], body["authority_id"]);
+    assert_eq!(response_body["schemas"], json!([schema]));
+}
+
+#[tokio::test]
+async fn test_query_authorization_batch() {
+    let (atm_test_context, config) = get_test_context().await;
+    let authorized = create_message_with_defaults(&format!("batch_{}", Uuid::new_v4()));
+    let mut unauthorized = create_message_with_defaults(&format!("batch_{}", Uuid::new_v4()));
+    unauthorized["authorized"] = json!(false);
+
+    for body in [&authorized, &unauthorized] {
+        send_message(
+            &atm_test_context.atm,
+            atm_test_context.profile.clone(),
+            &config.trust_registry_did,
+            &atm_test_context.protocols,
+            &config.mediator_did,
+            body,
+            CREATE_RECORD_MESSAGE_TYPE,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+        let _ = fetch_and_verify_response_with_retry(
+            &atm_test_context.atm,
+            &atm_test_context.profile,
+            CREATE_RECORD_RESPONSE_MESSAGE_TYPE,
+        )
+        .await;
+    }
+
+    let query = |body: &Value| {
+        json!({
+            "entity_id": body["entity_id"],
+            "authority_id": body["authority_id"],
+            "action": body["action"],
+            "resource": body["resource"],
+        })
+    };
+    let missing = create_test_record_body(&format!("batch_{}", Uuid::new_v4()));
+    send_message(
+        &atm_test_context.atm,
+        atm_test_context.profile.clone(),
+        &config.trust_registry_did,
+        &atm_test_context.protocols,
+        &config.mediator_did,
+        &json!({ "queries": [query(&unauthorized), query(&missing), query(&authorized)] }),
+        QUERY_AUTHORIZATION_BATCH_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_secs(config.message_wait_duration_secs)).await;
+
+    let response_body = fetch_and_verify_response_with_retry(
+        &atm_test_context.atm,
+        &atm_test_context.profile,
+        QUERY_AUTHORIZATION_BATCH_RESPONSE_MESSAGE_TYPE,
+    )
+    .await
+    .unwrap();
+
+    let results = response_body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["entity_id"], unauthorized["entity_id"]);
+    assert_eq!(results[0]["authorized"].as_bool(), Some(false));
+    assert_eq!(results[1], json!({}));
+    assert_eq!(results[2]["entity_id"], authorized["entity_id"]);
+    assert_eq!(results[2]["authorized"].as_bool(), Some(true));
+}
+
 async fn send_message(
     atm: &Arc<ATM>,
     profile: Arc<ATMProfile>,
@@ -317,6 +317,138 @@ async fn test_redis_find_by_query_not_found() {
     cleanup_test_data(&storage).await;
 }
 
+#[tokio::test]
+async fn test_redis_find_by_entity_and_authority() {
+    let Some(storage) = get_test_storage().await else {
+        return;
+    };
+    cleanup_test_data(&storage).await;
+
+    let records = vec![
+        ("did:example:issuer1", "did:example:authority1", "issue"),
+        ("did:example:issuer1", "did:example:authority1", "verify"),
+        ("did:example:issuer1", "did:example:authority2", "issue"),
+        ("did:example:issuer2", "did:example:authority1", "issue"),
+    ];
+    for (entity, authority, action) in records {
+        let record = create_test_record(
+            entity,
+            authority,
+            action,
+            "DriverLicense",
+            true,
+            true,
+            "assertion",
+        );
+        storage.create(record).await.unwrap();
+    }
+
+    let found = storage
+        .find_by_entity_and_authority(
+            &EntityId::new("did:example:issuer1"),
+            &AuthorityId::new("did:example:authority1"),
+        )
+        .await
+        .unwrap();
+
+    let mut actions: Vec<&str> = found.iter().map(|r| r.action().as_str()).collect();
+    actions.sort();
+    assert_eq!(actions, vec!["issue", "verify"]);
+
+    cleanup_test_data(&storage).await;
+}
+
+#[tokio::test]
+async fn test_redis_upsert_record() {
+    let Some(storage) = get_test_storage().await else {
+        return;
+    };
+    cleanup_test_data(&storage).await;
+
+    let record = create_test_record(
+        "did:example:issuer1",
+        "did:example:authority1",
+        "issue",
+        "DriverLicense",
+        true,
+        true,
+        "assertion",
+    );
+    let outcome = storage.upsert(record).await.unwrap();
+    assert_eq!(outcome, UpsertOutcome::Created);
+
+    let replacement = create_test_record(
+        "did:example:issuer1",
+        "did:example:authority1",
+        "issue",
+        "DriverLicense",
+        false,
+        false,
+        "assertion",
+    );
+    let outcome = storage.upsert(replacement).await.unwrap();
+    assert_eq!(outcome, UpsertOutcome::Updated);
+
+    let query = TrustRecordQuery::new(
+        EntityId::new("did:example:issuer1"),
+        AuthorityId::new("did:example:authority1"),
+        Action::new("issue"),
+        Resource::new("DriverLicense"),
+    );
+    let stored = storage.read(query).await.unwrap();
+    assert!(!stored.is_authorized());
+
+    cleanup_test_data(&storage).await;
+}
+
+#[tokio::test]
+async fn test_redis_read_many_records() {
+    let Some(storage) = get_test_storage().await else {
+        return;
+    };
+    cleanup_test_data(&storage).await;
+
+    for entity in ["did:example:issuer1", "did:example:issuer2"] {
+        let record = create_test_record(
+            entity,
+            "did:example:authority1",
+            "issue",
+            "DriverLicense",
+            true,
+            true,
+            "assertion",
+        );
+        storage.create(record).await.unwrap();
+    }
+
+    let query = |entity: &str| {
+        TrustRecordQuery::new(
+            EntityId::new(entity),
+            AuthorityId::new("did:example:authority1"),
+            Action::new("issue"),
+            Resource::new("DriverLicense"),
+        )
+    };
+    let found = storage
+        .read_many(vec![
+            query("did:example:issuer1"),
+            query("did:example:missing"),
+            query("did:example:issuer2"),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(found.len(), 3);
+    assert!(found[0].is_some());
+    assert!(found[1].is_none());
+    assert_eq!(
+        found[2].as_ref().unwrap().entity_id().as_str(),
+        "did:example:issuer2"
+    );
+
+    cleanup_test_data(&storage).await;
+}
+
 // Error handling tests - one per error scenario
 
 #[tokio::test]